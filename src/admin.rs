@@ -0,0 +1,300 @@
+//! 日志文件数据库的管理和监控接口，通过一个简单的HTTP服务导出统计信息，并支持触发整理和分裂
+//! 所有请求都需要在Authorization头中携带启动时指定的令牌，只在启用admin特性时编译
+
+use std::thread;
+use std::sync::{mpsc, atomic::{AtomicBool, Ordering}};
+use std::future::Future;
+use std::time::Duration;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::io::{BufRead, BufReader, Write};
+
+use atom::Atom;
+use futures::executor::block_on;
+use r#async::rt::AsyncRuntime;
+
+use crate::db::SResult;
+use crate::fork::ALL_TABLES;
+use crate::log_file_db::{STORE_RUNTIME, LogFileDB};
+
+/*
+* 等待管理请求在存储运行时上执行完成的最长时间，单位毫秒
+*/
+const ADMIN_REQUEST_TIMEOUT: u64 = 30000;
+
+/*
+* 管理连接读取请求和写入响应的超时时长，单位毫秒
+*/
+const ADMIN_IO_TIMEOUT: u64 = 5000;
+
+/*
+* 是否有管理接口触发的整理正在执行
+*/
+lazy_static! {
+	static ref ADMIN_COLLECTING: AtomicBool = AtomicBool::new(false);
+}
+
+//在指定地址上启动管理服务，返回实际绑定的地址，端口为0时由系统分配
+//管理服务在独立线程上接受连接，每个连接在独立的线程上处理，请求在STORE_RUNTIME上执行，请求必须携带"Authorization: Bearer <token>"头，令牌不允许为空
+pub fn start_admin(addr: &str, token: &str) -> SResult<SocketAddr> {
+	if token.is_empty() {
+		return Err(format!("start admin failed, addr: {}, reason: empty token", addr));
+	}
+	let token = token.to_string();
+
+	let listener = match TcpListener::bind(addr) {
+		Err(e) => return Err(format!("bind admin failed, addr: {}, reason: {:?}", addr, e)),
+		Ok(listener) => listener,
+	};
+	let local_addr = match listener.local_addr() {
+		Err(e) => return Err(format!("bind admin failed, addr: {}, reason: {:?}", addr, e)),
+		Ok(local_addr) => local_addr,
+	};
+
+	if let Err(e) = thread::Builder::new().name("Admin-Server".to_string()).spawn(move || {
+		for stream in listener.incoming() {
+			match stream {
+				Err(e) => warn!("accept admin connection failed, reason: {:?}", e),
+				Ok(stream) => {
+					//连接读写都有超时，缓慢或不发送请求的连接不会阻塞其它连接
+					let token = token.clone();
+					if let Err(e) = thread::Builder::new().name("Admin-Connection".to_string()).spawn(move || {
+						if let Err(e) = handle_connection(stream, &token) {
+							warn!("handle admin request failed, reason: {}", e);
+						}
+					}) {
+						warn!("spawn admin connection failed, reason: {:?}", e);
+					}
+				},
+			}
+		}
+	}) {
+		return Err(format!("start admin failed, addr: {}, reason: {:?}", addr, e));
+	}
+
+	info!("admin started, addr: {}", local_addr);
+	Ok(local_addr)
+}
+
+//处理一个管理连接，只处理请求行和认证头，每个连接处理一个请求
+fn handle_connection(mut stream: TcpStream, token: &str) -> SResult<()> {
+	let timeout = Some(Duration::from_millis(ADMIN_IO_TIMEOUT));
+	if let Err(e) = stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)) {
+		return Err(format!("{:?}", e));
+	}
+
+	let mut line = String::new();
+	let mut reader = BufReader::new(match stream.try_clone() {
+		Err(e) => return Err(format!("{:?}", e)),
+		Ok(s) => s,
+	});
+	if let Err(e) = reader.read_line(&mut line) {
+		return Err(format!("{:?}", e));
+	}
+
+	//只读取认证头，忽略其它请求头
+	let mut authorized = false;
+	loop {
+		let mut header = String::new();
+		match reader.read_line(&mut header) {
+			Ok(n) if n > 0 && header.trim().len() > 0 => {
+				let mut kv = header.splitn(2, ':');
+				let name = kv.next().unwrap_or("").trim();
+				if name.eq_ignore_ascii_case("authorization") {
+					let value = kv.next().unwrap_or("").trim();
+					authorized = match (value.get(..7), value.get(7..)) {
+						(Some(scheme), Some(credential)) => scheme.eq_ignore_ascii_case("bearer ") && token_eq(credential.trim(), token),
+						_ => false,
+					};
+				}
+			},
+			_ => break,
+		}
+	}
+
+	let mut parts = line.split_whitespace();
+	let method = parts.next().unwrap_or("");
+	let target = parts.next().unwrap_or("");
+	let (path, query) = match target.find('?') {
+		None => (target, ""),
+		Some(index) => (&target[..index], &target[index + 1..]),
+	};
+
+	let (status, body) = match (method, path) {
+		_ if !authorized => (401, error_json("unauthorized")),
+		("GET", "/stats") => run_on_store(async move {
+			let stats = LogFileDB::db_stats().await;
+			Ok(format!("{{\"tabs\":{},\"total_size\":{}}}", stats.tabs, stats.total_size))
+		}),
+		("GET", "/tab_stats") => match query_param(query, "tab") {
+			None => (400, error_json("missing tab")),
+			Some(tab) => run_on_store(async move {
+				//只返回已存在的表的统计信息，不为未知的表创建计数器
+				let tab = Atom::from(tab);
+				if ALL_TABLES.get(&tab).await.is_none() {
+					return Err(String::from("TabNotFound: ") + tab.as_str());
+				}
+
+				let stats = LogFileDB::tab_stats(&tab);
+				Ok(format!("{{\"splits\":{},\"lock_acquires\":{},\"lock_wait_total_us\":{},\"lock_wait_max_us\":{}}}",
+						   stats.splits,
+						   stats.lock_acquires,
+						   stats.lock_wait_total.as_micros(),
+						   stats.lock_wait_max.as_micros()))
+			}),
+		},
+		("GET", "/health") => {
+			let report = LogFileDB::health_report();
+			(200, format!("{{\"healthy\":{},\"skipped_meta\":{},\"quarantined_logs\":{}}}",
+						  report.skipped_meta.is_empty() && report.quarantined_logs.is_empty(),
+						  report.skipped_meta.len(),
+						  report.quarantined_logs.len()))
+		},
+		("POST", "/collect") => start_collect(),
+		("POST", "/split") => match query_param(query, "tab") {
+			None => run_on_store(async move {
+				LogFileDB::force_split().await.map(|_| ok_json())
+			}),
+			Some(tab) => run_on_store(async move {
+				LogFileDB::force_split_tab(&Atom::from(tab)).await.map(|_| ok_json())
+			}),
+		},
+		_ => (404, error_json("not found")),
+	};
+
+	let reason = match status {
+		200 => "OK",
+		202 => "Accepted",
+		400 => "Bad Request",
+		401 => "Unauthorized",
+		404 => "Not Found",
+		409 => "Conflict",
+		503 => "Service Unavailable",
+		504 => "Gateway Timeout",
+		_ => "Internal Server Error",
+	};
+	let auth = if status == 401 {
+		"WWW-Authenticate: Bearer\r\n"
+	} else {
+		""
+	};
+	let response = format!("HTTP/1.1 {} {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+						   status,
+						   reason,
+						   auth,
+						   body.len(),
+						   body);
+	if let Err(e) = stream.write_all(response.as_bytes()).and_then(|_| stream.flush()) {
+		return Err(format!("{:?}", e));
+	}
+
+	Ok(())
+}
+
+//在存储运行时上执行指定的异步任务，并同步等待结果
+//任务失败返回500，表不存在返回404，存储运行时未就绪返回503，等待超时返回504
+fn run_on_store<F>(future: F) -> (u16, String)
+	where F: Future<Output = SResult<String>> + Send + 'static {
+	let rt = match block_on(STORE_RUNTIME.read()).as_ref().cloned() {
+		None => return (503, error_json("store runtime not ready")),
+		Some(rt) => rt,
+	};
+
+	let (sender, receiver) = mpsc::channel();
+	if let Err(e) = rt.spawn(rt.alloc(), async move {
+		let _ = sender.send(future.await);
+	}) {
+		return (500, error_json(&format!("{:?}", e)));
+	}
+
+	match receiver.recv_timeout(Duration::from_millis(ADMIN_REQUEST_TIMEOUT)) {
+		Err(_) => (504, error_json("request timeout")),
+		Ok(Err(e)) if e.starts_with("TabNotFound:") => (404, error_json(&e)),
+		Ok(Err(e)) => (500, error_json(&e)),
+		Ok(Ok(body)) => (200, body),
+	}
+}
+
+//在存储运行时上异步执行整理，不等待整理完成，立即返回202，整理结果只记录日志
+//整理可能远超请求的超时时长，同时只允许执行一个管理接口触发的整理，已有整理在执行时返回409
+fn start_collect() -> (u16, String) {
+	let rt = match block_on(STORE_RUNTIME.read()).as_ref().cloned() {
+		None => return (503, error_json("store runtime not ready")),
+		Some(rt) => rt,
+	};
+	if ADMIN_COLLECTING.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_err() {
+		return (409, error_json("collect already running"));
+	}
+
+	if let Err(e) = rt.spawn(rt.alloc(), async move {
+		match LogFileDB::collect().await {
+			Err(e) => warn!("admin collect failed, reason: {}", e),
+			Ok(_) => info!("admin collect finished"),
+		}
+		ADMIN_COLLECTING.store(false, Ordering::Release);
+	}) {
+		ADMIN_COLLECTING.store(false, Ordering::Release);
+		return (500, error_json(&format!("{:?}", e)));
+	}
+
+	(202, "{\"ok\":true,\"accepted\":true}".to_string())
+}
+
+//比较请求携带的令牌，比较时间只与令牌长度有关，不会因为前缀匹配而提前返回
+fn token_eq(a: &str, b: &str) -> bool {
+	a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+//获取查询字符串中指定参数的值
+fn query_param(query: &str, name: &str) -> Option<String> {
+	for pair in query.split('&') {
+		let mut kv = pair.splitn(2, '=');
+		if kv.next() == Some(name) {
+			return kv.next().map(percent_decode);
+		}
+	}
+
+	None
+}
+
+//解码查询参数中的百分号编码
+fn percent_decode(s: &str) -> String {
+	let bytes = s.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'%' if i + 2 < bytes.len() => {
+				match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+					None => out.push(b'%'),
+					Some(b) => {
+						out.push(b);
+						i += 2;
+					},
+				}
+			},
+			b'+' => out.push(b' '),
+			b => out.push(b),
+		}
+		i += 1;
+	}
+
+	String::from_utf8_lossy(&out).into_owned()
+}
+
+fn ok_json() -> String {
+	"{\"ok\":true}".to_string()
+}
+
+fn error_json(reason: &str) -> String {
+	let mut escaped = String::with_capacity(reason.len());
+	for c in reason.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+
+	format!("{{\"ok\":false,\"error\":\"{}\"}}", escaped)
+}
\ No newline at end of file
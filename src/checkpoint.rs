@@ -0,0 +1,252 @@
+use std::{env, fs, path::PathBuf, sync::Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use atom::Atom;
+use hash::XHashSet;
+use bon::{Decode, Encode, ReadBuffer, WriteBuffer, ReadBonErr};
+use r#async::rt::multi_thread::MultiTaskRuntime;
+use pi_store::log_store::log_file::{read_log_paths, LogMethod};
+
+use crate::db::{Bin, SResult};
+use crate::fork::ALL_TABLES;
+use crate::log_file_db::{LogFileDB, read_log_records_from, read_format_version, decode_flagged_value, blob_path, run_blocking};
+
+/*
+* 内存索引检查点文件的扩展名
+*/
+const CHECKPOINT_EXT: &'static str = "ckpt";
+
+/*
+* 日志位置，由日志文件表最新的日志文件id和它的长度确定
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LogPosition {
+	pub log_id: usize,	//最新的日志文件id
+	pub len: usize,		//最新的日志文件的长度
+}
+
+/*
+* 内存索引检查点
+*/
+#[derive(Debug, Default, Clone)]
+pub struct IndexCheckpoint {
+	pub position: LogPosition,				//检查点对应的日志位置
+	pub entries: Vec<(Bin, Bin)>,			//索引的所有键值对
+	pub replay: Vec<(Bin, Option<Bin>)>,	//加载时读取的检查点之后需要重放的写入和删除，每个主键只有最新的一条，不写入检查点文件
+}
+
+/*
+* 定时检查点任务的句柄，停止后已安排的检查点不再执行
+*/
+#[derive(Clone)]
+pub struct CheckpointTask(Arc<AtomicBool>);
+
+impl CheckpointTask {
+	//停止定时检查点
+	pub fn stop(&self) {
+		self.0.store(false, Ordering::SeqCst);
+	}
+
+	//判断定时检查点是否在运行
+	pub fn is_running(&self) -> bool {
+		self.0.load(Ordering::SeqCst)
+	}
+}
+
+impl Encode for IndexCheckpoint {
+	fn encode(&self, bb: &mut WriteBuffer) {
+		self.position.log_id.encode(bb);
+		self.position.len.encode(bb);
+		self.entries.len().encode(bb);
+		for (key, value) in self.entries.iter() {
+			bb.write_bin(key.as_ref(), 0..key.len());
+			bb.write_bin(value.as_ref(), 0..value.len());
+		}
+	}
+}
+
+impl Decode for IndexCheckpoint {
+	fn decode(bb: &mut ReadBuffer) -> Result<Self, ReadBonErr> {
+		let log_id = usize::decode(bb)?;
+		let len = usize::decode(bb)?;
+		let count = usize::decode(bb)?;
+		let mut entries = Vec::with_capacity(count);
+		for _ in 0..count {
+			let key = bb.read_bin()?;
+			let value = bb.read_bin()?;
+			entries.push((Arc::new(key.to_vec()), Arc::new(value.to_vec())));
+		}
+
+		Ok(IndexCheckpoint {
+			position: LogPosition { log_id, len },
+			entries,
+			replay: Vec::new(),
+		})
+	}
+}
+
+//获取指定日志文件表当前的日志位置
+pub async fn log_position(tab: &Atom) -> SResult<LogPosition> {
+	let file = LogFileDB::open(tab).await?;
+	let paths = match read_log_paths(&file.1.log_file).await {
+		Err(e) => return Err(format!("read log paths failed, tab: {}, reason: {:?}", tab.as_str(), e)),
+		Ok(paths) => paths,
+	};
+
+	let path = match paths.last() {
+		None => return Ok(LogPosition::default()),
+		Some(path) => path,
+	};
+	let log_id = match path.file_name().and_then(|name| name.to_str()).and_then(|name| name.parse::<usize>().ok()) {
+		None => return Err(format!("invalid log file name, tab: {}, path: {:?}", tab.as_str(), path)),
+		Some(id) => id,
+	};
+	let meta_path = path.clone();
+	let len = match run_blocking(move || fs::metadata(meta_path)).await? {
+		Err(e) => return Err(format!("read log file metadata failed, tab: {}, path: {:?}, reason: {:?}", tab.as_str(), path, e)),
+		Ok(meta) => meta.len() as usize,
+	};
+
+	Ok(LogPosition { log_id, len })
+}
+
+//获取指定表的指定索引的检查点文件路径，检查点文件与表的日志文件目录同级，避免被当作日志文件
+fn checkpoint_path(tab: &Atom, name: &str) -> PathBuf {
+	let mut path = PathBuf::new();
+	path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+	path.push(format!("{}.{}.{}", tab.as_str(), name, CHECKPOINT_EXT));
+	path
+}
+
+//将指定表的指定内存索引写入检查点，并返回检查点对应的日志位置
+//获取索引快照的前后日志位置不一致，说明期间有新的写入，则放弃本次检查点
+pub async fn write_checkpoint<F>(tab: &Atom, name: &str, snapshot: &F) -> SResult<LogPosition>
+	where F: Fn() -> Vec<(Bin, Bin)> {
+	let position = log_position(tab).await?;
+	let entries = snapshot();
+	if log_position(tab).await? != position {
+		return Err(format!("checkpoint conflicted, tab: {}, index: {}", tab.as_str(), name));
+	}
+
+	let mut wb = WriteBuffer::new();
+	IndexCheckpoint { position, entries, replay: Vec::new() }.encode(&mut wb);
+
+	//先写入临时文件再替换，避免写入中断破坏已有的检查点，文件操作不在运行时的工作线程上执行
+	let path = checkpoint_path(tab, name);
+	let tmp_path = path.with_extension("tmp");
+	let bytes = wb.bytes;
+	if let Err(e) = run_blocking(move || fs::write(&tmp_path, &bytes).and_then(|_| fs::rename(&tmp_path, &path))).await? {
+		return Err(format!("write checkpoint failed, tab: {}, index: {}, reason: {:?}", tab.as_str(), name, e));
+	}
+
+	Ok(position)
+}
+
+//加载指定表的指定内存索引的检查点，并读取检查点之后需要重放的写入和删除
+//只重放检查点之后的记录，检查点所在的日志文件从检查点的长度开始重放，之后的日志文件全部重放
+//检查点不存在、无法解析，或检查点所在的日志文件已被整理或截断时返回None，由调用者从日志完整重建索引
+pub async fn load_checkpoint(tab: &Atom, name: &str) -> SResult<Option<IndexCheckpoint>> {
+	let path = checkpoint_path(tab, name);
+	let bin = match run_blocking(move || fs::read(&path)).await? {
+		Err(_) => return Ok(None),
+		Ok(bin) => bin,
+	};
+
+	let mut checkpoint = match IndexCheckpoint::decode(&mut ReadBuffer::new(&bin, 0)) {
+		Err(e) => {
+			warn!("decode checkpoint failed, rebuild index, tab: {}, index: {}, reason: {:?}", tab.as_str(), name, e);
+			return Ok(None);
+		},
+		Ok(checkpoint) => checkpoint,
+	};
+	if checkpoint.position == LogPosition::default() {
+		return Ok(None);
+	}
+
+	let file = LogFileDB::open(tab).await?;
+	let paths = match read_log_paths(&file.1.log_file).await {
+		Err(e) => return Err(format!("read log paths failed, tab: {}, reason: {:?}", tab.as_str(), e)),
+		Ok(paths) => paths,
+	};
+	let mut logs = Vec::new();
+	let mut matched = false;
+	for path in paths {
+		let id = match path.file_name().and_then(|name| name.to_str()).and_then(|name| name.parse::<usize>().ok()) {
+			None => return Err(format!("invalid log file name, tab: {}, path: {:?}", tab.as_str(), path)),
+			Some(id) => id,
+		};
+		if id < checkpoint.position.log_id {
+			continue;
+		}
+		if id == checkpoint.position.log_id {
+			let meta_path = path.clone();
+			matched = match run_blocking(move || fs::metadata(meta_path)).await? {
+				Err(_) => false,
+				Ok(meta) => meta.len() as usize >= checkpoint.position.len,
+			};
+		}
+		logs.push((id, path));
+	}
+	if !matched {
+		info!("checkpoint mismatch, rebuild index, tab: {}, index: {}, checkpoint: {:?}", tab.as_str(), name, checkpoint.position);
+		return Ok(None);
+	}
+	logs.sort_by_key(|(id, _)| *id);
+
+	//从最新的日志文件开始从新到旧读取，每个主键只重放最先读到的最新记录
+	let mut tab_path = PathBuf::new();
+	tab_path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+	tab_path.push(tab.to_string());
+	let has_flags = read_format_version(&tab_path)? >= 3;
+	let blob_dir = blob_path(&tab_path);
+	let block_size = ALL_TABLES.get(tab).await.map(|tm| tm.config.clone()).unwrap_or_default().load_block_size();
+	let mut seen = XHashSet::default();
+	for (id, log_path) in logs.into_iter().rev() {
+		let from = if id == checkpoint.position.log_id {
+			checkpoint.position.len
+		} else {
+			0
+		};
+		for (method, key, value) in read_log_records_from(log_path, block_size, from).await? {
+			if !seen.insert(key.clone()) {
+				continue;
+			}
+			let value = match (method, value) {
+				(LogMethod::Remove, _) | (_, None) => None,
+				(_, Some(value)) if has_flags => Some(decode_flagged_value(&blob_dir, value).await?.1),
+				(_, Some(value)) => Some(value),
+			};
+			checkpoint.replay.push((Arc::new(key), value.map(Arc::new)));
+		}
+	}
+
+	Ok(Some(checkpoint))
+}
+
+//定时将指定表的指定内存索引写入检查点，interval为间隔时间，单位毫秒，返回用于停止定时检查点的句柄
+pub fn checkpoint_index<F>(rt: MultiTaskRuntime<()>, tab: Atom, name: String, interval: usize, snapshot: Arc<F>) -> CheckpointTask
+	where F: Fn() -> Vec<(Bin, Bin)> + Send + Sync + 'static {
+	let task = CheckpointTask(Arc::new(AtomicBool::new(true)));
+	schedule_checkpoint(rt, tab, name, interval, snapshot, task.clone());
+	task
+}
+
+//在指定运行时上定时写入检查点，每次写入完成后再安排下一次，停止后不再安排
+fn schedule_checkpoint<F>(rt: MultiTaskRuntime<()>, tab: Atom, name: String, interval: usize, snapshot: Arc<F>, task: CheckpointTask)
+	where F: Fn() -> Vec<(Bin, Bin)> + Send + Sync + 'static {
+	let next = rt.clone();
+	let running = task.clone();
+	if let Err(e) = rt.spawn_timing(rt.alloc(), async move {
+		if !task.is_running() {
+			return;
+		}
+
+		if let Err(e) = write_checkpoint(&tab, &name, snapshot.as_ref()).await {
+			error!("checkpoint index failed, reason: {}", e);
+		}
+		schedule_checkpoint(next, tab, name, interval, snapshot, task);
+	}, interval) {
+		running.stop();
+		error!("schedule checkpoint index failed, reason: {:?}", e);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,74 @@
+//! 数据库使用的时钟，所有写入元信息或记录的时间戳都通过当前时钟获取
+//! 默认使用系统时钟，测试可以注入手动时钟，使依赖时间的行为不依赖真实的等待
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use r#async::lock::spin_lock::SpinLock;
+
+lazy_static! {
+	//当前时钟
+	static ref CLOCK: SpinLock<Arc<dyn Clock>> = SpinLock::new(Arc::new(SystemClock));
+}
+
+/*
+* 时钟，返回从UNIX纪元开始的毫秒数
+*/
+pub trait Clock: Send + Sync {
+	fn now_millis(&self) -> u64;
+}
+
+/*
+* 系统时钟
+*/
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now_millis(&self) -> u64 {
+		SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+	}
+}
+
+/*
+* 手动时钟，只在设置或推进时改变
+*/
+pub struct ManualClock(AtomicU64);
+
+impl Clock for ManualClock {
+	fn now_millis(&self) -> u64 {
+		self.0.load(Ordering::SeqCst)
+	}
+}
+
+impl ManualClock {
+	pub fn new(now: u64) -> Self {
+		ManualClock(AtomicU64::new(now))
+	}
+
+	//设置当前时间，单位毫秒
+	pub fn set(&self, now: u64) {
+		self.0.store(now, Ordering::SeqCst);
+	}
+
+	//推进指定的毫秒数，返回推进后的时间
+	pub fn advance(&self, millis: u64) -> u64 {
+		self.0.fetch_add(millis, Ordering::SeqCst) + millis
+	}
+}
+
+//替换当前时钟，之后获取的时间戳都来自指定的时钟
+pub fn set_clock(clock: Arc<dyn Clock>) {
+	*CLOCK.lock() = clock;
+}
+
+//恢复使用系统时钟
+pub fn reset_clock() {
+	set_clock(Arc::new(SystemClock));
+}
+
+//获取当前时钟的时间，单位毫秒
+pub fn now_millis() -> u64 {
+	let clock = CLOCK.lock().clone();
+	clock.now_millis()
+}
\ No newline at end of file
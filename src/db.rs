@@ -31,7 +31,12 @@ pub type DBResult = SResult<()>;
 /*
 * 事务提交结果，Bin表示提交成功的主键的二进制，RwLog表示事务的操作日志
 */
-pub type CommitResult = SResult<XHashMap<Bin, RwLog>>;
+pub type CommitResult = SResult<RwLogMap>;
+
+/*
+* 事务的操作日志表，Bin表示主键的二进制
+*/
+pub type RwLogMap = XHashMap<Bin, RwLog>;
 
 /*
 * 表的记录迭代器
@@ -48,9 +53,9 @@ pub type NextResult<T> = SResult<Option<T>>;
 pub type TxCallback = Arc<dyn Fn(DBResult)>;
 pub type TxQueryCallback = Arc<dyn Fn(SResult<Vec<TabKV>>)>;
 
-// 这个类型定义了，但从未使用，没实现Send， 不能在多线程运行时使用
-// pub type Filter = Option<Arc<dyn Fn(Bin)-> Option<Bin>>>;
-pub type Filter = Option<bool>;
+// 迭代过滤器，参数是迭代到的记录的主键和值，返回true的记录被返回，返回false的记录被跳过，None表示不过滤
+// 主键迭代器只迭代主键，值的参数为None；过滤只跳过记录，不改变迭代的起始主键和方向，没有匹配的记录则迭代直接结束
+pub type Filter = Option<Arc<dyn Fn(&Bin, Option<&Bin>) -> bool + Send + Sync>>;
 
 /**
 * 表的元信息
@@ -60,12 +65,13 @@ pub type Filter = Option<bool>;
 #[derive(Debug, Clone)]
 pub struct TabMeta {
 	pub k: EnumType,	//表的主键类型
-	pub v: EnumType		//表的值类型
+	pub v: EnumType,	//表的值类型
+	pub indexes: Vec<Atom>,	//表声明的二级索引名，索引键的提取函数由存储按索引名注册，不参与表元信息的序列化
 }
 
 impl PartialEq for TabMeta {
 	fn eq(&self, other: &Self) -> bool {
-		if self.k == other.k && self.v == other.v {
+		if self.k == other.k && self.v == other.v && self.indexes == other.indexes {
 			true
 		} else {
 			false
@@ -80,7 +86,8 @@ impl Default for TabMeta {
 	fn default() -> Self {
 		TabMeta {
 			k: EnumType::Str,
-			v: EnumType::Str
+			v: EnumType::Str,
+			indexes: Vec::new(),
 		}
 	}
 }
@@ -88,14 +95,22 @@ impl Default for TabMeta {
 impl TabMeta {
 	//构建表元信息
 	pub fn new(k: EnumType, v: EnumType) -> TabMeta{
-		TabMeta{k, v}
+		TabMeta{k, v, indexes: Vec::new()}
+	}
+
+	//声明表的二级索引，已声明的同名索引不重复声明
+	pub fn with_index(mut self, name: Atom) -> TabMeta {
+		if !self.indexes.contains(&name) {
+			self.indexes.push(name);
+		}
+		self
 	}
 }
 
 impl Decode for TabMeta{
 	//解码已序列化的表元信息，并返回表元信息
 	fn decode(bb: &mut ReadBuffer) -> Result<Self, ReadBonErr>{
-		Ok(TabMeta{k: EnumType::decode(bb)?, v: EnumType::decode(bb)?})
+		Ok(TabMeta{k: EnumType::decode(bb)?, v: EnumType::decode(bb)?, indexes: Vec::new()})
 	}
 }
 
@@ -113,7 +128,9 @@ pub struct Event {
 	pub seq: u64,
 	pub ware: Atom,
 	pub tab: Atom,
-	pub other: EventType
+	pub other: EventType,
+	// 产生事件的事务的链路追踪id
+	pub trace_id: Option<Atom>,
 }
 
 #[derive(Debug)]
@@ -209,11 +226,112 @@ impl TabKV {
 	}
 }
 
+/*
+* 定长位集，按位保存布尔值，用于大量主键的批量存在性检查
+*/
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct BitSet {
+	words: Vec<u64>,	//按64位分组保存的位
+	len: usize,			//位的数量
+}
+
+impl BitSet {
+	//创建指定位数量的位集，所有位为false
+	pub fn new(len: usize) -> Self {
+		BitSet {
+			words: vec![0; (len + 63) / 64],
+			len,
+		}
+	}
+
+	//位的数量
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	//设置指定位，超出范围则忽略
+	pub fn set(&mut self, index: usize, value: bool) {
+		if index >= self.len {
+			return;
+		}
+
+		if value {
+			self.words[index / 64] |= 1u64 << (index % 64);
+		} else {
+			self.words[index / 64] &= !(1u64 << (index % 64));
+		}
+	}
+
+	//获取指定位，超出范围则返回false
+	pub fn get(&self, index: usize) -> bool {
+		index < self.len && self.words[index / 64] & (1u64 << (index % 64)) != 0
+	}
+
+	//为true的位的数量
+	pub fn count_ones(&self) -> usize {
+		self.words.iter().map(|word| word.count_ones() as usize).sum()
+	}
+}
+
+/*
+* 迭代器，next返回Some(Ok(Some(item)))表示下一个元素，Some(Ok(None))或None表示迭代结束
+* 底层读取失败时，只返回一次Some(Err(reason))，之后总是返回None，调用者收到错误后应停止迭代
+*/
 pub trait Iter {
 	type Item;
 	fn next(&mut self) -> Option<NextResult<Self::Item>>;
 }
 
+impl<I: Iter + ?Sized> Iter for Box<I> {
+	type Item = I::Item;
+	fn next(&mut self) -> Option<NextResult<Self::Item>> {
+		(**self).next()
+	}
+}
+
+/*
+* 出错后终止的迭代器，保证底层迭代器返回错误后，只返回一次错误，之后总是返回None
+*/
+pub struct FuseOnErr<I: Iter> {
+	iter: I,
+	terminated: bool,	//是否已返回过错误
+}
+
+impl<I: Iter> FuseOnErr<I> {
+	pub fn new(iter: I) -> Self {
+		FuseOnErr {
+			iter,
+			terminated: false,
+		}
+	}
+
+	//判断迭代器是否已因错误终止
+	pub fn is_terminated(&self) -> bool {
+		self.terminated
+	}
+}
+
+impl<I: Iter> Iter for FuseOnErr<I> {
+	type Item = I::Item;
+	fn next(&mut self) -> Option<NextResult<Self::Item>> {
+		if self.terminated {
+			return None;
+		}
+
+		match self.iter.next() {
+			Some(Err(e)) => {
+				self.terminated = true;
+				Some(Err(e))
+			},
+			r => r,
+		}
+	}
+}
+
 /*
 * 预提交和提交时使用的操作日志
 */
@@ -222,9 +340,44 @@ pub enum RwLog {
 	Read,				//读操作
 	Write(Option<Bin>),	//写操作，为None则表示删除，否则主键不存在则为插入，主键存在则为更新
 	Meta(Option<Bin>),	//运行时创建、修改或删除表操作，为None表示删除，否则表名不存在则为创建，表名存在则为修改
+	Increment(i64),		//计数器的累加操作，只记录增量，提交时累加到当前值上，并发的累加操作之间不冲突
+}
+
+/*
+* 获取已提交事务的操作日志中所有被写入或删除的主键，按主键排序
+*/
+pub fn committed_keys(log: &RwLogMap) -> Vec<Bin> {
+	filter_committed(log, |_| true)
+}
+
+/*
+* 获取已提交事务的操作日志中所有被删除的主键，按主键排序
+*/
+pub fn committed_deletes(log: &RwLogMap) -> Vec<Bin> {
+	filter_committed(log, |value| value.is_none())
+}
+
+/*
+* 获取已提交事务的操作日志中所有被插入或更新的主键，按主键排序
+*/
+pub fn committed_upserts(log: &RwLogMap) -> Vec<Bin> {
+	filter_committed(log, |value| value.is_some())
+}
+
+//过滤操作日志中的写操作，忽略读操作和表操作
+fn filter_committed<F: Fn(&Option<Bin>) -> bool>(log: &RwLogMap, f: F) -> Vec<Bin> {
+	let mut keys: Vec<Bin> = log.iter().filter_map(|(key, rwlog)| {
+		match rwlog {
+			RwLog::Write(value) if f(value) => Some(key.clone()),
+			_ => None,
+		}
+	}).collect();
+	keys.sort();
+	keys
 }
 
 //为了按照Bon协议比较字节数组， 定义了类型Bon
+//内存表的主键顺序只由Bon顺序决定，与日志文件中记录的顺序和加载时键值缓冲的字节顺序无关，所以表在重新加载前后的迭代顺序完全一致
 #[derive(Default, Clone, Hash)]
 pub struct Bon(Arc<Vec<u8>>);
 
@@ -265,6 +418,7 @@ impl Ord for Bon{
 }
 
 //创建表事务的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuildDbType {
 	MemoryDB,	//内存表事务
 	LogFileDB,	//日志文件表事务
@@ -11,7 +11,7 @@ use r#async::rt::{AsyncRuntime, AsyncValue};
 use r#async::lock::spin_lock::SpinLock;
 use chrono::prelude::*;
 
-use crate::log_file_db::{AsyncLogFileStore, DB_META_TAB_NAME, LogFileDB, LogFileTab};
+use crate::log_file_db::{AsyncLogFileStore, DB_META_TAB_NAME, LogFileDB, LogFileTab, COMPACTION_IO_LIMITER};
 
 lazy_static! {
 	// 最近一次整理的时间戳
@@ -29,8 +29,13 @@ pub fn collect_db(rt: MultiTaskRuntime<()>, start_at: u32) {
 			let map = meta.1.map.lock();
 			for (key, _) in map.iter() {
 				let tab_name = Atom::decode(&mut ReadBuffer::new(key, 0)).unwrap();
-				let mut file = LogFileDB::open(&tab_name).await.unwrap();
+				//已删除的表不再整理
+				let mut file = match LogFileDB::open(&tab_name).await {
+					Err(_) => continue,
+					Ok(file) => file,
+				};
 				info!("collect tab {:?} ", tab_name);
+				let _permit = COMPACTION_IO_LIMITER.acquire().await;
 				file.1.log_file.collect(1024 * 1024, 32 * 1024, false).await;
 			}
 			let end = Local::now();
@@ -1,16 +1,93 @@
 use std::{collections::HashMap, sync::Arc};
+use std::ops::DerefMut;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use atom::Atom;
 use bon::{Encode, Decode, WriteBuffer, ReadBuffer, ReadBonErr};
 use r#async::lock::mutex_lock::Mutex;
+use r#async::lock::spin_lock::SpinLock;
 
 use crate::db::TabMeta;
+use crate::clock;
+use crate::log_file_db::{LOG_FILE_SIZE, BlobPolicy};
 
 /*
  用于缓存分叉元信息的所有日志文件表的元信息映射表，Atom表示日志文件表的表名
 */
 lazy_static! {
-	pub static ref ALL_TABLES: Arc<Mutex<HashMap<Atom, TableMetaInfo>>> = Arc::new(Mutex::new(HashMap::new()));
+	pub static ref ALL_TABLES: Arc<TableCatalog> = Arc::new(TableCatalog::new());
+}
+
+/// 所有表的元信息目录，元信息可以立即注册，也可以延迟注册
+/// 延迟注册的元信息只记录在待注册表中，不获取目录的锁，查询或打开表时只注册访问的表和它的分叉链，
+/// 只有需要整个目录的操作才一次注册所有延迟注册的元信息
+pub struct TableCatalog {
+	map: Mutex<HashMap<Atom, TableMetaInfo>>,	//已注册的元信息
+	pending: SpinLock<HashMap<Atom, TableMetaInfo>>,	//延迟注册的元信息
+	locks: AtomicUsize,	//获取目录的锁的次数
+}
+
+impl TableCatalog {
+	/// 构建一个空的元信息目录
+	pub fn new() -> Self {
+		TableCatalog {
+			map: Mutex::new(HashMap::new()),
+			pending: SpinLock::new(HashMap::new()),
+			locks: AtomicUsize::new(0),
+		}
+	}
+
+	/// 获取整个目录的锁，有延迟注册的元信息则先全部注册，延迟注册的元信息覆盖同名的已注册元信息
+	/// 只查询一个表的元信息时使用get，不会注册其它表的元信息
+	pub async fn lock(&self) -> impl DerefMut<Target = HashMap<Atom, TableMetaInfo>> + '_ {
+		self.locks.fetch_add(1, Ordering::Relaxed);
+		let mut map = self.map.lock().await;
+		let pending = std::mem::replace(&mut *self.pending.lock(), HashMap::new());
+		map.extend(pending);
+		map
+	}
+
+	/// 获取指定表的元信息，表的元信息是延迟注册的则只注册这个表
+	pub async fn get(&self, tab: &Atom) -> Option<TableMetaInfo> {
+		self.locks.fetch_add(1, Ordering::Relaxed);
+		let mut map = self.map.lock().await;
+		register_pending(&mut map, &self.pending, tab);
+		map.get(tab).cloned()
+	}
+
+	/// 获取从指定表作为叶节点开始的整个分叉链上所有表的元信息向量，只注册分叉链上延迟注册的表
+	pub async fn fork_chain(&self, tab: &Atom) -> Result<Vec<TableMetaInfo>, String> {
+		self.locks.fetch_add(1, Ordering::Relaxed);
+		let mut map = self.map.lock().await;
+		let mut current = Some(tab.clone());
+		while let Some(name) = current {
+			register_pending(&mut map, &self.pending, &name);
+			current = map.get(&name).and_then(|info| info.parent.clone());
+		}
+		fork_chain_from(&map, tab)
+	}
+
+	/// 延迟注册元信息，不获取目录的锁
+	pub fn register_lazy(&self, metas: HashMap<Atom, TableMetaInfo>) {
+		self.pending.lock().extend(metas);
+	}
+
+	/// 获取延迟注册还未注册的元信息数量
+	pub fn pending_len(&self) -> usize {
+		self.pending.lock().len()
+	}
+
+	/// 获取运行时获取目录的锁的次数，用于诊断锁竞争
+	pub fn locks(&self) -> usize {
+		self.locks.load(Ordering::Relaxed)
+	}
+}
+
+//在持有目录的锁时注册指定表延迟注册的元信息
+fn register_pending(map: &mut HashMap<Atom, TableMetaInfo>, pending: &SpinLock<HashMap<Atom, TableMetaInfo>>, tab: &Atom) {
+	if let Some(info) = pending.lock().remove(tab) {
+		map.insert(tab.clone(), info);
+	}
 }
 
 /// TODO: 被分叉表和分叉表之间的字段转换， 可以用一个转换函数来描述
@@ -35,17 +112,197 @@ pub struct TableMetaInfo {
 	pub parent_log_id: Option<usize>,
 	/// 表的引用计数， 产生一个分叉则引用计数加1， 删除一个叶节点表，父表引用计数减1， 引用计数为0才可以安全删除这个表
 	pub ref_count: usize,
+	/// 表的配置，覆盖数据库的默认配置
+	pub config: TabConfig,
+	/// 表的元信息的历史版本，只追加，最后一个版本与meta一致
+	pub history: Vec<SchemaVersion>,
+	/// 分叉表的分叉模式，非分叉表为默认的冻结模式
+	pub mode: ForkMode,
+}
+
+/// 分叉表的分叉模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkMode {
+	/// 冻结模式，分叉表只看到父表在分叉点的数据，父表在分叉后的写入对分叉表不可见
+	Frozen,
+	/// 实时模式，分叉表持续看到父表的写入，分叉表自己写入或删除过的主键以分叉表为准，实时分叉表不允许再分叉
+	Live,
+}
+
+impl Default for ForkMode {
+	fn default() -> Self {
+		ForkMode::Frozen
+	}
+}
+
+/// 表的元信息的一个历史版本
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SchemaVersion {
+	/// 版本号，从1开始递增
+	pub version: usize,
+	/// 该版本的key, value 类型
+	pub meta: TabMeta,
+	/// 该版本生效的时间，单位毫秒，未知为0
+	pub timestamp: usize,
+}
+
+impl Encode for SchemaVersion {
+	fn encode(&self, bb: &mut WriteBuffer) {
+		self.version.encode(bb);
+		self.meta.encode(bb);
+		self.timestamp.encode(bb);
+	}
+}
+
+impl Decode for SchemaVersion {
+	fn decode(bb: &mut ReadBuffer) -> Result<Self, ReadBonErr> {
+		Ok(Self {
+			version: usize::decode(bb)?,
+			meta: TabMeta::decode(bb)?,
+			timestamp: usize::decode(bb)?,
+		})
+	}
+}
+
+/// 表级配置，为None的配置项使用数据库的默认配置
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TabConfig {
+	/// 日志文件大小，单位MB，默认使用LOG_FILE_SIZE
+	pub log_file_size: Option<usize>,
+	/// 是否压缩日志文件，默认不压缩，当前日志文件暂不支持压缩，设置为压缩时修改配置失败
+	pub compression: Option<bool>,
+	/// 持久化级别，当前日志文件暂只支持延迟提交，设置后修改配置失败
+	pub durability: Option<usize>,
+	/// 加载时每次读取的日志块大小，单位字节，默认32KB
+	pub load_block_size: Option<usize>,
+	/// 加载时只重放最新的指定数量的日志文件，更早的日志文件在需要时再加载，默认重放全部日志文件，只对没有父表的表有效
+	/// 有未加载的日志文件时，点读按需读取未加载的主键，iter和key_iter返回TabCold错误，需要先调用load_cold
+	pub replay_logs: Option<usize>,
+	/// 值的内联上限，单位字节，超过的值外置存储，默认所有值都内联存储，只对日志格式版本3及以上的表有效
+	pub blob_inline_max: Option<usize>,
+	/// 热点值的最小大小，单位字节，默认为内联上限的一半
+	pub blob_hot_size: Option<usize>,
+	/// 热点值在两次整理之间的更新次数，达到后外置存储，默认不按更新次数外置存储
+	pub blob_hot_updates: Option<usize>,
+	/// 是否是只插入的日志表，日志表不维护内存表，提交只追加日志，只能顺序扫描，在表下次打开时生效，只对没有父表的表有效
+	/// 已有记录的表不能设置为日志表
+	pub log_sink: Option<bool>,
+	/// 计数器的重置周期，单位毫秒，设置后表的累加操作按周期计数，每个周期从0开始，默认不重置
+	/// 周期按时钟对齐，读取时周期已过则计数为0，不需要清理过期的计数器，修改后立即生效
+	pub counter_window: Option<u64>,
+}
+
+impl TabConfig {
+	/// 获取日志文件大小，单位字节
+	pub fn log_file_size(&self) -> usize {
+		self.log_file_size.unwrap_or(LOG_FILE_SIZE.load(Ordering::Relaxed)) * 1024 * 1024
+	}
+
+	/// 获取加载时每次读取的日志块大小，单位字节
+	pub fn load_block_size(&self) -> usize {
+		self.load_block_size.unwrap_or(32 * 1024)
+	}
+
+	/// 获取加载时重放的日志文件数量，0表示重放全部日志文件
+	pub fn replay_logs(&self) -> usize {
+		self.replay_logs.unwrap_or(0)
+	}
+
+	/// 是否是只插入的日志表
+	pub fn is_log_sink(&self) -> bool {
+		self.log_sink.unwrap_or(false)
+	}
+
+	/// 检查配置项是否都已支持，写入路径未实现的配置项不允许设置，避免保存了不生效的配置
+	pub fn check_supported(&self, tab_name: &Atom) -> Result<(), String> {
+		if self.compression == Some(true) {
+			return Err(format!("UnsupportedConfig: tab: {}, config: compression", tab_name.as_str()));
+		}
+		if let Some(level) = self.durability {
+			return Err(format!("UnsupportedConfig: tab: {}, config: durability, level: {}", tab_name.as_str(), level));
+		}
+
+		Ok(())
+	}
+
+	/// 获取值的外置存储策略
+	pub fn blob_policy(&self) -> BlobPolicy {
+		BlobPolicy {
+			inline_max: self.blob_inline_max,
+			hot_size: self.blob_hot_size.unwrap_or(self.blob_inline_max.unwrap_or(0) / 2),
+			hot_updates: self.blob_hot_updates.unwrap_or(0),
+		}
+	}
+}
+
+impl Encode for TabConfig {
+	fn encode(&self, bb: &mut WriteBuffer) {
+		self.log_file_size.encode(bb);
+		self.compression.map(|c| c as usize).encode(bb);
+		self.durability.encode(bb);
+		self.load_block_size.encode(bb);
+		self.replay_logs.encode(bb);
+		self.blob_inline_max.encode(bb);
+		self.blob_hot_size.encode(bb);
+		self.blob_hot_updates.encode(bb);
+		self.log_sink.map(|s| s as usize).encode(bb);
+		self.counter_window.encode(bb);
+	}
+}
+
+impl Decode for TabConfig {
+	fn decode(bb: &mut ReadBuffer) -> Result<Self, ReadBonErr> {
+		Ok(Self {
+			log_file_size: Option::decode(bb)?,
+			compression: Option::<usize>::decode(bb)?.map(|c| c != 0),
+			durability: Option::decode(bb)?,
+			load_block_size: Option::decode(bb)?,
+			//兼容没有重放限制的旧配置
+			replay_logs: Option::decode(bb).unwrap_or(None),
+			//兼容没有外置存储策略的旧配置
+			blob_inline_max: Option::decode(bb).unwrap_or(None),
+			blob_hot_size: Option::decode(bb).unwrap_or(None),
+			blob_hot_updates: Option::decode(bb).unwrap_or(None),
+			//兼容没有日志表配置的旧配置
+			log_sink: Option::<usize>::decode(bb).unwrap_or(None).map(|s| s != 0),
+			//兼容没有计数器重置周期的旧配置
+			counter_window: Option::decode(bb).unwrap_or(None),
+		})
+	}
 }
 
 impl TableMetaInfo {
 	pub fn new(tab_name: Atom, meta: TabMeta) -> Self {
+		let history = vec![SchemaVersion {
+			version: 1,
+			meta: meta.clone(),
+			timestamp: now_millis(),
+		}];
+
 		Self {
 			tab_name,
 			meta,
+			history,
 			.. TableMetaInfo::default()
 		}
 	}
 
+	/// 修改表的元信息，并追加一个新的历史版本
+	pub fn evolve(&mut self, meta: TabMeta) {
+		let version = self.history.last().map(|v| v.version).unwrap_or(0) + 1;
+		self.history.push(SchemaVersion {
+			version,
+			meta: meta.clone(),
+			timestamp: now_millis(),
+		});
+		self.meta = meta;
+	}
+
+	/// 获取表的当前元信息的版本号
+	pub fn schema_version(&self) -> usize {
+		self.history.last().map(|v| v.version).unwrap_or(0)
+	}
+
 
 	/// 增加表的引用计数
 	pub fn inc_refcount(&mut self) {
@@ -87,6 +344,24 @@ impl Encode for TableMetaInfo {
 		let mut bin5 = WriteBuffer::new();
 		self.ref_count.encode(&mut bin5);
 		bb.write_bin(bin5.bytes.as_ref(), 0..bin5.bytes.len());
+		let mut bin6 = WriteBuffer::new();
+		self.config.encode(&mut bin6);
+		bb.write_bin(bin6.bytes.as_ref(), 0..bin6.bytes.len());
+		let mut bin7 = WriteBuffer::new();
+		self.history.len().encode(&mut bin7);
+		for version in self.history.iter() {
+			version.encode(&mut bin7);
+		}
+		bb.write_bin(bin7.bytes.as_ref(), 0..bin7.bytes.len());
+		let mut bin8 = WriteBuffer::new();
+		((self.mode == ForkMode::Live) as usize).encode(&mut bin8);
+		bb.write_bin(bin8.bytes.as_ref(), 0..bin8.bytes.len());
+		let mut bin9 = WriteBuffer::new();
+		self.meta.indexes.len().encode(&mut bin9);
+		for index in self.meta.indexes.iter() {
+			index.encode(&mut bin9);
+		}
+		bb.write_bin(bin9.bytes.as_ref(), 0..bin9.bytes.len());
 	}
 }
 
@@ -96,38 +371,88 @@ impl Decode for TableMetaInfo {
 		let b = bb.read_bin()?;
 		let tab_name = Atom::decode(&mut ReadBuffer::new(&b, 0))?;
 		let bin1 = bb.read_bin()?;
-		let meta = TabMeta::decode(&mut ReadBuffer::new(&bin1, 0))?;
+		let mut meta = TabMeta::decode(&mut ReadBuffer::new(&bin1, 0))?;
 		let bin2 = bb.read_bin()?;
 		let parent = Option::decode(&mut ReadBuffer::new(&bin2, 0))?;
 		let bin4 = bb.read_bin()?;
 		let parent_log_id = Option::decode(&mut ReadBuffer::new(&bin4, 0))?;
 		let bin5 = bb.read_bin()?;
 		let ref_count = usize::decode(&mut ReadBuffer::new(&bin5, 0))?;
+		//兼容没有表配置的旧元信息
+		let config = match bb.read_bin() {
+			Ok(bin6) => TabConfig::decode(&mut ReadBuffer::new(&bin6, 0))?,
+			Err(_) => TabConfig::default(),
+		};
+		//兼容没有历史版本的旧元信息，将当前元信息作为第一个版本
+		let history = match bb.read_bin() {
+			Ok(bin7) => {
+				let mut rb = ReadBuffer::new(&bin7, 0);
+				let len = usize::decode(&mut rb)?;
+				let mut history = Vec::with_capacity(len);
+				for _ in 0..len {
+					history.push(SchemaVersion::decode(&mut rb)?);
+				}
+				history
+			},
+			Err(_) => vec![SchemaVersion {
+				version: 1,
+				meta: meta.clone(),
+				timestamp: 0,
+			}],
+		};
+		//兼容没有分叉模式的旧元信息，旧的分叉表都是冻结模式
+		let mode = match bb.read_bin() {
+			Ok(bin8) if usize::decode(&mut ReadBuffer::new(&bin8, 0))? != 0 => ForkMode::Live,
+			_ => ForkMode::Frozen,
+		};
+		//兼容没有二级索引声明的旧元信息
+		if let Ok(bin9) = bb.read_bin() {
+			let mut rb = ReadBuffer::new(&bin9, 0);
+			let len = usize::decode(&mut rb)?;
+			for _ in 0..len {
+				meta.indexes.push(Atom::decode(&mut rb)?);
+			}
+		}
 
 		Ok(Self {
 			tab_name,
 			meta,
 			parent,
 			parent_log_id,
-			ref_count
+			ref_count,
+			config,
+			history,
+			mode,
 		})
 	}
 }
 
+/// 获取当前时钟的时间，单位毫秒
+fn now_millis() -> usize {
+	clock::now_millis() as usize
+}
+
 /// 获取从指定表作为叶节点开始的整个分叉链上所有表的元信息向量
-pub async fn build_fork_chain(tab_name: Atom) -> Vec<TableMetaInfo> {
+pub async fn build_fork_chain(tab_name: Atom) -> Result<Vec<TableMetaInfo>, String> {
+	ALL_TABLES.fork_chain(&tab_name).await
+}
+
+/// 从指定的元信息映射表中获取从指定表作为叶节点开始的整个分叉链上所有表的元信息向量，不获取ALL_TABLES的锁
+/// 分叉链上有父表的元信息不存在，例如父表的元信息记录在加载时被跳过，则返回ParentUnavailable错误
+pub fn fork_chain_from(tables: &HashMap<Atom, TableMetaInfo>, tab_name: &Atom) -> Result<Vec<TableMetaInfo>, String> {
 	let mut chains = vec![];
-	let lock = ALL_TABLES.lock().await;
-	if let Some(mut tab_info) = lock.get(&tab_name) {
+	if let Some(mut tab_info) = tables.get(tab_name) {
 		chains.push(tab_info.clone());
-		while tab_info.parent.is_some() {
-			tab_info = lock.get(&tab_info.parent.clone().unwrap()).unwrap();
-			let t = tab_info.clone();
-			chains.push(t);
+		while let Some(parent) = &tab_info.parent {
+			tab_info = match tables.get(parent) {
+				None => return Err(format!("ParentUnavailable: tab: {}, parent: {}, reason: parent meta not found", tab_info.tab_name.as_str(), parent.as_str())),
+				Some(info) => info,
+			};
+			chains.push(tab_info.clone());
 		}
 	}
 
-	chains
+	Ok(chains)
 }
 
 /// 根据分叉路径，加载数据
@@ -146,6 +471,17 @@ mod tests {
 			parent: Some(Atom::from("world")),
 			parent_log_id: Some(1),
 			ref_count: 0,
+			config: TabConfig {
+				log_file_size: Some(1),
+				compression: Some(true),
+				..TabConfig::default()
+			},
+			history: vec![SchemaVersion {
+				version: 1,
+				meta: TabMeta::new(EnumType::Str, EnumType::Str),
+				timestamp: 0,
+			}],
+			mode: ForkMode::Live,
 		};
 
 		let mut bin = WriteBuffer::new();
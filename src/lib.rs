@@ -12,4 +12,12 @@ pub mod tabs;
 pub mod memery_db;
 pub mod log_file_db;
 pub mod fork;
-pub mod db_collect;
\ No newline at end of file
+pub mod db_collect;
+pub mod checkpoint;
+pub mod replica;
+pub mod shard;
+pub mod clock;
+#[cfg(feature = "admin")]
+pub mod admin;
+
+pub use mgr::{with_db, DbConfig};
\ No newline at end of file
@@ -1,11 +1,15 @@
 use std::sync::{Arc, atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}};
 use std::mem;
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::time::Instant;
-use std::collections::{VecDeque, BTreeMap, LinkedList};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, VecDeque, BTreeMap, LinkedList};
 use std::env;
-use std::io::{Error, Result, ErrorKind};
+use std::convert::TryInto;
+use std::future::Future;
+use std::io::{Error, Result, ErrorKind, Write};
+use std::thread;
 
 use ordmap::ordmap::{OrdMap, Entry, Iter as OIter, Keys};
 use ordmap::asbtree::Tree;
@@ -18,14 +22,20 @@ use pi_store::log_store::log_file::{read_log_paths, read_log_file, read_log_file
 use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
 use r#async::rt::{AsyncRuntime, AsyncValue};
 use r#async::lock::spin_lock::SpinLock;
-use async_file::file::{AsyncFile, AsyncFileOptions};
+use async_file::file::{AsyncFile, AsyncFileOptions, WriteOptions};
 use num_cpus;
+use futures::future::{select, Either};
+use futures::channel::oneshot;
 
-use crate::db::{Bin, TabKV, SResult, IterResult, KeyIterResult, NextResult, Event, Filter, TxState, Iter, RwLog, Bon, TabMeta, CommitResult, DBResult};
+use crate::db::{Bin, TabKV, SResult, IterResult, KeyIterResult, NextResult, Event, Filter, TxState, Iter, FuseOnErr, RwLog, RwLogMap, Bon, BitSet, TabMeta, CommitResult, DBResult};
 use crate::tabs::{TabLog, Tabs, Prepare};
 use crate::db::BuildDbType;
 use crate::tabs::TxnType;
-use crate::fork::{ALL_TABLES, TableMetaInfo, build_fork_chain};
+use crate::fork::{ALL_TABLES, TableMetaInfo, TabConfig, SchemaVersion, ForkMode, build_fork_chain, fork_chain_from};
+use crate::checkpoint::{LogPosition, log_position};
+use crate::replica::ReadonlyDB;
+use crate::clock::{self, Clock};
+use crate::shard::shard_name;
 use bon::{Decode, Encode, ReadBuffer, WriteBuffer};
 
 lazy_static! {
@@ -35,9 +45,683 @@ lazy_static! {
 	static ref LOG_FILE_TABS: Arc<RwLock<XHashMap<Atom, LogFileTab>>> = Arc::new(RwLock::new(XHashMap::default()));
 	pub static ref LOG_FILE_SIZE: AtomicUsize = AtomicUsize::new(200);
 	pub static ref LOG_FILE_TOTAL_SIZE: Arc<AtomicU64> = Arc::new(AtomicU64::new(0));
+	//日志文件表事务操作的超时时间，单位毫秒，0表示不限制
+	pub static ref OP_TIMEOUT: AtomicUsize = AtomicUsize::new(0);
+	//加载元信息表时遇到无法解析的记录是否立即中止，默认跳过并记录在健康报告中
+	pub static ref STRICT_META_LOAD: AtomicBool = AtomicBool::new(false);
+	//事务待写入记录的溢出限制，单位字节，超过限制后待写入的值会溢出到临时文件，事务内存中只保留值在文件中的位置，0表示不溢出
+	//溢出后的点读、批量读、存在性检查和记录数量包含已溢出的写入，按范围遍历的iter、key_iter和index不支持，返回TxnSpilled错误
+	pub static ref TXN_SPILL_SIZE: AtomicUsize = AtomicUsize::new(0);
+	//最大分叉深度，0表示不限制
+	pub static ref MAX_FORK_DEPTH: AtomicUsize = AtomicUsize::new(0);
+	//按删除记录整理时选择只读日志文件的删除比例限制，单位百分比
+	pub static ref TOMBSTONE_COLLECT_RATIO: AtomicUsize = AtomicUsize::new(50);
+	//同时执行整理文件I/O的最大日志文件表数量，0表示不限制
+	pub static ref COMPACTION_IO_CONCURRENCY: AtomicUsize = AtomicUsize::new(0);
+	//是否启用内存表锁的公平模式，启用后预提交、提交和回滚按到达顺序获取内存表锁
+	pub static ref FAIR_TAB_LOCK: AtomicBool = AtomicBool::new(false);
+	//是否为新的可写日志文件预分配空间，预分配不改变日志文件的大小
+	pub static ref PREALLOCATE_LOG_FILE: AtomicBool = AtomicBool::new(false);
+	//加载时是否校验主键顺序，用于尽早发现主键比较或编码的错误
+	pub static ref VERIFY_KEY_ORDER: AtomicBool = AtomicBool::new(false);
+	//加载时是否校验只读日志文件，并隔离无法读取或校验失败的只读日志文件，避免局部损坏导致整个表无法加载
+	pub static ref QUARANTINE_CORRUPT_LOGS: AtomicBool = AtomicBool::new(false);
+	//每个主键在内存中保留的历史版本数量，用于按提交序号读取历史值，0表示不保留
+	pub static ref AS_OF_HISTORY: AtomicUsize = AtomicUsize::new(0);
+	//每个表在内存中保留历史版本的主键数量上限，超过后淘汰最早保留历史版本的主键，0表示不限制
+	pub static ref AS_OF_HISTORY_KEYS: AtomicUsize = AtomicUsize::new(10000);
+	//每个表记录日志位置的最近提交数量，内存中没有的历史值按提交时的日志位置扫描日志文件读取，更早的提交不可读
+	pub static ref AS_OF_SCAN_COMMITS: AtomicUsize = AtomicUsize::new(1024);
+	//每个表已预提交但未提交的事务的最大数量，0表示不限制
+	pub static ref MAX_PREPARED_TXNS: AtomicUsize = AtomicUsize::new(0);
+	//每个表已预提交但未提交的事务的操作日志的最大大小，单位字节，0表示不限制
+	pub static ref MAX_PREPARED_SIZE: AtomicUsize = AtomicUsize::new(0);
+	//等待日志持久化的提交的最大数量，超过后新的提交等待已有的提交持久化完成，0表示不限制
+	pub static ref MAX_INFLIGHT_WRITES: AtomicUsize = AtomicUsize::new(0);
+	//日志文件表加载大小的高水位，单位字节，超过后按最近最少使用的顺序释放表的内存，0表示不限制
+	pub static ref MEMORY_HIGH_WATER: AtomicU64 = AtomicU64::new(0);
+	//日志文件表加载大小的低水位，单位字节，超过高水位后释放表的内存，直到低于低水位
+	pub static ref MEMORY_LOW_WATER: AtomicU64 = AtomicU64::new(0);
+	//分裂可写日志文件后，是否在接受新的写入前同步新日志文件和日志目录，保证崩溃恢复时分裂已持久化
+	//日志文件在写满后由存储在追加内部自动分裂，提交后检查到自动分裂时，在内存表锁内同步，之后的提交才能追加
+	pub static ref SPLIT_FSYNC: AtomicBool = AtomicBool::new(false);
+	//加载分叉表时父表的日志文件不可用，是否只加载分叉表自己的数据，默认返回ParentUnavailable错误
+	pub static ref LENIENT_PARENT_LOAD: AtomicBool = AtomicBool::new(false);
+	//并发查询时同时读取的最大数量，0或1表示逐个读取
+	pub static ref QUERY_PARALLELISM: AtomicUsize = AtomicUsize::new(8);
+	//加载分叉表时同时加载的父表的最大数量，0或1表示按分叉链的顺序逐个加载
+	pub static ref ANCESTOR_LOAD_CONCURRENCY: AtomicUsize = AtomicUsize::new(4);
+	//访问热度的采样间隔，每个表每N次读写采样一次，0表示不采样
+	pub static ref ACCESS_SAMPLE_RATE: AtomicUsize = AtomicUsize::new(0);
+	//访问热度按主键二进制的前缀分桶，前缀的字节数
+	pub static ref ACCESS_PREFIX_LEN: AtomicUsize = AtomicUsize::new(1);
+	//访问热度每个表最多记录的主键前缀数量，达到后记录新的前缀时淘汰采样次数最少的前缀
+	pub static ref ACCESS_HEATMAP_BUCKETS: AtomicUsize = AtomicUsize::new(1024);
+	//提交的日志持久化后，是否将表的提交水位写入表目录旁的水位文件，用于重新打开后检查已确认的提交是否丢失
+	pub static ref DURABLE_WATERMARK: AtomicBool = AtomicBool::new(false);
+	//整理时是否按表的主键范围重写有效记录，每个范围的记录写入独立的日志文件，只对设置了范围边界且没有父表和分叉的表有效
+	pub static ref RANGE_GROUPED_COLLECT: AtomicBool = AtomicBool::new(false);
+	//表的只读日志文件统计信息的告警长度，加载或整理后统计信息超过该长度则记录警告，提示表需要整理，0表示不告警
+	pub static ref STATISTICS_ALERT_LEN: AtomicUsize = AtomicUsize::new(1024);
+	//内存压力的后台检查是否正在运行
+	static ref MEMORY_SHRINK_RUNNING: AtomicBool = AtomicBool::new(false);
+	//运行时打开元信息表日志文件的次数
+	static ref META_STORE_OPENS: AtomicUsize = AtomicUsize::new(0);
+	//分裂后同步日志目录的次数
+	static ref SPLIT_SYNCS: AtomicUsize = AtomicUsize::new(0);
+	//聚合写入的次数
+	static ref VECTORED_WRITES: AtomicUsize = AtomicUsize::new(0);
+	//从外置存储文件读取的字节数
+	static ref BLOB_READ_BYTES: AtomicUsize = AtomicUsize::new(0);
+	//是否可能有中断的表交换，打开数据库时设置，交换完成或恢复后清除
+	static ref SWAP_PENDING: AtomicBool = AtomicBool::new(true);
+	//表交换和交换恢复的互斥锁
+	static ref SWAP_LOCK: Mutex<()> = Mutex::new(());
+	//最近一次打开数据库时获取ALL_TABLES锁的次数
+	static ref STARTUP_CATALOG_LOCKS: AtomicUsize = AtomicUsize::new(0);
+	//打开数据库时是否延迟注册表元信息，延迟注册时不获取ALL_TABLES的锁，表的元信息在第一次查询或打开表时注册
+	pub static ref LAZY_CATALOG: AtomicBool = AtomicBool::new(false);
+	//表的访问时钟，每次创建事务时递增，用于比较表最近访问的先后
+	static ref ACCESS_CLOCK: AtomicU64 = AtomicU64::new(0);
+	//整理文件I/O的并发限制器
+	pub(crate) static ref COMPACTION_IO_LIMITER: IoLimiter = IoLimiter {
+		limit: &COMPACTION_IO_CONCURRENCY,
+		running: AtomicUsize::new(0),
+		peak: AtomicUsize::new(0),
+		waiters: SpinLock::new(VecDeque::new()),
+		idle: SpinLock::new(Vec::new()),
+	};
+	//并发查询正在读取溢出日志的数量
+	static ref PARALLEL_READS: AtomicUsize = AtomicUsize::new(0);
+	//并发查询同时读取溢出日志的最大数量
+	static ref PARALLEL_READ_PEAK: AtomicUsize = AtomicUsize::new(0);
+	//等待日志持久化的提交的限制器
+	static ref INFLIGHT_WRITE_LIMITER: IoLimiter = IoLimiter {
+		limit: &MAX_INFLIGHT_WRITES,
+		running: AtomicUsize::new(0),
+		peak: AtomicUsize::new(0),
+		waiters: SpinLock::new(VecDeque::new()),
+		idle: SpinLock::new(Vec::new()),
+	};
+	//溢出日志文件的序号
+	static ref SPILL_ID: AtomicUsize = AtomicUsize::new(0);
+	//已溢出的写入在事务操作日志中的值标记，真实的值在溢出日志中
+	static ref SPILLED_VALUE: Bin = Arc::new(Vec::new());
+	//日志文件表事务的全局提交序号，每次提交递增，只在进程内有效
+	static ref COMMIT_SEQ: AtomicU64 = AtomicU64::new(0);
+	//日志文件数据库的健康报告
+	static ref HEALTH_REPORT: Arc<SpinLock<HealthReport>> = Arc::new(SpinLock::new(HealthReport::default()));
+	//日志文件表的运行时计数器表
+	static ref TAB_COUNTERS: Arc<SpinLock<XHashMap<Atom, Arc<TabCounters>>>> = Arc::new(SpinLock::new(XHashMap::default()));
+	//日志文件表的主键转换函数表
+	static ref KEY_TRANSFORMS: Arc<SpinLock<XHashMap<Atom, KeyTransform>>> = Arc::new(SpinLock::new(XHashMap::default()));
+	//日志文件表的提交校验函数表
+	static ref COMMIT_VALIDATORS: Arc<SpinLock<XHashMap<Atom, CommitValidator>>> = Arc::new(SpinLock::new(XHashMap::default()));
+	//父表的已加载的实时分叉表，父表提交后将写入同步到这些分叉表，已从缓存中移除的分叉表在同步时清理
+	static ref LIVE_FORKS: Arc<SpinLock<XHashMap<Atom, XHashSet<Atom>>>> = Arc::new(SpinLock::new(XHashMap::default()));
+	//日志文件表声明的二级索引的提取函数表，键为表名和索引名
+	static ref INDEX_EXTRACTORS: Arc<SpinLock<XHashMap<(Atom, Atom), IndexExtractor>>> = Arc::new(SpinLock::new(XHashMap::default()));
 }
 
+/*
+* 主键转换函数，将主键的二进制转换为规范化的主键的二进制，例如忽略大小写
+*/
+pub type KeyTransform = Arc<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync>;
+
+/*
+* 二级索引函数，从记录的主键和值中提取索引键，返回None表示记录不加入索引
+*/
+pub type IndexExtractor = Arc<dyn Fn(&[u8], &[u8]) -> Option<Vec<u8>> + Send + Sync>;
+
+/*
+* 提交校验函数，在提交前检查事务的全部操作日志，返回错误则拒绝提交
+*/
+pub type CommitValidator = Arc<dyn Fn(&RwLogMap) -> Result<(), String> + Send + Sync>;
+
 pub const DB_META_TAB_NAME: &'static str = "tabs_meta";
+//当前支持的日志格式版本，记录在表的格式文件中
+pub const LOG_FORMAT_VERSION: u8 = 3;
+//写入记录带标志字节的最低日志格式版本
+const FLAGS_FORMAT_VERSION: u8 = 3;
+//写入记录的标志字节的最高位，表示记录的值是外置存储的值的id，主键的标志不能使用这一位
+pub const BLOB_FLAG: u8 = 0x80;
+//溢出日志的写缓冲大小
+const SPILL_BUF_SIZE: usize = 64 * 1024;
+//外置存储目录中持久化id上限的文件名
+const BLOB_NEXT_FILE: &'static str = "next";
+//外置存储每次持久化id上限时预留的id数量
+const BLOB_ID_BATCH: u64 = 1024;
+//批量有序查询向前遍历到下一个主键的最大记录数量，超过则从内存表中重新定位到下一个主键
+const MULTI_GET_SEEK_STEPS: usize = 8;
+
+//在配置的超时时间内执行指定的异步操作，超时则返回OperationTimeout错误
+//超时会在任意等待点取消操作，所以只用于取消后不会留下部分修改的操作，例如只读操作和获取锁，预提交、提交和回滚只对获取锁限时
+pub async fn with_op_timeout<T, F>(op: &str, future: F) -> SResult<T>
+	where F: Future<Output = SResult<T>> {
+	with_timeout(op, OP_TIMEOUT.load(Ordering::Relaxed), future).await
+}
+
+//在指定的超时时间内执行指定的异步操作，超时时间为0或存储运行时未初始化则不限制
+pub async fn with_timeout<T, F>(op: &str, timeout: usize, future: F) -> SResult<T>
+	where F: Future<Output = SResult<T>> {
+	if timeout == 0 {
+		return future.await;
+	}
+
+	let rt = match STORE_RUNTIME.read().await.as_ref() {
+		None => return future.await,
+		Some(rt) => rt.clone(),
+	};
+
+	//使用存储运行时的定时任务通知超时，操作先完成则取消定时任务
+	let (sender, receiver) = oneshot::channel();
+	let timer = match rt.spawn_timing(rt.alloc(), async move {
+		let _ = sender.send(());
+	}, timeout) {
+		Err(e) => {
+			warn!("spawn op timer failed, op: {}, reason: {:?}", op, e);
+			return future.await;
+		},
+		Ok(timer) => timer,
+	};
+
+	match select(Box::pin(future), receiver).await {
+		Either::Left((r, _)) => {
+			rt.cancel_timing(timer);
+			r
+		},
+		Either::Right((Ok(_), _)) => Err(format!("OperationTimeout: op: {}, timeout: {}ms", op, timeout)),
+		Either::Right((Err(_), future)) => future.await, //定时任务被取消，则继续等待操作完成
+	}
+}
+
+/**
+* 文件I/O的并发限制器，用于限制整理和等待持久化的提交，超过并发限制的I/O按先后顺序等待
+*/
+pub(crate) struct IoLimiter {
+	limit: &'static AtomicUsize,						//并发限制的配置，0表示不限制
+	running: AtomicUsize,								//正在执行的I/O数量
+	peak: AtomicUsize,									//同时执行的I/O数量的峰值
+	waiters: SpinLock<VecDeque<oneshot::Sender<()>>>,	//等待执行的I/O
+	idle: SpinLock<Vec<oneshot::Sender<()>>>,			//等待全部I/O完成的任务
+}
+
+impl IoLimiter {
+	//获取执行I/O的许可，许可释放时唤醒下一个等待的I/O
+	pub(crate) async fn acquire(&'static self) -> IoPermit {
+		loop {
+			let limit = self.limit.load(Ordering::Relaxed);
+			let running = self.running.load(Ordering::SeqCst);
+			if limit == 0 || running < limit {
+				if self.running.compare_exchange(running, running + 1, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+					self.peak.fetch_max(running + 1, Ordering::Relaxed);
+					return IoPermit(self);
+				}
+				continue;
+			}
+
+			let (sender, receiver) = oneshot::channel();
+			self.waiters.lock().push_back(sender);
+			if self.running.load(Ordering::SeqCst) < limit {
+				//加入等待前已有I/O完成，则立即重试
+				continue;
+			}
+			let _ = receiver.await;
+		}
+	}
+
+	//等待正在执行的I/O全部完成，最后一个I/O的许可释放时唤醒
+	pub(crate) async fn wait_idle(&'static self) {
+		loop {
+			if self.running.load(Ordering::SeqCst) == 0 {
+				return;
+			}
+
+			let (sender, receiver) = oneshot::channel();
+			self.idle.lock().push(sender);
+			if self.running.load(Ordering::SeqCst) == 0 {
+				//加入等待前I/O已全部完成，则立即返回
+				return;
+			}
+			let _ = receiver.await;
+		}
+	}
+}
+
+/**
+* 文件I/O的许可
+*/
+pub(crate) struct IoPermit(&'static IoLimiter);
+
+impl Drop for IoPermit {
+	fn drop(&mut self) {
+		if self.0.running.fetch_sub(1, Ordering::SeqCst) == 1 {
+			//I/O已全部完成，唤醒所有等待全部I/O完成的任务
+			for sender in mem::replace(&mut *self.0.idle.lock(), Vec::new()) {
+				let _ = sender.send(());
+			}
+		}
+		//唤醒下一个仍在等待的I/O
+		while let Some(sender) = self.0.waiters.lock().pop_front() {
+			if sender.send(()).is_ok() {
+				break;
+			}
+		}
+	}
+}
+
+/**
+* 内存表锁的公平队列，公平模式下获取内存表锁前需要先按到达顺序获取许可
+*/
+struct FairGate(SpinLock<(bool, VecDeque<oneshot::Sender<()>>)>); //是否已被占用和等待中的事务
+
+impl FairGate {
+	fn new() -> Self {
+		FairGate(SpinLock::new((false, VecDeque::new())))
+	}
+
+	//按到达顺序获取许可，许可释放时直接移交给下一个等待的事务
+	async fn acquire(self: &Arc<Self>) -> FairPermit {
+		let receiver = {
+			let mut state = self.0.lock();
+			if !state.0 {
+				state.0 = true;
+				return FairPermit(self.clone());
+			}
+
+			let (sender, receiver) = oneshot::channel();
+			state.1.push_back(sender);
+			receiver
+		};
+
+		let mut wait = FairWait(Some(receiver), self.clone());
+		let _ = wait.0.as_mut().unwrap().await;
+		wait.0 = None;
+		FairPermit(self.clone())
+	}
+
+	//释放许可，移交给下一个仍在等待的事务，没有等待的事务则解除占用
+	fn release(&self) {
+		let mut state = self.0.lock();
+		while let Some(sender) = state.1.pop_front() {
+			if sender.send(()).is_ok() {
+				return;
+			}
+		}
+		state.0 = false;
+	}
+}
+
+/**
+* 内存表锁的公平许可
+*/
+struct FairPermit(Arc<FairGate>);
+
+impl Drop for FairPermit {
+	fn drop(&mut self) {
+		self.0.release();
+	}
+}
+
+/**
+* 等待中的公平许可，等待被取消时如果许可已移交，则继续移交，避免许可丢失
+*/
+struct FairWait(Option<oneshot::Receiver<()>>, Arc<FairGate>);
+
+impl Drop for FairWait {
+	fn drop(&mut self) {
+		if let Some(mut receiver) = self.0.take() {
+			receiver.close();
+			if let Ok(Some(_)) = receiver.try_recv() {
+				self.1.release();
+			}
+		}
+	}
+}
+
+/**
+* 内存表锁的守护，先释放内存表锁，再释放公平许可
+*/
+struct TabGuard<G> {
+	guard: G,
+	_permit: Option<FairPermit>,
+}
+
+impl<G: DerefMut<Target = MemeryTab>> Deref for TabGuard<G> {
+	type Target = MemeryTab;
+	fn deref(&self) -> &MemeryTab {
+		&self.guard
+	}
+}
+
+impl<G: DerefMut<Target = MemeryTab>> DerefMut for TabGuard<G> {
+	fn deref_mut(&mut self) -> &mut MemeryTab {
+		&mut self.guard
+	}
+}
+
+/**
+* 日志文件表的运行时计数器
+*/
+#[derive(Debug, Default)]
+struct TabCounters {
+	splits: AtomicUsize,			//可写日志文件的分裂次数
+	lock_acquires: AtomicUsize,		//内存表锁的获取次数
+	lock_wait_total: AtomicU64,		//等待内存表锁的总时间，单位微秒
+	lock_wait_max: AtomicU64,		//等待内存表锁的最长时间，单位微秒
+	approx_size: AtomicUsize,		//近似的记录数量，加载时初始化，每次提交时按净增减的记录数量调整
+	active_txns: AtomicUsize,		//未完成的事务数量，包括提交后等待日志持久化的事务
+	last_access: AtomicU64,			//最近一次创建事务时的访问时钟
+	watermark_seq: AtomicU64,		//表最近一次分配的提交水位，从加载时恢复的水位开始递增
+	durable_seq: AtomicU64,			//表已持久化的最大提交水位
+	recovered_seq: AtomicU64,		//最近一次加载时从水位文件恢复的提交水位
+	watermark_file: WatermarkFile,	//水位文件的写入状态
+	split_limit: AtomicUsize,		//运行时修改的日志文件大小，单位字节，提交后可写日志文件超过该大小则分裂，0表示不检查
+	file_len: AtomicUsize,			//打开表时的日志文件大小，单位字节，可写日志文件超过该大小后由日志文件在追加时自动分裂
+	heatmap: AccessHeatmap,			//采样的访问热度
+	ranges: RangeStats,				//按主键范围统计的写入和删除次数
+	statistics_len: AtomicUsize,	//最近一次加载或整理后只读日志文件统计信息的长度
+	statistics_alerts: AtomicUsize,	//统计信息超过告警长度的次数
+	counter_window: AtomicU64,		//计数器的重置周期，单位毫秒，0表示不重置
+	fenced: AtomicBool,				//是否隔离表的写入，compact_to_new导出后到swap_tabs交换前隔离源表的写入
+	multi_get_steps: AtomicUsize,	//批量有序查询访问内存表记录和重新定位的次数
+}
+
+/*
+* 按主键范围统计的写入和删除次数，范围由升序的边界划分，n个边界划分出n+1个范围，边界属于它开始的范围
+*/
+struct RangeStats {
+	buckets: SpinLock<(Vec<Vec<u8>>, Vec<(u64, u64)>)>,	//范围边界，以及每个范围的写入次数和删除次数
+	pending: AtomicU64,									//上次按范围重写后的写入和删除次数
+}
+
+impl Default for RangeStats {
+	fn default() -> Self {
+		RangeStats {
+			buckets: SpinLock::new((Vec::new(), Vec::new())),
+			pending: AtomicU64::new(0),
+		}
+	}
+}
+
+/*
+* 表的水位文件的写入状态，同一时间只有一个写入，写入期间推进的水位合并到下一次写入
+*/
+struct WatermarkFile {
+	written: Mutex<u64>,	//已写入水位文件的提交水位，写入水位文件时持有
+	pending: AtomicBool,	//是否已安排异步写入水位文件
+}
+
+impl Default for WatermarkFile {
+	fn default() -> Self {
+		WatermarkFile {
+			written: Mutex::new(0),
+			pending: AtomicBool::new(false),
+		}
+	}
+}
+
+impl std::fmt::Debug for WatermarkFile {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "WatermarkFile {{ pending: {} }}", self.pending.load(Ordering::Relaxed))
+	}
+}
+
+impl std::fmt::Debug for RangeStats {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "RangeStats {{ ranges: {} }}", self.buckets.lock().1.len())
+	}
+}
+
+impl RangeStats {
+	//按提交的操作日志累计每个范围的写入和删除次数，没有设置范围边界则忽略
+	fn record(&self, rwlog: &RwLogMap) {
+		let mut buckets = self.buckets.lock();
+		let (boundaries, counts) = &mut *buckets;
+		if boundaries.is_empty() {
+			return;
+		}
+
+		for (k, rw_v) in rwlog.iter() {
+			match rw_v {
+				RwLog::Write(Some(_)) => counts[range_index(boundaries, k)].0 += 1,
+				RwLog::Write(None) => counts[range_index(boundaries, k)].1 += 1,
+				_ => continue,
+			}
+			self.pending.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+}
+
+//获取主键所在的范围
+fn range_index(boundaries: &[Vec<u8>], key: &[u8]) -> usize {
+	match boundaries.binary_search_by(|boundary| boundary.as_slice().cmp(key)) {
+		Ok(index) => index + 1,
+		Err(index) => index,
+	}
+}
+
+/*
+* 采样的访问热度，按主键前缀记录采样到的读次数和写次数
+*/
+struct AccessHeatmap {
+	ticks: AtomicUsize,									//读写次数，用于决定是否采样
+	buckets: SpinLock<XHashMap<Vec<u8>, (u64, u64)>>,	//主键前缀的采样读次数和写次数
+}
+
+impl Default for AccessHeatmap {
+	fn default() -> Self {
+		AccessHeatmap {
+			ticks: AtomicUsize::new(0),
+			buckets: SpinLock::new(XHashMap::default()),
+		}
+	}
+}
+
+impl std::fmt::Debug for AccessHeatmap {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "AccessHeatmap {{ buckets: {} }}", self.buckets.lock().len())
+	}
+}
+
+impl AccessHeatmap {
+	//按采样间隔采样一次读写，未开启采样时只读取一次配置
+	fn sample(&self, key: &[u8], write: bool) {
+		let rate = ACCESS_SAMPLE_RATE.load(Ordering::Relaxed);
+		if rate == 0 || self.ticks.fetch_add(1, Ordering::Relaxed) % rate != 0 {
+			return;
+		}
+
+		let prefix = key[..key.len().min(ACCESS_PREFIX_LEN.load(Ordering::Relaxed))].to_vec();
+		let mut buckets = self.buckets.lock();
+		if !buckets.contains_key(&prefix) {
+			//前缀数量达到上限，淘汰采样次数最少的前缀，限制每个表的热度占用的内存
+			let limit = ACCESS_HEATMAP_BUCKETS.load(Ordering::Relaxed).max(1);
+			while buckets.len() >= limit {
+				let coldest = match buckets.iter().min_by_key(|(_, (reads, writes))| reads + writes) {
+					None => break,
+					Some((coldest, _)) => coldest.clone(),
+				};
+				buckets.remove(&coldest);
+			}
+		}
+		let counts = buckets.entry(prefix).or_insert((0, 0));
+		if write {
+			counts.1 += 1;
+		} else {
+			counts.0 += 1;
+		}
+	}
+}
+
+/*
+* 未完成的事务的计数，事务提交、回滚或释放时减少
+*/
+struct ActiveTxn(Arc<TabCounters>);
+
+impl ActiveTxn {
+	fn new(counters: &Arc<TabCounters>) -> Self {
+		counters.active_txns.fetch_add(1, Ordering::SeqCst);
+		counters.last_access.store(ACCESS_CLOCK.fetch_add(1, Ordering::Relaxed) + 1, Ordering::Relaxed);
+		ActiveTxn(counters.clone())
+	}
+}
+
+impl Drop for ActiveTxn {
+	fn drop(&mut self) {
+		self.0.active_txns.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+//获取指定表的运行时计数器，不存在则创建
+fn tab_counters(tab_name: &Atom) -> Arc<TabCounters> {
+	TAB_COUNTERS.lock().entry(tab_name.clone()).or_insert_with(|| Arc::new(TabCounters::default())).clone()
+}
+
+/**
+* 日志文件表的统计信息
+*/
+#[derive(Debug, Default, Clone)]
+pub struct TabStats {
+	pub splits: usize,				//可写日志文件的分裂次数
+	pub lock_acquires: usize,		//预提交、提交和回滚时获取内存表锁的次数
+	pub lock_wait_total: Duration,	//等待内存表锁的总时间
+	pub lock_wait_max: Duration,	//等待内存表锁的最长时间
+	pub ranges: Vec<RangeStat>,		//按主键范围统计的写入和删除次数，没有设置范围边界则为空
+	pub statistics_len: usize,		//最近一次加载或整理后只读日志文件统计信息的长度
+	pub statistics_alerts: usize,	//统计信息超过告警长度的次数，大于0说明表的整理已滞后
+}
+
+/**
+* 日志文件表一个主键范围的统计信息
+*/
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RangeStat {
+	pub start: Option<Vec<u8>>,	//范围的起始边界，包括边界，None表示没有下限
+	pub end: Option<Vec<u8>>,	//范围的结束边界，不包括边界，None表示没有上限
+	pub writes: u64,			//范围内的写入次数
+	pub deletes: u64,			//范围内的删除次数
+}
+
+/**
+* 日志文件数据库健康报告
+*/
+#[derive(Debug, Default, Clone)]
+pub struct HealthReport {
+	pub skipped_meta: Vec<(Bin, String)>,	//加载时被跳过的无法解析的元信息记录的主键和原因
+	pub quarantined_logs: Vec<(PathBuf, String)>,	//加载时被隔离的损坏的只读日志文件的隔离路径和原因
+	pub new_database: bool,	//打开时元信息表不存在，创建了新的空数据库
+}
+
+/**
+* 日志文件数据库的预检结果
+*/
+#[derive(Debug, Default, Clone)]
+pub struct ValidationSummary {
+	pub tabs: Vec<TabValidation>,	//每个表的预检结果，按表名排序
+	pub elapsed: Duration,			//预检的总时间
+}
+
+impl ValidationSummary {
+	//是否所有表都通过预检
+	pub fn is_ok(&self) -> bool {
+		self.tabs.iter().all(|tab| tab.error.is_none())
+	}
+
+	//获取未通过预检的表和原因
+	pub fn failed(&self) -> Vec<(Atom, String)> {
+		self.tabs.iter().filter_map(|tab| tab.error.clone().map(|e| (tab.name.clone(), e))).collect()
+	}
+}
+
+/**
+* 日志文件表的预检结果
+*/
+#[derive(Debug, Clone)]
+pub struct TabValidation {
+	pub name: Atom,				//表名
+	pub log_files: usize,		//读取的日志文件数量
+	pub error: Option<String>,	//未通过预检的原因，通过则为None
+	pub elapsed: Duration,		//预检表的时间
+}
+
+/**
+* 日志持久化确认，提交返回后，等待提交的日志被写入日志文件
+*/
+pub struct DurabilityAck(oneshot::Receiver<SResult<()>>);
+
+impl DurabilityAck {
+	//等待日志持久化完成
+	pub async fn wait(self) -> SResult<()> {
+		match self.0.await {
+			Err(_) => Err(String::from("DurabilityFailed: ack canceled")),
+			Ok(r) => r,
+		}
+	}
+
+	//检查日志是否已持久化，未完成则返回None
+	pub fn try_wait(&mut self) -> Option<SResult<()>> {
+		match self.0.try_recv() {
+			Err(_) => Some(Err(String::from("DurabilityFailed: ack canceled"))),
+			Ok(r) => r,
+		}
+	}
+}
+
+/**
+* 日志文件数据库的统计信息
+*/
+#[derive(Debug, Default, Clone)]
+pub struct DbStats {
+	pub tabs: usize,		//日志文件表数量
+	pub total_size: u64,	//已加载的数据总大小，单位字节
+}
+
+/**
+* 日志文件表的概要信息，记录数量和加载大小只对已加载到内存的表有效
+*/
+#[derive(Debug, Clone)]
+pub struct TabSummary {
+	pub name: Atom,					//表名
+	pub key_count: Option<usize>,	//近似的记录数量，表未加载则为None
+	pub byte_size: Option<usize>,	//加载时的数据大小，单位字节，表未加载则为None
+	pub is_leaf: bool,				//是否是叶节点，即没有从表分叉出的子表
+	pub parent: Option<Atom>,		//父表
+	pub ref_count: usize,			//从表分叉出的子表数量
+}
+
+/**
+* 日志文件表的检查点结果
+*/
+#[derive(Debug, Default, Clone)]
+pub struct TabCheckpoint {
+	pub logs: Vec<PathBuf>,		//检查点后的只读日志文件，只包含有效的记录
+	pub position: LogPosition,	//检查点后的日志位置，即分裂后新的可写日志文件的位置
+}
+
+/**
+* 日志文件数据库整理报告
+*/
+#[derive(Debug, Default, Clone)]
+pub struct CollectReport {
+	pub tabs: usize,					//已整理的日志文件表数量
+	pub removed_logs: Vec<PathBuf>,		//没有有效关键字，被直接移除的只读日志文件
+	pub collected_logs: Vec<PathBuf>,	//被合并整理的只读日志文件
+	pub deleted_files: Vec<PathBuf>,	//整理完成后，已确认从磁盘上删除的日志文件
+	pub elapsed: Duration,				//整理耗时
+}
+
+/**
+* 日志文件表的一次整理记录，保存在表的整理历史文件中
+*/
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CompactionRecord {
+	pub time: u64,				//整理完成的时间，单位毫秒
+	pub before: u64,			//整理前表的日志文件总大小，单位字节
+	pub after: u64,				//整理后表的日志文件总大小，单位字节
+	pub removed_logs: usize,	//被直接移除的只读日志文件数量
+	pub collected_logs: usize,	//被合并整理的只读日志文件数量
+}
 
 /**
 * 基于LogFile的日志文件数据库
@@ -50,22 +734,48 @@ impl LogFileDB {
 	* 构建基于LogFile的日志文件数据库
 	* @param db_path 数据库路径
 	* @param db_size 数据库文件最大大小(暂未使用)
-	* @returns 返回基于LogFile的日志文件数据库
+	* @returns 返回基于LogFile的日志文件数据库，数据库目录不可用时panic，需要处理错误的调用者使用try_new
 	*/
-	pub async fn new(db_path: Atom, _db_size: usize) -> Self {
-		if !Path::new(&db_path.to_string()).exists() {
-			let _ = fs::create_dir(db_path.to_string());
+	pub async fn new(db_path: Atom, db_size: usize) -> Self {
+		match LogFileDB::try_new(db_path, db_size).await {
+			Err(e) => panic!("!!!!!!open log file db failed, {}", e),
+			Ok(db) => db,
 		}
+	}
+
+	//创建日志文件数据库，先检查数据库目录和DB_PATH目录，目录不存在则创建，
+	//目录无法创建、不可写或路径是文件时返回StorageUnavailable错误，避免之后打开日志文件时才失败
+	pub async fn try_new(db_path: Atom, _db_size: usize) -> SResult<Self> {
+		check_storage_dir(Path::new(db_path.as_str()))?;
 
 		// 从元信息表加载所有表元信息
 		let db_path = env::var("DB_PATH").unwrap_or("./".to_string());
+		check_storage_dir(Path::new(&db_path))?;
+		//加载表之前完成中断的表交换
+		SWAP_PENDING.store(true, Ordering::SeqCst);
+		LogFileDB::recover_swap().await?;
 		let mut path = PathBuf::new();
 		path.push(db_path.clone());
 		path.push(DB_META_TAB_NAME);
 
-		let file = match AsyncLogFileStore::open(path, 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, None).await {
+		//元信息表不存在则是新的数据库，显式创建元信息表的目录，并以空的表目录继续
+		//元信息表存在但无法打开，则是损坏的元信息表，返回CorruptMeta错误，不会当作新的数据库覆盖
+		let is_new = !path.exists();
+		if is_new {
+			if let Err(e) = fs::create_dir_all(&path) {
+				return Err(format!("StorageUnavailable: path: {:?}, reason: {:?}", path, e));
+			}
+			info!("create new log file db, meta tab: {:?}", path);
+		} else if !path.is_dir() {
+			error!("open meta tab failed, meta tab: {:?}, reason: not a directory", path);
+			return Err(format!("CorruptMeta: path: {:?}, reason: not a directory", path));
+		}
+
+		let file = match AsyncLogFileStore::open(path.clone(), 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, None).await {
+			Err(e) if is_new => return Err(format!("StorageUnavailable: path: {:?}, reason: {:?}", path, e)),
 			Err(e) => {
-				panic!("!!!!!!open table = {:?} failed, e: {:?}", "tabs_meta", e);
+				error!("open meta tab failed, meta tab: {:?}, reason: {:?}", path, e);
+				return Err(format!("CorruptMeta: path: {:?}, reason: {:?}", path, e));
 			},
 			Ok(store) => store
 		};
@@ -79,6 +789,12 @@ impl LogFileDB {
 			is_statistics: Arc::new(AtomicBool::new(false)),
 			is_init: Arc::new(AtomicBool::new(true)),
 			statistics: Arc::new(SpinLock::new(VecDeque::new())),
+			has_flags: Arc::new(AtomicBool::new(false)),
+			flags: Arc::new(SpinLock::new(XHashMap::default())),
+			blobs: Arc::new(BlobStore::new()),
+			unapplied: Arc::new(SpinLock::new(VecDeque::new())),
+			appended: Arc::new(AtomicUsize::new(0)),
+			records: Arc::new(AtomicU64::new(0)),
 		};
 
 		file.load(&mut store, None, 32 * 1024, true).await;
@@ -91,16 +807,50 @@ impl LogFileDB {
 		let mut async_map = rt.map();
 		let start = std::time::Instant::now();
 		let mut count = 0;
+		let mut skipped_meta = Vec::new();
+		let catalog_locks = ALL_TABLES.locks();
+		//先解析所有表的元信息，再一次性注册到ALL_TABLES，避免大量表启动时逐个获取ALL_TABLES的锁
+		let mut metas = HashMap::new();
 		for (k, v) in map.iter() {
-			let tab_name = Atom::decode(&mut ReadBuffer::new(k, 0)).unwrap();
-			let meta = TableMetaInfo::decode(&mut ReadBuffer::new(v.clone().to_vec().as_ref(), 0)).unwrap();
+			let (tab_name, meta) = match decode_meta_record(k, v) {
+				Ok(r) => r,
+				Err(e) => {
+					if STRICT_META_LOAD.load(Ordering::Relaxed) {
+						panic!("!!!!!!load table meta failed, key: {:?}, e: {:?}", k, e);
+					}
+
+					//跳过无法解析的元信息记录，不影响其它表的加载
+					error!("skip malformed table meta, key: {:?}, reason: {}", k, e);
+					skipped_meta.push((Arc::new(k.clone()), e));
+					continue;
+				},
+			};
 			tabs.set_tab_meta(tab_name.clone(), Arc::new(meta.meta.clone())).await;
-			ALL_TABLES.lock().await.insert(tab_name.clone(), meta);
+			metas.insert(tab_name, meta);
+		}
 
-			let chains = build_fork_chain(tab_name.clone()).await;
+		//分叉链在所有元信息解析后构建，不依赖元信息记录的顺序
+		//父表的元信息被跳过的表不加载，元信息仍然注册，打开时返回ParentUnavailable错误
+		let mut all_chains = Vec::with_capacity(metas.len());
+		for tab_name in metas.keys() {
+			match fork_chain_from(&metas, tab_name) {
+				Ok(chains) => all_chains.push((tab_name.clone(), chains)),
+				Err(e) => error!("skip load table, tab: {}, reason: {}", tab_name.as_str(), e),
+			}
+		}
+		if LAZY_CATALOG.load(Ordering::Relaxed) {
+			ALL_TABLES.register_lazy(metas);
+		} else {
+			ALL_TABLES.lock().await.extend(metas);
+		}
+
+		for (tab_name, chains) in all_chains {
 			async_map.join(AsyncRuntime::Multi(rt.clone()), async move {
 				//并发异步的通过指定表的名称和分叉链，初始化加载指定表
-				Ok((tab_name.clone(), LogFileTab::new(&tab_name, &chains).await))
+				match LogFileTab::new(&tab_name, &chains).await {
+					Ok(tab) => Ok((tab_name.clone(), tab)),
+					Err(e) => Err(Error::new(ErrorKind::Other, e)),
+				}
 			});
 		}
 
@@ -124,1234 +874,6721 @@ impl LogFileDB {
 			}
 		}
 
-		info!("total tabs: {:?}, time: {:?}, {} KB", count, start.elapsed(), format!("{0} {1:.2}", "total size", LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed) as f64 / 1024.0));
-
-		LogFileDB(Arc::new(tabs))
-	}
+		STARTUP_CATALOG_LOCKS.store(ALL_TABLES.locks() - catalog_locks, Ordering::Relaxed);
 
-	//打开指定名称的日志文件表
-	pub async fn open(tab: &Atom) -> SResult<LogFileTab> {
-		let chains = build_fork_chain(tab.clone()).await;
-		let mut lock = LOG_FILE_TABS.write().await;
-		match lock.get(tab) {
-			Some(t) => Ok(t.clone()),
-			None => {
-				let cache = LogFileTab::new(tab, &chains).await;
-				lock.insert(tab.clone(), cache.clone());
-				Ok(cache.clone())
-			}
+		{
+			let mut report = HEALTH_REPORT.lock();
+			report.skipped_meta = skipped_meta;
+			report.new_database = is_new;
 		}
-	}
 
+		info!("total tabs: {:?}, time: {:?}, {} KB", count, start.elapsed(), format!("{0} {1:.2}", "total size", LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed) as f64 / 1024.0));
 
-	//复制日志文件数据库的表管理器
-	pub async fn tabs_clone(&self) -> Arc<Self> {
-		Arc::new(LogFileDB(Arc::new(self.0.clone_map())))
+		Ok(LogFileDB(Arc::new(tabs)))
 	}
 
-	//列出全部的日志文件表
-	pub async fn list(&self) -> Box<dyn Iterator<Item=Atom>> {
-		Box::new(self.0.list().await)
+	//设置指定表的表级配置，与alter_tab_config相同
+	pub async fn set_tab_config(tab_name: &Atom, config: TabConfig) -> SResult<()> {
+		LogFileDB::alter_tab_config(tab_name, config).await
 	}
 
-	//获取该库对预提交后的处理超时时间, 事务会用最大超时时间来预提交
-	pub fn timeout(&self) -> usize {
-		TIMEOUT
+	//在一个元信息事务中修改指定表的表级配置，写入元信息表后再应用到已打开的表，写入失败则不修改任何配置
+	//外置存储策略立即生效；日志文件大小变小时，之后的提交发现可写日志文件超过新的大小就分裂，变大时在表下次打开或重新加载后生效
+	//加载块大小和重放日志文件数量在表下次打开或重新加载时生效，暂不支持压缩和持久化级别，设置时返回UnsupportedConfig错误
+	pub async fn alter_tab_config(tab_name: &Atom, config: TabConfig) -> SResult<()> {
+		let txn = LogFileMetaTxn::new();
+		txn.alter_config(tab_name, config).await?;
+		txn.commit().await.map(|_| ())
 	}
 
-	//获取指定表的元信息，tab_name表名，例如"db/user"
-	pub async fn tab_info(&self, tab_name: &Atom) -> Option<Arc<TabMeta>> {
-		self.0.get(tab_name).await
+	//导出所有表的分叉拓扑，按表名排序
+	pub async fn export_topology() -> Vec<TableMetaInfo> {
+		let mut infos: Vec<TableMetaInfo> = ALL_TABLES.lock().await.values().cloned().collect();
+		infos.sort_by(|a, b| a.tab_name.as_str().cmp(b.tab_name.as_str()));
+		infos
 	}
 
-	//获取当前日志文件数据库的快照
-	pub async fn snapshot(&self) -> Arc<LogFileDBSnapshot> {
-		Arc::new(LogFileDBSnapshot(self.clone(), Mutex::new(self.0.snapshot().await)))
-	}
+	//导入表的分叉拓扑，并写入元信息表，导入的表会覆盖同名表的元信息
+	//导入前检查所有父表都存在，且导入的表和它们的父表的引用计数与子表数量一致
+	pub async fn import_topology(infos: Vec<TableMetaInfo>) -> SResult<()> {
+		let mut lock = ALL_TABLES.lock().await;
+		let mut merged = lock.clone();
+		for info in infos.iter() {
+			merged.insert(info.tab_name.clone(), info.clone());
+		}
 
-	//强制所有日志文件表分裂
-	pub async fn force_split() -> SResult<()> {
-		let meta = LogFileDB::open(&Atom::from(DB_META_TAB_NAME)).await.unwrap();
-		let map = meta.1.map.lock().clone();
+		let mut checks = XHashSet::default();
+		for info in infos.iter() {
+			info.config.check_supported(&info.tab_name)?;
+			checks.insert(info.tab_name.clone());
+			if let Some(parent) = &info.parent {
+				if !merged.contains_key(parent) {
+					return Err(format!("InvalidTopology: parent not found, tab: {}, parent: {}", info.tab_name.as_str(), parent.as_str()));
+				}
+				if info.parent_log_id.is_none() {
+					return Err(format!("InvalidTopology: parent_log_id not found, tab: {}", info.tab_name.as_str()));
+				}
+				checks.insert(parent.clone());
+			}
+		}
+		for tab_name in checks.iter() {
+			let children = merged.values().filter(|info| info.parent.as_ref() == Some(tab_name)).count();
+			let ref_count = merged.get(tab_name).unwrap().ref_count;
+			if ref_count != children {
+				return Err(format!("InvalidTopology: ref_count mismatch, tab: {}, ref_count: {}, children: {}", tab_name.as_str(), ref_count, children));
+			}
+		}
+
+		let mut bins = Vec::with_capacity(infos.len());
+		for info in infos.iter() {
+			let mut kt = WriteBuffer::new();
+			info.tab_name.encode(&mut kt);
+			let mut vt = WriteBuffer::new();
+			info.encode(&mut vt);
+			bins.push((kt.bytes, vt.bytes));
+		}
+		let pairs: Vec<(&[u8], &[u8])> = bins.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+		let store = open_meta_store().await?;
+		if let Err(e) = store.write_batch(&pairs).await {
+			return Err(format!("import topology failed, reason: {:?}", e));
+		}
 
-		for (key, _) in map.iter() {
-			let tab_name = Atom::decode(&mut ReadBuffer::new(key, 0)).unwrap();
-			let mut file = LogFileDB::open(&tab_name).await.unwrap();
-			file.1.log_file.split().await;
+		for info in infos {
+			lock.insert(info.tab_name.clone(), info);
 		}
 
 		Ok(())
 	}
 
-	//异步整理所有日志文件表
-	pub async fn collect() -> SResult<()> {
-		//获取LogFileDB的元信息
-		let meta = LogFileDB::open(&Atom::from(DB_META_TAB_NAME)).await.unwrap();
-		let map = meta.1.map.lock();
-
-		//遍历LogFileDB中的所有LogFileTab
-		for (key, _) in map.iter() {
-			let tab_name = Atom::decode(&mut ReadBuffer::new(key, 0)).unwrap();
-			let mut file = LogFileDB::open(&tab_name).await.unwrap();
-
-			//从LogFileTab中，根据文件名从小到大的选择需要整理的只读日志文件
-			let mut remove_logs = Vec::new();
-			let mut collect_logs = Vec::new();
-			let mut collected_logs = XHashMap::default();
-			for (log_path, log_len, key_len) in file.1.statistics.lock().iter() {
-				if *key_len == 0 {
-					//当前只读日志文件中没有新的关键字，则准备移除当前只读日志文件，并继续选择下一个只读日志文件
-					remove_logs.push(log_path.clone());
-					collected_logs.insert(log_path.clone(), ());
-					continue;
-				}
-
-				let f = *log_len as f64 / *key_len as f64;
-				if f < 1.5 {
-					//当前只读日志文件的关键字重复率未达限制，则立即停止选择，并准备整理已选择的只读日志文件
-					break; //TODO 后续还要判断分叉的分裂点，除了分裂点为最大的只读日志文件外，其它分裂点将无法选择作为整理的只读日志文件，至到对应分裂点的分叉表被删除...
-				}
+	//将分叉表分离为独立的根表，分叉链合并后的所有记录写入分叉表自己的日志文件，然后清除分叉表的父表，并减少父表的引用计数
+	//分离后分叉表的读取结果不变，且不再依赖父表的日志文件
+	pub async fn detach_fork(tab_name: &Atom) -> SResult<()> {
+		let info = match ALL_TABLES.get(tab_name).await {
+			None => return Err(String::from("TabNotFound: ") + tab_name.as_str()),
+			Some(info) => info,
+		};
+		let parent = match &info.parent {
+			None => return Err(format!("NotForked: tab: {}", tab_name.as_str())),
+			Some(parent) => parent.clone(),
+		};
 
-				//准备整理当前只读日志文件
-				collect_logs.push(log_path.clone());
-				collected_logs.insert(log_path.clone(), ());
+		//将分叉链合并后的记录中，分叉表自己没有的记录写入分叉表的日志文件
+		let file = LogFileDB::open(tab_name).await?;
+		file.ensure_loaded().await?;
+		let root = file.0.lock().await.root.clone();
+		let mut bins = Vec::new();
+		for Entry(k, v) in root.iter(None, false) {
+			match file.1.read(k.as_slice()) {
+				Some(value) if value.as_ref() == v.as_slice() => (),
+				_ => bins.push((k.to_vec(), v.clone())),
 			}
-
-			//整理需要整理的只读日志文件
-			if let Err(e) = file.1.log_file.collect_logs(remove_logs, collect_logs, 1024 * 1024, 32 * 1024, false).await {
-				//整理指定的LogFileTab失败，则立即退出整理
-				return Err(format!("Collect LogFileTab failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+		}
+		if bins.len() > 0 {
+			let pairs: Vec<(&[u8], &[u8])> = bins.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+			if let Err(e) = file.1.write_batch(&pairs).await {
+				return Err(format!("detach fork failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
 			}
+		}
 
-			//从LogFileTab中移除所有的只读日志文件统计信息
-			file.1.statistics.lock().clear();
+		//在同一批次中更新分叉表和父表的元信息
+		let mut lock = ALL_TABLES.lock().await;
+		let mut detached = info;
+		detached.parent = None;
+		detached.parent_log_id = None;
+		detached.mode = ForkMode::Frozen;
+		let mut parent_info = lock.get(&parent).cloned();
+		if let Some(parent_info) = &mut parent_info {
+			parent_info.ref_count = parent_info.ref_count.saturating_sub(1);
+		}
 
-			let collect_start_time = Instant::now();
+		let mut bins = Vec::with_capacity(2);
+		for info in Some(&detached).into_iter().chain(parent_info.iter()) {
+			let mut kt = WriteBuffer::new();
+			info.tab_name.encode(&mut kt);
+			let mut vt = WriteBuffer::new();
+			info.encode(&mut vt);
+			bins.push((kt.bytes, vt.bytes));
+		}
+		let pairs: Vec<(&[u8], &[u8])> = bins.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+		let store = open_meta_store().await?;
+		if let Err(e) = store.write_batch(&pairs).await {
+			return Err(format!("detach fork failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+		}
 
-			//清理加载时的移除缓冲和临时键值缓冲，并设置为不需要统计
-			file.1.removed.lock().clear();
-			file.1.tmp_map.lock().clear();
-			file.1.is_statistics.store(false, Ordering::Relaxed);
+		lock.insert(tab_name.clone(), detached);
+		//脱离后的实时分叉表不再同步父表的写入
+		if let Some(live) = LIVE_FORKS.lock().get_mut(&parent) {
+			live.remove(tab_name);
+		}
+		if let Some(parent_info) = parent_info {
+			lock.insert(parent, parent_info);
+		}
 
-			//获取整理后LogFileTab中的所有有效日志文件路径列表
-			if let Ok(mut log_paths) = read_log_paths(&file.1.log_file).await {
-				//从大到小的分析整理后的日志文件，并更新LogFileTab的统计信息
-				let mut offset = None;
-				let mut read_len = 32 * 1024;
-				let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
-				while let Some(log_path) = log_paths.pop() {
-					let log_file = match AsyncFile::open(rt.clone(), log_path.clone(), AsyncFileOptions::OnlyRead).await {
-						Err(e) => {
-							//打开指定日志文件失败，则继续下一个日志文件的分析
-							error!("Statistic failed after collected, tab: {}, reason: {:?}", tab_name.as_str(), e);
-							continue;
-						}
-						Ok(f) => {
-							f
-						},
-					};
+		Ok(())
+	}
 
-					loop {
-						match read_log_file(log_path.clone(),
-											log_file.clone(),
-											offset,
-											read_len).await {
-							Err(e) => {
-								error!("Statistic failed after collected, tab: {}, reason: {:?}", tab_name.as_str(), e);
-							},
-							Ok((file_offset, bin)) => {
-								match read_log_file_block(log_path.clone(),
-														  &bin,
-														  file_offset,
-														  read_len,
-														  true) {
-									Err(e) => {
-										error!("Statistic failed after collected, tab: {}, reason: {:?}", tab_name.as_str(), e);
-									},
-									Ok((next_file_offset, next_len, logs)) => {
-										//分析当前只读日志文件的日志块，并更新当前只读日志文件的统计信息
-										for (method, key, value) in logs {
-											if file.1.is_require(Some(&log_path), &key) {
-												//需要分析的关键字
-												file.1.load(Some(&log_path), method, key, value);
-											}
-										}
+	//获取同时执行整理文件I/O的日志文件表数量的峰值，并将峰值重置为当前的数量
+	pub fn take_compaction_io_peak() -> usize {
+		let running = COMPACTION_IO_LIMITER.running.load(Ordering::SeqCst);
+		COMPACTION_IO_LIMITER.peak.swap(running, Ordering::Relaxed)
+	}
 
-										if next_file_offset == 0 && next_len == 0 {
-											//已读到日志文件头，则继续下一个日志文件的读取
-											offset = None;
-											read_len = 3 * 1024;
-											break;
-										} else {
-											//更新日志文件位置
-											offset = Some(next_file_offset);
-											read_len = next_len;
-										}
-									},
-								}
-							},
-						}
-					}
-				}
-			}
+	//获取同时等待日志持久化的提交数量的峰值，并将峰值重置为当前的数量
+	pub fn take_inflight_write_peak() -> usize {
+		let running = INFLIGHT_WRITE_LIMITER.running.load(Ordering::SeqCst);
+		INFLIGHT_WRITE_LIMITER.peak.swap(running, Ordering::Relaxed)
+	}
 
-			file.1.tmp_map.lock().clear(); //清理临时键值缓冲区
-			info!("Collect LogFileTab ok, time: {:?}, tab: {}, Statistics: {:?}",
-				  Instant::now() - collect_start_time,
-				  tab_name.as_str(),
-				  &*file.1.statistics.lock());
+	//等待所有已追加但未持久化的提交持久化完成，超时则返回OperationTimeout错误，超时时间为0表示不限制
+	pub async fn flush(timeout: usize) -> SResult<()> {
+		with_timeout("flush", timeout, async move {
+			INFLIGHT_WRITE_LIMITER.wait_idle().await;
+			Ok(())
+		}).await
+	}
+
+	//关闭已缓存的日志文件表，释放没有未完成的事务的表的内存，返回关闭的表数量
+	//表的句柄仍保留在缓存中，之后再使用时从日志文件重新加载，调用前应先通过flush等待提交的日志持久化
+	pub async fn close() -> usize {
+		let tabs: Vec<LogFileTab> = LOG_FILE_TABS.read().await.values().cloned().collect();
+		let mut count = 0;
+		for tab in tabs {
+			match tab.evict_memory_with(true).await {
+				Ok(_) => count += 1,
+				Err(e) => debug!("skip close tab, reason: {}", e),
+			}
 		}
 
-		return Ok(());
+		count
 	}
-}
-
-/*
-* 日志文件数据库快照，包括日志文件数据库和日志文件数据库的元信息
-*/
-pub struct LogFileDBSnapshot(LogFileDB, Mutex<TabLog>);
 
-impl LogFileDBSnapshot {
-	//列出全部的表
-	pub async fn list(&self) -> Box<dyn Iterator<Item=Atom>> {
-		Box::new(self.1.lock().await.list())
+	//获取并发查询同时读取溢出日志的最大数量，并重置为0
+	pub fn take_parallel_read_peak() -> usize {
+		PARALLEL_READ_PEAK.swap(0, Ordering::Relaxed)
 	}
 
-	//表的元信息
-	pub async fn tab_info(&self, tab_name: &Atom) -> Option<Arc<TabMeta>> {
-		self.1.lock().await.get(tab_name)
+	//获取指定表的批量有序查询访问内存表记录和重新定位的次数，并重置为0
+	pub fn take_multi_get_steps(tab_name: &Atom) -> usize {
+		tab_counters(tab_name).multi_get_steps.swap(0, Ordering::Relaxed)
 	}
 
-	//检查该表是否可以创建
-	pub fn check(&self, _tab: &Atom, _meta: &Option<Arc<TabMeta>>) -> DBResult {
-		Ok(())
+	//获取运行时打开元信息表日志文件的次数，用于诊断
+	pub fn meta_store_opens() -> usize {
+		META_STORE_OPENS.load(Ordering::Relaxed)
 	}
 
-	//新增 修改 删除 表
-	pub async fn alter(&self, tab_name: &Atom, meta: Option<Arc<TabMeta>>) {
-		self.1.lock().await.alter(tab_name, meta)
+	//获取运行时分裂后同步日志目录的次数，用于诊断
+	pub fn split_syncs() -> usize {
+		SPLIT_SYNCS.load(Ordering::Relaxed)
 	}
 
-	//创建指定表的表事务
-	pub async fn tab_txn(&self, tab_name: &Atom, id: &Guid, writable: bool) -> SResult<TxnType> {
-		self.1.lock().await.build(BuildDbType::LogFileDB, tab_name, id, writable).await
+	//获取运行时聚合写入的次数，每次批量写入计一次，用于诊断
+	pub fn vectored_writes() -> usize {
+		VECTORED_WRITES.load(Ordering::Relaxed)
 	}
 
-	//创建一个元信息表事务
-	pub fn meta_txn(&self, _id: &Guid) -> Arc<LogFileMetaTxn> {
-		Arc::new(LogFileMetaTxn {
-			alters: Arc::new(Mutex::new(XHashMap::default())),
-		})
+	//获取运行时从外置存储文件读取的字节数，用于诊断加载和只读附加时外置存储的读取量
+	pub fn blob_read_bytes() -> usize {
+		BLOB_READ_BYTES.load(Ordering::Relaxed)
 	}
 
-	//元信息表的预提交
-	pub async fn prepare(&self, id: &Guid) -> DBResult{
-		(self.0).0.prepare(id, &mut *self.1.lock().await).await
+	//获取最近一次打开数据库时注册表元信息获取ALL_TABLES锁的次数，用于诊断启动时的锁竞争
+	pub fn startup_catalog_locks() -> usize {
+		STARTUP_CATALOG_LOCKS.load(Ordering::Relaxed)
 	}
 
-	//元信息表的提交
-	pub async fn commit(&self, id: &Guid){
-		(self.0).0.commit(id).await
+	//获取日志文件数据库的统计信息
+	pub async fn db_stats() -> DbStats {
+		DbStats {
+			tabs: ALL_TABLES.lock().await.len(),
+			total_size: LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed),
+		}
 	}
 
-	//元信息表的回滚
-	pub async fn rollback(&self, id: &Guid){
-		(self.0).0.rollback(id).await
+	//获取日志文件数据库的健康报告
+	pub fn health_report() -> HealthReport {
+		HEALTH_REPORT.lock().clone()
 	}
 
-	//日志文件库修改通知
-	pub fn notify(&self, _event: Event) {}
-}
+	//预检元信息中的所有表能否加载，逐个读取并校验表的格式文件、分叉链、所有日志文件的记录和外置存储的值的引用
+	//只读取不加载，不会修改或隔离日志文件，也不会缓存表，用于恢复或迁移的数据库开始服务前的检查
+	pub async fn validate_all() -> SResult<ValidationSummary> {
+		let start = Instant::now();
+		let mut names: Vec<Atom> = ALL_TABLES.lock().await.keys().cloned().collect();
+		names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+		let mut tabs = Vec::with_capacity(names.len());
+		for name in names {
+			let tab_start = Instant::now();
+			let chains = build_fork_chain(name.clone()).await;
+			let config = chains.as_ref().ok().and_then(|chains| chains.get(0)).map(|tm| tm.config.clone()).unwrap_or_default();
+			let mut path = PathBuf::new();
+			path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+			path.push(name.to_string());
 
-/*
-* 日志文件事务的引用
-*/
-pub struct RefLogFileTxn(Mutex<FileMemTxn>);
+			let result = match chains.and_then(|chains| check_fork_chain(&chains)) {
+				Err(e) => Err(e),
+				Ok(_) => validate_tab_files(&path, config.load_block_size()).await,
+			};
+			let (log_files, error) = match result {
+				Err(e) => {
+					warn!("validate tab failed, tab: {}, reason: {}", name.as_str(), e);
+					(0, Some(e))
+				},
+				Ok(log_files) => (log_files, None),
+			};
+			tabs.push(TabValidation {
+				name,
+				log_files,
+				error,
+				elapsed: tab_start.elapsed(),
+			});
+		}
 
-unsafe impl Sync for RefLogFileTxn  {}
+		Ok(ValidationSummary {
+			tabs,
+			elapsed: start.elapsed(),
+		})
+	}
 
-impl RefLogFileTxn {
-	//获取事务的状态
-	pub async fn get_state(&self) -> TxState {
-		self.0.lock().await.state.clone()
+	//判断表是否是叶节点，即没有从表分叉出的子表，表不存在则返回TabNotFound错误
+	pub async fn is_leaf(tab: &Atom) -> SResult<bool> {
+		match ALL_TABLES.get(tab).await {
+			None => Err(String::from("TabNotFound: ") + tab.as_str()),
+			Some(info) => Ok(info.ref_count == 0),
+		}
 	}
 
-	//查询指定主键集的记录集
-	pub async fn query(
-		&self,
-		arr: Arc<Vec<TabKV>>,
-		_lock_time: Option<usize>,
-		_readonly: bool
-	) -> SResult<Vec<TabKV>> {
-		let mut value_arr = Vec::new();
-		for tabkv in arr.iter() {
-			let value = match self.0.lock().await.get(tabkv.key.clone()).await {
-				Some(v) => Some(v),
-				_ => None
-			};
+	//判断表当前是否可以删除，只有叶节点可以删除，有分叉的表在提交删除时会失败，表不存在则返回TabNotFound错误
+	pub async fn can_delete(tab: &Atom) -> SResult<bool> {
+		if tab.as_str() == DB_META_TAB_NAME {
+			return Ok(false);
+		}
 
-			value_arr.push(
-				TabKV{
-					ware: tabkv.ware.clone(),
-					tab: tabkv.tab.clone(),
-					key: tabkv.key.clone(),
-					index: tabkv.index.clone(),
-					value: value,
-				}
-			)
+		LogFileDB::is_leaf(tab).await
+	}
+
+	//获取分叉表相对于父表的净修改，即分叉后分叉表自己的日志记录中与父表在分叉点的值不同的写入和删除，按主键排序
+	//写入与父表的值相同、删除父表中不存在的主键，不是净修改，不在返回结果中
+	pub async fn fork_delta(child: &Atom) -> SResult<Vec<(Bin, Option<Bin>)>> {
+		let chains = build_fork_chain(child.clone()).await?;
+		let info = match chains.get(0) {
+			None => return Err(String::from("TabNotFound: ") + child.as_str()),
+			Some(info) => info.clone(),
+		};
+		if info.parent.is_none() {
+			return Err(format!("fork delta failed, tab: {}, reason: not a fork", child.as_str()));
 		}
-		Ok(value_arr)
+		check_fork_chain(&chains)?;
+
+		//只加载分叉表自己的日志文件，得到分叉后的写入和删除
+		let tab = LogFileDB::open(child).await?;
+		let mut path = PathBuf::new();
+		path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		path.push(child.to_string());
+		let mut store = AsyncLogFileStore::new(tab.1.log_file.clone());
+		let version = read_format_version(&path)?;
+		load_with_version(version, &tab.1.log_file, &mut store, path, info.config.load_block_size()).await?;
+		let mut changes: BTreeMap<Vec<u8>, Option<Vec<u8>>> = BTreeMap::new();
+		for (k, v) in store.map.lock().iter() {
+			changes.insert(k.clone(), Some(v.to_vec()));
+		}
+		for k in store.removed.lock().keys() {
+			changes.insert(k.clone(), None);
+		}
+
+		let keys: XHashSet<Vec<u8>> = changes.keys().cloned().collect();
+		let parent_view = load_parent_view(child, &chains, &keys).await?;
+		Ok(changes.into_iter()
+			.filter(|(k, v)| parent_view.get(k) != v.as_ref())
+			.map(|(k, v)| (Arc::new(k), v.map(Arc::new)))
+			.collect())
 	}
 
-	//插入、修改和删除指定主键集的记录集，值为None就是删除，主键不存在则为插入，主键存在则为修改
-	pub async fn modify(&self, arr: Arc<Vec<TabKV>>, _lock_time: Option<usize>, _readonly: bool) -> DBResult {
-		for tabkv in arr.iter() {
-			if tabkv.value == None {
-				match self.0.lock().await.delete(tabkv.key.clone()).await {
-					Ok(_) => (),
-					Err(e) => return Err(e.to_string())
-				};
-			} else {
-				match self.0.lock().await.upsert(tabkv.key.clone(), tabkv.value.clone().unwrap()).await {
-					Ok(_) => (),
-					Err(e) => return Err(e.to_string())
-				};
+	//将无法恢复的表重置为空表，表的元信息和分叉关系保持不变，表自己的日志文件移动到表目录旁的备份目录，返回备份目录
+	//重置会丢弃表的所有数据，confirm必须为true；有分叉的表的日志文件被子表共享，不允许重置；分叉表重置后仍可见父表在分叉点的数据
+	//有未完成的事务时不允许重置，重置后已持有的表句柄不再可用，需要重新打开
+	pub async fn reset_tab(tab: &Atom, confirm: bool) -> SResult<PathBuf> {
+		if !confirm {
+			return Err(format!("reset tab refused, tab: {}, reason: not confirmed", tab.as_str()));
+		}
+		if tab.as_str() == DB_META_TAB_NAME {
+			return Err(format!("reset tab refused, tab: {}, reason: meta tab", tab.as_str()));
+		}
+		match ALL_TABLES.get(tab).await {
+			None => return Err(String::from("TabNotFound: ") + tab.as_str()),
+			Some(info) if info.ref_count > 0 => return Err(format!("HasForks: tab: {}, ref_count: {}", tab.as_str(), info.ref_count)),
+			Some(_) => (),
+		}
+
+		let chains = build_fork_chain(tab.clone()).await?;
+		let mut cache = LOG_FILE_TABS.write().await;
+		if let Some(old) = cache.get(tab) {
+			if let Err(e) = old.evict_memory_with(true).await {
+				return Err(format!("reset tab refused, tab: {}, reason: {}", tab.as_str(), e));
 			}
+			cache.remove(tab);
 		}
-		Ok(())
-	}
 
-	//获取指定表的记录迭代器
-	//key为None则从表头或表尾开始迭代，由descending确定，descending为true表示从表尾迭代，否则从表头迭代，key为Some一个指定主键的二进制，则从表的指定主键开始迭代，迭代方向由descending确定
-	pub async fn iter(
-		&self,
-		tab: &Atom,
-		key: Option<Bin>,
-		descending: bool,
-		filter: Filter
-	) -> IterResult {
-		let b = self.0.lock().await;
-		let key = match key {
-			Some(k) => Some(Bon::new(k)),
-			None => None,
-		};
-		let key = match &key {
-			&Some(ref k) => Some(k),
-			None => None,
-		};
+		//移动表目录和格式文件到备份位置，再按新表打开，新表使用当前的日志格式版本
+		let mut path = PathBuf::new();
+		path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		path.push(tab.to_string());
+		let now = clock::now_millis();
+		let mut backup = path.as_os_str().to_owned();
+		backup.push(format!(".reset.{}", now));
+		let backup = PathBuf::from(backup);
+		if path.exists() {
+			if let Err(e) = fs::rename(&path, &backup) {
+				return Err(format!("reset tab failed, tab: {}, reason: {:?}", tab.as_str(), e));
+			}
+		}
+		if format_path(&path).exists() {
+			if let Err(e) = fs::rename(format_path(&path), format_path(&backup)) {
+				return Err(format!("reset tab failed, tab: {}, reason: {:?}", tab.as_str(), e));
+			}
+		}
+		if blob_path(&path).exists() {
+			if let Err(e) = fs::rename(blob_path(&path), blob_path(&backup)) {
+				return Err(format!("reset tab failed, tab: {}, reason: {:?}", tab.as_str(), e));
+			}
+		}
+		if watermark_path(&path).exists() {
+			if let Err(e) = fs::rename(watermark_path(&path), watermark_path(&backup)) {
+				return Err(format!("reset tab failed, tab: {}, reason: {:?}", tab.as_str(), e));
+			}
+		}
+		if repair_path(&path).exists() {
+			if let Err(e) = fs::rename(repair_path(&path), repair_path(&backup)) {
+				return Err(format!("reset tab failed, tab: {}, reason: {:?}", tab.as_str(), e));
+			}
+		}
+
+		let fresh = LogFileTab::new(tab, &chains).await?;
+		cache.insert(tab.clone(), fresh);
+		error!("reset tab, tab: {}, backup: {:?}", tab.as_str(), backup);
 
-		Ok(Box::new(MemIter::new(tab, b.root.clone(), b.root.iter( key, descending), filter)))
+		Ok(backup)
 	}
 
-	//获取指定表的主键迭代器
-	//key为None则从表头或表尾开始迭代，由descending确定，descending为true表示从表尾迭代，否则从表头迭代，key为Some一个指定主键的二进制，则从表的指定主键开始迭代，迭代方向由descending确定
-	pub async fn key_iter(
-		&self,
-		key: Option<Bin>,
-		descending: bool,
-		filter: Filter
-	) -> KeyIterResult {
-		let b = self.0.lock().await;
-		let key = match key {
-			Some(k) => Some(Bon::new(k)),
-			None => None,
-		};
-		let key = match &key {
-			&Some(ref k) => Some(k),
-			None => None,
+	//将源表当前的有效记录流式导出到一个新建的目标表，目标表使用源表的元信息和表级配置，返回导出的记录数量
+	//导出不修改源表的日志文件，中断时源表不受影响，只需删除未完成的目标表目录；导出期间需要源表与目标表两份磁盘空间
+	//从导出开始到swap_tabs交换源表与目标表之前，源表的写入预提交返回TabFenced错误，避免导出后的写入在交换后丢失
+	//有已预提交的事务时不允许导出；放弃交换时调用unfence_tab恢复源表的写入
+	pub async fn compact_to_new(src: &Atom, dst: &Atom) -> SResult<usize> {
+		if src.as_str() == DB_META_TAB_NAME || dst.as_str() == DB_META_TAB_NAME {
+			return Err(format!("compact to new refused, src: {}, dst: {}, reason: meta tab", src.as_str(), dst.as_str()));
+		}
+		let info = {
+			let tables = ALL_TABLES.lock().await;
+			if tables.contains_key(dst) {
+				return Err(format!("compact to new failed, src: {}, dst: {}, reason: dst already exists", src.as_str(), dst.as_str()));
+			}
+			match tables.get(src) {
+				None => return Err(String::from("TabNotFound: ") + src.as_str()),
+				Some(info) => info.clone(),
+			}
 		};
-		let tab = b.tab.0.lock().await.tab.clone();
-		Ok(Box::new(MemKeyIter::new(&tab, b.root.clone(), b.root.keys(key, descending), filter)))
+
+		//先隔离源表的写入，再检查预提交，之后的预提交都会看到隔离
+		let file = LogFileDB::open(src).await?;
+		if file.2.fenced.swap(true, Ordering::SeqCst) {
+			return Err(format!("compact to new refused, src: {}, dst: {}, reason: src already fenced", src.as_str(), dst.as_str()));
+		}
+		let prepared = file.lock_tab().await.prepare.len();
+		if prepared > 0 {
+			file.2.fenced.store(false, Ordering::SeqCst);
+			return Err(format!("compact to new refused, src: {}, dst: {}, reason: prepared txns: {}", src.as_str(), dst.as_str(), prepared));
+		}
+
+		match LogFileDB::compact_into(&file, src, dst, &info).await {
+			Err(e) => {
+				file.2.fenced.store(false, Ordering::SeqCst);
+				Err(e)
+			},
+			Ok(count) => {
+				info!("compact to new, src: {}, dst: {}, count: {}", src.as_str(), dst.as_str(), count);
+				Ok(count)
+			},
+		}
 	}
 
-	//获取表的索引迭代器
-	//TODO...
-	pub fn index(
-		&self,
-		_tab: &Atom,
-		_index_key: &Atom,
-		_key: Option<Bin>,
-		_descending: bool,
-		_filter: Filter,
-	) -> IterResult {
-		Err("not implemeted".to_string())
+	//导出源表的快照到目标表的目录，导出完成后再创建目标表的元信息，目标表按导出的日志打开
+	async fn compact_into(file: &LogFileTab, src: &Atom, dst: &Atom, info: &TableMetaInfo) -> SResult<usize> {
+		let mut path = PathBuf::new();
+		path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		path.push(dst.to_string());
+		let count = file.export_snapshot(&path).await?;
+
+		let meta_txn = LogFileMetaTxn::new();
+		meta_txn.alter(dst, Some(Arc::new(info.meta.clone()))).await?;
+		meta_txn.alter_config(dst, info.config.clone()).await?;
+		meta_txn.prepare(0).await?;
+		meta_txn.commit().await?;
+		debug!("compact into new tab, src: {}, dst: {}, count: {}", src.as_str(), dst.as_str(), count);
+
+		Ok(count)
 	}
 
-	//获取指定表的记录数量
-	pub async fn tab_size(&self) -> SResult<usize> {
-		let txn = self.0.lock().await;
-		Ok(txn.root.size())
+	//取消compact_to_new对源表写入的隔离，放弃交换时调用
+	pub fn unfence_tab(tab: &Atom) {
+		tab_counters(tab).fenced.store(false, Ordering::SeqCst);
 	}
 
-	//预提交一个事务
-	pub async fn prepare(&self, _timeout: usize) -> DBResult {
-		let mut txn = self.0.lock().await;
-		txn.state = TxState::Preparing;
-		match txn.prepare_inner().await {
-			Ok(()) => {
-				txn.state = TxState::PreparOk;
-				return Ok(())
-			},
-			Err(e) => {
-				txn.state = TxState::PreparFail;
-				return Err(e.to_string())
-			},
+	//交换两个表的日志文件，表名和元信息不变，交换后各自的表名访问对方原来的数据，表级配置仍跟随表名
+	//两个表的元信息必须一致，且都不能是分叉表或有分叉的表；有未完成的事务时不允许交换，交换前已持有的表句柄失效，访问返回TabInvalid错误
+	//交换按交换日志分三个阶段移动表目录和表目录旁的文件，中断后在下次打开表或打开数据库时按交换日志完成交换
+	pub async fn swap_tabs(a: &Atom, b: &Atom) -> DBResult {
+		if a.as_str() == DB_META_TAB_NAME || b.as_str() == DB_META_TAB_NAME || a == b {
+			return Err(format!("swap tabs refused, a: {}, b: {}, reason: invalid tab", a.as_str(), b.as_str()));
+		}
+		{
+			let tables = ALL_TABLES.lock().await;
+			let (info_a, info_b) = match (tables.get(a), tables.get(b)) {
+				(None, _) => return Err(String::from("TabNotFound: ") + a.as_str()),
+				(_, None) => return Err(String::from("TabNotFound: ") + b.as_str()),
+				(Some(info_a), Some(info_b)) => (info_a, info_b),
+			};
+			for info in [info_a, info_b].iter() {
+				if info.ref_count > 0 {
+					return Err(format!("HasForks: tab: {}, ref_count: {}", info.tab_name.as_str(), info.ref_count));
+				}
+				if info.parent.is_some() {
+					return Err(format!("swap tabs refused, tab: {}, reason: forked tab", info.tab_name.as_str()));
+				}
+			}
+			if info_a.meta != info_b.meta {
+				return Err(format!("swap tabs refused, a: {}, b: {}, reason: meta mismatch", a.as_str(), b.as_str()));
+			}
 		}
-	}
 
-	//提交一个事务
-	pub async fn commit(&self) -> CommitResult {
-		let mut txn = self.0.lock().await;
-		txn.state = TxState::Committing;
-		match txn.commit_inner().await {
-			Ok(log) => {
-				txn.state = TxState::Commited;
-				return Ok(log)
-			},
-			Err(e) => {
-				txn.state = TxState::CommitFail;
-				return Err(e.to_string())
+		LogFileDB::recover_swap().await?;
+		let _swap = SWAP_LOCK.lock().await;
+
+		//在缓存锁内释放两个表的内存并使表句柄失效，之后在缓存锁外交换文件
+		{
+			let mut cache = LOG_FILE_TABS.write().await;
+			for tab in [a, b].iter() {
+				if let Some(old) = cache.get(*tab) {
+					if let Err(e) = old.evict_memory_with(true).await {
+						return Err(format!("swap tabs refused, tab: {}, reason: {}", tab.as_str(), e));
+					}
+				}
 			}
+			for tab in [a, b].iter() {
+				if let Some(old) = cache.remove(*tab) {
+					old.lock_tab().await.invalid = true;
+				}
+			}
+			//释放缓存锁前标记交换未完成，之后打开表时等待交换完成
+			SWAP_PENDING.store(true, Ordering::SeqCst);
+		}
+
+		let db_path = PathBuf::from(env::var("DB_PATH").unwrap_or(".".to_string()));
+		let journal = SwapJournal {
+			a: a.to_string(),
+			b: b.to_string(),
+			tmp: format!(".swap.{}", clock::now_millis()),
+			phase: 0,
+		};
+		match run_blocking(move || journal.run(&db_path)).await {
+			Err(e) => return Err(format!("swap tabs incomplete, a: {}, b: {}, reason: {}", a.as_str(), b.as_str(), e)),
+			Ok(Err(e)) => return Err(format!("swap tabs incomplete, a: {}, b: {}, reason: {:?}", a.as_str(), b.as_str(), e)),
+			Ok(Ok(())) => SWAP_PENDING.store(false, Ordering::SeqCst),
 		}
+
+		//交换完成后源表不再需要隔离，两个表在下次打开时按交换后的日志文件加载
+		tab_counters(a).fenced.store(false, Ordering::SeqCst);
+		tab_counters(b).fenced.store(false, Ordering::SeqCst);
+		info!("swap tabs, a: {}, b: {}", a.as_str(), b.as_str());
+
+		Ok(())
 	}
 
-	//回滚一个事务
-	pub async fn rollback(&self) -> DBResult {
-		let mut txn = self.0.lock().await;
-		txn.state = TxState::Rollbacking;
-		match txn.rollback_inner().await {
-			Ok(()) => {
-				txn.state = TxState::Rollbacked;
-				return Ok(())
-			},
-			Err(e) => {
-				txn.state = TxState::RollbackFail;
-				return Err(e.to_string())
-			}
+	//按交换日志完成中断的表交换，没有未完成的交换则直接返回
+	pub async fn recover_swap() -> DBResult {
+		if !SWAP_PENDING.load(Ordering::SeqCst) {
+			return Ok(());
+		}
+
+		let _swap = SWAP_LOCK.lock().await;
+		let db_path = PathBuf::from(env::var("DB_PATH").unwrap_or(".".to_string()));
+		let recovered = match run_blocking(move || SwapJournal::recover(&db_path)).await {
+			Err(e) => return Err(format!("recover swap failed, reason: {}", e)),
+			Ok(Err(e)) => return Err(format!("recover swap failed, reason: {:?}", e)),
+			Ok(Ok(recovered)) => recovered,
+		};
+		SWAP_PENDING.store(false, Ordering::SeqCst);
+		if let Some((a, b)) = recovered {
+			let (a, b) = (Atom::from(a), Atom::from(b));
+			tab_counters(&a).fenced.store(false, Ordering::SeqCst);
+			tab_counters(&b).fenced.store(false, Ordering::SeqCst);
+			warn!("recover swap tabs, a: {}, b: {}", a.as_str(), b.as_str());
 		}
+
+		Ok(())
 	}
 
-	///表分叉的预提交
-	pub async fn fork_prepare(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
-		let mut txn = self.0.lock().await;
-		txn.fork_prepare_inner(ware, tab_name, fork_tab_name, meta).await
+	//设置数据库使用的时钟，表的元信息历史和重置表的备份等时间戳都从这个时钟获取，测试可以注入手动时钟
+	//耗时统计使用单调时钟，不受影响
+	pub fn set_clock(clock: Arc<dyn Clock>) {
+		clock::set_clock(clock);
 	}
 
-	//表分叉的提交
-	pub async fn fork_commit(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
-		let mut txn = self.0.lock().await;
-		txn.fork_commit_inner(ware, tab_name, fork_tab_name, meta).await
+	//只读附加到由其它进程写入的数据库目录，path与主库的DB_PATH相同，不打开表的日志文件存储，可以与主库同时运行
+	//附加后通过refresh或start_tail读取主库之后追加的日志记录
+	pub async fn attach_readonly<P: AsRef<Path>>(path: P) -> SResult<ReadonlyDB> {
+		ReadonlyDB::attach(path.as_ref()).await
 	}
 
-	///表分叉的回滚
-	pub async fn fork_rollback(&self) -> DBResult {
-		let mut txn = self.0.lock().await;
-		txn.fork_rollback_inner().await
+	//打开指定名称的日志文件表，表不存在或已删除则返回TabNotFound错误
+	pub async fn open(tab: &Atom) -> SResult<LogFileTab> {
+		if tab.as_str() != DB_META_TAB_NAME && ALL_TABLES.get(tab).await.is_none() {
+			return Err(String::from("TabNotFound: ") + tab.as_str());
+		}
+
+		LogFileDB::recover_swap().await?;
+		let chains = build_fork_chain(tab.clone()).await?;
+		let mut lock = LOG_FILE_TABS.write().await;
+		match lock.get(tab) {
+			Some(t) => Ok(t.clone()),
+			None => {
+				let cache = LogFileTab::new(tab, &chains).await?;
+				lock.insert(tab.clone(), cache.clone());
+				Ok(cache.clone())
+			}
+		}
 	}
 
-	///强制日志文件分裂
-	pub async fn force_fork(&self) -> Result<usize> {
-		self.0.lock().await.force_fork_inner().await
+
+	//打开在同一事务中创建、元信息尚未提交的日志文件表，表按没有分叉链的新表打开，写入在元信息提交后才提交
+	//表已在ALL_TABLES中，则与open相同
+	pub async fn open_created(tab: &Atom) -> SResult<LogFileTab> {
+		if ALL_TABLES.get(tab).await.is_some() {
+			return LogFileDB::open(tab).await;
+		}
+
+		let mut lock = LOG_FILE_TABS.write().await;
+		match lock.get(tab) {
+			Some(t) => Ok(t.clone()),
+			None => {
+				let cache = LogFileTab::new(tab, &[]).await?;
+				lock.insert(tab.clone(), cache.clone());
+				Ok(cache)
+			}
+		}
 	}
 
-	//记录锁，主键可以不存在，根据lock_time的值决定是锁还是解锁
-	pub async fn key_lock(&self, _arr: Arc<Vec<TabKV>>, _lock_time: usize, _readonly: bool) -> DBResult {
-		Ok(())
+	//丢弃在未提交的元信息事务中通过open_created打开的表，表的元信息已提交则忽略
+	//从缓存中移除并使表句柄失效，再删除打开时创建的表目录和表目录旁的文件
+	pub(crate) async fn discard_created(tab: &Atom) {
+		let tables = ALL_TABLES.lock().await;
+		if tables.contains_key(tab) {
+			return;
+		}
+		let old = LOG_FILE_TABS.write().await.remove(tab);
+		drop(tables);
+		match old {
+			None => return,
+			Some(old) => old.lock_tab().await.invalid = true,
+		}
+
+		let db_path = PathBuf::from(env::var("DB_PATH").unwrap_or(".".to_string()));
+		for path in SwapJournal::tab_paths(&db_path, tab.as_str()) {
+			let r = if path.is_dir() {
+				fs::remove_dir_all(&path)
+			} else {
+				fs::remove_file(&path)
+			};
+			match r {
+				Err(e) if e.kind() != ErrorKind::NotFound => warn!("discard created tab failed, tab: {}, path: {:?}, reason: {:?}", tab.as_str(), path, e),
+				_ => (),
+			}
+		}
+		info!("discard created tab, tab: {}", tab.as_str());
 	}
-}
 
-/*
-* 日志文件事务
-*/
-pub struct FileMemTxn {
-	id: Guid,						//事务id
-	writable: bool,					//是否是可写事务
-	tab: LogFileTab,				//日志文件表的句柄
-	root: BinMap,					//日志文件表的内存表的句柄，在创建内存表事务时从内存表的句柄拷贝，在事务过程中可能会修改
-	old: BinMap,					//日志文件表的内存表的句柄，保留创建内存表事务时内存表的句柄，在事务过程中不会修改
-	rwlog: XHashMap<Bin, RwLog>,	//内存表事务的操作日志，Bin为主键的二进制，RwLog为事务的操作日志
-	state: TxState,					//事务的状态
-}
+	//清理日志文件表缓存中已删除的表，返回被清理的表数量
+	pub async fn gc_cache() -> usize {
+		let tables = ALL_TABLES.lock().await;
+		let mut cache = LOG_FILE_TABS.write().await;
+		let stale: Vec<Atom> = cache
+			.keys()
+			.filter(|tab| tab.as_str() != DB_META_TAB_NAME && !tables.contains_key(*tab))
+			.cloned()
+			.collect();
+		for tab in stale.iter() {
+			cache.remove(tab);
+			warn!("evict dangling log file tab, tab: {}", tab.as_str());
+		}
 
-impl FileMemTxn {
-	//开始事务
-	pub async fn new(tab: LogFileTab, id: &Guid, writable: bool) -> RefLogFileTxn {
-		let root = tab.0.lock().await.root.clone();
-		let txn = FileMemTxn {
-			id: id.clone(),
-			writable,
-			root: root.clone(),
-			tab,
-			old: root,
-			rwlog: XHashMap::default(),
-			state: TxState::Ok,
-		};
-		return RefLogFileTxn(Mutex::new(txn))
+		stale.len()
 	}
 
-	//获取指定主键的记录的值
-	pub async fn get(&mut self, key: Bin) -> Option<Bin> {
-		match self.root.get(&Bon::new(key.clone())) {
-			Some(v) => {
-				if self.writable {
-					match self.rwlog.get(&key) {
-						Some(_) => (),
-						None => {
-							&mut self.rwlog.insert(key, RwLog::Read);
-							()
-						}
-					}
-				}
+	//加载大小超过高水位时，按最近最少使用的顺序释放表的内存，直到低于低水位，返回释放的表数量
+	//有未完成的事务的表不释放，包括提交后仍在等待日志持久化的表，释放的表在下次创建事务时重新加载
+	pub async fn shrink_memory() -> usize {
+		let high = MEMORY_HIGH_WATER.load(Ordering::Relaxed);
+		if high == 0 || LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed) <= high {
+			return 0;
+		}
+		let low = MEMORY_LOW_WATER.load(Ordering::Relaxed).min(high);
 
-				return Some(v.clone())
-			},
-			None => return None
+		let mut tabs: Vec<LogFileTab> = LOG_FILE_TABS.read().await.values().cloned().collect();
+		tabs.sort_by_key(|tab| tab.2.last_access.load(Ordering::Relaxed));
+		let mut count = 0;
+		for tab in tabs {
+			if LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed) <= low {
+				break;
+			}
+
+			match tab.evict_memory_with(true).await {
+				Err(e) => debug!("skip shrink tab, reason: {}", e),
+				Ok(0) => (),
+				Ok(_) => count += 1,
+			}
+		}
+
+		let total = LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed);
+		if total > low {
+			warn!("shrink memory incomplete, total: {}, low: {}, evicted: {}", total, low, count);
+		} else {
+			info!("shrink memory, total: {}, low: {}, evicted: {}", total, low, count);
 		}
+
+		count
 	}
 
-	//插入或修改指定主键的记录
-	pub async fn upsert(&mut self, key: Bin, value: Bin) -> DBResult {
-		self.root.upsert(Bon::new(key.clone()), value.clone(), false);
-		self.rwlog.insert(key.clone(), RwLog::Write(Some(value.clone())));
+	//启动内存压力的后台检查，每隔interval毫秒检查一次加载大小是否超过高水位，已启动则忽略
+	pub async fn start_memory_shrink(interval: usize) -> SResult<()> {
+		let rt = match STORE_RUNTIME.read().await.as_ref() {
+			None => return Err(String::from("start memory shrink failed, reason: store runtime not ready")),
+			Some(rt) => rt.clone(),
+		};
+		if MEMORY_SHRINK_RUNNING.swap(true, Ordering::SeqCst) {
+			return Ok(());
+		}
 
+		schedule_memory_shrink(rt, interval.max(1));
 		Ok(())
 	}
 
-	//删除指定主键的记录
-	pub async fn delete(&mut self, key: Bin) -> DBResult {
-		self.root.delete(&Bon::new(key.clone()), false);
-		self.rwlog.insert(key, RwLog::Write(None));
+	//停止内存压力的后台检查，已在执行的检查会完成
+	pub fn stop_memory_shrink() {
+		MEMORY_SHRINK_RUNNING.store(false, Ordering::SeqCst);
+	}
 
-		Ok(())
+	//复制日志文件数据库的表管理器
+	pub async fn tabs_clone(&self) -> Arc<Self> {
+		Arc::new(LogFileDB(Arc::new(self.0.clone_map())))
 	}
 
-	//预提交
-	pub async fn prepare_inner(&mut self) -> DBResult {
-		let mut lock = self.tab.0.lock().await;
-		//遍历事务中的读写日志
-		for (key, rw_v) in self.rwlog.iter() {
-			//检查预提交是否冲突 
-			match lock.prepare.try_prepare(key, rw_v) {
-				Ok(_) => (),
-				Err(s) => return Err(s),
+	//列出全部的日志文件表
+	pub async fn list(&self) -> Box<dyn Iterator<Item=Atom>> {
+		Box::new(self.0.list().await)
+	}
+
+	//列出全部的日志文件表的概要信息，按表名排序，只读取表的元信息和已缓存的表，不会加载表
+	pub async fn list_detailed(&self) -> Vec<TabSummary> {
+		let names: Vec<Atom> = self.list().await.collect();
+		let infos = ALL_TABLES.lock().await.clone();
+		let tabs = LOG_FILE_TABS.read().await.clone();
+
+		let mut summaries = Vec::with_capacity(names.len());
+		for name in names {
+			let (parent, ref_count) = match infos.get(&name) {
+				None => (None, 0),
+				Some(info) => (info.parent.clone(), info.ref_count),
 			};
-			//检查Tab根节点是否改变
-			if lock.root.ptr_eq(&self.old) == false {
-				let key = Bon::new(key.clone());
-				match lock.root.get(&key) {
-					Some(r1) => match self.old.get(&key) {
-						Some(r2) if (r1.as_ptr() as usize == r2.as_ptr() as usize) => (),
-						_ => {
-							let key_str = format!("{:?}", &*key);
-							return Err(String::from("prepare conflicted value diff") + key_str.as_str())
-						}
-					},
-					_ => match self.old.get(&key) {
-						None => (),
-						_ => {
-							let key_str = format!("{:?}", &*key);
-							return Err(String::from("prepare conflicted old not None") + key_str.as_str())
-						}
+			let (key_count, byte_size) = match tabs.get(&name) {
+				None => (None, None),
+				Some(tab) => {
+					let lock = tab.0.lock().await;
+					if lock.unloaded {
+						(None, None)
+					} else {
+						(Some(tab.approx_size()), Some(lock.load_size))
 					}
-				}
-			}
+				},
+			};
+			summaries.push(TabSummary {
+				name,
+				key_count,
+				byte_size,
+				is_leaf: ref_count == 0,
+				parent,
+				ref_count,
+			});
 		}
-		let rwlog = mem::replace(&mut self.rwlog, XHashMap::with_capacity_and_hasher(0, Default::default()));
-		//写入预提交
-		lock.prepare.insert(self.id.clone(), rwlog);
+		summaries.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
 
-		return Ok(())
+		summaries
 	}
 
-	//提交
-	pub async fn commit_inner(&mut self) -> CommitResult {
-		let mut lock = self.tab.0.lock().await;
-		let logs = lock.prepare.remove(&self.id);
-		let logs = match logs {
-			Some(rwlog) => {
-				let root_if_eq = lock.root.ptr_eq(&self.old);
-				//判断根节点是否相等
-				if !root_if_eq {
-					for (k, rw_v) in rwlog.iter() {
-						match rw_v {
-							RwLog::Read => (),
-							_ => {
-								let k = Bon::new(k.clone());
-								match rw_v {
-									RwLog::Write(None) => {
-										lock.root.delete(&k, false);
-									},
-									RwLog::Write(Some(v)) => {
-										lock.root.upsert(k.clone(), v.clone(), false);
-									},
-									_ => (),
-								}
-							},
-						}
-					}
-				} else {
-					lock.root = self.root.clone();
+	//获取指定表的每个日志文件被哪些分叉表引用，按日志文件id升序，引用的分叉表按表名排序
+	//分叉表通过parent_log_id引用父表在分叉点及之前的所有日志文件，分叉表的子孙表通过分叉表间接引用这些日志文件
+	//只读取表的元信息和表目录，不会加载表
+	pub async fn log_file_refs(tab: &Atom) -> SResult<Vec<(PathBuf, Vec<Atom>)>> {
+		let infos = ALL_TABLES.lock().await.clone();
+		if !infos.contains_key(tab) {
+			return Err(String::from("TabNotFound: ") + tab.as_str());
+		}
+
+		//分叉链从叶节点到根节点，链上指定表的前一个表是经过它的子表，子表的parent_log_id是引用的上限
+		let mut limits = Vec::new();
+		for name in infos.keys() {
+			//分叉链不完整的表无法引用指定表的日志文件，忽略
+			let chains = match fork_chain_from(&infos, name) {
+				Err(_) => continue,
+				Ok(chains) => chains,
+			};
+			if let Some(index) = chains.iter().position(|tm| &tm.tab_name == tab) {
+				if index > 0 {
+					limits.push((name.clone(), chains[index - 1].parent_log_id.unwrap_or(0)));
 				}
-				rwlog
 			}
-			None => return Err(String::from("error prepare null"))
-		};
+		}
+		limits.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
 
-		let async_tab = self.tab.1.clone();
+		let mut path = PathBuf::new();
+		path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		path.push(tab.to_string());
+		let logs = list_log_files(&path).map_err(|e| format!("read log dir failed, tab: {}, reason: {:?}", tab.as_str(), e))?;
+
+		Ok(logs.into_iter().map(|(id, log_path)| {
+			let refs = limits.iter().filter(|(_, limit)| id < *limit).map(|(name, _)| name.clone()).collect();
+			(log_path, refs)
+		}).collect())
+	}
 
-		let mut insert_pairs: Vec<(&[u8], &[u8])> = vec![];
-		let mut delete_keys: Vec<&[u8]> = vec![];
+	//获取该库对预提交后的处理超时时间, 事务会用最大超时时间来预提交
+	pub fn timeout(&self) -> usize {
+		TIMEOUT
+	}
 
-		for (k, rw_v) in &logs {
-			match rw_v {
-				RwLog::Read => {},
-				_ => {
-					match rw_v {
-						RwLog::Write(None) => {
-							delete_keys.push(k);
-						}
-						RwLog::Write(Some(v)) => {
-							insert_pairs.push((k, v));
-						}
-						_ => {}
-					}
-				}
-			}
-		}
+	//获取指定表的元信息，tab_name表名，例如"db/user"
+	pub async fn tab_info(&self, tab_name: &Atom) -> Option<Arc<TabMeta>> {
+		self.0.get(tab_name).await
+	}
 
-		if insert_pairs.len() > 0 {
-			async_tab.write_batch(&insert_pairs).await;
+	//检查指定表已存储的元信息与期望的元信息是否一致，主键类型或值类型不一致则返回SchemaMismatch错误
+	pub async fn check_schema(&self, tab_name: &Atom, expected: &TabMeta) -> SResult<()> {
+		match self.0.get(tab_name).await {
+			Some(meta) => {
+				if meta.k == expected.k && meta.v == expected.v {
+					Ok(())
+				} else {
+					Err(format!("SchemaMismatch: tab: {}, stored: {:?}, expected: {:?}", tab_name.as_str(), meta, expected))
+				}
+			},
+			None => Err(String::from("TabNotFound: ") + tab_name.as_str())
 		}
+	}
 
-		if delete_keys.len() > 0 {
-			async_tab.remove_batch(&delete_keys).await;
-		}
+	//获取指定日志文件表中已删除且等待整理的主键列表，按主键升序排列
+	pub async fn tombstones(tab: &Atom) -> SResult<Vec<Bin>> {
+		let file = LogFileDB::open(tab).await?;
+		let mut keys: Vec<Bin> = file.1.removed.lock().keys().map(|k| Arc::new(k.clone())).collect();
+		keys.sort();
+		Ok(keys)
+	}
 
-		Ok(logs)
+	//获取当前日志文件数据库的快照
+	pub async fn snapshot(&self) -> Arc<LogFileDBSnapshot> {
+		Arc::new(LogFileDBSnapshot(self.clone(), Mutex::new(self.0.snapshot().await)))
 	}
 
-	//回滚
-	pub async fn rollback_inner(&mut self) -> DBResult {
-		let mut tab = self.tab.0.lock().await;
-		tab.prepare.remove(&self.id);
+	//强制所有日志文件表分裂
+	pub async fn force_split() -> SResult<()> {
+		let meta = LogFileDB::open(&Atom::from(DB_META_TAB_NAME)).await.unwrap();
+		let map = meta.1.map.lock().clone();
+
+		for (key, value) in map.iter() {
+			//跳过无法解析的元信息记录，不影响其它表的分裂
+			let tab_name = match decode_meta_record(key, value) {
+				Err(e) => {
+					warn!("skip malformed table meta, key: {:?}, reason: {}", key, e);
+					continue;
+				},
+				Ok((tab_name, _)) => tab_name,
+			};
+			//已删除的表不再分裂
+			let mut file = match LogFileDB::open(&tab_name).await {
+				Err(_) => continue,
+				Ok(file) => file,
+			};
+			file.split().await;
+		}
 
 		Ok(())
 	}
 
-	///表分叉的预提交
-	pub async fn fork_prepare_inner(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
-		//检查元信息表中是否有重复的表名
-		if let Some(_) = ALL_TABLES.lock().await.get(&fork_tab_name) {
-			return Err("duplicate fork tab name in meta tab".to_string())
+	//强制指定日志文件表分裂，返回分裂后的可写日志文件id
+	pub async fn force_split_tab(tab_name: &Atom) -> SResult<usize> {
+		let file = LogFileDB::open(tab_name).await?;
+		match file.split().await {
+			Err(e) => Err(format!("split tab failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+			Ok(index) => Ok(index),
 		}
-		Ok(())
 	}
 
-	///表分叉的提交，执行了真正的分叉
-	pub async fn fork_commit_inner(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
-		let index = match self.force_fork_inner().await {
-			Ok(idx) => idx,
-			Err(e) => return Err(e.to_string())
-		};
+	//获取指定日志文件表的元信息的历史版本，按版本号升序排列
+	pub async fn schema_history(tab_name: &Atom) -> SResult<Vec<SchemaVersion>> {
+		match ALL_TABLES.get(tab_name).await {
+			None => Err(String::from("TabNotFound: ") + tab_name.as_str()),
+			Some(info) => Ok(info.history.clone()),
+		}
+	}
 
-		let mut tmi = TableMetaInfo::new(fork_tab_name.clone(), meta);
-		tmi.parent = Some(tab_name.clone());
+	//获取指定表的整理历史，按整理的先后排序，没有整理过的表返回空
+	//整理历史在每次整理表后追加到表的整理历史文件，重启后仍然保留
+	pub fn compaction_history(tab_name: &Atom) -> Vec<CompactionRecord> {
+		let mut path = PathBuf::new();
+		path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		path.push(tab_name.to_string());
+		read_compactions(&path)
+	}
 
-		tmi.parent_log_id = Some(index);
-		tmi.parent = Some(tab_name.clone());
+	//获取指定日志文件表的统计信息
+	pub fn tab_stats(tab_name: &Atom) -> TabStats {
+		let counters = tab_counters(tab_name);
+		TabStats {
+			splits: counters.splits.load(Ordering::Relaxed),
+			lock_acquires: counters.lock_acquires.load(Ordering::Relaxed),
+			lock_wait_total: Duration::from_micros(counters.lock_wait_total.load(Ordering::Relaxed)),
+			lock_wait_max: Duration::from_micros(counters.lock_wait_max.load(Ordering::Relaxed)),
+			ranges: {
+				let buckets = counters.ranges.buckets.lock();
+				let (boundaries, counts) = &*buckets;
+				counts.iter().enumerate().map(|(index, (writes, deletes))| RangeStat {
+					start: if index == 0 { None } else { Some(boundaries[index - 1].clone()) },
+					end: boundaries.get(index).cloned(),
+					writes: *writes,
+					deletes: *deletes,
+				}).collect()
+			},
+			statistics_len: counters.statistics_len.load(Ordering::Relaxed),
+			statistics_alerts: counters.statistics_alerts.load(Ordering::Relaxed),
+		}
+	}
 
-		let mut wb = WriteBuffer::new();
-		tmi.encode(&mut wb);
-		let mut wb1 = WriteBuffer::new();
-		fork_tab_name.encode(&mut wb1);
+	//设置指定日志文件表的主键范围边界，边界是主键的二进制，按字节序比较，空的边界为取消按范围统计
+	//设置后之前的范围统计被清空，范围边界只在运行时有效，重新启动后需要重新设置
+	pub fn set_range_boundaries(tab_name: &Atom, mut boundaries: Vec<Vec<u8>>) {
+		boundaries.sort();
+		boundaries.dedup();
+		let counters = tab_counters(tab_name);
+		let mut buckets = counters.ranges.buckets.lock();
+		buckets.1 = if boundaries.is_empty() {
+			Vec::new()
+		} else {
+			vec![(0, 0); boundaries.len() + 1]
+		};
+		buckets.0 = boundaries;
+		counters.ranges.pending.store(0, Ordering::Relaxed);
+	}
 
-		let db_path = env::var("DB_PATH").unwrap_or("./".to_string());
+	//获取指定日志文件表采样的访问热度，返回主键前缀、采样的读次数和写次数，按读写次数之和降序排列
+	//采样由ACCESS_SAMPLE_RATE开启，次数是采样到的次数，乘以采样间隔为近似的实际次数
+	//最多返回ACCESS_HEATMAP_BUCKETS个前缀，已淘汰的前缀的次数不再保留
+	pub fn access_heatmap(tab_name: &Atom) -> Vec<(Vec<u8>, u64, u64)> {
+		let counters = tab_counters(tab_name);
+		let mut heatmap: Vec<(Vec<u8>, u64, u64)> = counters.heatmap.buckets.lock().iter().map(|(prefix, (reads, writes))| {
+			(prefix.clone(), *reads, *writes)
+		}).collect();
+		heatmap.sort_by(|a, b| (b.1 + b.2).cmp(&(a.1 + a.2)).then_with(|| a.0.cmp(&b.0)));
+		heatmap
+	}
 
-		ALL_TABLES.lock().await.insert(fork_tab_name, tmi);
+	//设置指定日志文件表的主键转换函数，None为移除，之后创建的事务在读写和迭代时都先转换主键，表中保存的是转换后的主键
+	//转换函数只影响之后的读写，不会转换已保存的主键，所以修改转换函数后需要重建表，否则已保存的主键可能无法被查找到
+	//需要保留原始主键的，由调用者将原始主键写入值中
+	pub fn set_key_transform(tab_name: &Atom, transform: Option<KeyTransform>) {
+		match transform {
+			None => {
+				KEY_TRANSFORMS.lock().remove(tab_name);
+			},
+			Some(transform) => {
+				KEY_TRANSFORMS.lock().insert(tab_name.clone(), transform);
+			},
+		}
+	}
 
-		let mut path = PathBuf::new();
-		path.push(db_path);
-		path.push(DB_META_TAB_NAME);
+	//设置指定日志文件表的提交校验函数，None为移除，之后的提交在写入前用事务的全部操作日志调用校验函数
+	//校验失败的提交返回ValidationFailed错误，并移除事务的预提交，事务的修改不会生效；累加操作在操作日志中是增量
+	pub fn set_commit_validator(tab_name: &Atom, validator: Option<CommitValidator>) {
+		match validator {
+			None => {
+				COMMIT_VALIDATORS.lock().remove(tab_name);
+			},
+			Some(validator) => {
+				COMMIT_VALIDATORS.lock().insert(tab_name.clone(), validator);
+			},
+		}
+	}
 
-		let file = match AsyncLogFileStore::open(path, 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, None).await {
-			Err(e) => {
-				panic!("!!!!!!open table = {:?} failed, e: {:?}", "tabs_meta", e);
+	//设置指定日志文件表在表元信息中声明的二级索引的提取函数，None为移除，索引在下次按索引迭代时按新的提取函数重建
+	pub fn set_index_extractor(tab_name: &Atom, index_name: &Atom, extractor: Option<IndexExtractor>) {
+		match extractor {
+			None => {
+				INDEX_EXTRACTORS.lock().remove(&(tab_name.clone(), index_name.clone()));
 			},
-			Ok(store) => store
+			Some(extractor) => {
+				INDEX_EXTRACTORS.lock().insert((tab_name.clone(), index_name.clone()), extractor);
+			},
+		}
+	}
+
+	//异步整理所有日志文件表
+	pub async fn collect() -> SResult<()> {
+		LogFileDB::collect_inner().await.map(|_| ())
+	}
+
+	//整理所有日志文件表，等待整理、统计信息重建和日志文件删除全部完成后，返回整理报告
+	//用于维护工具在运行时外部通过block_on调用，不允许在存储运行时的工作线程中阻塞调用，否则可能导致死锁
+	pub async fn collect_blocking() -> SResult<CollectReport> {
+		let mut report = LogFileDB::collect_inner().await?;
+
+		//确认被移除和被合并的只读日志文件已从磁盘上删除
+		for path in report.removed_logs.iter().chain(report.collected_logs.iter()) {
+			if !path.exists() {
+				report.deleted_files.push(path.clone());
+			}
+		}
+
+		Ok(report)
+	}
+
+	//整理指定日志文件表中删除记录占多数的只读日志文件，不考虑关键字的重复率，并返回整理报告
+	//只读日志文件的删除比例为删除记录和已删除主键的写入记录占全部记录的比例，从最旧的可整理日志文件开始，选择到最新的删除比例达到TOMBSTONE_COLLECT_RATIO的日志文件
+	//被分叉表引用的只读日志文件不整理；分叉表的删除记录需要覆盖父表的值，有更旧的日志文件未整理时删除记录需要覆盖更旧的值，这两种情况下整理时保留删除记录
+	pub async fn collect_tombstones(tab_name: &Atom) -> SResult<CollectReport> {
+		let start = Instant::now();
+		let mut report = CollectReport::default();
+
+		let info = match ALL_TABLES.get(tab_name).await {
+			None => return Err(String::from("TabNotFound: ") + tab_name.as_str()),
+			Some(info) => info,
 		};
 
-		let mut store = AsyncLogFileStore {
-			removed: Arc::new(SpinLock::new(XHashMap::default())),
-			map: Arc::new(SpinLock::new(BTreeMap::new())),
-			log_file: file.clone(),
-			tmp_map: Arc::new(SpinLock::new(XHashMap::default())),
-			writable_path: Arc::new(SpinLock::new(None)),
-			is_statistics: Arc::new(AtomicBool::new(false)),
-			is_init: Arc::new(AtomicBool::new(true)),
-			statistics: Arc::new(SpinLock::new(VecDeque::new())),
+		let file = LogFileDB::open(tab_name).await?;
+		//未加载的更早的日志文件中可能有被删除记录覆盖的值，丢弃删除记录前需要先加载全部日志文件
+		file.load_cold().await?;
+		let removed: XHashSet<Vec<u8>> = file.1.removed.lock().keys().cloned().collect();
+		if removed.is_empty() {
+			//没有等待整理的删除记录
+			report.elapsed = start.elapsed();
+			return Ok(report);
+		}
+
+		//被分叉表引用的日志文件是分叉点及之前的日志文件，按日志文件名匹配
+		let referenced: XHashSet<PathBuf> = LogFileDB::log_file_refs(tab_name).await?
+			.into_iter()
+			.filter(|(_, refs)| refs.len() > 0)
+			.filter_map(|(log_path, _)| log_path.file_name().map(PathBuf::from))
+			.collect();
+		let logs: Vec<(PathBuf, u64)> = file.1.statistics.lock().iter().map(|(log_path, _, key_len)| (log_path.clone(), *key_len)).collect();
+
+		//从最旧到最新计算未被引用的只读日志文件的删除比例，被引用的日志文件之后的日志文件才可以整理
+		let ratio = TOMBSTONE_COLLECT_RATIO.load(Ordering::Relaxed) as u64;
+		let mut candidates = Vec::new();
+		let mut has_older = false;
+		let mut last = None;
+		for (log_path, key_len) in logs {
+			if log_path.file_name().map(|name| referenced.contains(Path::new(name))).unwrap_or(false) {
+				has_older = true;
+				candidates.clear();
+				last = None;
+				continue;
+			}
+
+			let records = read_log_records(log_path.clone(), info.config.load_block_size()).await?;
+			let dead = records.iter().filter(|(method, key, _)| match method {
+				LogMethod::Remove => true,
+				_ => removed.contains(key),
+			}).count() as u64;
+			let total = records.len() as u64;
+			if total == 0 || dead * 100 >= total * ratio {
+				last = Some(candidates.len());
+			}
+			candidates.push((log_path, key_len));
+		}
+		let selected = match last {
+			None => {
+				//没有删除比例达到限制的只读日志文件
+				report.elapsed = start.elapsed();
+				return Ok(report);
+			},
+			Some(last) => &candidates[..last + 1],
 		};
 
-		// 找到父表的元信息，将它的引用计数加一
-		let mut lock = ALL_TABLES.lock().await;
-		if lock.contains_key(&tab_name) {
-			let mut value = lock.get_mut(&tab_name).unwrap();
-			value.ref_count += 1;
-			let mut b = WriteBuffer::new();
-			tab_name.encode(&mut b);
+		//没有有效关键字的只读日志文件直接移除，其它选择的只读日志文件合并
+		let mut remove_logs = Vec::new();
+		let mut collect_logs = Vec::new();
+		for (log_path, key_len) in selected {
+			if *key_len == 0 {
+				remove_logs.push(log_path.clone());
+			} else {
+				collect_logs.push(log_path.clone());
+			}
+		}
 
-			let mut b2 = WriteBuffer::new();
-			value.encode(&mut b2);
-			store.write(b.bytes, b2.bytes).await;
+		//没有父表且没有更旧的未整理日志文件时，删除记录没有需要覆盖的值，整理时可以直接丢弃
+		let before = log_files_size(tab_name);
+		let is_hidden_remove = info.parent.is_none() && !has_older;
+		let remove_logs_copy = remove_logs.clone();
+		let collect_logs_copy = collect_logs.clone();
+		let permit = COMPACTION_IO_LIMITER.acquire().await;
+		//整理会改变日志文件中的记录，整理后到下次加载前不能按提交序号扫描日志文件
+		file.lock_tab().await.reset_scan(None);
+		if let Err(e) = file.1.log_file.collect_logs(remove_logs, collect_logs, 1024 * 1024, info.config.load_block_size(), is_hidden_remove).await {
+			return Err(format!("Collect tombstones failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
 		}
+		drop(permit);
+		report.tabs = 1;
+		report.removed_logs = remove_logs_copy;
+		report.collected_logs = collect_logs_copy;
 
-		// 新创建的分叉表信息写入元信息表中
-		// TODO: 错误处理
-		store.write(wb1.bytes, wb.bytes).await;
+		LogFileDB::restat_after_collect(tab_name, &file, &info.config).await;
+		record_compaction(tab_name, before, &report.removed_logs, &report.collected_logs).await;
+		clear_repairs(tab_name).await;
 
-		Ok(())
+		report.elapsed = start.elapsed();
+		Ok(report)
 	}
 
-	///表分叉的回滚，表分叉已提交则无法回滚
-	pub async fn fork_rollback_inner(&self) -> DBResult {
-		Ok(())
-	}
+	//整理所有日志文件表，并返回整理报告
+	async fn collect_inner() -> SResult<CollectReport> {
+		let start = Instant::now();
+		let mut report = CollectReport::default();
 
-	///强制日志文件分裂
-	async fn force_fork_inner(&self) -> Result<usize> {
-		self.tab.1.clone().force_fork().await
-	}
-}
+		//获取LogFileDB的元信息
+		let meta = LogFileDB::open(&Atom::from(DB_META_TAB_NAME)).await.unwrap();
+		let map = meta.1.map.lock();
 
-//================================ 内部结构和方法
-const TIMEOUT: usize = 100;
+		//遍历LogFileDB中的所有LogFileTab
+		for (key, value) in map.iter() {
+			//跳过无法解析的元信息记录，不影响其它表的整理
+			let tab_name = match decode_meta_record(key, value) {
+				Err(e) => {
+					warn!("skip malformed table meta, key: {:?}, reason: {}", key, e);
+					continue;
+				},
+				Ok((tab_name, _)) => tab_name,
+			};
+			//已删除的表不再整理
+			let mut file = match LogFileDB::open(&tab_name).await {
+				Err(_) => continue,
+				Ok(file) => file,
+			};
+			//有未加载的更早的日志文件的表没有这些日志文件的统计信息，不整理
+			if file.is_cold().await {
+				continue;
+			}
+			let config = ALL_TABLES.get(&tab_name).await.map(|tm| tm.config.clone()).unwrap_or_default();
+			let before = log_files_size(&tab_name);
+			if RANGE_GROUPED_COLLECT.load(Ordering::Relaxed) {
+				//按范围重写后，重写前的只读日志文件中没有有效的关键字，在本次整理中移除
+				if let Err(e) = LogFileDB::group_by_range(&tab_name, &file, &config).await {
+					warn!("group by range failed, tab: {}, reason: {}", tab_name.as_str(), e);
+				}
+			}
 
+			//从LogFileTab中，根据文件名从小到大的选择需要整理的只读日志文件
+			let mut remove_logs = Vec::new();
+			let mut collect_logs = Vec::new();
+			let mut collected_logs = XHashMap::default();
+			for (log_path, log_len, key_len) in file.1.statistics.lock().iter() {
+				if *key_len == 0 {
+					//当前只读日志文件中没有新的关键字，则准备移除当前只读日志文件，并继续选择下一个只读日志文件
+					remove_logs.push(log_path.clone());
+					collected_logs.insert(log_path.clone(), ());
+					continue;
+				}
 
-type BinMap = OrdMap<Tree<Bon, Bin>>;
+				let f = *log_len as f64 / *key_len as f64;
+				if f < 1.5 {
+					//当前只读日志文件的关键字重复率未达限制，则立即停止选择，并准备整理已选择的只读日志文件
+					break; //TODO 后续还要判断分叉的分裂点，除了分裂点为最大的只读日志文件外，其它分裂点将无法选择作为整理的只读日志文件，至到对应分裂点的分叉表被删除...
+				}
 
-// 内存表
-struct MemeryTab {
-	pub prepare: Prepare,
-	pub root: BinMap,
-	pub tab: Atom,
-}
+				//准备整理当前只读日志文件
+				collect_logs.push(log_path.clone());
+				collected_logs.insert(log_path.clone(), ());
+			}
 
-pub struct MemIter{
-	_root: BinMap,
-	_filter: Filter,
-	point: usize,
-}
+			//整理需要整理的只读日志文件
+			let remove_logs_copy = remove_logs.clone();
+			let collect_logs_copy = collect_logs.clone();
+			let permit = COMPACTION_IO_LIMITER.acquire().await;
+			//整理会改变日志文件中的记录，整理后到下次加载前不能按提交序号扫描日志文件
+			file.lock_tab().await.reset_scan(None);
+			if let Err(e) = file.1.log_file.collect_logs(remove_logs, collect_logs, 1024 * 1024, config.load_block_size(), false).await {
+				//整理指定的LogFileTab失败，则立即退出整理
+				return Err(format!("Collect LogFileTab failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+			}
+			drop(permit);
+			report.tabs += 1;
+			LogFileDB::restat_after_collect(&tab_name, &file, &config).await;
+			record_compaction(&tab_name, before, &remove_logs_copy, &collect_logs_copy).await;
+			clear_repairs(&tab_name).await;
+			report.removed_logs.extend(remove_logs_copy);
+			report.collected_logs.extend(collect_logs_copy);
+		}
 
-impl Drop for MemIter{
-	fn drop(&mut self) {
-		unsafe{Box::from_raw(self.point as *mut <Tree<Bin, Bin> as OIter<'_>>::IterType)};
+		report.elapsed = start.elapsed();
+		return Ok(report);
 	}
-}
 
-impl MemIter{
-	pub fn new<'a>(tab: &Atom, root: BinMap, it: <Tree<Bon, Bin> as OIter<'a>>::IterType, filter: Filter) -> MemIter{
-		MemIter{
-			_root: root,
-			_filter: filter,
-			point: Box::into_raw(Box::new(it)) as usize,
+	//上次按范围重写后有新的写入或删除，则按范围重写指定日志文件表的有效记录，并重建只读日志文件的统计信息
+	//分叉表的内存表包括父表的记录，有分叉的表的日志文件被子表共享，都不按范围重写
+	async fn group_by_range(tab_name: &Atom, file: &LogFileTab, config: &TabConfig) -> SResult<()> {
+		let boundaries = file.2.ranges.buckets.lock().0.clone();
+		if boundaries.is_empty() || file.2.ranges.pending.load(Ordering::Relaxed) == 0 {
+			return Ok(());
+		}
+		match ALL_TABLES.get(tab_name).await {
+			Some(info) if info.parent.is_none() && info.ref_count == 0 => (),
+			_ => return Ok(()),
 		}
-	}
-}
 
-impl Iter for MemIter{
-	type Item = (Bin, Bin);
-	fn next(&mut self) -> Option<NextResult<Self::Item>>{
+		let permit = COMPACTION_IO_LIMITER.acquire().await;
+		let count = file.group_by_range(&boundaries).await?;
+		drop(permit);
+		file.2.ranges.pending.store(0, Ordering::Relaxed);
+		LogFileDB::restat_after_collect(tab_name, file, config).await;
+		info!("group by range, tab: {}, ranges: {}, count: {}", tab_name.as_str(), boundaries.len() + 1, count);
 
-		let mut it = unsafe{Box::from_raw(self.point as *mut <Tree<Bin, Bin> as OIter<'_>>::IterType)};
-		let r = Some(Ok(match it.next() {
-			Some(&Entry(ref k, ref v)) => {
-				Some((k.clone(), v.clone()))
-			},
-			None => None,
-		}));
-		mem::forget(it);
-		r
+		Ok(())
 	}
-}
 
-pub struct MemKeyIter{
-	_root: BinMap,
-	_filter: Filter,
-	point: usize,
-}
+	//整理后重新分析指定日志文件表的所有日志文件，并重建只读日志文件的统计信息
+	//整理只重写日志文件，不修改内存表，整理期间的读取始终看到完整的内存表
+	//统计信息和移除缓冲在独立的存储中重建，完成后在内存表锁内一次替换，避免并发的提交和读取看到部分重建的状态
+	async fn restat_after_collect(tab_name: &Atom, file: &LogFileTab, config: &TabConfig) {
+		let collect_start_time = Instant::now();
 
-impl Drop for MemKeyIter{
-	fn drop(&mut self) {
-		unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)};
-	}
-}
+		//只统计，不写入键值缓冲区
+		let mut store = AsyncLogFileStore::new(file.1.log_file.clone());
+		store.is_init.store(false, Ordering::SeqCst);
 
-impl MemKeyIter{
-	pub fn new(tab: &Atom, root: BinMap, keys: Keys<'_, Tree<Bon, Bin>>, filter: Filter) -> MemKeyIter{
-		MemKeyIter{
-			_root: root,
-			_filter: filter,
-			point: Box::into_raw(Box::new(keys)) as usize,
+		//获取整理后LogFileTab中的所有有效日志文件路径列表
+		if let Ok(mut log_paths) = read_log_paths(&file.1.log_file).await {
+			//从大到小的分析整理后的日志文件，并更新LogFileTab的统计信息
+			let mut offset = None;
+			let mut read_len = config.load_block_size();
+			let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+			while let Some(log_path) = log_paths.pop() {
+				let log_file = match AsyncFile::open(rt.clone(), log_path.clone(), AsyncFileOptions::OnlyRead).await {
+					Err(e) => {
+						//打开指定日志文件失败，则继续下一个日志文件的分析
+						error!("Statistic failed after collected, tab: {}, reason: {:?}", tab_name.as_str(), e);
+						continue;
+					}
+					Ok(f) => {
+						f
+					},
+				};
+
+				loop {
+					match read_log_file(log_path.clone(),
+										log_file.clone(),
+										offset,
+										read_len).await {
+						Err(e) => {
+							error!("Statistic failed after collected, tab: {}, reason: {:?}", tab_name.as_str(), e);
+						},
+						Ok((file_offset, bin)) => {
+							match read_log_file_block(log_path.clone(),
+													  &bin,
+													  file_offset,
+													  read_len,
+													  true) {
+								Err(e) => {
+									error!("Statistic failed after collected, tab: {}, reason: {:?}", tab_name.as_str(), e);
+								},
+								Ok((next_file_offset, next_len, logs)) => {
+									//分析当前只读日志文件的日志块，并更新当前只读日志文件的统计信息
+									for (method, key, value) in logs {
+										if store.is_require(Some(&log_path), &key) {
+											//需要分析的关键字
+											store.load(Some(&log_path), method, key, value);
+										}
+									}
+
+									if next_file_offset == 0 && next_len == 0 {
+										//已读到日志文件头，则继续下一个日志文件的读取
+										offset = None;
+										read_len = 3 * 1024;
+										break;
+									} else {
+										//更新日志文件位置
+										offset = Some(next_file_offset);
+										read_len = next_len;
+									}
+								},
+							}
+						},
+					}
+				}
+			}
 		}
-	}
-}
 
-impl Iter for MemKeyIter{
-	type Item = Bin;
-	fn next(&mut self) -> Option<NextResult<Self::Item>>{
-		let it = unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)};
-		let r = Some(Ok(match unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)}.next() {
-			Some(k) => {
-				Some(k.clone())
-			},
-			None => None,
-		}));
-		mem::forget(it);
-		r
+		//在内存表锁内替换统计信息和移除缓冲，重建期间被重新写入的关键字不再是已删除
+		{
+			let _lock = file.lock_tab().await;
+			let removed: XHashMap<Vec<u8>, ()> = {
+				let map = file.1.map.lock();
+				mem::replace(&mut *store.removed.lock(), XHashMap::default())
+					.into_iter()
+					.filter(|(key, _)| !map.contains_key(key))
+					.collect()
+			};
+			*file.1.removed.lock() = removed;
+			*file.1.statistics.lock() = mem::replace(&mut *store.statistics.lock(), VecDeque::new());
+			*file.1.writable_path.lock() = store.writable_path.lock().take();
+			file.1.tmp_map.lock().clear();
+			file.1.is_statistics.store(store.is_statistics.load(Ordering::Relaxed), Ordering::Relaxed);
+		}
+		observe_statistics(tab_name, &file.1, &file.2);
+		if let Err(e) = file.rebalance_blobs().await {
+			warn!("rebalance blobs failed after collected, tab: {}, reason: {}", tab_name.as_str(), e);
+		}
+		info!("Collect LogFileTab ok, time: {:?}, tab: {}, Statistics: {:?}",
+			  Instant::now() - collect_start_time,
+			  tab_name.as_str(),
+			  &*file.1.statistics.lock());
 	}
 }
 
-#[derive(Clone)]
-pub struct LogFileMetaTxn {
-	alters: Arc<Mutex<XHashMap<Atom, Option<Arc<TabMeta>>>>>,
-}
+/*
+* 日志文件数据库快照，包括日志文件数据库和日志文件数据库的元信息
+*/
+pub struct LogFileDBSnapshot(LogFileDB, Mutex<TabLog>);
 
-impl LogFileMetaTxn {
-	// 创建表、修改指定表的元数据
-	pub async fn alter(&self, tab_name: &Atom, meta: Option<Arc<TabMeta>>) -> DBResult {
-		self.alters.lock().await.insert(tab_name.clone(), meta);
-		Ok(())
+impl LogFileDBSnapshot {
+	//列出全部的表
+	pub async fn list(&self) -> Box<dyn Iterator<Item=Atom>> {
+		Box::new(self.1.lock().await.list())
 	}
 
-	//快照拷贝表
-	pub async fn snapshot(&self, _tab: &Atom, _from: &Atom) -> DBResult {
-		Ok(())
+	//表的元信息
+	pub async fn tab_info(&self, tab_name: &Atom) -> Option<Arc<TabMeta>> {
+		self.1.lock().await.get(tab_name)
 	}
 
-	//修改指定表的名字
-	pub async fn rename(&self, _tab: &Atom, _new_name: &Atom) -> DBResult {
+	//检查该表是否可以创建
+	pub fn check(&self, _tab: &Atom, _meta: &Option<Arc<TabMeta>>) -> DBResult {
 		Ok(())
 	}
 
-	//获得事务的状态
-	pub async fn get_state(&self) -> TxState {
-		TxState::Ok
+	//新增 修改 删除 表
+	pub async fn alter(&self, tab_name: &Atom, meta: Option<Arc<TabMeta>>) {
+		self.1.lock().await.alter(tab_name, meta)
 	}
 
-	//预提交一个事务
-	pub async fn prepare(&self, _timeout: usize) -> DBResult {
-		Ok(())
+	//创建指定表的表事务
+	pub async fn tab_txn(&self, tab_name: &Atom, id: &Guid, writable: bool) -> SResult<TxnType> {
+		self.1.lock().await.build(BuildDbType::LogFileDB, tab_name, id, writable).await
 	}
 
-	//提交一个事务
-	pub async fn commit(&self) -> CommitResult {
-		for (tab_name, meta) in self.alters.lock().await.iter() {
-			if ALL_TABLES.lock().await.get(tab_name).is_some() && meta.is_some() {
-				return Err(format!("tab_name: {:?} exist", tab_name))
-			}
-			let mut kt = WriteBuffer::new();
-			tab_name.clone().encode(&mut kt);
-			let db_path = env::var("DB_PATH").unwrap_or("./".to_string());
-			let mut path = PathBuf::new();
-			path.push(db_path.clone());
-			path.push(DB_META_TAB_NAME);
-
-			let file = match AsyncLogFileStore::open(path, 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, None).await {
-				Err(e) => {
-					panic!("!!!!!!open table = {:?} failed, e: {:?}", "tabs_meta", e);
-				},
-				Ok(store) => store
-			};
+	//创建一个元信息表事务
+	pub fn meta_txn(&self, _id: &Guid) -> Arc<LogFileMetaTxn> {
+		Arc::new(LogFileMetaTxn::new())
+	}
 
-			let mut store = AsyncLogFileStore {
-				removed: Arc::new(SpinLock::new(XHashMap::default())),
-				map: Arc::new(SpinLock::new(BTreeMap::new())),
-				log_file: file.clone(),
-				tmp_map: Arc::new(SpinLock::new(XHashMap::default())),
-				writable_path: Arc::new(SpinLock::new(None)),
-				is_statistics: Arc::new(AtomicBool::new(false)),
-				is_init: Arc::new(AtomicBool::new(true)),
-				statistics: Arc::new(SpinLock::new(VecDeque::new())),
-			};
+	//元信息表的预提交
+	pub async fn prepare(&self, id: &Guid) -> DBResult{
+		(self.0).0.prepare(id, &mut *self.1.lock().await).await
+	}
 
-			match meta {
-				Some(m) => {
-					//增加或修改元信息表中的元信息
-					let mt = TabMeta::new(m.k.clone(), m.v.clone());
-					let tmi = TableMetaInfo::new(tab_name.clone(), mt);
-					let mut vt = WriteBuffer::new();
-					tmi.encode(&mut vt);
+	//元信息表的提交
+	pub async fn commit(&self, id: &Guid){
+		(self.0).0.commit(id).await
+	}
 
-					// 新创建的表加入ALL_TABLES的缓存
-					let meta_name = Atom::from(db_path + &DB_META_TAB_NAME);
-					ALL_TABLES.lock().await.insert(tab_name.clone(), tmi.clone());
-					// 新创建表的元信息写入元信息表中
-					store.write(kt.bytes, vt.bytes).await;
-				}
+	//元信息表的回滚
+	pub async fn rollback(&self, id: &Guid){
+		(self.0).0.rollback(id).await
+	}
+
+	//日志文件库修改通知
+	pub fn notify(&self, _event: Event) {}
+}
+
+/*
+* 日志文件事务的引用
+*/
+pub struct RefLogFileTxn(Mutex<FileMemTxn>);
+
+unsafe impl Sync for RefLogFileTxn  {}
+
+impl RefLogFileTxn {
+	//获取事务的状态
+	pub async fn get_state(&self) -> TxState {
+		self.0.lock().await.state.clone()
+	}
+
+	//获取事务的提交序号，事务未提交则返回None
+	pub async fn commit_seq(&self) -> Option<u64> {
+		self.0.lock().await.commit_seq
+	}
+
+	//获取事务提交时分配的表的提交水位，应用在提交返回后记录，用于在重新打开后与恢复的水位比较，事务未提交则返回None
+	pub async fn watermark(&self) -> Option<u64> {
+		self.0.lock().await.watermark
+	}
+
+	//获取事务的链路追踪id
+	pub async fn trace_id(&self) -> Option<Atom> {
+		self.0.lock().await.trace_id.clone()
+	}
+
+	//设置事务的链路追踪id，链路追踪id会记录在事务的日志中
+	pub async fn set_trace_id(&self, trace_id: Option<Atom>) {
+		self.0.lock().await.trace_id = trace_id;
+	}
+
+	//获取指定主键在指定提交序号时的值，即提交序号小于等于seq的最后一次写入的值
+	//优先从内存中保留的历史版本读取，内存中没有则按提交序号对应的日志记录数量扫描表自己的日志文件
+	//指定的提交序号早于可扫描的最早提交、进程重启之前，或表是冷表、分叉表、日志表，或整理后还未重新加载，则返回HistoryUnavailable错误
+	pub async fn get_as_of(&self, tab: &Atom, key: Bin, seq: u64) -> SResult<Option<Bin>> {
+		let (key, file) = {
+			let txn = self.0.lock().await;
+			(txn.normalize(key), txn.tab.clone())
+		};
+
+		let (records, gen) = {
+			let lock = file.lock_tab().await;
+			match lock.history.get(&key) {
+				Some(versions) => if let Some((_, value)) = versions.iter().rev().find(|(s, _)| *s <= seq) {
+					return Ok(value.clone());
+				},
+				None if seq >= lock.history_from.max(lock.history_evicted) => {
+					//开启历史版本后主键没有被写入过，则当前值就是历史值
+					return Ok(lock.root.get(&Bon::new(key)).cloned());
+				},
+				None => (),
+			}
+			match lock.scan_records(seq) {
+				None => return Err(format!("HistoryUnavailable: tab: {}, seq: {}, oldest: {}", tab.as_str(), seq, lock.scan_base.map(|(s, _)| s).unwrap_or(lock.history_from))),
+				Some(records) => (records, lock.scan_gen),
+			}
+		};
+
+		//在表锁外扫描日志文件，扫描期间日志文件被整理或重写，则扫描的结果不可信
+		let value = scan_as_of(tab, &file, &key, records, seq).await?;
+		if file.lock_tab().await.scan_gen != gen {
+			return Err(format!("HistoryUnavailable: tab: {}, seq: {}, reason: log rewritten", tab.as_str(), seq));
+		}
+		Ok(value)
+	}
+
+	//批量检查指定主键集是否存在于指定表中，只获取一次事务锁，返回与输入顺序一致的位集
+	//只检查存在性，不记录读操作，所以不参与预提交的冲突检查
+	pub async fn contains_bitset(&self, _tab: &Atom, keys: &[Bin]) -> SResult<BitSet> {
+		let txn = self.0.lock().await;
+		let mut bits = BitSet::new(keys.len());
+		for (index, key) in keys.iter().enumerate() {
+			let key = txn.normalize(key.clone());
+			//已溢出的主键以溢出日志中的写入为准
+			let exists = match txn.spill.as_ref().and_then(|spill| spill.contains(&key)) {
+				Some(exists) => exists,
+				None => txn.root.get(&Bon::new(key)).is_some(),
+			};
+			if exists {
+				bits.set(index, true);
+			}
+		}
+
+		Ok(bits)
+	}
+
+	//获取指定主键的值的指定字节范围，范围超出值的长度则截断，主键不存在则返回None
+	//外置存储的值在加载时已读入内存表，所以范围读取直接从内存中的值截取，不读取外置存储文件，只复制指定范围的字节
+	pub async fn get_range(&self, tab: &Atom, key: Bin, offset: usize, len: usize) -> SResult<Option<Bin>> {
+		let mut txn = self.0.lock().await;
+		if txn.sink {
+			return Err(String::from("LogSink: point read unsupported, tab: ") + tab.as_str());
+		}
+		let key = txn.normalize(key);
+		let value = match txn.get(key).await? {
+			None => return Ok(None),
+			Some(value) => value,
+		};
+		let start = offset.min(value.len());
+		let end = offset.saturating_add(len).min(value.len());
+
+		Ok(Some(Arc::new(value[start..end].to_vec())))
+	}
+
+	//查询指定主键集的记录集
+	pub async fn query(
+		&self,
+		arr: Arc<Vec<TabKV>>,
+		_lock_time: Option<usize>,
+		_readonly: bool
+	) -> SResult<Vec<TabKV>> {
+		let mut value_arr = Vec::new();
+		for tabkv in arr.iter() {
+			let mut txn = self.0.lock().await;
+			if txn.sink {
+				return Err(String::from("LogSink: point read unsupported, tab: ") + tabkv.tab.as_str());
+			}
+			let key = txn.normalize(tabkv.key.clone());
+			let value = txn.get(key).await?;
+
+			value_arr.push(
+				TabKV{
+					ware: tabkv.ware.clone(),
+					tab: tabkv.tab.clone(),
+					key: tabkv.key.clone(),
+					index: tabkv.index.clone(),
+					value: value,
+				}
+			)
+		}
+		Ok(value_arr)
+	}
+
+	//批量查询指定主键集的值，先按内存表的主键顺序排序，再用一个只向前的游标顺序遍历一次内存表，返回与输入顺序一致的值
+	//相邻主键之间的记录不超过MULTI_GET_SEEK_STEPS时向前遍历，否则从内存表中重新定位，所以每条记录最多访问一次，
+	//每个主键最多访问MULTI_GET_SEEK_STEPS条记录，可写事务与get一样记录已存在主键的读操作
+	pub async fn multi_get_sorted(&self, _tab: &Atom, keys: &[Bin]) -> SResult<Vec<Option<Bin>>> {
+		let mut txn = self.0.lock().await;
+		let keys: Vec<Bin> = keys.iter().map(|key| txn.normalize(key.clone())).collect();
+		let bons: Vec<Bon> = keys.iter().map(|key| Bon::new(key.clone())).collect();
+		let mut order: Vec<usize> = (0..bons.len()).collect();
+		order.sort_by(|a, b| bons[*a].cmp(&bons[*b]));
+
+		let mut values: Vec<Option<Bin>> = vec![None; keys.len()];
+		if let Some(first) = order.first() {
+			let root = txn.root.clone();
+			let mut it = root.iter(Some(&bons[*first]), false);
+			let mut current = it.next();
+			let mut steps = 1;
+			for index in order.iter() {
+				let target = &bons[*index];
+				//主键已排序，游标只向前移动，重复的主键停在同一条记录上
+				let mut skipped = 0;
+				while let Some(Entry(k, _)) = current {
+					if k >= target {
+						break;
+					}
+					if skipped == MULTI_GET_SEEK_STEPS {
+						//与下一个主键之间的记录太多，直接定位到下一个主键
+						it = root.iter(Some(target), false);
+						current = it.next();
+						steps += 1;
+						break;
+					}
+					current = it.next();
+					skipped += 1;
+					steps += 1;
+				}
+				if let Some(Entry(k, v)) = current {
+					if k == target {
+						values[*index] = Some(v.clone());
+					}
+				}
+			}
+			txn.tab.2.multi_get_steps.fetch_add(steps, Ordering::Relaxed);
+		}
+
+		//已溢出的主键以溢出日志中的写入为准
+		if let Some(spill) = &txn.spill {
+			for (index, key) in keys.iter().enumerate() {
+				match spill.read(key).await {
+					Err(e) => return Err(format!("spill read failed, txn: {:?}, key: {:?}, reason: {:?}", txn.id, key, e)),
+					Ok(Some(value)) => values[index] = value,
+					Ok(None) => (),
+				}
+			}
+		}
+
+		if txn.writable {
+			for (key, value) in keys.into_iter().zip(values.iter()) {
+				if value.is_some() {
+					txn.rwlog.entry(key).or_insert(RwLog::Read);
+				}
+			}
+		}
+
+		Ok(values)
+	}
+
+	//并发查询指定主键集的记录集，返回结果与输入的顺序一致
+	//事务没有溢出时所有值都在事务的内存表中，直接使用普通查询；已溢出时内存表中的值直接读取，
+	//溢出日志中的值按QUERY_PARALLELISM分批，在存储运行时上并发读取，每批全部读取完成后再读取下一批
+	pub async fn query_parallel(&self, arr: Arc<Vec<TabKV>>) -> SResult<Vec<TabKV>> {
+		let mut txn = self.0.lock().await;
+		if txn.sink {
+			return Err(String::from("LogSink: point read unsupported, tab: ") + txn.tab.0.lock().await.tab.as_str());
+		}
+		if txn.spill.is_none() {
+			drop(txn);
+			return self.query(arr, None, false).await;
+		}
+
+		//先将溢出日志的写缓冲写入文件，之后只需要从文件中读取已溢出的值
+		let file = {
+			let txn_id = txn.id.clone();
+			let spill = txn.spill.as_mut().unwrap();
+			if let Err(e) = spill.flush().await {
+				return Err(format!("spill flush failed, txn: {:?}, reason: {:?}", txn_id, e));
+			}
+			spill.file.clone()
+		};
+
+		let mut values: Vec<Option<Bin>> = vec![None; arr.len()];
+		let mut reads = Vec::new();
+		for (index, tabkv) in arr.iter().enumerate() {
+			let key = txn.normalize(tabkv.key.clone());
+			txn.tab.2.heatmap.sample(&key, false);
+			let spilled = txn.spill.as_ref().unwrap().index.get(&key).cloned();
+			match spilled {
+				Some(Some((pos, len))) => reads.push((index, pos, len)),
+				Some(None) => (),
 				None => {
-					//删除元信息表中的元信息
-					let mut parent = None;
-					match ALL_TABLES.lock().await.get(&tab_name) {
-						Some(tab) => {
-							if tab.ref_count > 0 {
-								return Err(format!("delete tab: {:?} failed, ref_count = {:?}", tab.tab_name, tab.ref_count))
-							} else {
-								store.remove(kt.bytes).await;
-								parent = tab.parent.clone();
+					values[index] = txn.root.get(&Bon::new(key.clone())).cloned();
+					if values[index].is_some() && txn.writable && !txn.rwlog.contains_key(&key) {
+						txn.rwlog.insert(key, RwLog::Read);
+					}
+				},
+			}
+		}
+
+		let limit = QUERY_PARALLELISM.load(Ordering::Relaxed).max(1);
+		let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+		for batch in reads.chunks(limit) {
+			let mut async_map = rt.map();
+			for (index, pos, len) in batch {
+				let (index, pos, len, file) = (*index, *pos, *len, file.clone());
+				async_map.join(AsyncRuntime::Multi(rt.clone()), async move {
+					let inflight = PARALLEL_READS.fetch_add(1, Ordering::SeqCst) + 1;
+					PARALLEL_READ_PEAK.fetch_max(inflight, Ordering::Relaxed);
+					let r = file.read(pos, len).await;
+					PARALLEL_READS.fetch_sub(1, Ordering::SeqCst);
+					match r {
+						Ok(bin) if bin.len() < len => Err(Error::new(ErrorKind::UnexpectedEof, format!("spill log truncated, pos: {}, len: {}", pos, len))),
+						Ok(bin) => Ok((index, bin)),
+						Err(e) => Err(e),
+					}
+				});
+			}
+
+			let loaded = match async_map.map(AsyncRuntime::Multi(rt.clone())).await {
+				Err(e) => return Err(format!("query parallel failed, txn: {:?}, reason: {:?}", txn.id, e)),
+				Ok(loaded) => loaded,
+			};
+			for r in loaded {
+				match r {
+					Err(e) => return Err(format!("spill read failed, txn: {:?}, reason: {:?}", txn.id, e)),
+					Ok((index, bin)) => values[index] = Some(Arc::new(bin)),
+				}
+			}
+		}
+
+		Ok(arr.iter().zip(values).map(|(tabkv, value)| TabKV {
+			ware: tabkv.ware.clone(),
+			tab: tabkv.tab.clone(),
+			key: tabkv.key.clone(),
+			index: tabkv.index.clone(),
+			value,
+		}).collect())
+	}
+
+	//插入、修改和删除指定主键集的记录集，值为None就是删除，主键不存在则为插入，主键存在则为修改
+	pub async fn modify(&self, arr: Arc<Vec<TabKV>>, _lock_time: Option<usize>, _readonly: bool) -> DBResult {
+		for tabkv in arr.iter() {
+			let mut txn = self.0.lock().await;
+			let key = txn.normalize(tabkv.key.clone());
+			if tabkv.value == None {
+				match txn.delete(key).await {
+					Ok(_) => (),
+					Err(e) => return Err(e.to_string())
+				};
+			} else {
+				match txn.upsert(key, tabkv.value.clone().unwrap()).await {
+					Ok(_) => (),
+					Err(e) => return Err(e.to_string())
+				};
+			}
+		}
+		Ok(())
+	}
+
+	//累加指定主键的计数器，计数器的值是Bon编码的i64，并发事务对同一主键的累加不冲突
+	pub async fn increment(&self, key: Bin, delta: i64) -> DBResult {
+		let mut txn = self.0.lock().await;
+		let key = txn.normalize(key);
+		txn.increment(key, delta).await
+	}
+
+	//读取指定主键的计数器，包括事务中未提交的累加，计数器表的计数器周期已过则为0，主键不存在也为0
+	//只读取事务开始时的值，不记录读操作，与其它事务的累加不冲突
+	pub async fn counter(&self, key: Bin) -> SResult<i64> {
+		let txn = self.0.lock().await;
+		let key = txn.normalize(key);
+		let window = txn.tab.2.counter_window.load(Ordering::Relaxed);
+		let value = match txn.rwlog.get(&key) {
+			Some(RwLog::Increment(delta)) => increment_value(txn.root.get(&Bon::new(key.clone())), *delta, window)?,
+			_ => match txn.current(&key).await? {
+				None => return Ok(0),
+				Some(value) => value,
+			},
+		};
+
+		if window == 0 {
+			match i64::decode(&mut ReadBuffer::new(&value, 0)) {
+				Err(e) => Err(format!("InvalidCounter: value: {:?}, reason: {:?}", value.as_slice(), e)),
+				Ok(n) => Ok(n),
+			}
+		} else {
+			read_window_counter(Some(&value), window, clock::now_millis())
+		}
+	}
+
+	//插入指定主键的记录，主键在事务中已存在则返回KeyExists错误
+	pub async fn insert(&self, key: Bin, value: Bin) -> DBResult {
+		let mut txn = self.0.lock().await;
+		let key = txn.normalize(key);
+		txn.insert(key, value).await
+	}
+
+	//修改指定主键的记录，主键在事务中不存在则返回KeyNotFound错误
+	pub async fn update(&self, key: Bin, value: Bin) -> DBResult {
+		let mut txn = self.0.lock().await;
+		let key = txn.normalize(key);
+		txn.update(key, value).await
+	}
+
+	//将from的值移动到to，并删除from，与事务的其它修改一起原子提交，返回from是否存在
+	pub async fn move_key(&self, from: Bin, to: Bin) -> SResult<bool> {
+		let mut txn = self.0.lock().await;
+		let (from, to) = (txn.normalize(from), txn.normalize(to));
+		txn.move_key(from, to).await
+	}
+
+	//应用在其它地方产生的修改集，值为Some是写入，为None是删除，修改集与事务的其它修改一起在提交时原子生效
+	//修改集中同一个主键的多个修改，按主键转换后去重，以修改集中最后一个修改为准，返回去重后的主键数量
+	pub async fn apply_changeset(&self, tab: &Atom, changes: Vec<(Bin, Option<Bin>)>) -> SResult<usize> {
+		let mut txn = self.0.lock().await;
+		let tab_name = txn.tab.0.lock().await.tab.clone();
+		if &tab_name != tab {
+			return Err(format!("apply changeset failed, tab: {}, reason: txn tab is {}", tab.as_str(), tab_name.as_str()));
+		}
+
+		let mut deduped: XHashMap<Bin, Option<Bin>> = XHashMap::default();
+		for (key, value) in changes {
+			let key = txn.normalize(key);
+			deduped.insert(key, value);
+		}
+
+		let count = deduped.len();
+		for (key, value) in deduped {
+			match value {
+				None => txn.delete(key).await.map_err(|e| e.to_string())?,
+				Some(value) => txn.upsert(key, value).await.map_err(|e| e.to_string())?,
+			};
+		}
+
+		Ok(count)
+	}
+
+	//获取指定表的记录迭代器
+	//key为None则从表头或表尾开始迭代，由descending确定，descending为true表示从表尾迭代，否则从表头迭代，key为Some一个指定主键的二进制，则从表的指定主键开始迭代，迭代方向由descending确定
+	//指定的主键不存在时，升序从大于该主键的第一个主键开始，降序从小于该主键的第一个主键开始
+	pub async fn iter(
+		&self,
+		tab: &Atom,
+		key: Option<Bin>,
+		descending: bool,
+		filter: Filter
+	) -> IterResult {
+		let b = self.0.lock().await;
+		b.check_unspilled("iter")?;
+		b.check_warm("iter")?;
+		let key = match key {
+			Some(k) => Some(Bon::new(b.normalize(k))),
+			None => None,
+		};
+		let key = match &key {
+			&Some(ref k) => Some(k),
+			None => None,
+		};
+
+		Ok(Box::new(FuseOnErr::new(MemIter::new(tab, b.root.clone(), b.root.iter( key, descending), filter).with_pivot(key.cloned(), descending))))
+	}
+
+	//获取指定表的TabKV迭代器，参数同iter，返回的TabKV与query的结果一致，可以直接用于modify
+	pub async fn iter_tabkv(
+		&self,
+		ware: &Atom,
+		tab: &Atom,
+		key: Option<Bin>,
+		descending: bool,
+		filter: Filter
+	) -> SResult<Box<dyn Iter<Item = TabKV> + Send>> {
+		let iter = self.iter(tab, key, descending, filter).await?;
+		Ok(Box::new(TabKVIter {
+			ware: ware.clone(),
+			tab: tab.clone(),
+			iter: FuseOnErr::new(iter),
+		}))
+	}
+
+	//获取指定表的快照记录迭代器，参数同iter
+	//迭代器持有创建时事务内存表根节点的克隆，内存表是写时复制的，之后任何事务的提交都只会替换表的根节点，
+	//不会修改迭代器持有的根节点，所以迭代器始终只能看到创建时的记录，不会看到之后其它事务提交的修改
+	pub async fn snapshot_iter(
+		&self,
+		tab: &Atom,
+		key: Option<Bin>,
+		descending: bool,
+		filter: Filter
+	) -> IterResult {
+		self.iter(tab, key, descending, filter).await
+	}
+
+	//获取指定表的主键迭代器
+	//key为None则从表头或表尾开始迭代，由descending确定，descending为true表示从表尾迭代，否则从表头迭代，key为Some一个指定主键的二进制，则从表的指定主键开始迭代，迭代方向由descending确定
+	//指定的主键不存在时，升序从大于该主键的第一个主键开始，降序从小于该主键的第一个主键开始
+	pub async fn key_iter(
+		&self,
+		key: Option<Bin>,
+		descending: bool,
+		filter: Filter
+	) -> KeyIterResult {
+		let b = self.0.lock().await;
+		b.check_unspilled("key_iter")?;
+		b.check_warm("key_iter")?;
+		let key = match key {
+			Some(k) => Some(Bon::new(b.normalize(k))),
+			None => None,
+		};
+		let key = match &key {
+			&Some(ref k) => Some(k),
+			None => None,
+		};
+		let tab = b.tab.0.lock().await.tab.clone();
+		Ok(Box::new(FuseOnErr::new(MemKeyIter::new(&tab, b.root.clone(), b.root.keys(key, descending), filter).with_pivot(key.cloned(), descending))))
+	}
+
+	//按二级索引的索引键顺序迭代记录，返回主键和值，索引键相同的记录按主键顺序排列，index_key是表元信息中声明的索引名或LogFileTab::add_index增加的索引名
+	//声明的索引在首次迭代时按LogFileDB::set_index_extractor注册的提取函数建立，之后随提交增量维护
+	//key是起始的索引键，升序从不小于起始索引键的索引键开始，降序从不大于起始索引键的索引键开始
+	//迭代的是事务开始时的索引快照，并叠加事务中未提交的写入和删除，事务开始后其它事务的提交不影响迭代结果
+	pub async fn index(
+		&self,
+		tab: &Atom,
+		index_key: &Atom,
+		key: Option<Bin>,
+		descending: bool,
+		filter: Filter,
+	) -> IterResult {
+		let declared = match ALL_TABLES.get(tab).await {
+			Some(info) => info.meta.indexes.contains(index_key),
+			None => false,
+		};
+		let registered = if declared {
+			match INDEX_EXTRACTORS.lock().get(&(tab.clone(), index_key.clone())) {
+				None => return Err(format!("index unavailable, tab: {}, index: {}, reason: extractor not registered", tab.as_str(), index_key.as_str())),
+				Some(extractor) => Some(extractor.clone()),
+			}
+		} else {
+			None
+		};
+
+		let mut txn = self.0.lock().await;
+		txn.check_unspilled("index")?;
+		//事务开始时没有索引快照，或索引的提取函数已改变，则建立表的索引，并按事务开始时的内存表建立事务的索引快照
+		let stale = match txn.indexes.get(index_key) {
+			None => true,
+			Some(index) => registered.as_ref().map(|extractor| !Arc::ptr_eq(&index.extractor, extractor)).unwrap_or(false),
+		};
+		if stale {
+			let extractor = {
+				let mut lock = txn.tab.lock_tab().await;
+				if lock.bulk {
+					return Err(format!("index unavailable, tab: {}, index: {}, reason: bulk mode", lock.tab.as_str(), index_key.as_str()));
+				}
+				if let Some(extractor) = &registered {
+					let rebuild = match lock.indexes.get(index_key) {
+						None => true,
+						Some(index) => !Arc::ptr_eq(&index.extractor, extractor),
+					};
+					if rebuild {
+						let mut index = SecondaryIndex::new(extractor.clone());
+						index.rebuild(&lock.root);
+						lock.indexes.insert(index_key.clone(), index);
+					}
+				}
+				match lock.indexes.get(index_key) {
+					None => return Err(format!("index unavailable, tab: {}, index: {}, reason: index not found", lock.tab.as_str(), index_key.as_str())),
+					Some(index) => index.extractor.clone(),
+				}
+			};
+			let mut snapshot = SecondaryIndex::new(extractor);
+			snapshot.rebuild(&txn.old);
+			txn.indexes.insert(index_key.clone(), snapshot);
+		}
+		let snapshot = txn.indexes.get(index_key).cloned().unwrap();
+
+		//事务中写入的主键在快照中的索引条目被隐藏，按事务中的值重新计算索引键后叠加
+		let start = key.map(|k| IndexPos(k.to_vec(), if descending { IndexBound::Max } else { IndexBound::Min }));
+		let mut written = XHashSet::default();
+		let mut overlay = Vec::new();
+		for (k, rw_v) in txn.rwlog.iter() {
+			if let RwLog::Write(_) = rw_v {
+				written.insert(k.clone());
+				if let Some(v) = txn.root.get(&Bon::new(k.clone())) {
+					if let Some(index_key) = (snapshot.extractor)(k.as_slice(), v.as_slice()) {
+						let pos = IndexPos(index_key, IndexBound::Key(Bon::new(k.clone())));
+						if start.as_ref().map(|start| pos.is_after(start, descending)).unwrap_or(true) {
+							overlay.push((pos, k.clone()));
+						}
+					}
+				}
+			}
+		}
+		overlay.sort_by(|a, b| a.0.cmp(&b.0));
+		if descending {
+			overlay.reverse();
+		}
+
+		Ok(Box::new(FuseOnErr::new(IndexIter {
+			entries: snapshot.entries,
+			root: txn.root.clone(),
+			written,
+			overlay: overlay.into(),
+			cursor: start,
+			descending,
+			filter,
+		})))
+	}
+
+	//获取指定表的记录数量，事务已溢出时按溢出日志中的写入和删除修正事务内存表的记录数量
+	pub async fn tab_size(&self) -> SResult<usize> {
+		let txn = self.0.lock().await;
+		let mut size = txn.root.size();
+		if let Some(spill) = &txn.spill {
+			for (key, pos) in spill.index.iter() {
+				match (pos.is_some(), txn.root.get(&Bon::new(key.clone())).is_some()) {
+					(true, false) => size += 1,
+					(false, true) => size -= 1,
+					_ => (),
+				}
+			}
+		}
+		Ok(size)
+	}
+
+	//预提交一个事务
+	pub async fn prepare(&self, _timeout: usize) -> DBResult {
+		let mut txn = self.0.lock().await;
+		txn.state = TxState::Preparing;
+		match txn.prepare_inner().await {
+			Ok(()) => {
+				txn.state = TxState::PreparOk;
+				return Ok(())
+			},
+			Err(e) => {
+				debug!("prepare failed, txn: {:?}, trace_id: {:?}, reason: {}", txn.id, txn.trace_id, e);
+				txn.state = TxState::PreparFail;
+				return Err(e.to_string())
+			},
+		}
+	}
+
+	//提交一个事务，只能提交预提交成功的事务
+	pub async fn commit(&self) -> CommitResult {
+		let mut txn = self.0.lock().await;
+		txn.check_prepared()?;
+		txn.state = TxState::Committing;
+		let r = txn.commit_inner().await;
+		txn.active.take();
+		match r {
+			Ok(log) => {
+				txn.state = TxState::Commited;
+				txn.tab.sync_live_forks(&log).await;
+				txn.tab.split_if_oversized().await;
+				return Ok(log)
+			},
+			Err(e) => {
+				txn.state = TxState::CommitFail;
+				return Err(e.to_string())
+			}
+		}
+	}
+
+	//提交一个事务，逻辑提交完成后立即返回，并返回用于等待日志持久化的确认
+	pub async fn commit_with_ack(&self) -> SResult<(RwLogMap, DurabilityAck)> {
+		let mut txn = self.0.lock().await;
+		txn.check_prepared()?;
+		txn.state = TxState::Committing;
+		//等待日志持久化的事务的计数，由持久化任务在完成后释放
+		let r = txn.commit_with_ack_inner().await;
+		txn.active.take();
+		match r {
+			Ok(r) => {
+				txn.state = TxState::Commited;
+				txn.tab.sync_live_forks(&r.0).await;
+				Ok(r)
+			},
+			Err(e) => {
+				txn.state = TxState::CommitFail;
+				Err(e)
+			}
+		}
+	}
+
+	//回滚一个事务，重复回滚和预提交失败后的回滚都直接成功
+	pub async fn rollback(&self) -> DBResult {
+		let mut txn = self.0.lock().await;
+		if txn.state == TxState::Rollbacked {
+			return Ok(());
+		}
+		txn.state = TxState::Rollbacking;
+		let r = txn.rollback_inner().await;
+		txn.active.take();
+		match r {
+			Ok(()) => {
+				txn.state = TxState::Rollbacked;
+				return Ok(())
+			},
+			Err(e) => {
+				txn.state = TxState::RollbackFail;
+				return Err(e.to_string())
+			}
+		}
+	}
+
+	///表分叉的预提交
+	pub async fn fork_prepare(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
+		let mut txn = self.0.lock().await;
+		txn.fork_prepare_inner(ware, tab_name, fork_tab_name, meta).await
+	}
+
+	//表分叉的提交，mode为分叉表的分叉模式
+	pub async fn fork_commit(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta, mode: ForkMode) -> DBResult {
+		let mut txn = self.0.lock().await;
+		txn.fork_commit_inner(ware, tab_name, fork_tab_name, meta, mode).await
+	}
+
+	///表分叉的回滚
+	pub async fn fork_rollback(&self) -> DBResult {
+		let mut txn = self.0.lock().await;
+		txn.fork_rollback_inner().await
+	}
+
+	///批量表分叉，specs为父表名、分叉表名和分叉表的元信息，所有分叉要么全部创建，要么全部未创建
+	pub async fn fork_tabs(&self, specs: Vec<(Atom, Atom, TabMeta)>) -> DBResult {
+		let txn = self.0.lock().await;
+		txn.fork_tabs_inner(specs).await
+	}
+
+	///强制日志文件分裂
+	pub async fn force_fork(&self) -> Result<usize> {
+		self.0.lock().await.force_fork_inner().await
+	}
+
+	//记录锁，主键可以不存在，根据lock_time的值决定是锁还是解锁，lock_time为0时释放本事务持有的指定主键的锁
+	//lock_time为锁的有效时长，单位毫秒，超时的锁可以被其它事务回收；只读锁可以被多个事务同时持有，写锁只能被一个事务持有
+	//其它事务持有的锁与请求的锁冲突则返回KeyLocked错误，所有主键都成功才加锁；本事务提交或回滚时释放持有的所有锁
+	pub async fn key_lock(&self, arr: Arc<Vec<TabKV>>, lock_time: usize, readonly: bool) -> DBResult {
+		let mut txn = self.0.lock().await;
+		let keys: Vec<Bin> = arr.iter().map(|tabkv| txn.normalize(tabkv.key.clone())).collect();
+		let id = txn.id.clone();
+		let mut lock = txn.tab.lock_tab().await;
+		if lock_time == 0 {
+			for key in keys.iter() {
+				if let Some(entry) = lock.locks.get_mut(key) {
+					entry.owners.remove(&id);
+					if entry.owners.is_empty() {
+						lock.locks.remove(key);
+					}
+				}
+			}
+			return Ok(());
+		}
+
+		let now = clock::now_millis();
+		for key in keys.iter() {
+			if let Some(entry) = lock.locks.get_mut(key) {
+				entry.owners.retain(|_, expire| *expire > now);
+				if entry.conflicts(&id, readonly) {
+					return Err(format!("KeyLocked: tab: {}, key: {:?}", lock.tab.as_str(), key));
+				}
+			}
+		}
+		let expire = now + lock_time as u64;
+		for key in keys {
+			let entry = lock.locks.entry(key).or_insert_with(|| LockEntry {
+				readonly,
+				owners: XHashMap::default(),
+			});
+			//只有本事务持有的锁按本次请求的锁类型重新加锁
+			if entry.owners.is_empty() || (entry.owners.len() == 1 && entry.owners.contains_key(&id)) {
+				entry.readonly = readonly;
+			}
+			entry.owners.insert(id.clone(), expire);
+		}
+		drop(lock);
+		txn.locked = true;
+
+		Ok(())
+	}
+}
+
+/*
+* 大事务的溢出日志，只在临时文件中保存事务已溢出的待写入值，内存中只保留主键到值在文件中位置的索引，释放时删除临时文件
+*/
+struct SpillLog {
+	path: PathBuf,									//溢出日志的临时文件路径
+	file: AsyncFile<()>,							//溢出日志的异步文件
+	buf: Vec<u8>,									//还未写入文件的值的缓冲
+	flushed: u64,									//已写入文件的长度
+	index: XHashMap<Bin, Option<(u64, usize)>>,		//已溢出的主键的最新值在溢出日志中的位置和长度，None表示删除
+}
+
+impl Drop for SpillLog {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+impl SpillLog {
+	//创建指定表的溢出日志
+	async fn new(tab: &Atom) -> Result<Self> {
+		let mut path = PathBuf::new();
+		path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		path.push(format!("{}.{}.spill", tab.as_str(), SPILL_ID.fetch_add(1, Ordering::Relaxed)));
+		let rt = match STORE_RUNTIME.read().await.as_ref() {
+			None => return Err(Error::new(ErrorKind::Other, "store runtime not ready")),
+			Some(rt) => rt.clone(),
+		};
+		let file = AsyncFile::open(rt, path.clone(), AsyncFileOptions::ReadWrite).await?;
+
+		Ok(SpillLog {
+			path,
+			file,
+			buf: Vec::new(),
+			flushed: 0,
+			index: XHashMap::default(),
+		})
+	}
+
+	//追加一个主键的待写入值，值为None表示删除，同一个主键的值以最后追加的为准
+	async fn append(&mut self, key: &Bin, value: Option<&Bin>) -> Result<()> {
+		let pos = match value {
+			None => None,
+			Some(value) => {
+				let pos = self.flushed + self.buf.len() as u64;
+				self.buf.extend_from_slice(value);
+				Some((pos, value.len()))
+			},
+		};
+		self.index.insert(key.clone(), pos);
+
+		if self.buf.len() >= SPILL_BUF_SIZE {
+			self.flush().await?;
+		}
+		Ok(())
+	}
+
+	//将写缓冲中的值追加到溢出日志文件
+	async fn flush(&mut self) -> Result<()> {
+		if self.buf.is_empty() {
+			return Ok(());
+		}
+
+		let buf: Arc<[u8]> = Arc::from(mem::replace(&mut self.buf, Vec::new()));
+		let len = buf.len() as u64;
+		self.file.write(self.flushed, buf, WriteOptions::None).await?;
+		self.flushed += len;
+		Ok(())
+	}
+
+	//读取已溢出的主键的值，主键未溢出返回None，已溢出的删除返回Some(None)
+	async fn read(&self, key: &Bin) -> Result<Option<Option<Bin>>> {
+		let (pos, len) = match self.index.get(key) {
+			None => return Ok(None),
+			Some(None) => return Ok(Some(None)),
+			Some(Some(pos)) => *pos,
+		};
+
+		if pos >= self.flushed {
+			let start = (pos - self.flushed) as usize;
+			return Ok(Some(Some(Arc::new(self.buf[start..start + len].to_vec()))));
+		}
+		let bin = self.file.read(pos, len).await?;
+		if bin.len() < len {
+			return Err(Error::new(ErrorKind::UnexpectedEof, format!("spill log truncated, path: {:?}, pos: {}, len: {}", self.path, pos, len)));
+		}
+		Ok(Some(Some(Arc::new(bin))))
+	}
+
+	//已溢出的主键在事务中是否存在，主键未溢出返回None，不读取溢出日志文件
+	fn contains(&self, key: &Bin) -> Option<bool> {
+		self.index.get(key).map(|pos| pos.is_some())
+	}
+
+	//读取所有已溢出的主键的最新值，按值在文件中的位置顺序，每次最多读取SPILL_BUF_SIZE字节的文件块
+	//每个值只从文件块复制一次，读取时的额外内存只有一个文件块，超过文件块大小的值单独读取
+	async fn load(mut self) -> Result<Vec<(Bin, Option<Bin>)>> {
+		self.flush().await?;
+		let index = mem::replace(&mut self.index, XHashMap::default());
+		let mut values = Vec::with_capacity(index.len());
+		let mut positions = Vec::with_capacity(index.len());
+		for (key, pos) in index {
+			match pos {
+				None => values.push((key, None)),
+				Some((pos, len)) => positions.push((pos, len, key)),
+			}
+		}
+		positions.sort_by_key(|(pos, _, _)| *pos);
+
+		let mut chunk: Vec<u8> = Vec::new();
+		let mut chunk_pos = 0;
+		for (pos, len, key) in positions {
+			if pos < chunk_pos || pos + len as u64 > chunk_pos + chunk.len() as u64 {
+				chunk_pos = pos;
+				let read_len = len.max(SPILL_BUF_SIZE).min((self.flushed - pos) as usize);
+				chunk = self.file.read(pos, read_len).await?;
+				if chunk.len() < len {
+					return Err(Error::new(ErrorKind::UnexpectedEof, format!("spill log truncated, path: {:?}, pos: {}, len: {}", self.path, pos, len)));
+				}
+			}
+			let start = (pos - chunk_pos) as usize;
+			values.push((key, Some(Arc::new(chunk[start..start + len].to_vec()))));
+		}
+
+		Ok(values)
+	}
+}
+
+pub struct FileMemTxn {
+	id: Guid,						//事务id
+	writable: bool,					//是否是可写事务
+	tab: LogFileTab,				//日志文件表的句柄
+	root: BinMap,					//日志文件表的内存表的句柄，在创建内存表事务时从内存表的句柄拷贝，在事务过程中可能会修改
+	old: BinMap,					//日志文件表的内存表的句柄，保留创建内存表事务时内存表的句柄，在事务过程中不会修改
+	rwlog: XHashMap<Bin, RwLog>,	//内存表事务的操作日志，Bin为主键的二进制，RwLog为事务的操作日志
+	state: TxState,					//事务的状态
+	pending_size: usize,			//事务待写入记录的大小
+	spill: Option<SpillLog>,		//事务的溢出日志
+	commit_seq: Option<u64>,		//事务的提交序号
+	key_transform: Option<KeyTransform>,	//创建事务时表的主键转换函数
+	trace_id: Option<Atom>,			//事务的链路追踪id，用于关联上游请求
+	active: Option<ActiveTxn>,		//事务未完成时，表的未完成事务的计数
+	rt: Option<MultiTaskRuntime<()>>,	//存储运行时，用于释放未完成的事务时异步清理预提交
+	watermark: Option<u64>,			//事务提交时分配的表的提交水位
+	sink: bool,						//是否是日志表的事务，日志表的事务只记录插入，不修改事务的内存表
+	locked: bool,					//事务是否持有过记录锁
+	indexes: XHashMap<Atom, SecondaryIndex>,	//事务开始时表的二级索引的快照，批量模式下没有快照
+	blob_values: Vec<(Bin, Bin)>,	//预提交的写入中可能外置存储的主键和值，提交时在内存表锁外写入外置存储
+	cold: bool,						//创建事务时表是否有按重放限制未加载的日志文件，有则读取不存在的主键时从未加载的日志文件读取
+	snapshot_seq: u64,				//创建事务时表的最近一次提交的提交序号
+}
+
+//事务在未完成时被释放，通常是调用者遗漏了提交或回滚，记录警告
+//已预提交或正在预提交的事务在存储运行时上异步移除表中的预提交，避免预提交一直参与冲突检查
+impl Drop for FileMemTxn {
+	fn drop(&mut self) {
+		let prepared = match self.state {
+			TxState::Preparing | TxState::PreparOk => true,
+			TxState::Ok if self.writable && !self.rwlog.is_empty() => false,
+			TxState::Ok | TxState::CommitFail if self.locked => false,
+			_ => return,
+		};
+		warn!("txn dropped without commit or rollback, txn: {:?}, state: {:?}, trace_id: {:?}, keys: {}", self.id, self.state, self.trace_id, self.rwlog.len());
+		if !prepared && !self.locked {
+			return;
+		}
+
+		let tab = self.tab.clone();
+		let id = self.id.clone();
+		let rt = match &self.rt {
+			None => {
+				error!("release dropped prepare failed, txn: {:?}, reason: store runtime not ready", id);
+				return;
+			},
+			Some(rt) => rt.clone(),
+		};
+		if let Err(e) = rt.spawn(rt.alloc(), async move {
+			let mut lock = tab.lock_tab().await;
+			lock.prepare.remove(&id);
+			release_key_locks(&mut lock.locks, &id);
+		}) {
+			error!("release dropped prepare failed, txn: {:?}, reason: {:?}", self.id, e);
+		}
+	}
+}
+
+impl FileMemTxn {
+	//开始事务
+	pub async fn new(tab: LogFileTab, id: &Guid, writable: bool) -> RefLogFileTxn {
+		//在内存表锁内计数，保证释放内存时不会遗漏刚创建的事务
+		let (root, key_transform, active, sink, indexes, cold, snapshot_seq) = {
+			let lock = tab.0.lock().await;
+			let indexes = if lock.bulk {
+				XHashMap::default()
+			} else {
+				lock.indexes.clone()
+			};
+			(lock.root.clone(), KEY_TRANSFORMS.lock().get(&lock.tab).cloned(), ActiveTxn::new(&tab.2), lock.sink.is_some(), indexes, !lock.cold.is_empty(), lock.last_seq)
+		};
+		let txn = FileMemTxn {
+			id: id.clone(),
+			writable,
+			root: root.clone(),
+			tab,
+			old: root,
+			rwlog: XHashMap::default(),
+			state: TxState::Ok,
+			pending_size: 0,
+			spill: None,
+			commit_seq: None,
+			key_transform,
+			trace_id: None,
+			active: Some(active),
+			rt: STORE_RUNTIME.read().await.as_ref().cloned(),
+			watermark: None,
+			sink,
+			locked: false,
+			indexes,
+			blob_values: Vec::new(),
+			cold,
+			snapshot_seq,
+		};
+		return RefLogFileTxn(Mutex::new(txn))
+	}
+
+	//使用表的主键转换函数转换主键，没有转换函数则返回原主键
+	fn normalize(&self, key: Bin) -> Bin {
+		match &self.key_transform {
+			None => key,
+			Some(transform) => Arc::new(transform(key.as_slice())),
+		}
+	}
+
+	//获取指定主键的记录的值
+	pub async fn get(&mut self, key: Bin) -> SResult<Option<Bin>> {
+		self.tab.2.heatmap.sample(&key, false);
+		let value = self.current(&key).await?;
+		if value.is_some() && self.writable && !self.rwlog.contains_key(&key) {
+			self.rwlog.insert(key, RwLog::Read);
+		}
+
+		Ok(value)
+	}
+
+	//读取事务中指定主键的当前值，已溢出的主键从溢出日志中读取，不记录读操作
+	async fn current(&self, key: &Bin) -> SResult<Option<Bin>> {
+		if let Some(spill) = &self.spill {
+			match spill.read(key).await {
+				Err(e) => return Err(format!("spill read failed, txn: {:?}, key: {:?}, reason: {:?}", self.id, key, e)),
+				Ok(Some(value)) => return Ok(value),
+				Ok(None) => (),
+			}
+		}
+
+		let value = self.root.get(&Bon::new(key.clone())).cloned();
+		match self.rwlog.get(key) {
+			//冷表中事务没有写入或删除过的不存在的主键，可能是按重放限制未加载的主键，从未加载的日志文件读取
+			None | Some(RwLog::Read) if value.is_none() && self.cold => self.tab.load_cold_key(key, self.snapshot_seq).await,
+			_ => Ok(value),
+		}
+	}
+
+	//事务已溢出时，事务的内存表中没有已溢出的写入，不支持需要按范围遍历事务内存表的操作，见TXN_SPILL_SIZE
+	fn check_unspilled(&self, op: &str) -> DBResult {
+		if self.spill.is_some() {
+			return Err(format!("TxnSpilled: txn: {:?}, op: {}", self.id, op));
+		}
+
+		Ok(())
+	}
+
+	//冷表的事务内存表中没有未加载的主键，按范围遍历会遗漏未加载的记录，需要先通过LogFileTab::load_cold加载全部日志文件
+	fn check_warm(&self, op: &str) -> DBResult {
+		if self.cold {
+			return Err(format!("TabCold: txn: {:?}, op: {}", self.id, op));
+		}
+
+		Ok(())
+	}
+
+	//插入指定主键的记录，主键在事务中已存在则返回KeyExists错误
+	pub async fn insert(&mut self, key: Bin, value: Bin) -> DBResult {
+		if self.get(key.clone()).await?.is_some() {
+			return Err(format!("KeyExists: tab: {}, key: {:?}", self.tab.0.lock().await.tab.as_str(), key));
+		}
+
+		self.upsert(key, value).await
+	}
+
+	//修改指定主键的记录，主键在事务中不存在则返回KeyNotFound错误
+	pub async fn update(&mut self, key: Bin, value: Bin) -> DBResult {
+		if self.get(key.clone()).await?.is_none() {
+			return Err(format!("KeyNotFound: tab: {}, key: {:?}", self.tab.0.lock().await.tab.as_str(), key));
+		}
+
+		self.upsert(key, value).await
+	}
+
+	//插入或修改指定主键的记录
+	pub async fn upsert(&mut self, key: Bin, value: Bin) -> DBResult {
+		if self.sink {
+			self.rwlog.insert(key, RwLog::Write(Some(value)));
+			return Ok(());
+		}
+		self.write(key, Some(value)).await
+	}
+
+	//删除指定主键的记录
+	pub async fn delete(&mut self, key: Bin) -> DBResult {
+		if self.sink {
+			return Err(String::from("LogSink: delete unsupported"));
+		}
+		self.write(key, None).await
+	}
+
+	//移动指定主键的值，读取from后将值写入to，并删除from，to已存在则被覆盖
+	//from不存在则不做任何修改并返回false，from与to相同则只读取from
+	pub async fn move_key(&mut self, from: Bin, to: Bin) -> SResult<bool> {
+		let value = match self.get(from.clone()).await? {
+			None => return Ok(false),
+			Some(value) => value,
+		};
+		if from == to {
+			return Ok(true);
+		}
+
+		self.upsert(to, value).await?;
+		self.delete(from).await?;
+		Ok(true)
+	}
+
+	//累加指定主键的计数器，只在操作日志中记录增量，不读取当前值，提交时再将增量累加到内存表的当前值上
+	//累加的结果在提交前对本事务不可见，如果本事务已读取或写入过该主键，则直接在事务内的值上累加，按普通写操作提交
+	pub async fn increment(&mut self, key: Bin, delta: i64) -> DBResult {
+		if self.sink {
+			return Err(String::from("LogSink: increment unsupported"));
+		}
+		let pending = match self.rwlog.get(&key) {
+			None => Some(delta),
+			Some(RwLog::Increment(d)) => Some(d.wrapping_add(delta)),
+			Some(_) => None,
+		};
+
+		match pending {
+			Some(d) => {
+				self.rwlog.insert(key, RwLog::Increment(d));
+				Ok(())
+			},
+			None => {
+				let current = self.current(&key).await?;
+				let value = increment_value(current.as_ref(), delta, self.tab.2.counter_window.load(Ordering::Relaxed))?;
+				self.upsert(key, value).await
+			},
+		}
+	}
+
+	//记录事务的写入，值为None表示删除，累计事务的待写入大小，首次超过溢出限制时创建溢出日志
+	//事务已溢出则值只写入溢出日志，操作日志中只保留溢出标记，事务的内存表不修改，提交时再从溢出日志读取
+	async fn write(&mut self, key: Bin, value: Option<Bin>) -> DBResult {
+		let limit = TXN_SPILL_SIZE.load(Ordering::Relaxed);
+		if limit > 0 {
+			self.pending_size += key.len() + value.as_ref().map(|v| v.len()).unwrap_or(0);
+			if self.spill.is_none() && self.pending_size > limit {
+				self.spill_all().await?;
+			}
+		}
+
+		if let Some(spill) = &mut self.spill {
+			if let Err(e) = spill.append(&key, value.as_ref()).await {
+				return Err(format!("spill write failed, txn: {:?}, reason: {:?}", self.id, e));
+			}
+			self.rwlog.insert(key, RwLog::Write(value.map(|_| SPILLED_VALUE.clone())));
+			return Ok(());
+		}
+
+		match &value {
+			None => {
+				self.root.delete(&Bon::new(key.clone()), false);
+			},
+			Some(v) => {
+				self.root.upsert(Bon::new(key.clone()), v.clone(), false);
+			},
+		}
+		self.rwlog.insert(key, RwLog::Write(value));
+		Ok(())
+	}
+
+	//创建溢出日志，将之前所有的待写入值移入溢出日志，并将事务内存表中对应主键的值恢复为事务开始时的值
+	async fn spill_all(&mut self) -> DBResult {
+		let tab_name = self.tab.0.lock().await.tab.clone();
+		let mut spill = match SpillLog::new(&tab_name).await {
+			Err(e) => return Err(format!("create spill log failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+			Ok(spill) => spill,
+		};
+
+		let writes: Vec<(Bin, Option<Bin>)> = self.rwlog.iter().filter_map(|(k, rw_v)| match rw_v {
+			RwLog::Write(v) => Some((k.clone(), v.clone())),
+			_ => None,
+		}).collect();
+		for (key, value) in writes {
+			if let Err(e) = spill.append(&key, value.as_ref()).await {
+				return Err(format!("spill write failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+			}
+			let bon = Bon::new(key.clone());
+			match self.old.get(&bon).cloned() {
+				None => {
+					self.root.delete(&bon, false);
+				},
+				Some(old) => {
+					self.root.upsert(bon, old, false);
+				},
+			}
+			self.rwlog.insert(key, RwLog::Write(value.map(|_| SPILLED_VALUE.clone())));
+		}
+		self.spill = Some(spill);
+
+		Ok(())
+	}
+
+	//事务的待写入值已溢出，则在获取内存表锁前读取溢出日志中所有的值，溢出日志在读取后删除
+	async fn load_spill(&mut self) -> SResult<Option<Vec<(Bin, Option<Bin>)>>> {
+		match self.spill.take() {
+			None => Ok(None),
+			Some(spill) => match spill.load().await {
+				Err(e) => Err(format!("commit spill log failed, txn: {:?}, reason: {:?}", self.id, e)),
+				Ok(values) => Ok(Some(values)),
+			},
+		}
+	}
+
+	//在获取内存表锁前将事务中可能外置存储的写入值写入外置存储，包括已溢出的值，追加日志时直接引用
+	async fn stage_blobs(&mut self, spilled: Option<&Vec<(Bin, Option<Bin>)>>) -> StagedBlobs {
+		let mut values = mem::replace(&mut self.blob_values, Vec::new());
+		if let Some(spilled) = spilled {
+			for (key, value) in spilled.iter() {
+				if let Some(value) = value {
+					if self.tab.1.blobs.wants(key, value.len()) {
+						values.push((key.clone(), value.clone()));
+					}
+				}
+			}
+		}
+
+		let pairs: Vec<(&[u8], &[u8])> = values.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+		self.tab.1.blobs.stage(&pairs).await
+	}
+
+	//已溢出的事务的写入按溢出限制分块追加到日志文件，每块持久化后再追加下一块，日志文件的内存日志块中最多只有一块的记录
+	//值已在内存表中，追加时直接引用，不再复制整个事务，返回是否全部持久化
+	async fn append_chunked(store: &AsyncLogFileStore, pairs: &[(&[u8], &[u8])], keys: &[&[u8]]) -> bool {
+		let limit = TXN_SPILL_SIZE.load(Ordering::Relaxed).max(SPILL_BUF_SIZE);
+		let mut start = 0;
+		let mut size = 0;
+		for (index, (key, value)) in pairs.iter().enumerate() {
+			size += key.len() + value.len();
+			if size < limit && index + 1 < pairs.len() {
+				continue;
+			}
+
+			if let Some(id) = store.append_deferred(&pairs[start..index + 1], &[]).await {
+				if store.apply_when_durable(id).await.is_err() {
+					return false;
+				}
+			}
+			start = index + 1;
+			size = 0;
+		}
+
+		match store.append_deferred(&[], keys).await {
+			None => true,
+			Some(id) => store.apply_when_durable(id).await.is_ok(),
+		}
+	}
+
+	//用溢出日志中的值替换预提交的操作日志中的溢出标记
+	fn restore_spilled(lock: &mut MemeryTab, id: &Guid, values: Option<Vec<(Bin, Option<Bin>)>>) {
+		let values = match values {
+			None => return,
+			Some(values) => values,
+		};
+		if let Some(rwlog) = lock.prepare.get_mut(id) {
+			for (key, value) in values {
+				rwlog.insert(key, RwLog::Write(value));
+			}
+		}
+	}
+
+	//预提交，只对获取内存表锁限时，获取锁后的检查不能被取消，避免事务停留在预提交中的状态
+	pub async fn prepare_inner(&mut self) -> DBResult {
+		let handle = &self.tab;
+		let mut lock = with_op_timeout("prepare", async move {
+			Ok(handle.lock_tab().await)
+		}).await?;
+		//表的内存已被释放且重新加载失败，事务看到的是空表，不允许提交
+		if lock.unloaded {
+			if lock.invalid {
+				return Err(format!("TabInvalid: tab: {}", lock.tab.as_str()));
+			}
+			return Err(format!("TabUnloaded: tab: {}", lock.tab.as_str()));
+		}
+		//表的写入已被隔离，只允许只读的预提交
+		if self.tab.2.fenced.load(Ordering::SeqCst) && self.rwlog.values().any(|rw_v| match rw_v {
+			RwLog::Read => false,
+			_ => true,
+		}) {
+			return Err(format!("TabFenced: tab: {}", lock.tab.as_str()));
+		}
+		//表的根节点在事务开始后未改变，则没有其它事务提交，不需要逐个检查主键的值
+		//根节点改变只说明有其它事务提交，只检查本事务读写的主键，写入不相交主键的事务之间不会冲突
+		let root_changed = !lock.root.ptr_eq(&self.old);
+		//遍历事务中的读写日志
+		let now = clock::now_millis();
+		for (key, rw_v) in self.rwlog.iter() {
+			//检查主键是否被其它事务锁住，只读锁只阻止写入，写锁阻止所有读写，超时的锁被忽略
+			if let Some(entry) = lock.locks.get(key) {
+				let write = match rw_v {
+					RwLog::Read => false,
+					_ => true,
+				};
+				if entry.blocks(&self.id, write, now) {
+					return Err(format!("KeyLocked: tab: {}, key: {:?}", lock.tab.as_str(), key));
+				}
+			}
+			//检查预提交是否冲突 
+			match lock.prepare.try_prepare(key, rw_v) {
+				Ok(_) => (),
+				Err(s) => return Err(s),
+			};
+			//累加操作不依赖事务开始时的值，只检查当前值是否是有效的计数器
+			if let RwLog::Increment(_) = rw_v {
+				increment_value(lock.root.get(&Bon::new(key.clone())), 0, self.tab.2.counter_window.load(Ordering::Relaxed))?;
+				continue;
+			}
+			//检查主键的值是否改变，重新加载后未修改的值是新的拷贝，所以值的地址不同时再比较值的内容
+			if root_changed {
+				let key = Bon::new(key.clone());
+				match lock.root.get(&key) {
+					Some(r1) => match self.old.get(&key) {
+						Some(r2) if Arc::ptr_eq(r1, r2) || r1 == r2 => (),
+						_ => {
+							let key_str = format!("{:?}", &*key);
+							return Err(String::from("prepare conflicted value diff") + key_str.as_str())
+						}
+					},
+					_ => match self.old.get(&key) {
+						None => (),
+						_ => {
+							let key_str = format!("{:?}", &*key);
+							return Err(String::from("prepare conflicted old not None") + key_str.as_str())
+						}
+					}
+				}
+			}
+		}
+		//检查已预提交的事务是否超过限制，超过则拒绝新的预提交
+		let max_txns = MAX_PREPARED_TXNS.load(Ordering::Relaxed);
+		if max_txns > 0 && lock.prepare.len() >= max_txns {
+			return Err(format!("TooManyPrepared: tab: {}, prepared: {}, limit: {}", lock.tab.as_str(), lock.prepare.len(), max_txns));
+		}
+		let max_size = MAX_PREPARED_SIZE.load(Ordering::Relaxed);
+		if max_size > 0 {
+			let size = lock.prepare.values().map(rwlog_size).sum::<usize>() + rwlog_size(&self.rwlog);
+			if size > max_size {
+				return Err(format!("TooManyPrepared: tab: {}, prepared size: {}, limit: {}", lock.tab.as_str(), size, max_size));
+			}
+		}
+
+		//记录可能外置存储的写入，提交时在内存表锁外写入外置存储，已溢出的值在提交时读取溢出日志后再检查
+		let blobs = &self.tab.1.blobs;
+		let blob_values = self.rwlog.iter().filter_map(|(k, rw_v)| match rw_v {
+			RwLog::Write(Some(v)) if !Arc::ptr_eq(v, &SPILLED_VALUE) && blobs.wants(k, v.len()) => Some((k.clone(), v.clone())),
+			_ => None,
+		}).collect();
+		self.blob_values = blob_values;
+
+		let rwlog = mem::replace(&mut self.rwlog, XHashMap::with_capacity_and_hasher(0, Default::default()));
+		//写入预提交
+		lock.prepare.insert(self.id.clone(), rwlog);
+
+		return Ok(())
+	}
+
+	//提交
+	pub async fn commit_inner(&mut self) -> CommitResult {
+		//等待持久化的提交过多，则在获取内存表锁前等待，避免未持久化的写入无限堆积
+		//只对获取许可和内存表锁限时，获取锁后开始修改内存表和追加日志，不能再被取消
+		let spilled = self.load_spill().await?;
+		let has_spilled = spilled.is_some();
+		let _staged = self.stage_blobs(spilled.as_ref()).await;
+		let tab = &self.tab;
+		let (_permit, mut lock) = with_op_timeout("commit", async move {
+			let permit = INFLIGHT_WRITE_LIMITER.acquire().await;
+			Ok((permit, tab.lock_tab().await))
+		}).await?;
+		//记录锁在提交完成后才释放，校验或合并失败的事务仍持有锁，直到回滚
+		FileMemTxn::restore_spilled(&mut *lock, &self.id, spilled);
+		FileMemTxn::validate_commit(&mut *lock, &self.id)?;
+		if self.sink {
+			let (logs, id) = self.commit_sink(&mut *lock)?;
+			release_key_locks(&mut lock.locks, &self.id);
+			//在内存表锁外等待日志持久化，等待期间其它事务可以继续提交
+			let tab_name = lock.tab.clone();
+			drop(lock);
+			if let Some(id) = id {
+				if let Err(e) = self.tab.1.log_file.delay_commit(id, false, 1).await {
+					return Err(format!("commit log sink failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+				}
+			}
+			record_watermark(&tab_name, &self.tab.2, self.watermark.unwrap_or(0)).await;
+			return Ok(logs);
+		}
+		let logs = FileMemTxn::commit_mem(&mut *lock, &self.id, &self.old, &self.root, &self.tab.2, has_spilled)?;
+		lock.mark_cold(&logs, &self.tab.1);
+		self.commit_seq = Some(lock.last_seq);
+		let watermark = self.tab.2.watermark_seq.fetch_add(1, Ordering::SeqCst) + 1;
+		self.watermark = Some(watermark);
+
+		let async_tab = self.tab.1.clone();
+		let (insert_pairs, delete_keys) = FileMemTxn::split_logs(&logs);
+		//写入和删除在一次持久化中完成，键值缓冲与带确认的提交一样按日志顺序更新
+		let durable = if has_spilled {
+			FileMemTxn::append_chunked(&async_tab, &insert_pairs, &delete_keys).await
+		} else {
+			match async_tab.append_deferred(&insert_pairs, &delete_keys).await {
+				None => true,
+				Some(id) => async_tab.apply_when_durable(id).await.is_ok(),
+			}
+		};
+		lock.mark_commit(async_tab.records.load(Ordering::Relaxed));
+		release_key_locks(&mut lock.locks, &self.id);
+
+		//日志持久化后才推进表的持久化水位
+		if durable {
+			record_watermark(&lock.tab, &self.tab.2, watermark).await;
+		}
+
+		Ok(logs)
+	}
+
+	//提交，完成逻辑提交和日志追加后立即返回，日志持久化完成后通过持久化确认通知
+	//键值缓冲在日志持久化后才更新，持久化失败则不更新
+	pub async fn commit_with_ack_inner(&mut self) -> SResult<(RwLogMap, DurabilityAck)> {
+		//日志表和已溢出的事务的提交在日志持久化后返回，已溢出的事务在提交时分块持久化
+		if self.sink || self.spill.is_some() {
+			let logs = self.commit_inner().await?;
+			let (sender, receiver) = oneshot::channel();
+			let _ = sender.send(Ok(()));
+			return Ok((logs, DurabilityAck(receiver)));
+		}
+
+		//许可在日志持久化完成后才释放
+		let spilled = self.load_spill().await?;
+		let has_spilled = spilled.is_some();
+		let _staged = self.stage_blobs(spilled.as_ref()).await;
+		let tab = &self.tab;
+		let (permit, mut lock) = with_op_timeout("commit", async move {
+			let permit = INFLIGHT_WRITE_LIMITER.acquire().await;
+			Ok((permit, tab.lock_tab().await))
+		}).await?;
+		//记录锁在提交完成后才释放，校验或合并失败的事务仍持有锁，直到回滚
+		FileMemTxn::restore_spilled(&mut *lock, &self.id, spilled);
+		FileMemTxn::validate_commit(&mut *lock, &self.id)?;
+		let logs = FileMemTxn::commit_mem(&mut *lock, &self.id, &self.old, &self.root, &self.tab.2, has_spilled)?;
+		lock.mark_cold(&logs, &self.tab.1);
+		self.commit_seq = Some(lock.last_seq);
+		let watermark = self.tab.2.watermark_seq.fetch_add(1, Ordering::SeqCst) + 1;
+		self.watermark = Some(watermark);
+
+		let async_tab = self.tab.1.clone();
+		let (sender, receiver) = oneshot::channel();
+
+		//在内存表锁内追加日志，保证日志顺序与提交顺序一致
+		let (insert_pairs, delete_keys) = FileMemTxn::split_logs(&logs);
+		let last_id = async_tab.append_deferred(&insert_pairs, &delete_keys).await;
+		lock.mark_commit(async_tab.records.load(Ordering::Relaxed));
+		release_key_locks(&mut lock.locks, &self.id);
+
+		match last_id {
+			None => {
+				//没有需要持久化的日志
+				record_watermark(&lock.tab, &self.tab.2, watermark).await;
+				let _ = sender.send(Ok(()));
+			},
+			Some(id) => {
+				let tab_name = lock.tab.clone();
+				let trace_id = self.trace_id.clone();
+				let active = self.active.take();
+				let store = async_tab.clone();
+				let counters = self.tab.2.clone();
+				let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+				let _ = rt.spawn(rt.alloc(), async move {
+					let r = match store.apply_when_durable(id).await {
+						Err(e) => {
+							error!("durability failed, tab: {}, trace_id: {:?}, reason: {:?}", tab_name.as_str(), trace_id, e);
+							Err(format!("DurabilityFailed: tab: {}, reason: {:?}", tab_name.as_str(), e))
+						},
+						Ok(_) => {
+							record_watermark(&tab_name, &counters, watermark).await;
+							Ok(())
+						},
+					};
+					drop(permit);
+					drop(active);
+					let _ = sender.send(r);
+				});
+			},
+		}
+
+		Ok((logs, DurabilityAck(receiver)))
+	}
+
+	//使用表的提交校验函数检查已预提交事务的操作日志，校验失败则移除事务的预提交
+	fn validate_commit(lock: &mut MemeryTab, id: &Guid) -> SResult<()> {
+		let validator = match COMMIT_VALIDATORS.lock().get(&lock.tab) {
+			None => return Ok(()),
+			Some(validator) => validator.clone(),
+		};
+		let result = match lock.prepare.get(id) {
+			None => return Ok(()),
+			Some(rwlog) => validator(rwlog),
+		};
+		if let Err(e) = result {
+			lock.prepare.remove(id);
+			return Err(format!("ValidationFailed: tab: {}, reason: {}", lock.tab.as_str(), e));
+		}
+
+		Ok(())
+	}
+
+	//提交日志表的事务，按主键顺序追加插入的记录，只更新表的摘要，不修改内存表和键值缓冲
+	//只在内存表锁内追加日志和更新摘要，返回最后一条记录的日志id，由调用者释放锁后等待持久化和推进水位
+	fn commit_sink(&mut self, lock: &mut MemeryTab) -> SResult<(RwLogMap, Option<usize>)> {
+		let rwlog = match lock.prepare.remove(&self.id) {
+			Some(rwlog) => rwlog,
+			None => return Err(String::from("error prepare null")),
+		};
+		let mut pairs: Vec<(&[u8], &[u8])> = rwlog.iter().filter_map(|(k, rw_v)| match rw_v {
+			RwLog::Write(Some(v)) => Some((k.as_slice(), v.as_slice())),
+			_ => None,
+		}).collect();
+		pairs.sort();
+
+		let seq = COMMIT_SEQ.fetch_add(1, Ordering::SeqCst) + 1;
+		lock.last_seq = seq;
+		self.commit_seq = Some(seq);
+		let watermark = self.tab.2.watermark_seq.fetch_add(1, Ordering::SeqCst) + 1;
+		self.watermark = Some(watermark);
+		let id = self.tab.1.append_batch(&pairs);
+		if id.is_some() {
+			if let Some(footer) = &mut lock.sink {
+				footer.count += pairs.len() as u64;
+				footer.last_key = pairs.last().map(|(k, _)| k.to_vec());
+			}
+		}
+
+		Ok((rwlog, id))
+	}
+
+	//将事务的修改合并到内存表，并返回事务的操作日志
+	//事务已溢出时，事务的内存表中没有已溢出的写入，即使根节点相等也需要逐个合并
+	fn commit_mem(lock: &mut MemeryTab, id: &Guid, old: &BinMap, root: &BinMap, counters: &TabCounters, spilled: bool) -> SResult<RwLogMap> {
+		let mut rwlog = match lock.prepare.remove(id) {
+			Some(rwlog) => rwlog,
+			None => return Err(String::from("error prepare null")),
+		};
+
+		//记录首次写入的主键在本次提交前的值，作为主键的基础历史版本
+		let history_limit = AS_OF_HISTORY.load(Ordering::Relaxed);
+		let bases: Vec<(Bin, Option<Bin>)> = if history_limit > 0 {
+			rwlog.iter().filter_map(|(k, rw_v)| match rw_v {
+				RwLog::Write(_) | RwLog::Increment(_) if !lock.history.contains_key(k) => {
+					Some((k.clone(), lock.root.get(&Bon::new(k.clone())).cloned()))
+				},
+				_ => None,
+			}).collect()
+		} else {
+			vec![]
+		};
+
+		//记录写入的主键在本次提交前的值，用于增量维护二级索引，批量模式下暂停维护
+		let index_olds: Vec<(Bin, Option<Bin>)> = if !lock.bulk && lock.indexes.len() > 0 {
+			rwlog.iter().filter_map(|(k, rw_v)| match rw_v {
+				RwLog::Write(_) | RwLog::Increment(_) => Some((k.clone(), lock.root.get(&Bon::new(k.clone())).cloned())),
+				_ => None,
+			}).collect()
+		} else {
+			vec![]
+		};
+
+		let before_size = lock.root.size();
+		let root_if_eq = lock.root.ptr_eq(old);
+		//判断根节点是否相等
+		if !root_if_eq || spilled {
+			for (k, rw_v) in rwlog.iter() {
+				match rw_v {
+					RwLog::Read => (),
+					_ => {
+						let k = Bon::new(k.clone());
+						match rw_v {
+							RwLog::Write(None) => {
+								lock.root.delete(&k, false);
+							},
+							RwLog::Write(Some(v)) => {
+								lock.root.upsert(k.clone(), v.clone(), false);
+							},
+							_ => (),
+						}
+					},
+				}
+			}
+		} else {
+			lock.root = root.clone();
+		}
+
+		//将累加操作的增量累加到内存表的当前值上，并在操作日志中替换为累加后的写操作
+		//事务的内存表中没有累加的结果，所以根节点相等时也需要累加
+		let window = counters.counter_window.load(Ordering::Relaxed);
+		for (k, rw_v) in rwlog.iter_mut() {
+			if let RwLog::Increment(delta) = rw_v {
+				let key = Bon::new(k.clone());
+				let value = increment_value(lock.root.get(&key), *delta, window)?;
+				lock.root.upsert(key, value.clone(), false);
+				*rw_v = RwLog::Write(Some(value));
+			}
+		}
+
+		for (k, old) in index_olds {
+			let new = lock.root.get(&Bon::new(k.clone())).cloned();
+			for index in lock.indexes.values_mut() {
+				index.update(&k, old.as_ref(), new.as_ref());
+			}
+		}
+
+		//分配提交序号，并记录本次提交写入的历史版本，未开启历史版本时，之前的历史版本全部失效
+		let seq = COMMIT_SEQ.fetch_add(1, Ordering::SeqCst) + 1;
+		lock.last_seq = seq;
+		//淘汰过历史版本的主键，基础历史版本只从淘汰的最大提交序号开始有效
+		if history_limit > 0 {
+			let from = lock.history_from.max(lock.history_evicted);
+			for (k, v) in bases {
+				lock.history_order.push_back(k.clone());
+				lock.history.entry(k).or_insert_with(VecDeque::new).push_back((from, v));
+			}
+			for (k, rw_v) in rwlog.iter() {
+				if let RwLog::Write(v) = rw_v {
+					let versions = lock.history.entry(k.clone()).or_insert_with(VecDeque::new);
+					versions.push_back((seq, v.clone()));
+					while versions.len() > history_limit {
+						versions.pop_front();
+					}
+				}
+			}
+
+			//保留历史版本的主键超过上限，则从最早保留的主键开始淘汰
+			let max_keys = AS_OF_HISTORY_KEYS.load(Ordering::Relaxed);
+			while max_keys > 0 && lock.history.len() > max_keys {
+				let key = match lock.history_order.pop_front() {
+					None => break,
+					Some(key) => key,
+				};
+				if let Some(versions) = lock.history.remove(&key) {
+					let last = versions.back().map(|(s, _)| *s).unwrap_or(0);
+					lock.history_evicted = lock.history_evicted.max(last);
+				}
+			}
+		} else {
+			lock.history_from = seq;
+			if lock.history.len() > 0 {
+				lock.history.clear();
+				lock.history_order.clear();
+			}
+		}
+
+		for (k, rw_v) in rwlog.iter() {
+			if let RwLog::Write(_) = rw_v {
+				counters.heatmap.sample(k, true);
+			}
+		}
+		counters.ranges.record(&rwlog);
+
+		//按本次提交净增减的记录数量调整近似记录数量
+		let after_size = lock.root.size();
+		if after_size >= before_size {
+			counters.approx_size.fetch_add(after_size - before_size, Ordering::Relaxed);
+		} else {
+			counters.approx_size.fetch_sub(before_size - after_size, Ordering::Relaxed);
+		}
+
+		Ok(rwlog)
+	}
+
+	//将事务的操作日志分为需要写入的键值对和需要删除的主键
+	fn split_logs(logs: &RwLogMap) -> (Vec<(&[u8], &[u8])>, Vec<&[u8]>) {
+		let mut insert_pairs: Vec<(&[u8], &[u8])> = vec![];
+		let mut delete_keys: Vec<&[u8]> = vec![];
+
+		for (k, rw_v) in logs {
+			match rw_v {
+				RwLog::Write(None) => {
+					delete_keys.push(k);
+				}
+				RwLog::Write(Some(v)) => {
+					insert_pairs.push((k, v));
+				}
+				_ => {}
+			}
+		}
+
+		(insert_pairs, delete_keys)
+	}
+
+	//回滚
+	//检查事务是否已预提交成功，预提交失败的事务返回PrepareFailed错误，其它未预提交的事务返回NotPrepared错误
+	fn check_prepared(&self) -> DBResult {
+		match self.state {
+			TxState::PreparOk => Ok(()),
+			TxState::PreparFail => Err(format!("PrepareFailed: txn: {:?}", self.id)),
+			ref state => Err(format!("NotPrepared: txn: {:?}, state: {:?}", self.id, state)),
+		}
+	}
+
+	pub async fn rollback_inner(&mut self) -> DBResult {
+		let handle = &self.tab;
+		let mut tab = with_op_timeout("rollback", async move {
+			Ok(handle.lock_tab().await)
+		}).await?;
+		tab.prepare.remove(&self.id);
+		release_key_locks(&mut tab.locks, &self.id);
+		self.spill = None; //删除溢出日志
+		self.blob_values.clear();
+
+		Ok(())
+	}
+
+	///表分叉的预提交
+	pub async fn fork_prepare_inner(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
+		//检查分叉后的分叉深度是否超过限制，分叉表的深度为它的所有父表的数量
+		let max_depth = MAX_FORK_DEPTH.load(Ordering::Relaxed);
+		if max_depth > 0 {
+			let depth = build_fork_chain(tab_name.clone()).await?.len();
+			if depth > max_depth {
+				return Err(format!("ForkTooDeep: tab: {}, fork tab: {}, depth: {}, max depth: {}", tab_name.as_str(), fork_tab_name.as_str(), depth, max_depth));
+			}
+		}
+
+		//检查元信息表中是否有重复的表名，实时分叉表持续同步父表的写入，不允许再分叉
+		let tables = ALL_TABLES.lock().await;
+		if let Some(_) = tables.get(&fork_tab_name) {
+			return Err("duplicate fork tab name in meta tab".to_string())
+		}
+		if let Some(info) = tables.get(&tab_name) {
+			if info.mode == ForkMode::Live {
+				return Err(format!("LiveFork: tab: {}, fork tab: {}, reason: live fork can not be forked", tab_name.as_str(), fork_tab_name.as_str()));
+			}
+		}
+		Ok(())
+	}
+
+	///表分叉的提交，执行了真正的分叉
+	pub async fn fork_commit_inner(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta, mode: ForkMode) -> DBResult {
+		let index = match self.force_fork_inner().await {
+			Ok(idx) => idx,
+			Err(e) => return Err(e.to_string())
+		};
+
+		let mut tmi = TableMetaInfo::new(fork_tab_name.clone(), meta);
+		tmi.parent = Some(tab_name.clone());
+
+		tmi.parent_log_id = Some(index);
+		tmi.parent = Some(tab_name.clone());
+		tmi.mode = mode;
+
+		let mut wb = WriteBuffer::new();
+		tmi.encode(&mut wb);
+		let mut wb1 = WriteBuffer::new();
+		fork_tab_name.encode(&mut wb1);
+
+		let db_path = env::var("DB_PATH").unwrap_or("./".to_string());
+
+		let mut path = PathBuf::new();
+		path.push(db_path);
+		path.push(DB_META_TAB_NAME);
+
+		let file = match AsyncLogFileStore::open(path, 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, None).await {
+			Err(e) => {
+				panic!("!!!!!!open table = {:?} failed, e: {:?}", "tabs_meta", e);
+			},
+			Ok(store) => store
+		};
+
+		let store = AsyncLogFileStore::new(file);
+
+		// 找到父表的元信息，将它的引用计数加一，并和新创建的分叉表信息在同一批次中写入元信息表，保证分叉的元信息要么全部写入，要么全部未写入
+		let mut lock = ALL_TABLES.lock().await;
+		let parent = lock.get(&tab_name).map(|info| {
+			let mut info = info.clone();
+			info.ref_count += 1;
+			info
+		});
+
+		let mut b = WriteBuffer::new();
+		let mut b2 = WriteBuffer::new();
+		let mut pairs: Vec<(&[u8], &[u8])> = Vec::with_capacity(2);
+		if let Some(info) = &parent {
+			tab_name.encode(&mut b);
+			info.encode(&mut b2);
+			pairs.push((&b.bytes, &b2.bytes));
+		}
+		pairs.push((&wb1.bytes, &wb.bytes));
+
+		if let Err(e) = store.write_batch(&pairs).await {
+			return Err(format!("write fork meta failed, tab: {}, fork tab: {}, reason: {:?}", tab_name.as_str(), fork_tab_name.as_str(), e));
+		}
+
+		// 元信息写入成功后，再更新缓存的元信息
+		if let Some(info) = parent {
+			lock.insert(tab_name, info);
+		}
+		lock.insert(fork_tab_name, tmi);
+
+		Ok(())
+	}
+
+	///表分叉的回滚，表分叉已提交则无法回滚
+	pub async fn fork_rollback_inner(&self) -> DBResult {
+		Ok(())
+	}
+
+	///批量表分叉，先检查所有分叉，再分裂所有父表，最后在同一批次中写入所有父表和分叉表的元信息
+	///任意分叉表名重复或分叉深度超过限制，则拒绝整个批次，父表的分裂不影响父表的数据
+	pub async fn fork_tabs_inner(&self, specs: Vec<(Atom, Atom, TabMeta)>) -> DBResult {
+		let mut names = XHashSet::default();
+		for (tab_name, fork_tab_name, meta) in specs.iter() {
+			if !names.insert(fork_tab_name.clone()) {
+				return Err(format!("duplicate fork tab name in batch, fork tab: {}", fork_tab_name.as_str()));
+			}
+			self.fork_prepare_inner(Atom::from(""), tab_name.clone(), fork_tab_name.clone(), meta.clone()).await?;
+		}
+
+		//分裂所有父表，同一个父表只分裂一次
+		let mut indexes: XHashMap<Atom, usize> = XHashMap::default();
+		for (tab_name, _, _) in specs.iter() {
+			if indexes.contains_key(tab_name) {
+				continue;
+			}
+			let parent = LogFileDB::open(tab_name).await?;
+			match parent.split().await {
+				Err(e) => return Err(format!("split parent tab failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+				Ok(index) => {
+					indexes.insert(tab_name.clone(), index);
+				},
+			}
+		}
+
+		let store = open_meta_store().await?;
+		let mut lock = ALL_TABLES.lock().await;
+		let mut parents: XHashMap<Atom, TableMetaInfo> = XHashMap::default();
+		let mut forks = Vec::with_capacity(specs.len());
+		for (tab_name, fork_tab_name, meta) in specs {
+			//分裂期间可能有其它事务创建了同名的表
+			if lock.contains_key(&fork_tab_name) {
+				return Err("duplicate fork tab name in meta tab".to_string())
+			}
+
+			if !parents.contains_key(&tab_name) {
+				match lock.get(&tab_name) {
+					None => return Err(String::from("TabNotFound: ") + tab_name.as_str()),
+					Some(info) => {
+						parents.insert(tab_name.clone(), info.clone());
+					},
+				}
+			}
+			parents.get_mut(&tab_name).unwrap().ref_count += 1;
+
+			let mut tmi = TableMetaInfo::new(fork_tab_name.clone(), meta);
+			tmi.parent = Some(tab_name.clone());
+			tmi.parent_log_id = indexes.get(&tab_name).cloned();
+			forks.push(tmi);
+		}
+
+		let mut bins = Vec::with_capacity(parents.len() + forks.len());
+		for info in parents.values().chain(forks.iter()) {
+			let mut key = WriteBuffer::new();
+			info.tab_name.encode(&mut key);
+			let mut value = WriteBuffer::new();
+			info.encode(&mut value);
+			bins.push((key.bytes, value.bytes));
+		}
+		let pairs: Vec<(&[u8], &[u8])> = bins.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+		if let Err(e) = store.write_batch(&pairs).await {
+			return Err(format!("write fork meta failed, forks: {}, reason: {:?}", forks.len(), e));
+		}
+
+		// 元信息写入成功后，再更新缓存的元信息
+		for (tab_name, info) in parents {
+			lock.insert(tab_name, info);
+		}
+		for tmi in forks {
+			lock.insert(tmi.tab_name.clone(), tmi);
+		}
+
+		Ok(())
+	}
+
+	///强制日志文件分裂
+	async fn force_fork_inner(&self) -> Result<usize> {
+		self.tab.split().await
+	}
+}
+
+//================================ 内部结构和方法
+const TIMEOUT: usize = 100;
+
+
+type BinMap = OrdMap<Tree<Bon, Bin>>;
+
+// 内存表
+struct MemeryTab {
+	pub prepare: Prepare,
+	pub root: BinMap,
+	pub tab: Atom,
+	load_size: usize,	//加载时的数据大小，用于维护LOG_FILE_TOTAL_SIZE
+	unloaded: bool,		//内存表和键值缓冲是否已被释放，已释放的表在下次创建事务时重新加载
+	history: XHashMap<Bin, VecDeque<(u64, Option<Bin>)>>,	//主键的历史版本，按提交序号从旧到新排列，值为None表示已删除
+	history_from: u64,	//历史版本的起始提交序号，没有历史版本的主键从该序号起没有被写入过
+	history_order: VecDeque<Bin>,	//保留历史版本的主键，按开始保留的顺序排列，超过上限时从最早的开始淘汰
+	history_evicted: u64,	//已淘汰历史版本的主键的最大提交序号，没有历史版本的主键在该序号后没有被写入过
+	seq_marks: VecDeque<(u64, u64)>,	//最近的提交序号和提交后表的日志记录数量
+	scan_base: Option<(u64, u64)>,	//可扫描日志文件的最早的提交序号和此时表的日志记录数量，None表示不能扫描
+	scan_gen: u64,		//日志文件被整理或重写的次数，扫描期间改变则扫描结果无效
+	last_seq: u64,		//最近一次提交的提交序号
+	indexes: XHashMap<Atom, SecondaryIndex>,	//表的二级索引
+	bulk: bool,			//是否处于批量模式，批量模式下暂停维护二级索引，结束时按内存表重建
+	cold: Vec<PathBuf>,	//按重放限制未加载的更早的日志文件，从旧到新排列，为空表示已加载全部日志文件
+	cold_writes: XHashMap<Bin, u64>,	//加载的日志文件中没有记录的主键被首次提交的提交序号，用于判断事务开始时主键是否仍是未加载的值
+	cold_index: Option<Arc<XHashMap<Bin, usize>>>,	//未加载的日志文件中的主键到主键最新记录所在的未加载日志文件序号的索引，首次读取未加载的主键时建立
+	replay_all: bool,	//是否忽略重放限制，加载过更早的日志文件后，重新加载时仍然加载全部日志文件
+	sink: Option<SinkFooter>,	//日志表的摘要，日志表不维护内存表，只追加日志
+	locks: XHashMap<Bin, LockEntry>,	//主键的记录锁，由key_lock获取，持有锁的事务提交或回滚时释放
+	invalid: bool,		//表的日志文件是否已被交换，已交换的表句柄不能再加载和提交
+}
+
+impl MemeryTab {
+	//记录本次提交后表的日志记录数量，只保留最近的AS_OF_SCAN_COMMITS个提交，更早的提交合并到可扫描的起点
+	fn mark_commit(&mut self, records: u64) {
+		if self.scan_base.is_none() {
+			return;
+		}
+
+		self.seq_marks.push_back((self.last_seq, records));
+		let limit = AS_OF_SCAN_COMMITS.load(Ordering::Relaxed);
+		while self.seq_marks.len() > limit {
+			self.scan_base = self.seq_marks.pop_front();
+		}
+	}
+
+	//获取指定提交序号时表的日志记录数量，早于可扫描的起点或不能扫描则返回None
+	fn scan_records(&self, seq: u64) -> Option<u64> {
+		let (base_seq, base_records) = self.scan_base?;
+		if seq < base_seq {
+			return None;
+		}
+
+		Some(self.seq_marks.iter().rev().find(|(s, _)| *s <= seq).map(|(_, records)| *records).unwrap_or(base_records))
+	}
+
+	//记录冷表中加载的日志文件和之前的提交中都没有记录的主键被首次提交的提交序号，必须在追加本次提交的日志前调用
+	fn mark_cold(&mut self, logs: &RwLogMap, store: &AsyncLogFileStore) {
+		if self.cold.is_empty() {
+			return;
+		}
+
+		for (k, rw_v) in logs.iter() {
+			if let RwLog::Read = rw_v {
+				continue;
+			}
+			if !self.cold_writes.contains_key(k) && !store.is_touched(k) {
+				self.cold_writes.insert(k.clone(), self.last_seq);
+			}
+		}
+	}
+
+	//重置可扫描的起点，之前的提交不能再扫描日志文件读取，records为None表示在下次加载前不能扫描
+	fn reset_scan(&mut self, records: Option<u64>) {
+		self.seq_marks.clear();
+		self.scan_base = records.map(|records| (COMMIT_SEQ.load(Ordering::SeqCst), records));
+		self.scan_gen += 1;
+	}
+}
+
+/*
+* 主键的记录锁，只读锁可以被多个事务同时持有，写锁只能被一个事务持有
+*/
+struct LockEntry {
+	readonly: bool,					//是否是只读锁
+	owners: XHashMap<Guid, u64>,	//持有锁的事务和锁的超时时间，单位毫秒
+}
+
+impl LockEntry {
+	//指定事务请求的锁是否与其它事务持有的锁冲突，只读锁之间不冲突，调用前已移除超时的持有者
+	fn conflicts(&self, id: &Guid, readonly: bool) -> bool {
+		let others = self.owners.keys().any(|owner| owner != id);
+		others && !(self.readonly && readonly)
+	}
+
+	//其它事务持有的未超时的锁是否阻止指定事务预提交对主键的读写
+	fn blocks(&self, id: &Guid, write: bool, now: u64) -> bool {
+		let others = self.owners.iter().any(|(owner, expire)| owner != id && *expire > now);
+		others && (write || !self.readonly)
+	}
+}
+
+//释放指定事务持有的所有记录锁，没有持有者的锁被移除，同时回收已超时的锁
+fn release_key_locks(locks: &mut XHashMap<Bin, LockEntry>, id: &Guid) {
+	if locks.is_empty() {
+		return;
+	}
+
+	let now = clock::now_millis();
+	locks.retain(|_, entry| {
+		entry.owners.retain(|owner, expire| owner != id && *expire > now);
+		!entry.owners.is_empty()
+	});
+}
+
+/**
+* 只插入的日志表的摘要
+*/
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SinkFooter {
+	pub count: u64,					//日志表的记录数量
+	pub last_key: Option<Vec<u8>>,	//最后追加的记录的主键
+}
+
+/*
+* 二级索引中的位置，按索引键和主键排序，Min和Max是索引键的边界，用于定位索引键的起始和结束位置
+*/
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum IndexBound {
+	Min,
+	Key(Bon),
+	Max,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct IndexPos(Vec<u8>, IndexBound);
+
+impl IndexPos {
+	//判断位置是否在指定位置之后，降序时之后的位置更小
+	fn is_after(&self, pos: &IndexPos, descending: bool) -> bool {
+		if descending {
+			self < pos
+		} else {
+			self > pos
+		}
+	}
+}
+
+type IndexMap = OrdMap<Tree<IndexPos, Bin>>;
+
+/*
+* 表的二级索引，索引键相同的记录按主键排列，索引是有序的持久化映射，事务开始时的快照与内存表的根节点一样只拷贝句柄
+*/
+#[derive(Clone)]
+struct SecondaryIndex {
+	extractor: IndexExtractor,
+	entries: IndexMap,
+}
+
+impl SecondaryIndex {
+	fn new(extractor: IndexExtractor) -> Self {
+		SecondaryIndex {
+			extractor,
+			entries: OrdMap::<Tree<IndexPos, Bin>>::new(None),
+		}
+	}
+
+	//按主键从旧值到新值的变化增量维护索引，值为None表示不存在
+	fn update(&mut self, key: &Bin, old: Option<&Bin>, new: Option<&Bin>) {
+		if let Some(index_key) = old.and_then(|v| (self.extractor)(key.as_slice(), v.as_slice())) {
+			self.entries.delete(&IndexPos(index_key, IndexBound::Key(Bon::new(key.clone()))), false);
+		}
+		if let Some(index_key) = new.and_then(|v| (self.extractor)(key.as_slice(), v.as_slice())) {
+			self.entries.upsert(IndexPos(index_key, IndexBound::Key(Bon::new(key.clone()))), key.clone(), false);
+		}
+	}
+
+	//按内存表的所有记录重建索引
+	fn rebuild(&mut self, root: &BinMap) {
+		self.entries = OrdMap::<Tree<IndexPos, Bin>>::new(None);
+		for Entry(k, v) in root.iter(None, false) {
+			if let Some(index_key) = (self.extractor)(k.as_slice(), v.as_slice()) {
+				let key = Arc::new(k.to_vec());
+				self.entries.upsert(IndexPos(index_key, IndexBound::Key(Bon::new(key.clone()))), key, false);
+			}
+		}
+	}
+
+	//获取从指定位置之后开始的索引条目，按索引键和主键顺序排列
+	fn entries_from(&self, start: Option<&IndexPos>) -> Vec<(Vec<u8>, Bin)> {
+		let mut entries = Vec::new();
+		for Entry(pos, key) in self.entries.iter(start, false) {
+			if start.map(|start| pos.is_after(start, false)).unwrap_or(true) {
+				entries.push((pos.0.clone(), key.clone()));
+			}
+		}
+		entries
+	}
+}
+
+/*
+* 二级索引的迭代器，按索引键顺序返回记录的主键和值
+* 每次从上次返回的位置在索引快照中重新定位，跳过事务中写入的主键，并与事务写入的索引条目合并
+*/
+pub struct IndexIter {
+	entries: IndexMap,						//事务开始时的索引快照
+	root: BinMap,							//事务的内存表，用于获取主键的值
+	written: XHashSet<Bin>,					//事务中写入的主键
+	overlay: VecDeque<(IndexPos, Bin)>,		//事务写入的索引条目，按迭代方向排列
+	cursor: Option<IndexPos>,				//上次返回的位置或起始位置，下次从该位置之后开始
+	descending: bool,
+	filter: Filter,
+}
+
+impl IndexIter {
+	//获取索引快照中上次返回的位置之后的第一个未被事务写入的条目
+	fn next_committed(&self) -> Option<(IndexPos, Bin)> {
+		for Entry(pos, key) in self.entries.iter(self.cursor.as_ref(), self.descending) {
+			if let Some(cursor) = &self.cursor {
+				if !pos.is_after(cursor, self.descending) {
+					continue;
+				}
+			}
+			if self.written.contains(key) {
+				continue;
+			}
+			return Some((pos.clone(), key.clone()));
+		}
+		None
+	}
+}
+
+impl Iter for IndexIter {
+	type Item = (Bin, Bin);
+	fn next(&mut self) -> Option<NextResult<Self::Item>> {
+		loop {
+			let committed = self.next_committed();
+			let (pos, key) = match (committed, self.overlay.front()) {
+				(None, None) => return Some(Ok(None)),
+				(Some(c), Some(o)) if o.0.is_after(&c.0, self.descending) => c,
+				(Some(c), None) => c,
+				(_, Some(_)) => self.overlay.pop_front().unwrap(),
+			};
+			self.cursor = Some(pos);
+
+			if let Some(value) = self.root.get(&Bon::new(key.clone())) {
+				if let Some(filter) = &self.filter {
+					if !filter(&key, Some(value)) {
+						continue;
+					}
+				}
+				return Some(Ok(Some((key, value.clone()))));
+			}
+		}
+	}
+}
+
+pub struct MemIter{
+	_root: BinMap,
+	filter: Filter,
+	point: usize,
+	pivot: Option<(Bon, bool)>,	//迭代的起始主键和迭代方向，跳过起始主键之前的记录后清除
+}
+
+impl Drop for MemIter{
+	fn drop(&mut self) {
+		unsafe{Box::from_raw(self.point as *mut <Tree<Bin, Bin> as OIter<'_>>::IterType)};
+	}
+}
+
+impl MemIter{
+	pub fn new<'a>(tab: &Atom, root: BinMap, it: <Tree<Bon, Bin> as OIter<'a>>::IterType, filter: Filter) -> MemIter{
+		MemIter{
+			_root: root,
+			filter,
+			point: Box::into_raw(Box::new(it)) as usize,
+			pivot: None,
+		}
+	}
+
+	//设置迭代的起始主键，起始主键不存在时，升序从大于起始主键的第一个主键开始，降序从小于起始主键的第一个主键开始
+	pub fn with_pivot(mut self, pivot: Option<Bon>, descending: bool) -> Self {
+		self.pivot = pivot.map(|key| (key, descending));
+		self
+	}
+}
+
+impl Iter for MemIter{
+	type Item = (Bin, Bin);
+	fn next(&mut self) -> Option<NextResult<Self::Item>>{
+
+		let mut it = unsafe{Box::from_raw(self.point as *mut <Tree<Bin, Bin> as OIter<'_>>::IterType)};
+		let r = loop {
+			match it.next() {
+				Some(&Entry(ref k, ref v)) => {
+					if is_before_pivot(&mut self.pivot, k) {
+						continue;
+					}
+					if let Some(filter) = &self.filter {
+						if !filter(k, Some(v)) {
+							continue;
+						}
+					}
+					break Some(Ok(Some((k.clone(), v.clone()))));
+				},
+				None => break Some(Ok(None)),
+			}
+		};
+		mem::forget(it);
+		r
+	}
+}
+
+/*
+* 日志文件表的TabKV迭代器，将记录迭代器返回的键值对转换为与查询结果一致的TabKV
+*/
+pub struct TabKVIter {
+	ware: Atom,
+	tab: Atom,
+	iter: FuseOnErr<Box<dyn Iter<Item = (Bin, Bin)> + Send>>,
+}
+
+impl Iter for TabKVIter {
+	type Item = TabKV;
+	fn next(&mut self) -> Option<NextResult<Self::Item>> {
+		match self.iter.next() {
+			Some(Ok(Some((key, value)))) => Some(Ok(Some(TabKV {
+				ware: self.ware.clone(),
+				tab: self.tab.clone(),
+				key,
+				index: 0,
+				value: Some(value),
+			}))),
+			Some(Ok(None)) => Some(Ok(None)),
+			Some(Err(e)) => Some(Err(e)),
+			None => None,
+		}
+	}
+}
+
+pub struct MemKeyIter{
+	_root: BinMap,
+	filter: Filter,
+	point: usize,
+	pivot: Option<(Bon, bool)>,	//迭代的起始主键和迭代方向，跳过起始主键之前的主键后清除
+}
+
+impl Drop for MemKeyIter{
+	fn drop(&mut self) {
+		unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)};
+	}
+}
+
+impl MemKeyIter{
+	pub fn new(tab: &Atom, root: BinMap, keys: Keys<'_, Tree<Bon, Bin>>, filter: Filter) -> MemKeyIter{
+		MemKeyIter{
+			_root: root,
+			filter,
+			point: Box::into_raw(Box::new(keys)) as usize,
+			pivot: None,
+		}
+	}
+
+	//设置迭代的起始主键，语义同MemIter::with_pivot
+	pub fn with_pivot(mut self, pivot: Option<Bon>, descending: bool) -> Self {
+		self.pivot = pivot.map(|key| (key, descending));
+		self
+	}
+}
+
+impl Iter for MemKeyIter{
+	type Item = Bin;
+	fn next(&mut self) -> Option<NextResult<Self::Item>>{
+		let mut it = unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)};
+		let r = loop {
+			match it.next() {
+				Some(k) => {
+					if is_before_pivot(&mut self.pivot, k) {
+						continue;
+					}
+					if let Some(filter) = &self.filter {
+						if !filter(k, None) {
+							continue;
+						}
+					}
+					break Some(Ok(Some(k.clone())));
+				},
+				None => break Some(Ok(None)),
+			}
+		};
+		mem::forget(it);
+		r
+	}
+}
+
+//判断迭代到的主键是否在起始主键之前，升序时小于起始主键，降序时大于起始主键，则需要跳过
+//第一个不需要跳过的主键之后的主键都已在起始主键之后，清除起始主键，不再比较
+fn is_before_pivot(pivot: &mut Option<(Bon, bool)>, key: &Bin) -> bool {
+	let before = match pivot {
+		None => return false,
+		Some((start, descending)) => {
+			let key = Bon::new(key.clone());
+			if *descending {
+				key > *start
+			} else {
+				key < *start
+			}
+		},
+	};
+	if !before {
+		*pivot = None;
+	}
+
+	before
+}
+
+#[derive(Clone)]
+pub struct LogFileMetaTxn {
+	alters: Arc<Mutex<XHashMap<Atom, Option<Arc<TabMeta>>>>>,
+	configs: Arc<Mutex<XHashMap<Atom, TabConfig>>>,	//修改的表级配置，在表元信息的修改之后应用
+}
+
+impl LogFileMetaTxn {
+	pub(crate) fn new() -> Self {
+		LogFileMetaTxn {
+			alters: Arc::new(Mutex::new(XHashMap::default())),
+			configs: Arc::new(Mutex::new(XHashMap::default())),
+		}
+	}
+
+	// 创建表、修改指定表的元数据
+	pub async fn alter(&self, tab_name: &Atom, meta: Option<Arc<TabMeta>>) -> DBResult {
+		self.alters.lock().await.insert(tab_name.clone(), meta);
+		Ok(())
+	}
+
+	//修改指定表的表级配置，表可以在同一事务中创建
+	pub async fn alter_config(&self, tab_name: &Atom, config: TabConfig) -> DBResult {
+		config.check_supported(tab_name)?;
+		self.configs.lock().await.insert(tab_name.clone(), config);
+		Ok(())
+	}
+
+	//快照拷贝表
+	pub async fn snapshot(&self, _tab: &Atom, _from: &Atom) -> DBResult {
+		Ok(())
+	}
+
+	//修改指定表的名字
+	pub async fn rename(&self, _tab: &Atom, _new_name: &Atom) -> DBResult {
+		Ok(())
+	}
+
+	//获得事务的状态
+	pub async fn get_state(&self) -> TxState {
+		TxState::Ok
+	}
+
+	//预提交一个事务
+	pub async fn prepare(&self, _timeout: usize) -> DBResult {
+		Ok(())
+	}
+
+	//提交一个事务，先检查所有的修改，全部通过后只打开一次元信息表，并在一次持久化中写入所有的修改
+	//任意一个修改检查失败则不写入任何修改，写入失败也不修改内存中的表元信息，并丢弃本事务中创建并已打开的表
+	pub async fn commit(&self) -> CommitResult {
+		let r = self.commit_inner().await;
+		if r.is_err() {
+			self.discard_created().await;
+		}
+		r
+	}
+
+	async fn commit_inner(&self) -> CommitResult {
+		let alters = self.alters.lock().await;
+		let configs = self.configs.lock().await;
+		if alters.len() == 0 && configs.len() == 0 {
+			return Ok(XHashMap::with_capacity_and_hasher(0, Default::default()));
+		}
+
+		//在表元信息的副本上依次应用所有修改，同一事务中的后续修改可以看到之前的修改
+		//从读取表元信息到更新内存中的表元信息都持有ALL_TABLES锁，并发的元信息事务不会基于过期的引用计数修改
+		let mut tables = ALL_TABLES.lock().await;
+		let mut staged: XHashMap<Atom, Option<TableMetaInfo>> = XHashMap::default();
+		for (tab_name, meta) in alters.iter() {
+			let current = match staged.get(tab_name) {
+				Some(info) => info.clone(),
+				None => tables.get(tab_name).cloned(),
+			};
+
+			match meta {
+				Some(m) => {
+					//表已存在且元信息未改变，则不允许重复创建，元信息改变则追加新的历史版本
+					let mt = m.as_ref().clone();
+					let tmi = match current {
+						Some(mut info) => {
+							if info.meta == mt {
+								return Err(format!("tab_name: {:?} exist", tab_name))
+							}
+							info.evolve(mt);
+							info
+						},
+						None => TableMetaInfo::new(tab_name.clone(), mt),
+					};
+					staged.insert(tab_name.clone(), Some(tmi));
+				},
+				None => {
+					//删除表，有分叉的表不允许删除，父表的引用计数减一
+					let info = match current {
+						None => return Err(format!("delete tab: {:?} not found", tab_name)),
+						Some(info) => info,
+					};
+					if info.ref_count > 0 {
+						return Err(format!("delete tab: {:?} failed, ref_count = {:?}", info.tab_name, info.ref_count))
+					}
+					if let Some(parent) = info.parent.clone() {
+						let parent_info = match staged.get(&parent) {
+							Some(info) => info.clone(),
+							None => tables.get(&parent).cloned(),
+						};
+						if let Some(mut parent_info) = parent_info {
+							parent_info.ref_count -= 1;
+							staged.insert(parent, Some(parent_info));
+						}
+					}
+					staged.insert(tab_name.clone(), None);
+
+					//逻辑表的分片表不能单独存在，与逻辑表一起删除
+					let mut index = 0;
+					loop {
+						let shard = shard_name(tab_name, index);
+						let shard_info = match staged.get(&shard) {
+							Some(info) => info.clone(),
+							None => tables.get(&shard).cloned(),
+						};
+						match shard_info {
+							None => break,
+							Some(info) if info.ref_count > 0 => {
+								return Err(format!("delete tab: {:?} failed, shard: {:?}, ref_count = {:?}", tab_name, info.tab_name, info.ref_count))
+							},
+							Some(_) => {
+								staged.insert(shard, None);
+							},
+						}
+						index += 1;
+					}
+				},
+			}
+		}
+
+		for (tab_name, config) in configs.iter() {
+			let current = match staged.get(tab_name) {
+				Some(info) => info.clone(),
+				None => tables.get(tab_name).cloned(),
+			};
+			match current {
+				None => return Err(String::from("TabNotFound: ") + tab_name.as_str()),
+				Some(mut info) => {
+					//日志表不加载已有的记录，已有记录的表切换为日志表后记录无法再读取，所以不允许
+					if config.is_log_sink() && !info.config.is_log_sink() && tab_has_records(tab_name).await {
+						return Err(String::from("LogSink: table not empty, tab: ") + tab_name.as_str());
+					}
+					info.config = config.clone();
+					staged.insert(tab_name.clone(), Some(info));
+				},
+			}
+		}
+
+		//所有修改检查通过后，只打开一次元信息表，并批量写入
+		let store = open_meta_store().await?;
+		let mut pairs = Vec::new();
+		let mut keys = Vec::new();
+		for (tab_name, info) in staged.iter() {
+			let mut kt = WriteBuffer::new();
+			tab_name.clone().encode(&mut kt);
+			match info {
+				Some(info) => {
+					let mut vt = WriteBuffer::new();
+					info.encode(&mut vt);
+					pairs.push((kt.bytes, vt.bytes));
+				},
+				None => keys.push(kt.bytes),
+			}
+		}
+		let pairs_ref: Vec<(&[u8], &[u8])> = pairs.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+		let keys_ref: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+		if let Err(e) = store.commit_batch(&pairs_ref, &keys_ref).await {
+			return Err(format!("commit meta failed, tabs: {}, reason: {:?}", staged.len(), e));
+		}
+
+		//元信息持久化后，更新内存中的表元信息，已删除的表从日志文件表缓存中移除，避免再次打开时返回已删除的数据
+		for (tab_name, info) in staged.into_iter() {
+			match info {
+				Some(info) => {
+					tables.insert(tab_name, info);
+				},
+				None => {
+					tables.remove(&tab_name);
+					LOG_FILE_TABS.write().await.remove(&tab_name);
+				},
+			}
+		}
+		drop(tables);
+
+		//配置持久化后，应用到已打开的表
+		for (tab_name, config) in configs.iter() {
+			if let Some(tab) = LOG_FILE_TABS.read().await.get(tab_name) {
+				tab.apply_config(config);
+			}
+		}
+
+		Ok(XHashMap::with_capacity_and_hasher(0, Default::default()))
+	}
+
+	//回滚一个事务，丢弃本事务中创建并已打开的表
+	pub async fn rollback(&self) -> DBResult {
+		self.discard_created().await;
+		self.alters.lock().await.clear();
+		Ok(())
+	}
+
+	//丢弃本事务中创建的表在元信息提交前打开时产生的缓存和文件
+	async fn discard_created(&self) {
+		let created: Vec<Atom> = self.alters.lock().await.iter()
+			.filter(|(_, meta)| meta.is_some())
+			.map(|(tab_name, _)| tab_name.clone())
+			.collect();
+		for tab_name in created.iter() {
+			LogFileDB::discard_created(tab_name).await;
+		}
+	}
+}
+
+/*
+* 值的外置存储策略，值超过内联上限，或值达到热点大小且在两次整理之间的更新次数达到热点次数时外置存储
+* 整理后不再满足外置存储条件的值重新内联存储
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlobPolicy {
+	pub inline_max:		Option<usize>,	//值的内联上限，单位字节，None表示所有值都内联存储
+	pub hot_size:		usize,			//热点值的最小大小，单位字节
+	pub hot_updates:	usize,			//热点值在两次整理之间的更新次数，0表示不按更新次数外置存储
+}
+
+impl BlobPolicy {
+	//是否需要记录主键的更新次数
+	fn is_hot_candidate(&self, len: usize) -> bool {
+		self.inline_max.is_some() && self.hot_updates > 0 && len >= self.hot_size
+	}
+
+	//指定大小和更新次数的值是否外置存储
+	fn is_blob(&self, len: usize, updates: usize) -> bool {
+		match self.inline_max {
+			None => false,
+			Some(max) => len > max || (self.is_hot_candidate(len) && updates >= self.hot_updates),
+		}
+	}
+}
+
+/*
+* 表的外置存储，外置存储的值保存在表目录旁的.blobs目录中，每个值一个文件，文件名是值的id
+* 外置存储的写入记录的值是值的id，写入记录的标志字节设置BLOB_FLAG，加载时读取外置存储的值，内存表中始终是完整的值
+* 值的id只增不减，已分配的id上限持久化在外置存储目录的next文件中，整理删除的值的id不会被重新分配
+*/
+pub struct BlobStore {
+	dir:		SpinLock<Option<PathBuf>>,				//外置存储目录，没有目录的存储不外置存储
+	policy:		SpinLock<BlobPolicy>,					//外置存储策略
+	refs:		SpinLock<XHashMap<Vec<u8>, u64>>,		//当前外置存储的主键和值的id
+	updates:	SpinLock<XHashMap<Vec<u8>, usize>>,		//热点候选的主键在上次整理后的更新次数
+	staged:		SpinLock<XHashMap<Vec<u8>, (u64, Bin)>>,	//已写入外置存储但还未追加日志的主键、值的id和值
+	pending:	SpinLock<Vec<(Vec<u8>, u64)>>,			//加载时遇到的外置存储的主键和值的id，加载完成后异步读取
+	next:		AtomicU64,								//下一个值的id
+	limit:		AtomicU64,								//已持久化的id上限，分配到上限时先持久化新的上限
+	reserving:	Mutex<()>,								//持久化id上限的锁
+	ready:		AtomicBool,								//外置存储目录是否已创建
+}
+
+impl BlobStore {
+	pub fn new() -> Self {
+		BlobStore {
+			dir: SpinLock::new(None),
+			policy: SpinLock::new(BlobPolicy::default()),
+			refs: SpinLock::new(XHashMap::default()),
+			updates: SpinLock::new(XHashMap::default()),
+			staged: SpinLock::new(XHashMap::default()),
+			pending: SpinLock::new(Vec::new()),
+			next: AtomicU64::new(1),
+			limit: AtomicU64::new(1),
+			reserving: Mutex::new(()),
+			ready: AtomicBool::new(false),
+		}
+	}
+
+	//设置外置存储目录和策略，下一个值的id从持久化的id上限开始，没有持久化上限的旧目录从已有的最大id之后开始
+	fn open(&self, dir: PathBuf, policy: BlobPolicy) {
+		let max = blob_ids(&dir).into_iter().max().unwrap_or(0);
+		let persisted = match fs::read(dir.join(BLOB_NEXT_FILE)) {
+			Ok(bin) if bin.len() == 8 => u64::from_le_bytes(bin[..].try_into().unwrap()),
+			_ => 0,
+		};
+		let next = persisted.max(max + 1);
+		self.next.store(next, Ordering::SeqCst);
+		self.limit.store(next, Ordering::SeqCst);
+		self.ready.store(dir.is_dir(), Ordering::SeqCst);
+		*self.dir.lock() = Some(dir);
+		*self.policy.lock() = policy;
+	}
+
+	//设置外置存储策略，之后的写入使用新的策略，已外置存储的值在整理后按新的策略重新内联
+	pub fn set_policy(&self, policy: BlobPolicy) {
+		*self.policy.lock() = policy;
+	}
+
+	//主键的值当前是否外置存储
+	pub fn contains(&self, key: &[u8]) -> bool {
+		self.refs.lock().contains_key(key)
+	}
+
+	//主键的下一次写入是否可能外置存储，只检查不修改更新次数
+	fn wants(&self, key: &[u8], len: usize) -> bool {
+		let policy = self.policy.lock();
+		let updates = if policy.is_hot_candidate(len) {
+			self.updates.lock().get(key).cloned().unwrap_or(0) + 1
+		} else {
+			0
+		};
+		policy.is_blob(len, updates)
+	}
+
+	//在追加日志前将可能外置存储的值异步写入外置存储，追加日志时直接使用已写入的值的id，需要在内存表锁外调用
+	//同一个主键同时只能有一个调用者写入，事务在预提交成功后调用，预提交保证同一个主键同时只有一个事务在提交
+	//写入失败的值在追加日志时内联存储，返回的暂存在释放时移除未被使用的值，未被引用的值在整理时删除
+	async fn stage(self: &Arc<Self>, pairs: &[(&[u8], &[u8])]) -> StagedBlobs {
+		let mut staged = StagedBlobs {
+			store: self.clone(),
+			keys: Vec::new(),
+		};
+		let dir = match self.dir.lock().clone() {
+			None => return staged,
+			Some(dir) => dir,
+		};
+
+		for (key, value) in pairs {
+			if !self.wants(key, value.len()) {
+				continue;
+			}
+			if let Some((_, v)) = self.staged.lock().get(*key) {
+				if v.as_slice() == *value {
+					continue;
+				}
+			}
+
+			//先暂存再写入，写入过程中的值也被引用，不会被整理删除
+			let id = match self.alloc_id(&dir).await {
+				Err(e) => {
+					warn!("alloc blob id failed, store inline, key: {:?}, reason: {:?}", key, e);
+					continue;
+				},
+				Ok(id) => id,
+			};
+			self.staged.lock().insert(key.to_vec(), (id, Arc::new(value.to_vec())));
+			staged.keys.push((key.to_vec(), id));
+			if let Err(e) = self.write(&dir, id, value).await {
+				warn!("write blob failed, store inline, key: {:?}, reason: {:?}", key, e);
+				self.staged.lock().remove(*key);
+			}
+		}
+
+		staged
+	}
+
+	//将写入记录的标志字节和值追加到缓冲区，满足外置存储条件且已写入外置存储的值，缓冲区中只追加值的id
+	//没有预先写入外置存储的值内联存储，追加日志时不读写外置存储的文件
+	fn encode_into(&self, buf: &mut Vec<u8>, key: &[u8], value: &[u8], flags: u8) {
+		let flags = flags & !BLOB_FLAG;
+		let policy = self.policy.lock().clone();
+		let updates = if policy.is_hot_candidate(value.len()) {
+			let mut updates = self.updates.lock();
+			let count = updates.entry(key.to_vec()).or_insert(0);
+			*count += 1;
+			*count
+		} else {
+			0
+		};
+
+		if policy.is_blob(value.len(), updates) {
+			let staged = self.staged.lock().remove(key);
+			match staged {
+				Some((id, staged)) if staged.as_slice() == value => {
+					buf.push(flags | BLOB_FLAG);
+					buf.extend_from_slice(&id.to_le_bytes());
+					self.refs.lock().insert(key.to_vec(), id);
+					return;
+				},
+				_ => debug!("blob not staged, store inline, key: {:?}", key),
+			}
+		}
+
+		self.refs.lock().remove(key);
+		buf.push(flags);
+		buf.extend_from_slice(value);
+	}
+
+	//将主键当前的值重新追加到缓冲区，值已外置存储则继续引用原来的值的id，不重复写入外置存储
+	//只用于重写主键当前已提交的值，需要在内存表锁内调用
+	fn encode_current(&self, buf: &mut Vec<u8>, key: &[u8], value: &[u8], flags: u8) {
+		let flags = flags & !BLOB_FLAG;
+		match self.refs.lock().get(key) {
+			Some(id) => {
+				buf.push(flags | BLOB_FLAG);
+				buf.extend_from_slice(&id.to_le_bytes());
+			},
+			None => {
+				buf.push(flags);
+				buf.extend_from_slice(value);
+			},
+		}
+	}
+
+	//记录加载时遇到的外置存储的主键，加载完成后再异步读取值
+	fn defer(&self, key: &[u8], reference: &[u8]) -> SResult<()> {
+		let id = blob_id(reference)?;
+		self.refs.lock().insert(key.to_vec(), id);
+		self.pending.lock().push((key.to_vec(), id));
+		Ok(())
+	}
+
+	//删除的主键不再外置存储
+	fn forget(&self, key: &[u8]) {
+		self.refs.lock().remove(key);
+		self.updates.lock().remove(key);
+	}
+
+	//异步写入和持久化外置存储的值
+	async fn write(&self, dir: &Path, id: u64, value: &[u8]) -> Result<()> {
+		if !self.ready.load(Ordering::SeqCst) {
+			let path = dir.to_path_buf();
+			run_blocking(move || fs::create_dir_all(path)).await
+				.map_err(|e| Error::new(ErrorKind::Other, e))??;
+			self.ready.store(true, Ordering::SeqCst);
+		}
+
+		write_file_durable(dir.join(id.to_string()), value.to_vec()).await
+	}
+
+	//分配下一个值的id，分配到已持久化的上限时，先持久化新的上限，保证重启后不会重新分配已使用过的id
+	async fn alloc_id(&self, dir: &Path) -> Result<u64> {
+		let id = self.next.fetch_add(1, Ordering::SeqCst);
+		if id < self.limit.load(Ordering::SeqCst) {
+			return Ok(id);
+		}
+
+		let _guard = self.reserving.lock().await;
+		let limit = self.limit.load(Ordering::SeqCst);
+		if id < limit {
+			return Ok(id);
+		}
+		let limit = (id + 1).max(limit) + BLOB_ID_BATCH;
+		write_file_durable(dir.join(BLOB_NEXT_FILE), limit.to_le_bytes().to_vec()).await?;
+		self.limit.store(limit, Ordering::SeqCst);
+		Ok(id)
+	}
+
+	//删除外置存储目录中不再被引用的值，返回删除的值的数量，需要在内存表锁内调用，避免删除并发写入的值
+	//已暂存但还未追加日志的值也被引用
+	fn collect_garbage(&self) -> usize {
+		let dir = match self.dir.lock().clone() {
+			None => return 0,
+			Some(dir) => dir,
+		};
+
+		let mut live: XHashSet<u64> = self.refs.lock().values().cloned().collect();
+		live.extend(self.staged.lock().values().map(|(id, _)| *id));
+		let mut count = 0;
+		for id in blob_ids(&dir) {
+			if live.contains(&id) {
+				continue;
+			}
+			match fs::remove_file(dir.join(id.to_string())) {
+				Err(e) => warn!("remove blob failed, dir: {:?}, id: {}, reason: {:?}", dir, id, e),
+				Ok(_) => count += 1,
+			}
+		}
+
+		count
+	}
+
+	//从重新加载的存储复制外置存储的状态，用于重新加载后替换存储，更新次数是运行时的统计，重新加载后保留
+	fn replace_from(&self, other: &BlobStore) {
+		*self.dir.lock() = other.dir.lock().clone();
+		*self.policy.lock() = other.policy.lock().clone();
+		*self.refs.lock() = mem::replace(&mut *other.refs.lock(), XHashMap::default());
+		self.next.fetch_max(other.next.load(Ordering::SeqCst), Ordering::SeqCst);
+		self.limit.fetch_max(other.limit.load(Ordering::SeqCst), Ordering::SeqCst);
+		self.ready.store(other.ready.load(Ordering::SeqCst), Ordering::SeqCst);
+	}
+}
+
+/*
+* 已写入外置存储但还未追加日志的值，释放时移除未被追加日志使用的值
+*/
+struct StagedBlobs {
+	store: Arc<BlobStore>,
+	keys: Vec<(Vec<u8>, u64)>,
+}
+
+impl Drop for StagedBlobs {
+	fn drop(&mut self) {
+		let mut staged = self.store.staged.lock();
+		for (key, id) in self.keys.iter() {
+			if staged.get(key).map(|(staged_id, _)| staged_id == id).unwrap_or(false) {
+				staged.remove(key);
+			}
+		}
+	}
+}
+
+//获取表的外置存储目录，外置存储目录在表目录旁，与表目录同名加.blobs后缀，避免被当作日志文件
+pub(crate) fn blob_path(tab_path: &Path) -> PathBuf {
+	let mut path = tab_path.as_os_str().to_owned();
+	path.push(".blobs");
+	PathBuf::from(path)
+}
+
+//获取外置存储目录中的所有值的id
+fn blob_ids(dir: &Path) -> Vec<u64> {
+	match fs::read_dir(dir) {
+		Err(_) => Vec::new(),
+		Ok(entries) => entries.filter_map(|entry| entry.ok())
+			.filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse::<u64>().ok()))
+			.collect(),
+	}
+}
+
+//解析写入记录中的值的id
+fn blob_id(reference: &[u8]) -> SResult<u64> {
+	if reference.len() != 8 {
+		return Err(format!("invalid blob reference, reference: {:?}", reference));
+	}
+	Ok(u64::from_le_bytes(reference.try_into().unwrap()))
+}
+
+//使用异步文件写入指定文件的全部内容，并在返回前持久化
+async fn write_file_durable(path: PathBuf, bin: Vec<u8>) -> Result<()> {
+	let rt = match STORE_RUNTIME.read().await.as_ref() {
+		None => return Err(Error::new(ErrorKind::Other, "store runtime not ready")),
+		Some(rt) => rt.clone(),
+	};
+	let file = AsyncFile::open(rt, path, AsyncFileOptions::TruncateWrite).await?;
+	file.write(0, Arc::from(bin), WriteOptions::SyncAll(true)).await?;
+	Ok(())
+}
+
+//使用异步文件读取指定文件的指定字节范围，范围超出文件长度则截断
+async fn read_file_range(path: &Path, offset: u64, len: usize) -> Result<Vec<u8>> {
+	let rt = match STORE_RUNTIME.read().await.as_ref() {
+		None => return Err(Error::new(ErrorKind::Other, "store runtime not ready")),
+		Some(rt) => rt.clone(),
+	};
+	let file = AsyncFile::open(rt, path.to_path_buf(), AsyncFileOptions::OnlyRead).await?;
+	let size = file.get_size();
+	if offset >= size {
+		return Ok(Vec::new());
+	}
+	file.read(offset, len.min((size - offset) as usize)).await
+}
+
+//按写入记录中的值的id读取外置存储的值
+async fn read_blob(dir: Option<&Path>, reference: &[u8]) -> SResult<Vec<u8>> {
+	let id = blob_id(reference)?;
+	match dir {
+		None => Err(format!("read blob failed, id: {}, reason: blob dir not set", id)),
+		Some(dir) => {
+			let value = read_file_range(&dir.join(id.to_string()), 0, usize::MAX).await
+				.map_err(|e| format!("read blob failed, dir: {:?}, id: {}, reason: {:?}", dir, id, e))?;
+			BLOB_READ_BYTES.fetch_add(value.len(), Ordering::Relaxed);
+			Ok(value)
+		},
+	}
+}
+
+//解析带标志字节的写入记录的值，返回主键的标志和完整的值，外置存储的值从表的外置存储目录读取
+pub(crate) async fn decode_flagged_value(blob_dir: &Path, mut value: Vec<u8>) -> SResult<(u8, Vec<u8>)> {
+	if value.len() == 0 {
+		return Ok((0, value));
+	}
+
+	let flags = value.remove(0);
+	if flags & BLOB_FLAG == 0 {
+		return Ok((flags, value));
+	}
+	Ok((flags & !BLOB_FLAG, read_blob(Some(blob_dir), &value).await?))
+}
+
+#[derive(Clone)]
+pub struct AsyncLogFileStore {
+	pub removed: Arc<SpinLock<XHashMap<Vec<u8>, ()>>>,
+	pub map: Arc<SpinLock<BTreeMap<Vec<u8>, Arc<[u8]>>>>,
+	pub log_file: LogFile,
+	pub tmp_map: Arc<SpinLock<XHashMap<Vec<u8>, ()>>>,
+	pub writable_path: Arc<SpinLock<Option<PathBuf>>>,
+	pub is_statistics: Arc<AtomicBool>,
+	pub is_init: Arc<AtomicBool>,
+	pub statistics: Arc<SpinLock<VecDeque<(PathBuf, u64, u64)>>>,
+	pub has_flags: Arc<AtomicBool>,
+	pub flags: Arc<SpinLock<XHashMap<Vec<u8>, u8>>>,
+	pub blobs: Arc<BlobStore>,
+	unapplied: Arc<SpinLock<VecDeque<UnappliedLog>>>,	//已追加但未持久化的提交，按追加顺序排列，持久化后才更新键值缓冲
+	appended: Arc<AtomicUsize>,							//可写日志文件已追加的近似字节数，分裂后清零
+	records: Arc<AtomicU64>,							//表自己的日志文件中的记录数量，加载时累计读取的记录，之后累计追加的记录
+}
+
+/*
+* 已追加但未持久化的提交的写入和删除，id为提交追加的最后一条日志的id
+*/
+struct UnappliedLog {
+	id: usize,
+	writes: Vec<(Vec<u8>, Arc<[u8]>)>,
+	removes: Vec<Vec<u8>>,
+}
+
+unsafe impl Send for AsyncLogFileStore {}
+unsafe impl Sync for AsyncLogFileStore {}
+
+impl PairLoader for AsyncLogFileStore {
+	fn is_require(&self, log_file: Option<&PathBuf>, key: &Vec<u8>) -> bool {
+		self.records.fetch_add(1, Ordering::Relaxed);
+		let b = !self.removed.lock().contains_key(key) && !self.is_loaded(key);
+
+		if self.is_statistics.load(Ordering::Relaxed) {
+			//需要统计
+			let mut init = false;
+			if !b {
+				//已删除的记录，则不需要加载，但需要统计
+				if let Some((path, log_len, key_len)) = self.statistics.lock().get_mut(0) {
+					if Some(&*path) == log_file {
+						//指定只读日志文件的统计信息存在，则继续累计
+						*log_len += 1;
+						if !self.is_loaded(key) {
+							//如果需要加载的关键字不存在，则累计关键字数量
+							*key_len += 1;
+						}
+					} else {
+						//指定只读日志文件的统计信息不存在，则初始化
+						init = true;
+					}
+				} else {
+					init = true;
+				};
+			}
+
+			if init {
+				//当前没有任何统计信息，则初始化统计信息
+				if !b {
+					//已删除的记录，则不需要加载，但需要统计
+					if self.is_loaded(key) {
+						//如果不需要加载的关键字已存在，则不累计关键字数量
+						self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 0));
+					} else {
+						//如果不需要加载的关键字不存在，则累计关键字数量
+						self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 1));
+					}
+				} else {
+					//插入或更新的记录，需要加载，但不需要在判断是否加载时统计
+					self.statistics.lock().push_front((log_file.cloned().unwrap(), 0, 0));
+				}
+			}
+		} else {
+			if self.writable_path.lock().is_none() {
+				//如果当前是可写日志文件，且未记录，则记录，并忽略统计
+				*self.writable_path.lock() = log_file.cloned();
+			} else {
+				if self.writable_path.lock().as_ref() != log_file {
+					//当前可写日志文件已记录，且开始加载只读日志文件，则设置为需要统计，并开始初始化统计信息
+					if !b {
+						//已删除的记录，则不需要加载，但需要统计
+						self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 1));
+					} else {
+						//插入或更新的记录，需要加载，但不需要在判断是否加载时统计
+						self.statistics.lock().push_front((log_file.cloned().unwrap(), 0, 0));
+					}
+
+					//设置为需要统计
+					self.is_statistics.store(true, Ordering::SeqCst);
+				}
+			}
+		}
+
+		b
+	}
+
+	fn load(&mut self, log_file: Option<&PathBuf>, method: LogMethod, key: Vec<u8>, value: Option<Vec<u8>>) {
+		if self.is_statistics.load(Ordering::Relaxed) {
+			//需要统计
+			let mut init = false;
+			if let Some((path, log_len, key_len)) = self.statistics.lock().get_mut(0) {
+				if Some(&*path) == log_file {
+					//指定只读日志文件的统计信息存在，则继续累计
+					*log_len += 1;
+					if !self.is_loaded(&key) {
+						//如果需要加载的关键字不存在，则累计关键字数量
+						*key_len += 1;
+					}
+				} else {
+					//指定只读日志文件的统计信息不存在，则初始化
+					init = true;
+				}
+			} else {
+				init = true;
+			};
+
+			if init {
+				//当前没有任何统计信息，则初始化统计信息
+				if self.is_loaded(&key) {
+					//如果需要加载的关键字已存在，则不累计关键字数量
+					self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 0));
+				} else {
+					//如果需要加载的关键字不存在，则累计关键字数量
+					self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 1));
+				}
+			}
+		}
+
+		//日志从最新到最旧加载，关键字已加载或已删除，说明已有更新的记录，不允许被更旧的记录覆盖
+		if self.is_loaded(&key) || self.removed.lock().contains_key(&key) {
+			return;
+		}
+
+		if let Some(mut value) = value {
+			if self.is_init.load(Ordering::Relaxed) {
+				//带标志字节的记录，值的第一个字节是主键的标志，只记录非零的标志
+				if self.has_flags.load(Ordering::Relaxed) && value.len() > 0 {
+					let mut flags = value.remove(0);
+					if flags & BLOB_FLAG != 0 {
+						//外置存储的值，加载完成后再异步读取完整的值，先写入空值占位，引用无效则忽略这条记录
+						if let Err(e) = self.blobs.defer(&key, &value) {
+							error!("load blob failed, key: {:?}, reason: {}", key, e);
+							return;
+						}
+						value = Vec::new();
+						flags &= !BLOB_FLAG;
+					}
+					if flags != 0 {
+						self.flags.lock().insert(key.clone(), flags);
+					}
+				}
+				//启动初始化，才写入键值缓冲区，已加载的关键字就是键值缓冲区的关键字，不需要再记录
+				self.map.lock().insert(key, value.into());
+			} else {
+				self.tmp_map.lock().insert(key, ());
+			}
+		} else {
+			self.removed.lock().insert(key, ());
+		}
+	}
+}
+
+impl AsyncLogFileStore {
+	//判断加载时关键字是否已有更新的值，启动初始化时使用键值缓冲区判断，只统计时使用临时的已加载关键字表判断
+	fn is_loaded(&self, key: &Vec<u8>) -> bool {
+		if self.is_init.load(Ordering::Relaxed) {
+			self.map.lock().contains_key(key)
+		} else {
+			self.tmp_map.lock().contains_key(key)
+		}
+	}
+
+	//获取加载缓冲的大小，依次为已加载关键字表和已删除关键字表的关键字数量，用于诊断
+	//启动初始化时不使用已加载关键字表，只在整理后重建统计信息时临时使用，重建完成后清空
+	//已删除关键字表记录等待整理的删除记录，整理后按剩余的日志文件重建
+	pub fn buffer_sizes(&self) -> (usize, usize) {
+		(self.tmp_map.lock().len(), self.removed.lock().len())
+	}
+
+	//设置存储的日志格式版本，版本3及以上的写入记录的值前有一个字节的标志
+	pub fn set_format_version(&self, version: u8) {
+		self.has_flags.store(version >= FLAGS_FORMAT_VERSION, Ordering::Relaxed);
+	}
+
+	//获取主键的标志，没有设置过标志的主键为0
+	pub fn flags(&self, key: &[u8]) -> u8 {
+		self.flags.lock().get(key).cloned().unwrap_or(0)
+	}
+
+	//追加写入记录，带标志字节的存储使用主键当前的标志
+	fn append_value(&self, key: &[u8], value: &[u8]) -> usize {
+		let flags = self.flags(key);
+		self.append_with_flags(key, value, flags)
+	}
+
+	//追加主键当前已提交的值的写入记录，已外置存储的值继续引用原来的值的id
+	fn append_current(&self, key: &[u8], value: &[u8], flags: u8) -> usize {
+		if !self.has_flags.load(Ordering::Relaxed) {
+			return self.append_log(LogMethod::PlainAppend, key, value);
+		}
+
+		let mut bin = Vec::with_capacity(value.len() + 1);
+		self.blobs.encode_current(&mut bin, key, value, flags);
+		self.append_log(LogMethod::PlainAppend, key, &bin)
+	}
+
+	//加载完成后异步读取加载时遇到的外置存储的值，替换键值缓冲中的占位值，读取失败的主键不加载
+	async fn resolve_blobs(&self) {
+		let pending = mem::replace(&mut *self.blobs.pending.lock(), Vec::new());
+		if pending.is_empty() {
+			return;
+		}
+
+		let dir = self.blobs.dir.lock().clone();
+		for (key, id) in pending {
+			match read_blob(dir.as_deref(), &id.to_le_bytes()).await {
+				Err(e) => {
+					error!("load blob failed, key: {:?}, reason: {}", key, e);
+					self.map.lock().remove(&key);
+					self.flags.lock().remove(&key);
+					self.blobs.refs.lock().remove(&key);
+				},
+				Ok(value) => {
+					self.map.lock().insert(key, value.into());
+				},
+			}
+		}
+	}
+
+	//追加指定标志的写入记录，不带标志字节的存储忽略标志，带标志字节的存储按外置存储策略外置存储值
+	fn append_with_flags(&self, key: &[u8], value: &[u8], flags: u8) -> usize {
+		if !self.has_flags.load(Ordering::Relaxed) {
+			return self.append_log(LogMethod::PlainAppend, key, value);
+		}
+
+		let mut bin = Vec::with_capacity(value.len() + 1);
+		self.blobs.encode_into(&mut bin, key, value, flags);
+		self.append_log(LogMethod::PlainAppend, key, &bin)
+	}
+
+	//追加一条日志，并累计可写日志文件已追加的字节数
+	fn append_log(&self, method: LogMethod, key: &[u8], value: &[u8]) -> usize {
+		self.appended.fetch_add(key.len() + value.len(), Ordering::Relaxed);
+		self.records.fetch_add(1, Ordering::Relaxed);
+		self.log_file.append(method, key, value)
+	}
+
+	//通过已打开的日志文件，创建一个用于加载的存储
+	pub fn new(log_file: LogFile) -> Self {
+		AsyncLogFileStore {
+			removed: Arc::new(SpinLock::new(XHashMap::default())),
+			map: Arc::new(SpinLock::new(BTreeMap::new())),
+			log_file,
+			tmp_map: Arc::new(SpinLock::new(XHashMap::default())),
+			writable_path: Arc::new(SpinLock::new(None)),
+			is_statistics: Arc::new(AtomicBool::new(false)),
+			is_init: Arc::new(AtomicBool::new(true)),
+			statistics: Arc::new(SpinLock::new(VecDeque::new())),
+			has_flags: Arc::new(AtomicBool::new(false)),
+			flags: Arc::new(SpinLock::new(XHashMap::default())),
+			blobs: Arc::new(BlobStore::new()),
+			unapplied: Arc::new(SpinLock::new(VecDeque::new())),
+			appended: Arc::new(AtomicUsize::new(0)),
+			records: Arc::new(AtomicU64::new(0)),
+		}
+	}
+
+	pub async fn open<P: AsRef<Path> + std::fmt::Debug>(path: P, buf_len: usize, file_len: usize, log_file_index: Option<usize>) -> Result<LogFile> {
+		AsyncLogFileStore::open_with(path, buf_len, file_len, log_file_index, PREALLOCATE_LOG_FILE.load(Ordering::Relaxed)).await
+	}
+
+	//打开日志文件，preallocate为是否为可写日志文件预分配file_len大小的空间
+	pub async fn open_with<P: AsRef<Path> + std::fmt::Debug>(path: P, buf_len: usize, file_len: usize, log_file_index: Option<usize>, preallocate: bool) -> Result<LogFile> {
+		// println!("AsyncLogFileStore open ====== {:?}, log_index = {:?}", path, log_file_index);
+		match LogFile::open(STORE_RUNTIME.read().await.as_ref().unwrap().clone(), path, buf_len, file_len, log_file_index).await {
+			Err(e) => {
+				error!("LogFile::open error {:?}", e);
+				Err(e)
+			},
+			Ok(file) => {
+				if preallocate {
+					preallocate_writable(&file, file_len).await;
+				}
+				Ok(file)
+			},
+		}
+	}
+
+	//追加多个写入记录，不等待持久化，不更新键值缓冲，用于不维护内存表的日志表，返回最后一条记录的日志id，没有写入记录则返回None
+	pub fn append_batch(&self, pairs: &[(&[u8], &[u8])]) -> Option<usize> {
+		let mut id = None;
+		for (key, value) in pairs.iter() {
+			id = Some(self.append_with_flags(key, value, 0));
+		}
+
+		id
+	}
+
+	pub async fn write_batch(&self, pairs: &[(&[u8], &[u8])]) -> Result<()> {
+		if let Err(e) = self.write_batch_vectored(pairs).await {
+			warn!("write batch failed, len: {}, reason: {:?}", pairs.len(), e);
+			return Err(e);
+		}
+
+		Ok(())
+	}
+
+	//聚合写入多个键值对，只持久化一次
+	//日志文件的追加只把记录编码到日志文件的内存日志块中，不产生系统调用，整批记录在持久化时一次写入日志文件
+	//带标志字节的存储复用一个编码缓冲区逐条编码标志和值，不再把整批记录复制到一个额外的缓冲区
+	pub async fn write_batch_vectored(&self, pairs: &[(&[u8], &[u8])]) -> Result<()> {
+		if pairs.len() == 0 {
+			return Ok(());
+		}
+
+		match self.append_deferred(pairs, &[]).await {
+			None => Ok(()),
+			Some(id) => self.apply_when_durable(id).await,
+		}
+	}
+
+	//追加多个写入和删除记录，并按日志id顺序记录为未更新到键值缓冲的提交，返回最后一条记录的id，没有记录则返回None
+	//所有对键值缓冲的更新都经过未更新的提交队列，保证键值缓冲按日志顺序更新，追加和记录之间不等待
+	async fn append_deferred(&self, pairs: &[(&[u8], &[u8])], keys: &[&[u8]]) -> Option<usize> {
+		let mut id = None;
+		if self.has_flags.load(Ordering::Relaxed) && pairs.len() > 0 {
+			//可能外置存储的值先写入外置存储，提交前已暂存的值不会重复写入
+			let _staged = self.blobs.stage(pairs).await;
+			let flags: Vec<u8> = {
+				let flags = self.flags.lock();
+				pairs.iter().map(|(key, _)| flags.get(*key).cloned().unwrap_or(0)).collect()
+			};
+			let mut bin = Vec::new();
+			for ((key, value), flags) in pairs.iter().zip(flags) {
+				bin.clear();
+				self.blobs.encode_into(&mut bin, key, value, flags);
+				id = Some(self.append_log(LogMethod::PlainAppend, key, &bin));
+			}
+		} else {
+			for (key, value) in pairs.iter() {
+				id = Some(self.append_log(LogMethod::PlainAppend, key, value));
+			}
+		}
+		if pairs.len() > 0 {
+			VECTORED_WRITES.fetch_add(1, Ordering::Relaxed);
+		}
+		for key in keys {
+			id = Some(self.append_log(LogMethod::Remove, key, &[]));
+		}
+
+		if let Some(id) = id {
+			self.defer_apply(id, pairs, keys);
+		}
+		id
+	}
+
+	//等待日志持久化到指定id，持久化后按日志顺序将不晚于该id的提交更新到键值缓冲，持久化失败则丢弃该id的提交
+	async fn apply_when_durable(&self, id: usize) -> Result<()> {
+		if let Err(e) = self.log_file.delay_commit(id, false, 1).await {
+			self.discard_unapplied(id);
+			return Err(e);
+		}
+
+		self.apply_durable(id);
+		Ok(())
+	}
+
+	pub async fn write(&self, key: Vec<u8>, value: Vec<u8>) -> Result<Option<Vec<u8>>> {
+		let id = match self.append_deferred(&[(key.as_slice(), value.as_slice())], &[]).await {
+			None => return Ok(None),
+			Some(id) => id,
+		};
+		let old = self.map.lock().get(&key).cloned();
+		self.apply_when_durable(id).await?;
+
+		//更新指定key的存储数据，则返回更新前的存储数据
+		Ok(old.map(|value| value.to_vec()))
+	}
+
+	pub fn read(&self, key: &[u8]) -> Option<Arc<[u8]>> {
+		if let Some(value) = self.map.lock().get(key) {
+			return Some(value.clone())
+		}
+
+		None
+	}
+
+	//在一次持久化中写入和删除多个主键，写入和删除的主键不能重复
+	pub async fn commit_batch(&self, pairs: &[(&[u8], &[u8])], keys: &[&[u8]]) -> Result<()> {
+		match self.append_deferred(pairs, keys).await {
+			None => Ok(()),
+			Some(id) => self.apply_when_durable(id).await,
+		}
+	}
+
+	pub async fn remove_batch(&self, keys: &[&[u8]]) -> Result<()> {
+		match self.append_deferred(&[], keys).await {
+			None => Ok(()),
+			Some(id) => self.apply_when_durable(id).await,
+		}
+	}
+
+	//记录已追加但未持久化的提交，按日志id顺序插入，并发追加的提交也按日志顺序更新到键值缓冲
+	fn defer_apply(&self, id: usize, pairs: &[(&[u8], &[u8])], keys: &[&[u8]]) {
+		let log = UnappliedLog {
+			id,
+			writes: pairs.iter().map(|(key, value)| (key.to_vec(), Arc::from(*value))).collect(),
+			removes: keys.iter().map(|key| key.to_vec()).collect(),
+		};
+		let mut unapplied = self.unapplied.lock();
+		let index = unapplied.iter().rposition(|log| log.id < id).map(|index| index + 1).unwrap_or(0);
+		unapplied.insert(index, log);
+	}
+
+	//日志持久化到指定id后，按追加顺序将不晚于该id的提交更新到键值缓冲
+	fn apply_durable(&self, id: usize) {
+		loop {
+			let log = {
+				let mut unapplied = self.unapplied.lock();
+				match unapplied.front() {
+					Some(log) if log.id <= id => unapplied.pop_front().unwrap(),
+					_ => break,
+				}
+			};
+			{
+				let mut map = self.map.lock();
+				let mut removed = self.removed.lock();
+				for (key, value) in log.writes {
+					removed.remove(&key);
+					map.insert(key, value);
+				}
+			}
+			let keys: Vec<&[u8]> = log.removes.iter().map(|key| key.as_slice()).collect();
+			self.apply_removes(&keys);
+		}
+	}
+
+	//主键在加载的日志文件或之后的提交中是否有写入或删除记录，包括已追加但未持久化的提交
+	fn is_touched(&self, key: &[u8]) -> bool {
+		self.map.lock().contains_key(key)
+			|| self.removed.lock().contains_key(key)
+			|| self.unapplied.lock().iter().any(|log| log.writes.iter().any(|(k, _)| k.as_slice() == key) || log.removes.iter().any(|k| k.as_slice() == key))
+	}
+
+	//日志持久化失败，丢弃提交未更新到键值缓冲的写入和删除
+	fn discard_unapplied(&self, id: usize) {
+		self.unapplied.lock().retain(|log| log.id != id);
+	}
+
+	//从键值缓冲中移除删除的主键，删除的主键的标志同时清除
+	fn apply_removes(&self, keys: &[&[u8]]) {
+		for key in keys {
+			self.map.lock().remove(key.clone());
+			self.flags.lock().remove(*key);
+			self.blobs.forget(key);
+			self.removed.lock().insert(key.to_vec(), ()); //记录已删除且等待整理的主键
+		}
+	}
+
+	pub async fn remove(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+		let id = match self.append_deferred(&[], &[key.as_slice()]).await {
+			None => return Ok(None),
+			Some(id) => id,
+		};
+		let old = self.map.lock().get(&key).cloned();
+		self.apply_when_durable(id).await?;
+
+		Ok(old.map(|value| value.to_vec()))
+	}
+
+	pub fn last_key(&self) -> Option<Vec<u8>> {
+		self.map.lock().iter().last().map(|(k, _)| {
+			k.clone()
+		})
+	}
+
+	/// 强制产生分裂
+	pub async fn force_fork(&self) -> Result<usize> {
+		let index = self.log_file.split().await?;
+		self.appended.store(0, Ordering::Relaxed);
+		if SPLIT_FSYNC.load(Ordering::Relaxed) {
+			sync_writable(&self.log_file).await?;
+		}
+
+		Ok(index)
+	}
+}
+
+//在存储运行时上定时执行内存压力检查，每次检查完成后再安排下一次，停止后不再安排
+fn schedule_memory_shrink(rt: MultiTaskRuntime<()>, interval: usize) {
+	let next = rt.clone();
+	if let Err(e) = rt.spawn_timing(rt.alloc(), async move {
+		if !MEMORY_SHRINK_RUNNING.load(Ordering::SeqCst) {
+			return;
+		}
+
+		LogFileDB::shrink_memory().await;
+		schedule_memory_shrink(next, interval);
+	}, interval) {
+		MEMORY_SHRINK_RUNNING.store(false, Ordering::SeqCst);
+		error!("schedule memory shrink failed, reason: {:?}", e);
+	}
+}
+
+//打开元信息表的日志文件，用于写入表的元信息
+async fn open_meta_store() -> SResult<AsyncLogFileStore> {
+	META_STORE_OPENS.fetch_add(1, Ordering::Relaxed);
+	let mut path = PathBuf::new();
+	path.push(env::var("DB_PATH").unwrap_or("./".to_string()));
+	path.push(DB_META_TAB_NAME);
+	match AsyncLogFileStore::open(path, 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, None).await {
+		Err(e) => Err(format!("open meta tab failed, reason: {:?}", e)),
+		Ok(file) => Ok(AsyncLogFileStore::new(file)),
+	}
+}
+
+//并发加载分叉链中的父表在分叉点及之前的日志文件，同时加载的父表数量不超过ANCESTOR_LOAD_CONCURRENCY
+//返回的结果与父表在分叉链中的顺序相同
+async fn load_ancestors(tab: &Atom, ancestors: &[TableMetaInfo], fork_log_ids: &[usize]) -> Vec<SResult<AsyncLogFileStore>> {
+	let concurrency = ANCESTOR_LOAD_CONCURRENCY.load(Ordering::Relaxed);
+	let mut results = Vec::with_capacity(ancestors.len());
+	if concurrency <= 1 || ancestors.len() <= 1 {
+		for (tm, fork_log_id) in ancestors.iter().zip(fork_log_ids) {
+			results.push(load_ancestor(tab.clone(), tm.clone(), *fork_log_id).await);
+		}
+		return results;
+	}
+
+	let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+	let tasks: Vec<(usize, &TableMetaInfo, usize)> = ancestors.iter().zip(fork_log_ids).enumerate().map(|(index, (tm, id))| (index, tm, *id)).collect();
+	for batch in tasks.chunks(concurrency) {
+		let mut async_map = rt.map();
+		for (index, tm, fork_log_id) in batch {
+			let (index, tab, tm, fork_log_id) = (*index, tab.clone(), (*tm).clone(), *fork_log_id);
+			async_map.join(AsyncRuntime::Multi(rt.clone()), async move {
+				Ok((index, load_ancestor(tab, tm, fork_log_id).await))
+			});
+		}
+
+		//结果的顺序与加入的顺序无关，按父表在分叉链中的位置排序
+		let mut loaded: Vec<(usize, SResult<AsyncLogFileStore>)> = match async_map.map(AsyncRuntime::Multi(rt.clone())).await {
+			Err(e) => batch.iter().map(|(index, tm, _)| {
+				(*index, Err(format!("load ancestor failed, tab: {}, parent: {}, reason: {:?}", tab.as_str(), tm.tab_name.as_str(), e)))
+			}).collect(),
+			Ok(loaded) => loaded.into_iter().filter_map(|r| r.ok()).collect(),
+		};
+		loaded.sort_by_key(|(index, _)| *index);
+		results.extend(loaded.into_iter().map(|(_, store)| store));
+	}
+
+	results
+}
+
+//加载分叉链中的一个父表在分叉点及之前的日志文件，父表在分叉点的日志文件不存在或无法打开，则返回ParentUnavailable错误
+async fn load_ancestor(tab: Atom, tm: TableMetaInfo, fork_log_id: usize) -> SResult<AsyncLogFileStore> {
+	let mut path = PathBuf::new();
+	path.push(tm.tab_name.clone().as_ref());
+	path.push(format!("{:0>width$}", fork_log_id, width = 6));
+	if !path.is_file() {
+		return Err(format!("ParentUnavailable: tab: {}, parent: {}, reason: log not found, path: {:?}", tab.as_str(), tm.tab_name.as_str(), path));
+	}
+
+	let file = AsyncLogFileStore::open(tm.tab_name.as_ref(), 8000, tm.config.log_file_size(), tm.parent_log_id).await
+		.map_err(|e| format!("ParentUnavailable: tab: {}, parent: {}, reason: {:?}", tab.as_str(), tm.tab_name.as_str(), e))?;
+	let mut store = AsyncLogFileStore::new(file.clone());
+	//父表的外置存储的值从父表的外置存储目录读取，加载父表时不写入
+	store.blobs.open(blob_path(Path::new(tm.tab_name.as_ref())), BlobPolicy::default());
+
+	let version = read_format_version(Path::new(tm.tab_name.as_ref()))?;
+	load_with_version(version, &file, &mut store, path, tm.config.load_block_size()).await?;
+
+	Ok(store)
+}
+
+//校验主键按内存表的主键顺序严格递增，返回第一个违反顺序的主键
+pub fn verify_key_order<'a, I: Iterator<Item = &'a Vec<u8>>>(keys: I) -> SResult<()> {
+	let mut prev: Option<Bon> = None;
+	for (index, key) in keys.enumerate() {
+		let key = Bon::new(Arc::new(key.clone()));
+		if let Some(prev) = &prev {
+			if prev.partial_cmp(&key) != Some(std::cmp::Ordering::Less) {
+				error!("key order violation, index: {}, prev: {:?}, key: {:?}", index, prev.as_slice(), key.as_slice());
+				return Err(format!("KeyOrderViolation: index: {}, prev: {:?}, key: {:?}", index, prev.as_slice(), key.as_slice()));
+			}
+		}
+		prev = Some(key);
+	}
+
+	Ok(())
+}
+
+//获取表的水位文件路径，水位文件在表目录旁，与表目录同名
+fn watermark_path(tab_path: &Path) -> PathBuf {
+	let mut path = tab_path.as_os_str().to_owned();
+	path.push(".watermark");
+	PathBuf::from(path)
+}
+
+//获取表的读修复文件路径，读修复文件在表目录旁，记录已写入覆盖父表值的删除记录的主键
+fn repair_path(tab_path: &Path) -> PathBuf {
+	let mut path = tab_path.as_os_str().to_owned();
+	path.push(".repairs");
+	PathBuf::from(path)
+}
+
+//读取读修复文件中已修复的主键，每个主键前是4字节的长度，没有修复文件或无法解析的部分忽略
+async fn read_repairs(path: &Path) -> XHashSet<Vec<u8>> {
+	let mut keys = XHashSet::default();
+	if !path.exists() {
+		return keys;
+	}
+
+	let bin = match read_file_range(path, 0, usize::MAX).await {
+		Err(e) => {
+			warn!("read repair file failed, path: {:?}, reason: {:?}", path, e);
+			return keys;
+		},
+		Ok(bin) => bin,
+	};
+	let mut pos = 0;
+	while pos + 4 <= bin.len() {
+		let len = u32::from_le_bytes(bin[pos..pos + 4].try_into().unwrap()) as usize;
+		pos += 4;
+		if pos + len > bin.len() {
+			break;
+		}
+		keys.insert(bin[pos..pos + len].to_vec());
+		pos += len;
+	}
+
+	keys
+}
+
+//整理表后删除表的读修复文件，整理可能丢弃读修复写入的删除记录，下次加载时重新检查并写入仍然需要修复的主键
+async fn clear_repairs(tab_name: &Atom) {
+	let mut path = PathBuf::new();
+	path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+	path.push(tab_name.to_string());
+	let repaired_path = repair_path(&path);
+	match run_blocking(move || fs::remove_file(repaired_path)).await {
+		Ok(Err(e)) if e.kind() == ErrorKind::NotFound => (),
+		Ok(Err(e)) => warn!("clear read repair file failed, tab: {}, reason: {:?}", tab_name.as_str(), e),
+		Err(e) => warn!("clear read repair file failed, tab: {}, reason: {}", tab_name.as_str(), e),
+		Ok(Ok(_)) => (),
+	}
+}
+
+//用指定的主键替换读修复文件的内容
+async fn write_repairs(path: PathBuf, keys: &XHashSet<Vec<u8>>) -> Result<()> {
+	let mut bin = Vec::new();
+	for key in keys {
+		bin.extend_from_slice(&(key.len() as u32).to_le_bytes());
+		bin.extend_from_slice(key);
+	}
+	write_file_durable(path, bin).await
+}
+
+//读取表的水位文件中的提交水位，没有水位文件或无法解析则为0
+fn read_watermark(tab_path: &Path) -> u64 {
+	match fs::read(watermark_path(tab_path)) {
+		Ok(bin) if bin.len() >= 8 => u64::from_le_bytes(bin[..8].try_into().unwrap()),
+		_ => 0,
+	}
+}
+
+//推进表的持久化水位，并在开启持久化水位时安排异步写入水位文件，不在调用者的内存表锁内写入文件
+//已安排但还未开始的写入会合并之后推进的水位，所以连续的提交只写入一次水位文件
+//并发的持久化完成顺序可能与水位顺序不同，水位文件可能短暂落后于内存中的持久化水位，恢复的水位只会偏小，不会遗漏丢失的提交
+async fn record_watermark(tab_name: &Atom, counters: &Arc<TabCounters>, watermark: u64) {
+	if counters.durable_seq.fetch_max(watermark, Ordering::SeqCst) >= watermark {
+		return;
+	}
+	if !DURABLE_WATERMARK.load(Ordering::Relaxed) || counters.watermark_file.pending.swap(true, Ordering::SeqCst) {
+		return;
+	}
+
+	let rt = match STORE_RUNTIME.read().await.as_ref() {
+		None => {
+			counters.watermark_file.pending.store(false, Ordering::SeqCst);
+			return;
+		},
+		Some(rt) => rt.clone(),
+	};
+	let tab_name = tab_name.clone();
+	let counters = counters.clone();
+	let _ = rt.spawn(rt.alloc(), async move {
+		//先清除标志再写入，写入期间推进的水位会安排下一次写入
+		counters.watermark_file.pending.store(false, Ordering::SeqCst);
+		if let Err(e) = flush_watermark(&tab_name, &counters).await {
+			warn!("write watermark failed, tab: {}, reason: {:?}", tab_name.as_str(), e);
+		}
+	});
+}
+
+//将表当前的持久化水位写入水位文件并持久化，已写入的水位不小于当前的持久化水位则忽略
+async fn flush_watermark(tab_name: &Atom, counters: &TabCounters) -> Result<()> {
+	let mut written = counters.watermark_file.written.lock().await;
+	let watermark = counters.durable_seq.load(Ordering::SeqCst);
+	if *written >= watermark {
+		return Ok(());
+	}
+
+	let mut path = PathBuf::new();
+	path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+	path.push(tab_name.to_string());
+	write_file_durable(watermark_path(&path), watermark.to_le_bytes().to_vec()).await?;
+	*written = watermark;
+	Ok(())
+}
+
+//从水位文件恢复表的提交水位，之后分配的提交水位从恢复的水位之后开始
+fn recover_watermark(tab_path: &Path, counters: &TabCounters) {
+	let recovered = read_watermark(tab_path);
+	counters.recovered_seq.store(recovered, Ordering::SeqCst);
+	counters.durable_seq.fetch_max(recovered, Ordering::SeqCst);
+	counters.watermark_seq.fetch_max(recovered, Ordering::SeqCst);
+}
+
+//记录表的只读日志文件统计信息的长度，超过告警长度则记录警告，每个只读日志文件有一条统计信息，只在整理后减少
+fn observe_statistics(tab_name: &Atom, store: &AsyncLogFileStore, counters: &TabCounters) {
+	let len = store.statistics.lock().len();
+	counters.statistics_len.store(len, Ordering::Relaxed);
+
+	let limit = STATISTICS_ALERT_LEN.load(Ordering::Relaxed);
+	if limit > 0 && len > limit {
+		counters.statistics_alerts.fetch_add(1, Ordering::Relaxed);
+		warn!("statistics too long, compaction overdue, tab: {}, len: {}, limit: {}", tab_name.as_str(), len, limit);
+	}
+}
+
+/*
+* 表交换的日志，记录交换的两个表、临时路径的后缀和已开始的阶段，保存在数据库目录下
+* 阶段0将a的文件移动到临时路径，阶段1将b的文件移动到a，阶段2将临时路径的文件移动到b，每个阶段开始前持久化交换日志
+* 每次移动只在源路径存在且目标路径不存在时执行，中断后重新执行已开始的阶段是幂等的
+*/
+struct SwapJournal {
+	a: String,		//交换的表
+	b: String,		//交换的表
+	tmp: String,	//临时路径的后缀
+	phase: usize,	//已开始的阶段
+}
+
+impl SwapJournal {
+	//获取交换日志的路径
+	fn path(db_path: &Path) -> PathBuf {
+		db_path.join(".swap_journal")
+	}
+
+	//获取表目录和表目录旁的文件的路径
+	fn tab_paths(db_path: &Path, tab: &str) -> Vec<PathBuf> {
+		let tab_path = db_path.join(tab);
+		vec![format_path(&tab_path), blob_path(&tab_path), watermark_path(&tab_path), compaction_path(&tab_path), repair_path(&tab_path), tab_path]
+	}
+
+	//在路径后追加临时后缀
+	fn tmp_path(path: &Path, tmp: &str) -> PathBuf {
+		let mut p = path.as_os_str().to_owned();
+		p.push(tmp);
+		PathBuf::from(p)
+	}
+
+	//持久化交换日志，先写入临时文件再替换，替换后同步数据库目录
+	fn save(&self, db_path: &Path) -> Result<()> {
+		let path = SwapJournal::path(db_path);
+		let tmp = SwapJournal::tmp_path(&path, ".tmp");
+		let mut file = fs::File::create(&tmp)?;
+		file.write_all(format!("{}\n{}\n{}\n{}\n", self.a, self.b, self.tmp, self.phase).as_bytes())?;
+		file.sync_all()?;
+		fs::rename(&tmp, &path)?;
+		sync_dir(db_path)
+	}
+
+	//读取交换日志，没有交换日志则返回None
+	fn load(db_path: &Path) -> Result<Option<SwapJournal>> {
+		let text = match fs::read_to_string(SwapJournal::path(db_path)) {
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e),
+			Ok(text) => text,
+		};
+		let lines: Vec<&str> = text.lines().collect();
+		if lines.len() != 4 {
+			return Err(Error::new(ErrorKind::InvalidData, format!("invalid swap journal, lines: {}", lines.len())));
+		}
+		let phase = lines[3].parse::<usize>().map_err(|e| Error::new(ErrorKind::InvalidData, format!("invalid swap journal phase, reason: {:?}", e)))?;
+		Ok(Some(SwapJournal {
+			a: lines[0].to_string(),
+			b: lines[1].to_string(),
+			tmp: lines[2].to_string(),
+			phase,
+		}))
+	}
+
+	//源路径存在且目标路径不存在时移动
+	fn move_if(from: &Path, to: &Path) -> Result<()> {
+		if from.exists() && !to.exists() {
+			fs::rename(from, to)?;
+		}
+		Ok(())
+	}
+
+	//从当前阶段开始执行交换，完成后删除交换日志
+	fn run(mut self, db_path: &Path) -> Result<()> {
+		let a = SwapJournal::tab_paths(db_path, &self.a);
+		let b = SwapJournal::tab_paths(db_path, &self.b);
+		while self.phase < 3 {
+			self.save(db_path)?;
+			for (a, b) in a.iter().zip(b.iter()) {
+				let tmp = SwapJournal::tmp_path(a, &self.tmp);
+				match self.phase {
+					0 => SwapJournal::move_if(a, &tmp)?,
+					1 => SwapJournal::move_if(b, a)?,
+					_ => SwapJournal::move_if(&tmp, b)?,
+				}
+			}
+			sync_dir(db_path)?;
+			self.phase += 1;
+		}
+		fs::remove_file(SwapJournal::path(db_path))?;
+		sync_dir(db_path)
+	}
+
+	//按交换日志完成中断的交换，返回完成交换的两个表
+	fn recover(db_path: &Path) -> Result<Option<(String, String)>> {
+		match SwapJournal::load(db_path)? {
+			None => Ok(None),
+			Some(journal) => {
+				let tabs = (journal.a.clone(), journal.b.clone());
+				journal.run(db_path)?;
+				Ok(Some(tabs))
+			},
+		}
+	}
+}
+
+//在独立的线程上执行阻塞的文件操作，不阻塞异步运行时
+pub(crate) async fn run_blocking<T, F>(f: F) -> SResult<T>
+	where F: FnOnce() -> T + Send + 'static,
+		  T: Send + 'static {
+	let (sender, receiver) = oneshot::channel();
+	if let Err(e) = thread::Builder::new().name("Blocking-IO".to_string()).spawn(move || {
+		let _ = sender.send(f());
+	}) {
+		return Err(format!("spawn blocking io failed, reason: {:?}", e));
+	}
+
+	receiver.await.map_err(|e| format!("blocking io canceled, reason: {:?}", e))
+}
+
+//获取表的整理历史文件路径，整理历史文件在表目录旁，与表目录同名，每次整理追加一行
+fn compaction_path(tab_path: &Path) -> PathBuf {
+	let mut path = tab_path.as_os_str().to_owned();
+	path.push(".compactions");
+	PathBuf::from(path)
+}
+
+//获取表目录中所有日志文件的总大小
+fn log_files_size(tab_name: &Atom) -> u64 {
+	let mut path = PathBuf::new();
+	path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+	path.push(tab_name.to_string());
+	match list_log_files(&path) {
+		Err(_) => 0,
+		Ok(logs) => logs.iter().filter_map(|(_, log_path)| fs::metadata(log_path).ok()).map(|meta| meta.len()).sum(),
+	}
+}
+
+//整理表后追加一条整理记录到表的整理历史文件，每条记录一行，一次写入并同步到磁盘，写入失败只记录警告
+//统计日志文件大小和写入都在独立的线程上执行，不阻塞存储运行时的工作线程
+async fn record_compaction(tab_name: &Atom, before: u64, removed_logs: &[PathBuf], collected_logs: &[PathBuf]) {
+	let name = tab_name.clone();
+	let removed_logs = removed_logs.len();
+	let collected_logs = collected_logs.len();
+	let r = run_blocking(move || {
+		let record = CompactionRecord {
+			time: clock::now_millis(),
+			before,
+			after: log_files_size(&name),
+			removed_logs,
+			collected_logs,
+		};
+
+		let mut path = PathBuf::new();
+		path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		path.push(name.to_string());
+		let line = format!("{} {} {} {} {}\n", record.time, record.before, record.after, record.removed_logs, record.collected_logs);
+		fs::OpenOptions::new().create(true).append(true).open(compaction_path(&path)).and_then(|mut file| {
+			file.write_all(line.as_bytes())?;
+			file.sync_data()
+		}).map_err(|e| format!("record: {:?}, reason: {:?}", record, e))
+	}).await.and_then(|r| r);
+	if let Err(e) = r {
+		warn!("write compaction history failed, tab: {}, {}", tab_name.as_str(), e);
+	}
+}
+
+//读取表的整理历史，无法解析的行被忽略，例如写入中断的最后一行
+fn read_compactions(tab_path: &Path) -> Vec<CompactionRecord> {
+	let text = match fs::read_to_string(compaction_path(tab_path)) {
+		Err(_) => return Vec::new(),
+		Ok(text) => text,
+	};
+
+	text.lines().filter_map(|line| {
+		let fields: Vec<u64> = line.split_whitespace().map(|field| field.parse::<u64>().ok()).collect::<Option<Vec<u64>>>()?;
+		if fields.len() != 5 {
+			return None;
+		}
+		Some(CompactionRecord {
+			time: fields[0],
+			before: fields[1],
+			after: fields[2],
+			removed_logs: fields[3] as usize,
+			collected_logs: fields[4] as usize,
+		})
+	}).collect()
+}
+
+//获取表的格式文件路径，格式文件在表目录旁，与表目录同名
+fn format_path(tab_path: &Path) -> PathBuf {
+	let mut path = tab_path.as_os_str().to_owned();
+	path.push(".format");
+	PathBuf::from(path)
+}
+
+//读取表的日志格式版本，没有格式文件的表是版本1，版本高于当前支持的版本则返回UnsupportedFormatVersion错误
+pub fn read_format_version(tab_path: &Path) -> SResult<u8> {
+	let version = match fs::read(format_path(tab_path)) {
+		Err(e) if e.kind() == ErrorKind::NotFound => 1,
+		Err(e) => return Err(format!("read format version failed, path: {:?}, reason: {:?}", tab_path, e)),
+		Ok(bin) => match bin.first() {
+			None => return Err(format!("UnsupportedFormatVersion: path: {:?}, version: none, supported: {}", tab_path, LOG_FORMAT_VERSION)),
+			Some(version) => *version,
+		},
+	};
+
+	if version == 0 || version > LOG_FORMAT_VERSION {
+		return Err(format!("UnsupportedFormatVersion: path: {:?}, version: {}, supported: {}", tab_path, version, LOG_FORMAT_VERSION));
+	}
+
+	Ok(version)
+}
+
+//按表的日志格式版本加载日志文件，版本2只增加了格式文件，日志记录的布局与版本1相同
+//版本3的写入记录的值前增加了一个字节的标志，加载时由存储剥离
+async fn load_with_version(version: u8, file: &LogFile, store: &mut AsyncLogFileStore, path: PathBuf, block_size: usize) -> SResult<()> {
+	match version {
+		1 | 2 | 3 => {
+			store.set_format_version(version);
+			file.load(store, Some(path), block_size, true).await;
+			store.resolve_blobs().await;
+			Ok(())
+		},
+		version => Err(format!("UnsupportedFormatVersion: path: {:?}, version: {}, supported: {}", path, version, LOG_FORMAT_VERSION)),
+	}
+}
+
+//加载表自己的日志文件，limit为0或日志文件数量不超过limit时加载全部日志文件，否则从最新的日志文件开始只重放limit个日志文件
+//返回未加载的更早的日志文件，从旧到新排列
+async fn load_recent(version: u8, file: &LogFile, store: &mut AsyncLogFileStore, path: PathBuf, block_size: usize, limit: usize) -> SResult<Vec<PathBuf>> {
+	if limit == 0 {
+		load_with_version(version, file, store, path, block_size).await?;
+		return Ok(Vec::new());
+	}
+
+	let log_paths = match read_log_paths(file).await {
+		Err(e) => return Err(format!("read log paths failed, path: {:?}, reason: {:?}", path, e)),
+		Ok(paths) => paths,
+	};
+	if log_paths.len() <= limit {
+		load_with_version(version, file, store, path, block_size).await?;
+		return Ok(Vec::new());
+	}
+
+	match version {
+		1 | 2 | 3 => store.set_format_version(version),
+		version => return Err(format!("UnsupportedFormatVersion: path: {:?}, version: {}, supported: {}", path, version, LOG_FORMAT_VERSION)),
+	}
+	for log_path in log_paths.iter().rev().take(limit) {
+		//与日志文件的加载相同，从最新到最旧加载，每个主键只采用最先加载的记录
+		for (method, key, value) in read_log_records(log_path.clone(), block_size).await? {
+			if store.is_require(Some(log_path), &key) {
+				store.load(Some(log_path), method, key, value);
+			}
+		}
+	}
+	store.resolve_blobs().await;
+	info!("replay recent logs, path: {:?}, replayed: {}, cold: {}", path, limit, log_paths.len() - limit);
+
+	Ok(log_paths[..log_paths.len() - limit].to_vec())
+}
+
+//加载分叉链中父表在分叉点的视图，只返回指定主键中在父表中有值的主键的值，分叉链的第一个表是分叉表
+//与加载表数据相同，从最近的父表到根表加载，已被更近的父表写入或删除的主键，不再从更远的父表加载
+async fn load_parent_view(tab: &Atom, chains: &[TableMetaInfo], keys: &XHashSet<Vec<u8>>) -> SResult<XHashMap<Vec<u8>, Vec<u8>>> {
+	let mut view = XHashMap::default();
+	let mut resolved: XHashSet<Vec<u8>> = XHashSet::default();
+	let mut log_file_id = chains.get(0).and_then(|tm| tm.parent_log_id);
+	for tm in chains.iter().skip(1) {
+		if resolved.len() == keys.len() {
+			break;
+		}
+
+		let file = match AsyncLogFileStore::open(tm.tab_name.as_ref(), 8000, tm.config.log_file_size(), tm.parent_log_id).await {
+			Err(e) => return Err(format!("open parent tab failed, tab: {}, parent: {}, reason: {:?}", tab.as_str(), tm.tab_name.as_str(), e)),
+			Ok(file) => file
+		};
+		let mut store = AsyncLogFileStore::new(file.clone());
+		let fork_log_id = match log_file_id {
+			Some(id) if id > 0 => id - 1,
+			_ => return Err(format!("invalid parent_log_id, tab: {}, parent: {}, parent_log_id: {:?}", tab.as_str(), tm.tab_name.as_str(), log_file_id)),
+		};
+		let version = read_format_version(Path::new(tm.tab_name.as_ref()))?;
+		let mut path = PathBuf::new();
+		path.push(tm.tab_name.clone().as_ref());
+		path.push(format!("{:0>width$}", fork_log_id, width = 6));
+		load_with_version(version, &file, &mut store, path, tm.config.load_block_size()).await?;
+
+		let map = store.map.lock();
+		let removed = store.removed.lock();
+		for key in keys.iter() {
+			if resolved.contains(key) {
+				continue;
+			}
+
+			if let Some(value) = map.get(key) {
+				view.insert(key.clone(), value.to_vec());
+				resolved.insert(key.clone());
+			} else if removed.contains_key(key) {
+				resolved.insert(key.clone());
+			}
+		}
+		log_file_id = tm.parent_log_id;
+	}
+
+	Ok(view)
+}
+
+//计算操作日志占用的大小，包括主键和写入的值
+fn rwlog_size(rwlog: &RwLogMap) -> usize {
+	rwlog.iter().map(|(k, rw_v)| match rw_v {
+		RwLog::Write(Some(v)) | RwLog::Meta(Some(v)) => k.len() + v.len(),
+		_ => k.len(),
+	}).sum()
+}
+
+//将增量累加到计数器的值上，返回累加后的值，计数器的值是Bon编码的i64，值为None则从0开始累加，溢出时回绕
+//值不是有效的整数编码则返回错误，原值不会被修改
+pub fn add_counter(value: Option<&Bin>, delta: i64) -> SResult<Bin> {
+	let current = match value {
+		None => 0,
+		Some(v) => match i64::decode(&mut ReadBuffer::new(v, 0)) {
+			Err(e) => return Err(format!("InvalidCounter: value: {:?}, reason: {:?}", v.as_slice(), e)),
+			Ok(n) => n,
+		},
+	};
+
+	let mut wb = WriteBuffer::new();
+	current.wrapping_add(delta).encode(&mut wb);
+	Ok(Arc::new(wb.bytes))
+}
+
+//累加计数器表的计数器，值是Bon编码的周期开始时间和计数，周期开始时间按周期对齐
+//当前时间已不在计数器的周期内，则从新周期的0开始累加；计数溢出时饱和到i64的最大值或最小值，不会回绕
+pub fn add_window_counter(value: Option<&Bin>, delta: i64, window: u64, now: u64) -> SResult<Bin> {
+	if window == 0 {
+		return Err(String::from("InvalidCounter: window is zero"));
+	}
+	let start = now - now % window;
+	let current = read_window_counter(value, window, now)?;
+
+	let mut wb = WriteBuffer::new();
+	start.encode(&mut wb);
+	current.saturating_add(delta).encode(&mut wb);
+	Ok(Arc::new(wb.bytes))
+}
+
+//读取计数器表的计数器在当前时间的计数，计数器的周期已过则计数为0，没有周期开始时间的旧计数器也为0
+pub fn read_window_counter(value: Option<&Bin>, window: u64, now: u64) -> SResult<i64> {
+	let v = match value {
+		None => return Ok(0),
+		Some(v) => v,
+	};
+	let mut rb = ReadBuffer::new(v, 0);
+	let (start, count) = match u64::decode(&mut rb).and_then(|start| i64::decode(&mut rb).map(|count| (start, count))) {
+		//开启重置周期前写入的不重置的计数器只有计数，作为已过期周期的计数，读取为0，下次累加从新周期开始
+		Err(_) if i64::decode(&mut ReadBuffer::new(v, 0)).is_ok() => return Ok(0),
+		Err(e) => return Err(format!("InvalidCounter: value: {:?}, reason: {:?}", v.as_slice(), e)),
+		Ok(r) => r,
+	};
+
+	if now >= start && now - start < window {
+		Ok(count)
+	} else {
+		Ok(0)
+	}
+}
+
+//按表的计数器重置周期累加计数器，周期为0则是不重置的计数器
+fn increment_value(value: Option<&Bin>, delta: i64, window: u64) -> SResult<Bin> {
+	if window == 0 {
+		add_counter(value, delta)
+	} else {
+		add_window_counter(value, delta, window, clock::now_millis())
+	}
+}
+
+//为指定日志文件的可写日志文件预分配空间，预分配失败不影响日志文件的使用
+async fn preallocate_writable(log_file: &LogFile, len: usize) {
+	match read_log_paths(log_file).await {
+		Err(e) => warn!("preallocate log file failed, reason: {:?}", e),
+		Ok(paths) => {
+			if let Some(path) = paths.last() {
+				if let Err(e) = preallocate_file(path, len) {
+					warn!("preallocate log file failed, path: {:?}, reason: {:?}", path, e);
+				}
+			}
+		},
+	}
+}
+
+//同步指定日志文件的可写日志文件和所在目录，保证分裂产生的新日志文件的目录项已持久化
+async fn sync_writable(log_file: &LogFile) -> Result<()> {
+	let paths = match read_log_paths(log_file).await {
+		Err(e) => return Err(Error::new(ErrorKind::Other, format!("sync writable failed, reason: {:?}", e))),
+		Ok(paths) => paths,
+	};
+	if let Some(path) = paths.last() {
+		sync_file_and_dir(path.clone()).await?;
+	}
+	SPLIT_SYNCS.fetch_add(1, Ordering::Relaxed);
+
+	Ok(())
+}
+
+//在独立的线程中同步文件和所在目录，同步是阻塞的文件操作，不占用存储运行时的工作线程
+async fn sync_file_and_dir(path: PathBuf) -> Result<()> {
+	let (sender, receiver) = oneshot::channel();
+	thread::Builder::new().name("Sync-Log-File".to_string()).spawn(move || {
+		let r = fs::File::open(&path).and_then(|file| file.sync_all()).and_then(|_| match path.parent() {
+			None => Ok(()),
+			Some(dir) => sync_dir(dir),
+		});
+		let _ = sender.send(r);
+	})?;
+
+	match receiver.await {
+		Err(_) => Err(Error::new(ErrorKind::Other, "sync thread canceled")),
+		Ok(r) => r,
+	}
+}
+
+//同步目录，使目录中新建文件的目录项持久化
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+	fs::File::open(dir)?.sync_all()
+}
+
+//当前平台不支持打开目录同步，则忽略
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+	Ok(())
+}
+
+//为指定文件预分配空间，保持文件大小不变，避免影响日志文件的追加和加载
+#[cfg(target_os = "linux")]
+fn preallocate_file(path: &Path, len: usize) -> Result<()> {
+	use std::os::unix::io::AsRawFd;
+
+	let file = fs::OpenOptions::new().write(true).open(path)?;
+	if unsafe { libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len as libc::off_t) } != 0 {
+		return Err(Error::last_os_error());
+	}
+
+	Ok(())
+}
+
+//当前平台不支持预分配，则忽略
+#[cfg(not(target_os = "linux"))]
+fn preallocate_file(_path: &Path, _len: usize) -> Result<()> {
+	Ok(())
+}
+
+//检查存储目录是否可用，目录不存在则创建，并通过写入和删除探测文件检查是否可写
+fn check_storage_dir(path: &Path) -> SResult<()> {
+	if path.exists() {
+		if !path.is_dir() {
+			return Err(format!("StorageUnavailable: path: {:?}, reason: not a directory", path));
+		}
+	} else if let Err(e) = fs::create_dir_all(path) {
+		return Err(format!("StorageUnavailable: path: {:?}, reason: {:?}", path, e));
+	}
+
+	let probe = path.join(".pi_db_probe");
+	if let Err(e) = fs::write(&probe, []).and_then(|_| fs::remove_file(&probe)) {
+		return Err(format!("StorageUnavailable: path: {:?}, reason: {:?}", path, e));
+	}
+
+	Ok(())
+}
+
+//读取指定日志文件的所有日志记录，日志块从文件尾向文件头读取，返回的记录从最新到最旧
+pub async fn read_log_records(log_path: PathBuf, block_size: usize) -> SResult<Vec<(LogMethod, Vec<u8>, Option<Vec<u8>>)>> {
+	read_log_records_from(log_path, block_size, 0).await
+}
+
+//读取指定日志文件中从指定偏移开始的日志记录，日志块从文件尾向文件头读取，读到早于指定偏移的日志块就停止，返回的记录从最新到最旧
+pub async fn read_log_records_from(log_path: PathBuf, block_size: usize, from: usize) -> SResult<Vec<(LogMethod, Vec<u8>, Option<Vec<u8>>)>> {
+	let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+	let log_file = match AsyncFile::open(rt, log_path.clone(), AsyncFileOptions::OnlyRead).await {
+		Err(e) => return Err(format!("{:?}", e)),
+		Ok(f) => f,
+	};
+
+	let mut records = Vec::new();
+	let mut offset = None;
+	let mut read_len = block_size;
+	loop {
+		let (file_offset, bin) = match read_log_file(log_path.clone(), log_file.clone(), offset, read_len).await {
+			Err(e) => return Err(format!("{:?}", e)),
+			Ok(r) => r,
+		};
+		if (file_offset as usize) < from {
+			//日志块早于指定偏移
+			return Ok(records);
+		}
+		match read_log_file_block(log_path.clone(), &bin, file_offset, read_len, true) {
+			Err(e) => return Err(format!("{:?}", e)),
+			Ok((next_file_offset, next_len, logs)) => {
+				records.extend(logs);
+				if next_file_offset == 0 && next_len == 0 {
+					//已读到日志文件头
+					return Ok(records);
+				}
+				offset = Some(next_file_offset);
+				read_len = next_len;
+			},
+		}
+	}
+}
+
+//表是否已有记录，已打开的表检查内存表和未加载的日志文件，未打开的表检查表目录中是否有非空的日志文件
+async fn tab_has_records(tab_name: &Atom) -> bool {
+	let opened = LOG_FILE_TABS.read().await.get(tab_name).cloned();
+	if let Some(tab) = opened {
+		let lock = tab.lock_tab().await;
+		return lock.root.size() > 0 || !lock.cold.is_empty();
+	}
+
+	let path = PathBuf::from(env::var("DB_PATH").unwrap_or(".".to_string())).join(tab_name.as_str());
+	match fs::read_dir(&path) {
+		Err(_) => false,
+		Ok(entries) => entries.filter_map(|entry| entry.ok()).any(|entry| {
+			let is_log = entry.file_name().to_str().map(|name| name.parse::<usize>().is_ok()).unwrap_or(false);
+			is_log && entry.metadata().map(|meta| meta.len() > 0).unwrap_or(false)
+		}),
+	}
+}
+
+//加载后表自己的日志文件中的记录数量，作为按提交序号扫描日志文件的起点，冷表和分叉表的日志文件中的记录不完整或不只属于自己，不能扫描
+fn scan_records(store: &AsyncLogFileStore, chains: &[TableMetaInfo], cold: &[PathBuf]) -> Option<u64> {
+	if !cold.is_empty() || chains.len() > 1 {
+		None
+	} else {
+		Some(store.records.load(Ordering::Relaxed))
+	}
+}
+
+//从最旧的日志文件开始按写入顺序读取表自己的前records条日志记录，返回其中指定主键的最后一次写入的值
+//日志文件中的记录少于records条，说明部分提交的日志还未写入日志文件，则返回HistoryUnavailable错误
+async fn scan_as_of(tab_name: &Atom, file: &LogFileTab, key: &[u8], records: u64, seq: u64) -> SResult<Option<Bin>> {
+	let config = ALL_TABLES.get(tab_name).await.map(|tm| tm.config.clone()).unwrap_or_default();
+	let log_paths = match read_log_paths(&file.1.log_file).await {
+		Err(e) => return Err(format!("scan as of failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+		Ok(paths) => paths,
+	};
+
+	let mut read = 0;
+	let mut last = None;
+	'scan: for log_path in log_paths {
+		let mut logs = read_log_records(log_path.clone(), config.load_block_size()).await
+			.map_err(|e| format!("scan as of failed, tab: {}, log: {:?}, reason: {}", tab_name.as_str(), log_path, e))?;
+		logs.reverse();
+		for (method, k, value) in logs {
+			if read >= records {
+				break 'scan;
+			}
+			read += 1;
+			if k.as_slice() == key {
+				last = Some(match method {
+					LogMethod::Remove => None,
+					_ => value,
+				});
+			}
+		}
+	}
+	if read < records {
+		return Err(format!("HistoryUnavailable: tab: {}, seq: {}, reason: log not durable", tab_name.as_str(), seq));
+	}
+
+	match last.and_then(|value| value) {
+		None => Ok(None),
+		Some(value) if file.1.has_flags.load(Ordering::Relaxed) => {
+			let mut tab_path = PathBuf::new();
+			tab_path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+			tab_path.push(tab_name.to_string());
+			Ok(Some(Arc::new(decode_flagged_value(&blob_path(&tab_path), value).await?.1)))
+		},
+		Some(value) => Ok(Some(Arc::new(value))),
+	}
+}
+
+//从日志文件计算日志表的摘要，从最新的日志文件开始逐块读取，只保留记录数量和最后追加的主键
+async fn load_sink_footer(file: &LogFile, block_size: usize) -> SResult<SinkFooter> {
+	let log_paths = match read_log_paths(file).await {
+		Err(e) => return Err(format!("load sink footer failed, reason: {:?}", e)),
+		Ok(paths) => paths,
+	};
+
+	let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+	let mut footer = SinkFooter::default();
+	for log_path in log_paths.into_iter().rev() {
+		let log_file = match AsyncFile::open(rt.clone(), log_path.clone(), AsyncFileOptions::OnlyRead).await {
+			Err(e) => return Err(format!("load sink footer failed, path: {:?}, reason: {:?}", log_path, e)),
+			Ok(f) => f,
+		};
+
+		let mut offset = None;
+		let mut read_len = block_size;
+		loop {
+			let (file_offset, bin) = match read_log_file(log_path.clone(), log_file.clone(), offset, read_len).await {
+				Err(e) => return Err(format!("load sink footer failed, path: {:?}, reason: {:?}", log_path, e)),
+				Ok(r) => r,
+			};
+			match read_log_file_block(log_path.clone(), &bin, file_offset, read_len, true) {
+				Err(e) => return Err(format!("load sink footer failed, path: {:?}, reason: {:?}", log_path, e)),
+				Ok((next_file_offset, next_len, logs)) => {
+					for (method, key, _) in logs {
+						if let LogMethod::PlainAppend = method {
+							footer.count += 1;
+							if footer.last_key.is_none() {
+								footer.last_key = Some(key);
 							}
 						}
-						None => {
-							return Err(format!("delete tab: {:?} not found", tab_name))
-						}
 					}
-					ALL_TABLES.lock().await.remove(&tab_name);
-					// 找到他的父表，将父表的引用计数减一
-					let mut wb = WriteBuffer::new();
-					if let Some(parent) = parent {
-						let mut lock = ALL_TABLES.lock().await;
-						if lock.contains_key(&parent) {
-							let mut value = lock.get_mut(&parent).unwrap();
-							value.ref_count -= 1;
-							let mut wb2 = WriteBuffer::new();
-							value.encode(&mut wb2);
-							parent.encode(&mut wb);
-							store.write(wb.bytes, wb2.bytes).await;
-						}
-					} else {
-						tab_name.encode(&mut wb);
-						store.remove(wb.bytes).await;
+					if next_file_offset == 0 && next_len == 0 {
+						break;
 					}
-				}
+					offset = Some(next_file_offset);
+					read_len = next_len;
+				},
 			}
 		}
-		Ok(XHashMap::with_capacity_and_hasher(0, Default::default()))
 	}
 
-	//回滚一个事务
-	pub async fn rollback(&self) -> DBResult {
-		self.alters.lock().await.clear();
-		Ok(())
-	}
+	Ok(footer)
 }
 
-#[derive(Clone)]
-pub struct AsyncLogFileStore {
-	pub removed: Arc<SpinLock<XHashMap<Vec<u8>, ()>>>,
-	pub map: Arc<SpinLock<BTreeMap<Vec<u8>, Arc<[u8]>>>>,
-	pub log_file: LogFile,
-	pub tmp_map: Arc<SpinLock<XHashMap<Vec<u8>, ()>>>,
-	pub writable_path: Arc<SpinLock<Option<PathBuf>>>,
-	pub is_statistics: Arc<AtomicBool>,
-	pub is_init: Arc<AtomicBool>,
-	pub statistics: Arc<SpinLock<VecDeque<(PathBuf, u64, u64)>>>,
+//获取表目录中的所有日志文件的id和路径，按日志文件id升序
+fn list_log_files(tab_path: &Path) -> Result<Vec<(usize, PathBuf)>> {
+	let mut logs: Vec<(usize, PathBuf)> = fs::read_dir(tab_path)?
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| {
+			let path = entry.path();
+			let id = path.file_name().and_then(|name| name.to_str()).and_then(|name| name.parse::<usize>().ok());
+			id.map(|id| (id, path))
+		})
+		.collect();
+	logs.sort();
+
+	Ok(logs)
 }
 
-unsafe impl Send for AsyncLogFileStore {}
-unsafe impl Sync for AsyncLogFileStore {}
+//校验指定日志文件的所有日志块，读取或校验失败则返回错误
+async fn verify_log_file(log_path: PathBuf, block_size: usize) -> SResult<()> {
+	let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+	let log_file = match AsyncFile::open(rt, log_path.clone(), AsyncFileOptions::OnlyRead).await {
+		Err(e) => return Err(format!("{:?}", e)),
+		Ok(f) => f,
+	};
+
+	let mut offset = None;
+	let mut read_len = block_size;
+	loop {
+		let (file_offset, bin) = match read_log_file(log_path.clone(), log_file.clone(), offset, read_len).await {
+			Err(e) => return Err(format!("{:?}", e)),
+			Ok(r) => r,
+		};
+		match read_log_file_block(log_path.clone(), &bin, file_offset, read_len, true) {
+			Err(e) => return Err(format!("{:?}", e)),
+			Ok((0, 0, _)) => return Ok(()), //已读到日志文件头
+			Ok((next_file_offset, next_len, _)) => {
+				offset = Some(next_file_offset);
+				read_len = next_len;
+			},
+		}
+	}
+}
 
-impl PairLoader for AsyncLogFileStore {
-	fn is_require(&self, log_file: Option<&PathBuf>, key: &Vec<u8>) -> bool {
-		let b = !self.removed.lock().contains_key(key) && !self.tmp_map.lock().contains_key(key);
+//读取并校验表的所有日志文件的记录，新格式的外置存储的值只检查引用的文件是否存在，返回读取的日志文件数量
+//表目录不存在的表还没有写入，不需要校验
+async fn validate_tab_files(tab_path: &Path, block_size: usize) -> SResult<usize> {
+	if !tab_path.exists() {
+		return Ok(0);
+	}
 
-		if self.is_statistics.load(Ordering::Relaxed) {
-			//需要统计
-			let mut init = false;
-			if !b {
-				//已删除的记录，则不需要加载，但需要统计
-				if let Some((path, log_len, key_len)) = self.statistics.lock().get_mut(0) {
-					if path.to_str().unwrap() == log_file.as_ref().unwrap().to_str().unwrap() {
-						//指定只读日志文件的统计信息存在，则继续累计
-						*log_len += 1;
-						if !self.tmp_map.lock().contains_key(key) {
-							//如果需要加载的关键字不存在，则累计关键字数量
-							*key_len += 1;
-						}
-					} else {
-						//指定只读日志文件的统计信息不存在，则初始化
-						init = true;
+	let version = read_format_version(tab_path)?;
+	let logs = list_log_files(tab_path).map_err(|e| format!("read log dir failed, path: {:?}, reason: {:?}", tab_path, e))?;
+	let blob_dir = blob_path(tab_path);
+	for (_, log_path) in logs.iter() {
+		let records = read_log_records(log_path.clone(), block_size).await
+			.map_err(|e| format!("read log failed, log: {:?}, reason: {}", log_path, e))?;
+		if version < 3 {
+			continue;
+		}
+
+		for (_, _, value) in records {
+			match value {
+				Some(value) if value.len() > 0 && value[0] & BLOB_FLAG != 0 => {
+					if value.len() != 9 {
+						return Err(format!("invalid blob reference, log: {:?}, reference: {:?}", log_path, &value[1..]));
 					}
-				} else {
-					init = true;
-				};
+					let id = u64::from_le_bytes(value[1..].try_into().unwrap());
+					if !blob_dir.join(id.to_string()).exists() {
+						return Err(format!("blob not found, log: {:?}, id: {}", log_path, id));
+					}
+				},
+				_ => (),
 			}
+		}
+	}
 
-			if init {
-				//当前没有任何统计信息，则初始化统计信息
-				if !b {
-					//已删除的记录，则不需要加载，但需要统计
-					if self.tmp_map.lock().contains_key(key) {
-						//如果不需要加载的关键字已存在，则不累计关键字数量
-						self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 0));
-					} else {
-						//如果不需要加载的关键字不存在，则累计关键字数量
-						self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 1));
+	Ok(logs.len())
+}
+
+//校验表的所有只读日志文件，将无法读取或校验失败的只读日志文件移动到隔离目录，并记录到健康报告
+//隔离目录在表目录旁，与表目录同名加.corrupt后缀，不放在表目录中，避免被当作日志文件，隔离的日志文件可以离线检查
+//最新的日志文件是可写日志文件，不隔离
+async fn quarantine_corrupt_logs(tab: &Atom, tab_path: &Path, block_size: usize) {
+	let mut logs = match list_log_files(tab_path) {
+		Err(e) => {
+			warn!("read log dir failed, tab: {}, reason: {:?}", tab.as_str(), e);
+			return;
+		},
+		Ok(logs) => logs,
+	};
+	logs.pop();
+
+	for (_, log_path) in logs {
+		let reason = match verify_log_file(log_path.clone(), block_size).await {
+			Ok(_) => continue,
+			Err(e) => e,
+		};
+
+		let mut dir = tab_path.as_os_str().to_owned();
+		dir.push(".corrupt");
+		let dir = PathBuf::from(dir);
+		let to = dir.join(log_path.file_name().unwrap());
+		if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::rename(&log_path, &to)) {
+			error!("quarantine log failed, tab: {}, log: {:?}, reason: {:?}", tab.as_str(), log_path, e);
+			continue;
+		}
+
+		error!("quarantine corrupt log, tab: {}, log: {:?}, to: {:?}, reason: {}", tab.as_str(), log_path, to, reason);
+		HEALTH_REPORT.lock().quarantined_logs.push((to, reason));
+	}
+}
+
+//解析元信息表中的一条记录，返回表名和表的元信息
+pub(crate) fn decode_meta_record(key: &[u8], value: &[u8]) -> SResult<(Atom, TableMetaInfo)> {
+	let tab_name = match Atom::decode(&mut ReadBuffer::new(key, 0)) {
+		Err(e) => return Err(format!("decode tab name failed, reason: {:?}", e)),
+		Ok(name) => name,
+	};
+	match TableMetaInfo::decode(&mut ReadBuffer::new(value, 0)) {
+		Err(e) => Err(format!("decode table meta failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+		Ok(meta) => Ok((tab_name, meta)),
+	}
+}
+
+//检查分叉链，有父表的表的parent_log_id必须存在且大于0，因为分叉点的只读日志文件id是parent_log_id - 1
+fn check_fork_chain(chains: &[TableMetaInfo]) -> SResult<()> {
+	for tm in chains {
+		if tm.parent.is_none() {
+			continue;
+		}
+
+		match tm.parent_log_id {
+			Some(id) if id > 0 => (),
+			id => return Err(format!("invalid parent_log_id, tab: {}, parent: {:?}, parent_log_id: {:?}", tm.tab_name.as_str(), tm.parent, id)),
+		}
+	}
+
+	Ok(())
+}
+
+#[derive(Clone)]
+pub struct LogFileTab(Arc<Mutex<MemeryTab>>, pub AsyncLogFileStore, Arc<TabCounters>, Arc<FairGate>);
+
+unsafe impl Send for LogFileTab {}
+unsafe impl Sync for LogFileTab {}
+
+impl LogFileTab {
+	async fn new(tab: &Atom, chains: &[TableMetaInfo]) -> SResult<Self> {
+		check_fork_chain(chains)?;
+
+		let mut file_mem_tab = MemeryTab {
+			prepare: Prepare::new(XHashMap::with_capacity_and_hasher(0, Default::default())),
+			root: OrdMap::<Tree<Bon, Bin>>::new(None),
+			tab: tab.clone(),
+			load_size: 0,
+			unloaded: false,
+			history: XHashMap::default(),
+			history_from: COMMIT_SEQ.load(Ordering::SeqCst),
+			history_order: VecDeque::new(),
+			history_evicted: 0,
+			seq_marks: VecDeque::new(),
+			scan_base: None,
+			scan_gen: 0,
+			last_seq: 0,
+			indexes: XHashMap::default(),
+			bulk: false,
+			cold: Vec::new(),
+			cold_writes: XHashMap::default(),
+			cold_index: None,
+			replay_all: false,
+			sink: None,
+			locks: XHashMap::default(),
+			invalid: false,
+		};
+
+		let mut path = PathBuf::new();
+		let db_path = env::var("DB_PATH").unwrap_or(".".to_string());
+		path.push(db_path);
+		let tab_name = tab.clone();
+		path.push(tab_name.clone().to_string());
+
+		// 首先加载叶子节点数据
+		let log_file_index = if chains.len() > 0 {
+			chains[0].parent_log_id
+		} else {
+			None
+		};
+		// println!("LogFileTab::new  log_file_index = {:?}, tab = {:?}, chains = {:?}", log_file_index, tab, chains);
+		let config = chains.get(0).map(|tm| tm.config.clone()).unwrap_or_default();
+		let is_new = !path.exists();
+		if !is_new && QUARANTINE_CORRUPT_LOGS.load(Ordering::Relaxed) {
+			//有分叉的表的只读日志文件被子表共享，隔离后子表无法加载，所以不隔离
+			match chains.get(0) {
+				Some(tm) if tm.ref_count > 0 => warn!("skip quarantine, tab: {}, reason: has forks", tab_name.as_str()),
+				_ => quarantine_corrupt_logs(&tab_name, &path, config.load_block_size()).await,
+			}
+		}
+		let file = match AsyncLogFileStore::open(path.clone(), 8000, config.log_file_size(), log_file_index).await {
+			Err(e) => panic!("!!!!!!open table = {:?} failed, e: {:?}", tab_name, e),
+			Ok(file) => file
+		};
+		if is_new {
+			//新建的表使用当前的日志格式版本，已有日志文件但没有格式文件的旧表保持版本1
+			if let Err(e) = fs::write(format_path(&path), [LOG_FORMAT_VERSION]) {
+				warn!("write format version failed, tab: {}, reason: {:?}", tab_name.as_str(), e);
+			}
+		}
+
+		recover_watermark(&path, &tab_counters(tab));
+		tab_counters(tab).file_len.store(config.log_file_size(), Ordering::Relaxed);
+		tab_counters(tab).counter_window.store(config.counter_window.unwrap_or(0), Ordering::Relaxed);
+		if config.is_log_sink() && chains.len() <= 1 {
+			//日志表不加载记录，只从日志文件计算摘要
+			let store = AsyncLogFileStore::new(file.clone());
+			store.set_format_version(read_format_version(&path)?);
+			store.blobs.open(blob_path(&path), config.blob_policy());
+			file_mem_tab.sink = Some(load_sink_footer(&file, config.load_block_size()).await?);
+			return Ok(LogFileTab(Arc::new(Mutex::new(file_mem_tab)), store, tab_counters(tab), Arc::new(FairGate::new())));
+		}
+		let (store, root, load_size, cold) = LogFileTab::load_data(tab, file, path, chains, false).await?;
+		let counters = tab_counters(tab);
+		observe_statistics(tab, &store, &counters);
+		counters.approx_size.store(root.size(), Ordering::Relaxed);
+		file_mem_tab.root = root;
+		file_mem_tab.load_size = load_size;
+		file_mem_tab.reset_scan(scan_records(&store, chains, &cold));
+		file_mem_tab.cold = cold;
+		file_mem_tab.cold_index = None;
+		if let (Some(tm), Some(parent)) = (chains.get(0), chains.get(0).and_then(|tm| tm.parent.as_ref())) {
+			if tm.mode == ForkMode::Live {
+				LIVE_FORKS.lock().entry(parent.clone()).or_insert_with(XHashSet::default).insert(tab.clone());
+			}
+		}
+
+		return Ok(LogFileTab(Arc::new(Mutex::new(file_mem_tab)), store, counters, Arc::new(FairGate::new())));
+	}
+
+	//从指定的叶节点日志文件和分叉链加载表数据，返回叶节点的存储、合并后的内存表和叶节点的加载大小
+	//replay_all为是否忽略表的重放限制，返回值的最后一项为按重放限制未加载的日志文件
+	async fn load_data(tab: &Atom, file: LogFile, path: PathBuf, chains: &[TableMetaInfo], replay_all: bool) -> SResult<(AsyncLogFileStore, BinMap, usize, Vec<PathBuf>)> {
+		let mut log_file_id = if chains.len() > 0 {
+			chains[0].parent_log_id
+		} else {
+			None
+		};
+
+		//同一个表的多个日志文件按日志顺序从最新到最旧加载，包括多次分裂产生的只读日志文件
+		//每个主键只采用最先加载的记录，即最新的写入或删除，更旧的记录会被忽略
+		let mut store = AsyncLogFileStore::new(file.clone());
+		let config = chains.get(0).map(|tm| tm.config.clone()).unwrap_or_default();
+		let version = read_format_version(&path)?;
+		//分叉表未加载的日志文件中的写入和删除需要覆盖父表的值，所以重放限制只对没有父表的表有效
+		let limit = if replay_all || chains.len() > 1 {
+			0
+		} else {
+			config.replay_logs()
+		};
+		store.blobs.open(blob_path(&path), config.blob_policy());
+		let repaired_path = repair_path(&path);
+		let cold = load_recent(version, &file, &mut store, path, config.load_block_size(), limit).await?;
+		let mut root= OrdMap::<Tree<Bon, Bin>>::new(None);
+		let mut load_size = 0;
+		{
+			let map = store.map.lock();
+			if VERIFY_KEY_ORDER.load(Ordering::Relaxed) {
+				verify_key_order(map.keys()).map_err(|e| format!("{}, tab: {}", e, tab.as_str()))?;
+			}
+			for (k, v) in map.iter() {
+				load_size += k.len() + v.len();
+				root.upsert(Bon::new(Arc::new(k.clone())), Arc::new(v.to_vec()), false);
+			}
+		}
+		store.is_init.store(false, Ordering::SeqCst);
+		LOG_FILE_TOTAL_SIZE.fetch_add(load_size as u64, Ordering::Relaxed);
+		info!("load tab: {} {} KB", tab.as_str(), format!("{0} {1:.2}", "size", load_size as f64 / 1024.0));
+
+		//子表覆盖父表：分叉链从叶节点到根节点加载，已被更近的表写入或删除的主键，不再从更远的父表加载
+		let mut shadowed: XHashSet<Vec<u8>> = store.map.lock().keys().cloned().collect();
+		let mut tombstones: XHashSet<Vec<u8>> = store.removed.lock().keys().cloned().collect();
+		shadowed.extend(tombstones.iter().cloned());
+		//被更近的表删除，但更远的父表仍有值的主键，需要修复
+		let mut repairs: XHashSet<Vec<u8>> = XHashSet::default();
+		//从父表加载的主键的标志合并到叶节点的存储
+		let leaf_flags = store.flags.clone();
+
+		// 再加载分叉路径中的表的数据
+		//分叉点是子表分叉时父表强制分裂后的日志文件id，分叉时的最新只读日志文件是它的前一个日志文件
+		let mut fork_log_ids = Vec::with_capacity(chains.len());
+		for tm in chains.iter().skip(1) {
+			match log_file_id {
+				Some(id) if id > 0 => fork_log_ids.push(id - 1),
+				_ => return Err(format!("invalid parent_log_id, tab: {}, parent: {}, parent_log_id: {:?}", tab.as_str(), tm.tab_name.as_str(), log_file_id)),
+			}
+			log_file_id = tm.parent_log_id;
+		}
+		//实时分叉表加载父表当前的所有日志文件，而不是父表在分叉点的日志文件
+		if chains.len() > 1 && chains[0].mode == ForkMode::Live {
+			let parent = &chains[1].tab_name;
+			match list_log_files(Path::new(parent.as_ref())) {
+				Err(e) => return Err(format!("ParentUnavailable: tab: {}, parent: {}, reason: {:?}", tab.as_str(), parent.as_str(), e)),
+				Ok(logs) => match logs.last() {
+					None => return Err(format!("ParentUnavailable: tab: {}, parent: {}, reason: log not found", tab.as_str(), parent.as_str())),
+					Some((id, _)) => fork_log_ids[0] = *id,
+				},
+			}
+		}
+
+		//父表的日志文件并发加载，加载完成后再按分叉链的顺序从近到远合并，合并的结果与顺序加载相同
+		let ancestors = load_ancestors(tab, chains.get(1..).unwrap_or(&[]), &fork_log_ids).await;
+		for (tm, store) in chains.iter().skip(1).zip(ancestors) {
+			//宽松模式下只合并已合并的更近的表的数据，不再合并更远的父表，否则返回ParentUnavailable错误
+			let store = match store {
+				Err(e) if e.starts_with("ParentUnavailable:") && LENIENT_PARENT_LOAD.load(Ordering::Relaxed) => {
+					warn!("parent data unavailable, load without ancestors, {}", e);
+					break;
+				},
+				Err(e) => return Err(e),
+				Ok(store) => store,
+			};
+
+			let mut load_size = 0;
+			let start_time = Instant::now();
+			let map = store.map.lock();
+			if VERIFY_KEY_ORDER.load(Ordering::Relaxed) {
+				verify_key_order(map.keys()).map_err(|e| format!("{}, tab: {}", e, tm.tab_name.as_str()))?;
+			}
+			for (k, v) in map.iter() {
+				if shadowed.contains(k) {
+					if tombstones.contains(k) {
+						repairs.insert(k.clone());
 					}
-				} else {
-					//插入或更新的记录，需要加载，但不需要在判断是否加载时统计
-					self.statistics.lock().push_front((log_file.cloned().unwrap(), 0, 0));
+					continue;
+				}
+
+				load_size += k.len() + v.len();
+				root.upsert(Bon::new(Arc::new(k.clone())), Arc::new(v.to_vec()), false);
+				if let Some(flags) = store.flags.lock().get(k) {
+					leaf_flags.lock().insert(k.clone(), *flags);
 				}
 			}
-		} else {
-			if self.writable_path.lock().is_none() {
-				//如果当前是可写日志文件，且未记录，则记录，并忽略统计
-				*self.writable_path.lock() = log_file.cloned();
-			} else {
-				if self.writable_path.lock().as_ref().unwrap().to_str().unwrap() != log_file.as_ref().unwrap().to_str().unwrap() {
-					//当前可写日志文件已记录，且开始加载只读日志文件，则设置为需要统计，并开始初始化统计信息
-					if !b {
-						//已删除的记录，则不需要加载，但需要统计
-						self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 1));
-					} else {
-						//插入或更新的记录，需要加载，但不需要在判断是否加载时统计
-						self.statistics.lock().push_front((log_file.cloned().unwrap(), 0, 0));
+			shadowed.extend(map.keys().cloned());
+			for k in store.removed.lock().keys() {
+				if shadowed.insert(k.clone()) {
+					tombstones.insert(k.clone());
+				}
+			}
+			store.is_init.store(false, Ordering::SeqCst);
+			debug!("====> load tab: {:?} size: {:?}byte time elapsed: {:?} <====", tm.tab_name, load_size, start_time.elapsed());
+		}
+
+		//读修复，将覆盖父表值的删除重新写入叶节点的可写日志文件，避免叶节点整理后删除记录丢失，导致父表的值重新可见
+		//已修复的主键记录在修复文件中，之后加载时不再重复写入，修复文件只保留仍然覆盖父表值的主键
+		let repaired = read_repairs(&repaired_path).await;
+		let keys: Vec<&[u8]> = repairs.iter().filter(|k| !repaired.contains(*k)).map(|k| k.as_slice()).collect();
+		let mut recorded: XHashSet<Vec<u8>> = repaired.intersection(&repairs).cloned().collect();
+		if keys.len() > 0 {
+			match store.remove_batch(&keys).await {
+				Err(e) => warn!("read repair failed, tab: {}, keys: {}, reason: {:?}", tab.as_str(), keys.len(), e),
+				Ok(_) => {
+					info!("read repair, tab: {}, keys: {}", tab.as_str(), keys.len());
+					recorded.extend(keys.iter().map(|k| k.to_vec()));
+				},
+			}
+		}
+		if recorded != repaired {
+			if let Err(e) = write_repairs(repaired_path, &recorded).await {
+				warn!("write read repair file failed, tab: {}, reason: {:?}", tab.as_str(), e);
+			}
+		}
+
+		//已追加的字节数从加载时可写日志文件的大小开始累计
+		let writable_len = store.writable_path.lock().as_ref().and_then(|path| fs::metadata(path).ok()).map(|meta| meta.len() as usize).unwrap_or(0);
+		store.appended.store(writable_len, Ordering::Relaxed);
+
+		Ok((store, root, load_size, cold))
+	}
+
+	//在整理后原地重新加载日志文件表，重建键值缓冲和内存表，已缓存的日志文件表句柄和未完成的事务不受影响
+	//重新加载期间如果有事务提交，则放弃本次替换并返回错误，由调用者决定是否重试
+	//表有重放限制时，重新加载仍然只重放最新的日志文件，除非已通过load_cold加载过全部日志文件
+	pub async fn reload(&self) -> SResult<()> {
+		let replay_all = self.0.lock().await.replay_all;
+		self.reload_with(replay_all).await
+	}
+
+	//忽略表的重放限制，重新加载表的全部日志文件，之后重新加载时也加载全部日志文件
+	//没有未加载的日志文件则直接返回
+	pub async fn load_cold(&self) -> SResult<()> {
+		{
+			let mut tab = self.0.lock().await;
+			if tab.cold.is_empty() && !tab.unloaded {
+				return Ok(());
+			}
+			tab.replay_all = true;
+		}
+
+		self.reload_with(true).await
+	}
+
+	//是否有按重放限制未加载的更早的日志文件
+	pub async fn is_cold(&self) -> bool {
+		!self.0.lock().await.cold.is_empty()
+	}
+
+	//从未加载的日志文件中读取冷表中指定主键的值，snapshot为事务开始时表的最近一次提交的提交序号
+	//主键在加载的日志文件或事务开始前的提交中有记录，则事务的内存表中的值就是主键的值，不需要读取
+	//按未加载日志文件的主键索引只读取主键最新记录所在的日志文件，索引中没有的主键不读取日志文件
+	async fn load_cold_key(&self, key: &Bin, snapshot: u64) -> SResult<Option<Bin>> {
+		let (tab_name, cold, cold_index) = {
+			let lock = self.lock_tab().await;
+			if lock.cold.is_empty() {
+				return Ok(None);
+			}
+			match lock.cold_writes.get(key) {
+				Some(seq) if *seq <= snapshot => return Ok(None),
+				Some(_) => (),
+				None if self.1.is_touched(key) => return Ok(None),
+				None => (),
+			}
+			(lock.tab.clone(), lock.cold.clone(), lock.cold_index.clone())
+		};
+
+		//在表锁外建立未加载日志文件的主键索引，建立期间未加载的日志文件已改变则不保存
+		let block_size = ALL_TABLES.get(&tab_name).await.map(|tm| tm.config.clone()).unwrap_or_default().load_block_size();
+		let cold_index = match cold_index {
+			Some(cold_index) => cold_index,
+			None => {
+				let mut index = XHashMap::default();
+				for (i, log_path) in cold.iter().enumerate() {
+					let logs = read_log_records(log_path.clone(), block_size).await
+						.map_err(|e| format!("load cold key failed, tab: {}, log: {:?}, reason: {}", tab_name.as_str(), log_path, e))?;
+					for (_, k, _) in logs {
+						//未加载的日志文件从旧到新排列，更新的日志文件覆盖更早的日志文件
+						index.insert(Arc::new(k), i);
 					}
+				}
+				let index = Arc::new(index);
+
+				let mut lock = self.lock_tab().await;
+				if lock.cold == cold {
+					lock.cold_index = Some(index.clone());
+				}
+				index
+			},
+		};
+
+		//主键最新记录所在的日志文件中，主键的第一条记录就是主键在未加载的日志文件中的值
+		let log_path = match cold_index.get(key) {
+			None => return Ok(None),
+			Some(i) => cold[*i].clone(),
+		};
+		let logs = read_log_records(log_path.clone(), block_size).await
+			.map_err(|e| format!("load cold key failed, tab: {}, log: {:?}, reason: {}", tab_name.as_str(), log_path, e))?;
+		let value = match logs.into_iter().find(|(_, k, _)| k.as_slice() == key.as_slice()) {
+			None | Some((LogMethod::Remove, _, _)) | Some((_, _, None)) => return Ok(None),
+			Some((_, _, Some(value))) => value,
+		};
+		if !self.1.has_flags.load(Ordering::Relaxed) {
+			return Ok(Some(Arc::new(value)));
+		}
+
+		let mut tab_path = PathBuf::new();
+		tab_path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		tab_path.push(tab_name.to_string());
+		Ok(Some(Arc::new(decode_flagged_value(&blob_path(&tab_path), value).await?.1)))
+	}
+
+	//表的内存是否已释放，释放的表在下次创建事务时从日志文件重新加载
+	pub async fn is_unloaded(&self) -> bool {
+		self.0.lock().await.unloaded
+	}
+
+	async fn reload_with(&self, replay_all: bool) -> SResult<()> {
+		let (tab_name, old_root) = {
+			let tab = self.0.lock().await;
+			if tab.sink.is_some() {
+				//日志表没有需要重新加载的内存表
+				return Ok(());
+			}
+			(tab.tab.clone(), tab.root.clone())
+		};
+
+		let mut path = PathBuf::new();
+		let db_path = env::var("DB_PATH").unwrap_or(".".to_string());
+		path.push(db_path);
+		path.push(tab_name.to_string());
+
+		let chains = build_fork_chain(tab_name.clone()).await?;
+		check_fork_chain(&chains)?;
+		recover_watermark(&path, &self.2);
+		let (store, root, load_size, cold) = LogFileTab::load_data(&tab_name, self.1.log_file.clone(), path, &chains, replay_all).await?;
+
+		let mut tab = self.0.lock().await;
+		if !tab.root.ptr_eq(&old_root) {
+			LOG_FILE_TOTAL_SIZE.fetch_sub(load_size as u64, Ordering::Relaxed);
+			return Err(format!("reload conflicted, tab: {}", tab_name.as_str()));
+		}
+
+		//替换键值缓冲、移除缓冲和统计信息，并替换内存表
+		*self.1.map.lock() = mem::replace(&mut *store.map.lock(), BTreeMap::new());
+		*self.1.removed.lock() = mem::replace(&mut *store.removed.lock(), XHashMap::default());
+		*self.1.statistics.lock() = mem::replace(&mut *store.statistics.lock(), VecDeque::new());
+		*self.1.writable_path.lock() = store.writable_path.lock().take();
+		self.1.appended.store(store.appended.load(Ordering::Relaxed), Ordering::Relaxed);
+		*self.1.flags.lock() = mem::replace(&mut *store.flags.lock(), XHashMap::default());
+		self.1.has_flags.store(store.has_flags.load(Ordering::Relaxed), Ordering::Relaxed);
+		self.1.blobs.replace_from(&store.blobs);
+		self.1.tmp_map.lock().clear();
+		self.1.is_statistics.store(store.is_statistics.load(Ordering::Relaxed), Ordering::Relaxed);
+		observe_statistics(&tab_name, &self.1, &self.2);
+		self.2.approx_size.store(root.size(), Ordering::Relaxed);
+		tab.root = root;
+		LOG_FILE_TOTAL_SIZE.fetch_sub(tab.load_size as u64, Ordering::Relaxed);
+		tab.load_size = load_size;
+		tab.unloaded = false;
+		//重新加载后日志文件可能已被整理，之前的提交不能再扫描日志文件读取
+		self.1.records.store(store.records.load(Ordering::Relaxed), Ordering::Relaxed);
+		tab.reset_scan(scan_records(&store, &chains, &cold));
+		tab.cold = cold;
+		tab.cold_index = None;
+		if tab.cold.is_empty() {
+			tab.cold_writes.clear();
+		}
+
+		//重新加载的记录可能与释放前不同，重建二级索引
+		if !tab.bulk {
+			let MemeryTab { root, indexes, .. } = &mut *tab;
+			for index in indexes.values_mut() {
+				index.rebuild(root);
+			}
+		}
+
+		Ok(())
+	}
+
+	//释放表的内存表和键值缓冲，表的句柄仍保留在缓存中，下次创建事务时从日志文件重新加载，返回释放的加载大小
+	//有已预提交但未提交的事务时不允许释放，已提交的修改都已写入日志文件，重新加载后不会丢失
+	pub async fn evict_memory(&self) -> SResult<usize> {
+		self.evict_memory_with(false).await
+	}
+
+	//释放表的内存，skip_active为是否在有未完成的事务时也不允许释放
+	async fn evict_memory_with(&self, skip_active: bool) -> SResult<usize> {
+		let mut tab = self.lock_tab().await;
+		if tab.tab.as_str() == DB_META_TAB_NAME {
+			return Err(format!("evict memory failed, tab: {}, reason: meta tab", tab.tab.as_str()));
+		}
+		if tab.unloaded {
+			return Ok(0);
+		}
+		if tab.prepare.len() > 0 {
+			return Err(format!("evict memory failed, tab: {}, reason: prepared txns: {}", tab.tab.as_str(), tab.prepare.len()));
+		}
+		let active = self.2.active_txns.load(Ordering::SeqCst);
+		if skip_active && active > 0 {
+			return Err(format!("evict memory failed, tab: {}, reason: active txns: {}", tab.tab.as_str(), active));
+		}
+
+		tab.root = OrdMap::<Tree<Bon, Bin>>::new(None);
+		self.1.map.lock().clear();
+		self.1.removed.lock().clear();
+		LOG_FILE_TOTAL_SIZE.fetch_sub(tab.load_size as u64, Ordering::Relaxed);
+		let size = mem::replace(&mut tab.load_size, 0);
+		tab.unloaded = true;
+		info!("evict tab memory, tab: {}, size: {}", tab.tab.as_str(), size);
+
+		Ok(size)
+	}
+
+	//将表当前已提交的记录导出到指定路径，导出的日志只包含有效的记录，没有删除记录和被覆盖的旧记录，可以直接作为新表打开
+	//导出只读取内存表的快照，不修改源表的日志文件，导出期间的提交不会出现在导出的日志中，返回导出的记录数量
+	pub async fn export_snapshot<P: AsRef<Path>>(&self, path: P) -> SResult<usize> {
+		let path = path.as_ref();
+		if path.exists() || format_path(path).exists() {
+			return Err(format!("export snapshot failed, path: {:?}, reason: path already exists", path));
+		}
+
+		self.ensure_loaded().await?;
+		let (tab_name, root) = {
+			let tab = self.0.lock().await;
+			(tab.tab.clone(), tab.root.clone())
+		};
+		let mut size = 0;
+		for Entry(k, v) in root.iter(None, false) {
+			size += k.len() + v.len();
+		}
 
-					//设置为需要统计
-					self.is_statistics.store(true, Ordering::SeqCst);
-				}
+		//导出的日志文件足够容纳所有记录，导出过程中不会分裂
+		let config = ALL_TABLES.get(&tab_name).await.map(|tm| tm.config.clone()).unwrap_or_default();
+		let file = AsyncLogFileStore::open_with(path, 8000, size.max(config.log_file_size()) * 2, None, false).await
+			.map_err(|e| format!("export snapshot failed, tab: {}, path: {:?}, reason: {:?}", tab_name.as_str(), path, e))?;
+
+		//导出的日志使用当前的日志格式版本，写入记录带主键的标志
+		let _permit = COMPACTION_IO_LIMITER.acquire().await;
+		let export = AsyncLogFileStore::new(file.clone());
+		export.set_format_version(LOG_FORMAT_VERSION);
+		let mut count = 0;
+		let mut id = 0;
+		for Entry(k, v) in root.iter(None, false) {
+			id = export.append_with_flags(k.as_slice(), v.as_slice(), self.1.flags(k.as_slice()));
+			count += 1;
+		}
+		if count > 0 {
+			if let Err(e) = file.delay_commit(id, false, 1).await {
+				return Err(format!("export snapshot failed, tab: {}, path: {:?}, reason: {:?}", tab_name.as_str(), path, e));
 			}
 		}
+		if let Err(e) = fs::write(format_path(path), [LOG_FORMAT_VERSION]) {
+			return Err(format!("export snapshot failed, tab: {}, path: {:?}, reason: {:?}", tab_name.as_str(), path, e));
+		}
+		info!("export snapshot, tab: {}, path: {:?}, count: {}, size: {}", tab_name.as_str(), path, count, size);
 
-		b
+		Ok(count)
 	}
 
-	fn load(&mut self, log_file: Option<&PathBuf>, method: LogMethod, key: Vec<u8>, value: Option<Vec<u8>>) {
-		if self.is_statistics.load(Ordering::Relaxed) {
-			//需要统计
-			let mut init = false;
-			if let Some((path, log_len, key_len)) = self.statistics.lock().get_mut(0) {
-				if path.to_str().unwrap() == log_file.as_ref().unwrap().to_str().unwrap() {
-					//指定只读日志文件的统计信息存在，则继续累计
-					*log_len += 1;
-					if !self.tmp_map.lock().contains_key(&key) {
-						//如果需要加载的关键字不存在，则累计关键字数量
-						*key_len += 1;
-					}
-				} else {
-					//指定只读日志文件的统计信息不存在，则初始化
-					init = true;
-				}
-			} else {
-				init = true;
-			};
+	//按写入顺序读取表自己的所有日志记录，包括被覆盖的写入和删除记录，删除记录的值为None，用于复制和诊断
+	//外置存储的值读取完整的值，被覆盖且外置存储的值已被整理删除的写入记录的值也为None
+	//只读取表自己的日志文件，不包括分叉表的父表的日志文件，已被整理的日志文件中只有整理后保留的记录
+	pub async fn iter_physical(&self) -> SResult<impl Iterator<Item = (LogMethod, Bin, Option<Bin>)>> {
+		let tab_name = self.0.lock().await.tab.clone();
+		let config = ALL_TABLES.get(&tab_name).await.map(|tm| tm.config.clone()).unwrap_or_default();
+		let log_paths = match read_log_paths(&self.1.log_file).await {
+			Err(e) => return Err(format!("iter physical failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+			Ok(paths) => paths,
+		};
 
-			if init {
-				//当前没有任何统计信息，则初始化统计信息
-				if self.tmp_map.lock().contains_key(&key) {
-					//如果需要加载的关键字已存在，则不累计关键字数量
-					self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 0));
-				} else {
-					//如果需要加载的关键字不存在，则累计关键字数量
-					self.statistics.lock().push_front((log_file.cloned().unwrap(), 1, 1));
-				}
+		//从最新的日志文件开始读取，全部读取后反转为从最旧到最新
+		let has_flags = self.1.has_flags.load(Ordering::Relaxed);
+		let mut tab_path = PathBuf::new();
+		tab_path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		tab_path.push(tab_name.to_string());
+		let blob_dir = blob_path(&tab_path);
+		let mut records = Vec::new();
+		for log_path in log_paths.into_iter().rev() {
+			let logs = read_log_records(log_path.clone(), config.load_block_size()).await
+				.map_err(|e| format!("iter physical failed, tab: {}, log: {:?}, reason: {}", tab_name.as_str(), log_path, e))?;
+			for (method, key, value) in logs {
+				let value = match (&method, value) {
+					(LogMethod::PlainAppend, Some(value)) if has_flags => {
+						//带标志字节的记录，剥离值前的标志，外置存储的值读取完整的值
+						//被覆盖的外置存储的值在整理后可能已被删除，这些写入记录的值为None
+						match decode_flagged_value(&blob_dir, value).await {
+							Err(e) => {
+								debug!("blob of overwritten record unavailable, tab: {}, log: {:?}, reason: {}", tab_name.as_str(), log_path, e);
+								None
+							},
+							Ok((_, value)) => Some(Arc::new(value)),
+						}
+					},
+					(LogMethod::PlainAppend, Some(value)) => Some(Arc::new(value)),
+					_ => None,
+				};
+				records.push((method, Arc::new(key), value));
 			}
 		}
+		records.reverse();
 
-		if let Some(value) = value {
-			if self.is_init.load(Ordering::Relaxed) {
-				//启动初始化，才写入键值缓冲区
-				self.map.lock().insert(key.clone(), value.into());
-			}
-			self.tmp_map.lock().insert(key, ());
-		} else {
-			self.removed.lock().insert(key, ());
-		}
+		Ok(records.into_iter())
 	}
-}
 
-impl AsyncLogFileStore {
-	pub async fn open<P: AsRef<Path> + std::fmt::Debug>(path: P, buf_len: usize, file_len: usize, log_file_index: Option<usize>) -> Result<LogFile> {
-		// println!("AsyncLogFileStore open ====== {:?}, log_index = {:?}", path, log_file_index);
-		match LogFile::open(STORE_RUNTIME.read().await.as_ref().unwrap().clone(), path, buf_len, file_len, log_file_index).await {
-			Err(e) =>panic!("LogFile::open error {:?}", e),
-			Ok(file) => Ok(file),
+	//强制检查点，先分裂可写日志文件，使所有已提交的记录都在只读日志文件中，再整理所有只读日志文件，只保留有效的记录
+	//完成后表的日志由整理后的只读日志文件和新的空的可写日志文件组成，可以作为复制表的干净基线
+	//有分叉的表的只读日志文件被子表共享，不允许整理；子表的删除记录需要覆盖父表的值，整理时保留删除记录
+	pub async fn checkpoint(&self) -> SResult<TabCheckpoint> {
+		let tab_name = self.0.lock().await.tab.clone();
+		let info = match ALL_TABLES.get(&tab_name).await {
+			None => return Err(String::from("TabNotFound: ") + tab_name.as_str()),
+			Some(info) => info,
+		};
+		if info.ref_count > 0 {
+			return Err(format!("HasForks: tab: {}, ref_count: {}", tab_name.as_str(), info.ref_count));
+		}
+		//检查点整理全部只读日志文件，需要先加载按重放限制未加载的日志文件
+		self.load_cold().await?;
+
+		if let Err(e) = self.split().await {
+			return Err(format!("checkpoint failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+		}
+
+		//分裂后除最新的可写日志文件外，都是需要整理的只读日志文件
+		let mut collect_logs = match read_log_paths(&self.1.log_file).await {
+			Err(e) => return Err(format!("checkpoint failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+			Ok(paths) => paths,
+		};
+		collect_logs.pop();
+		if collect_logs.len() > 0 {
+			let permit = COMPACTION_IO_LIMITER.acquire().await;
+			//整理会改变日志文件中的记录，整理后到下次加载前不能按提交序号扫描日志文件
+			self.lock_tab().await.reset_scan(None);
+			if let Err(e) = self.1.log_file.collect_logs(vec![], collect_logs, 1024 * 1024, info.config.load_block_size(), info.parent.is_none()).await {
+				return Err(format!("checkpoint failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+			}
+			drop(permit);
+			LogFileDB::restat_after_collect(&tab_name, self, &info.config).await;
+			clear_repairs(&tab_name).await;
 		}
+
+		let mut logs = match read_log_paths(&self.1.log_file).await {
+			Err(e) => return Err(format!("checkpoint failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+			Ok(paths) => paths,
+		};
+		logs.pop();
+		let position = log_position(&tab_name).await?;
+		info!("checkpoint tab: {}, logs: {:?}, position: {:?}", tab_name.as_str(), logs, position);
+
+		Ok(TabCheckpoint {
+			logs,
+			position,
+		})
 	}
 
-	pub async fn write_batch(&self, pairs: &[(&[u8], &[u8])]) -> Result<()> {
-		let mut id = 0;
-		for (key, value) in pairs {
-			id = self.log_file.append(LogMethod::PlainAppend, key, value);
-		}
-		match self.log_file.delay_commit(id, false, 1).await {
-			Ok(_) => {
-				{
-					let mut map = self.map.lock();
-					for (key, value) in pairs {
-						map.insert(key.to_vec(), value.clone().into());
-					}
-				}
-				Ok(())
+	//表的内存已被释放，则从日志文件重新加载，并发的重新加载只有一个会替换成功，其它的直接使用加载后的表
+	async fn ensure_loaded(&self) -> SResult<()> {
+		{
+			let lock = self.0.lock().await;
+			if lock.invalid {
+				return Err(format!("TabInvalid: tab: {}", lock.tab.as_str()));
 			}
-			Err(e) => {
-				println!("write batch error");
-				Err(e)
+			if !lock.unloaded {
+				return Ok(());
 			}
 		}
-	}
 
-	pub async fn write(&self, key: Vec<u8>, value: Vec<u8>) -> Result<Option<Vec<u8>>> {
-		let id = self.log_file.append(LogMethod::PlainAppend, key.as_ref(), value.as_ref());
-		if let Err(e) = self.log_file.delay_commit(id, false, 1).await {
-			Err(e)
-		} else {
-			if let Some(value) = self.map.lock().insert(key, value.into()) {
-				//更新指定key的存储数据，则返回更新前的存储数据
-				Ok(Some(value.to_vec()))
-			} else {
-				Ok(None)
+		if let Err(e) = self.reload().await {
+			if self.0.lock().await.unloaded {
+				return Err(e);
 			}
 		}
+
+		Ok(())
 	}
 
-	pub fn read(&self, key: &[u8]) -> Option<Arc<[u8]>> {
-		if let Some(value) = self.map.lock().get(key) {
-			return Some(value.clone())
+	//创建事务，表的内存已被释放且重新加载失败，则返回错误，避免事务读到空表
+	pub async fn transaction(&self, id: &Guid, writable: bool) -> SResult<RefLogFileTxn> {
+		if let Err(e) = self.ensure_loaded().await {
+			error!("reload evicted tab failed, reason: {}", e);
+			return Err(e);
 		}
+		Ok(FileMemTxn::new(self.clone(), id, writable).await)
+	}
 
-		None
+	//创建带链路追踪id的事务
+	pub async fn transaction_with_trace(&self, id: &Guid, writable: bool, trace_id: Option<Atom>) -> SResult<RefLogFileTxn> {
+		let txn = self.transaction(id, writable).await?;
+		txn.set_trace_id(trace_id).await;
+		Ok(txn)
 	}
 
-	pub async fn remove_batch(&self, keys: &[&[u8]]) -> Result<()> {
-		let mut id = 0;
+	//获取主键已提交的值和标志，没有设置过标志的主键的标志为0，主键不存在则返回None
+	pub async fn get_with_meta(&self, key: &Bin) -> SResult<Option<(Bin, u8)>> {
+		self.ensure_loaded().await?;
+		let value = self.0.lock().await.root.get(&Bon::new(key.clone())).cloned();
+		Ok(value.map(|value| (value, self.1.flags(key.as_slice()))))
+	}
+
+	//主键的值当前是否外置存储，读取时外置存储和内联存储的值没有区别
+	pub fn is_blob(&self, key: &[u8]) -> bool {
+		self.1.blobs.contains(key)
+	}
+
+	//整理后重新内联不再满足外置存储条件的值，并删除不再被引用的外置存储的值，返回重新内联的主键数量
+	//重新内联的值追加到可写日志文件，之后清零两次整理之间的更新次数
+	//有分叉的表的外置存储的值可能被子表通过父表的日志文件引用，不删除
+	async fn rebalance_blobs(&self) -> SResult<usize> {
+		let tab_name = self.0.lock().await.tab.clone();
+		let has_forks = ALL_TABLES.get(&tab_name).await.map(|tm| tm.ref_count > 0).unwrap_or(false);
+
+		let lock = self.lock_tab().await;
+		let blobs = &self.1.blobs;
+		let policy = blobs.policy.lock().clone();
+		let updates = mem::replace(&mut *blobs.updates.lock(), XHashMap::default());
+		let keys: Vec<Vec<u8>> = blobs.refs.lock().keys().cloned().collect();
+
+		let mut id = None;
+		let mut count = 0;
 		for key in keys {
-			id = self.log_file.append(LogMethod::Remove, key, &[]);
-		}
+			let value = match lock.root.get(&Bon::new(Arc::new(key.clone()))) {
+				None => continue,
+				Some(value) => value.clone(),
+			};
+			if policy.is_blob(value.len(), updates.get(&key).cloned().unwrap_or(0)) {
+				continue;
+			}
 
-		match self.log_file.delay_commit(id, false, 1).await {
-			Ok(_) => {
-				for key in keys {
-					self.map.lock().remove(key.clone());
-				}
-				Ok(())
+			id = Some(self.1.append_value(&key, &value));
+			count += 1;
+		}
+		blobs.updates.lock().clear();
+		if let Some(id) = id {
+			if let Err(e) = self.1.log_file.delay_commit(id, false, 1).await {
+				return Err(format!("DurabilityFailed: tab: {}, reason: {:?}", lock.tab.as_str(), e));
 			}
-			Err(e) => Err(e)
 		}
+
+		let removed = if has_forks {
+			0
+		} else {
+			blobs.collect_garbage()
+		};
+		if count > 0 || removed > 0 {
+			info!("rebalance blobs, tab: {}, demoted: {}, removed: {}", lock.tab.as_str(), count, removed);
+		}
+
+		Ok(count)
 	}
 
-	pub async fn remove(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
-		let id = self.log_file.append(LogMethod::Remove, key.as_ref(), &[]);
-		if let Err(e) = self.log_file.delay_commit(id, false, 1).await {
-			Err(e)
+	//设置已存在的主键的标志，标志与主键的当前值一起追加到日志，之后对主键的写入保留标志，删除主键则清除标志
+	//只有日志格式版本3及以上的表的日志记录有标志字节，更旧的表返回UnsupportedFormatVersion错误
+	pub async fn set_flags(&self, key: Bin, flags: u8) -> SResult<()> {
+		self.ensure_loaded().await?;
+		let lock = self.lock_tab().await;
+		if !self.1.has_flags.load(Ordering::Relaxed) {
+			return Err(format!("UnsupportedFormatVersion: tab: {}, reason: flags require version {}", lock.tab.as_str(), FLAGS_FORMAT_VERSION));
+		}
+		if flags & BLOB_FLAG != 0 {
+			return Err(format!("set flags failed, tab: {}, flags: {}, reason: flag {} is reserved", lock.tab.as_str(), flags, BLOB_FLAG));
+		}
+		let value = match lock.root.get(&Bon::new(key.clone())) {
+			None => return Err(format!("set flags failed, tab: {}, key: {:?}, reason: key not found", lock.tab.as_str(), key.as_slice())),
+			Some(value) => value.clone(),
+		};
+
+		//在内存表锁内追加日志，保证与并发提交的日志顺序一致
+		let id = self.1.append_current(key.as_slice(), value.as_slice(), flags);
+		if flags == 0 {
+			self.1.flags.lock().remove(key.as_slice());
 		} else {
-			if let Some(value) = self.map.lock().remove(&key) {
-				Ok(Some(value.to_vec()))
-			} else {
-				Ok(None)
-			}
+			self.1.flags.lock().insert(key.to_vec(), flags);
+		}
+		if let Err(e) = self.1.log_file.delay_commit(id, false, 1).await {
+			return Err(format!("DurabilityFailed: tab: {}, reason: {:?}", lock.tab.as_str(), e));
 		}
+
+		Ok(())
 	}
 
-	pub fn last_key(&self) -> Option<Vec<u8>> {
-		self.map.lock().iter().last().map(|(k, _)| {
-			k.clone()
-		})
+	//为表增加指定名称的二级索引，已有同名索引则替换，索引立即按当前记录构建，批量模式下延迟到结束批量模式时构建
+	//二级索引只在内存中维护，不写入日志文件
+	pub async fn add_index(&self, name: Atom, extractor: IndexExtractor) -> SResult<()> {
+		self.ensure_loaded().await?;
+		let mut lock = self.lock_tab().await;
+		let mut index = SecondaryIndex::new(extractor);
+		if !lock.bulk {
+			index.rebuild(&lock.root);
+		}
+		lock.indexes.insert(name, index);
+
+		Ok(())
 	}
 
-	/// 强制产生分裂
-	pub async fn force_fork(&self) -> Result<usize> {
-		self.log_file.split().await
+	//移除表的指定名称的二级索引，返回索引是否存在
+	pub async fn remove_index(&self, name: &Atom) -> bool {
+		self.lock_tab().await.indexes.remove(name).is_some()
 	}
-}
 
-#[derive(Clone)]
-pub struct LogFileTab(Arc<Mutex<MemeryTab>>, pub AsyncLogFileStore);
+	//查询二级索引中索引键对应的所有主键，按主键顺序排列，批量模式下索引未维护，返回错误
+	pub async fn index_lookup(&self, name: &Atom, index_key: &[u8]) -> SResult<Vec<Bin>> {
+		let start = IndexPos(index_key.to_vec(), IndexBound::Min);
+		Ok(self.index_snapshot(name).await?.entries_from(Some(&start)).into_iter().take_while(|(k, _)| k.as_slice() == index_key).map(|(_, key)| key).collect())
+	}
 
-unsafe impl Send for LogFileTab {}
-unsafe impl Sync for LogFileTab {}
+	//获取二级索引的所有条目，按索引键和主键顺序排列，批量模式下索引未维护，返回错误
+	pub async fn index_entries(&self, name: &Atom) -> SResult<Vec<(Vec<u8>, Bin)>> {
+		Ok(self.index_snapshot(name).await?.entries_from(None))
+	}
 
-impl LogFileTab {
-	async fn new(tab: &Atom, chains: &[TableMetaInfo]) -> Self {
-		let mut file_mem_tab = MemeryTab {
-			prepare: Prepare::new(XHashMap::with_capacity_and_hasher(0, Default::default())),
-			root: OrdMap::<Tree<Bon, Bin>>::new(None),
-			tab: tab.clone(),
-		};
+	//获取二级索引当前的快照，批量模式下索引未维护，返回错误
+	async fn index_snapshot(&self, name: &Atom) -> SResult<SecondaryIndex> {
+		self.ensure_loaded().await?;
+		let lock = self.lock_tab().await;
+		if lock.bulk {
+			return Err(format!("index unavailable, tab: {}, index: {}, reason: bulk mode", lock.tab.as_str(), name.as_str()));
+		}
+		match lock.indexes.get(name) {
+			None => Err(format!("index unavailable, tab: {}, index: {}, reason: index not found", lock.tab.as_str(), name.as_str())),
+			Some(index) => Ok(index.clone()),
+		}
+	}
 
-		let mut path = PathBuf::new();
-		let db_path = env::var("DB_PATH").unwrap_or(".".to_string());
-		path.push(db_path);
-		let tab_name = tab.clone();
-		let tab_name_clone = tab.clone();
-		path.push(tab_name.clone().to_string());
+	//开始批量模式，之后的提交不再逐条维护二级索引，直到调用end_bulk，已处于批量模式则返回错误
+	pub async fn begin_bulk(&self) -> SResult<()> {
+		let mut lock = self.lock_tab().await;
+		if lock.bulk {
+			return Err(format!("begin bulk failed, tab: {}, reason: already in bulk mode", lock.tab.as_str()));
+		}
+		lock.bulk = true;
+
+		Ok(())
+	}
 
+	//结束批量模式，按内存表的最终记录一次重建所有二级索引，返回后索引与内存表一致
+	//重建与结束批量模式在同一次内存表锁内完成，不会遗漏并发提交的修改
+	pub async fn end_bulk(&self) -> SResult<()> {
+		self.ensure_loaded().await?;
+		let mut lock = self.lock_tab().await;
+		if !lock.bulk {
+			return Err(format!("end bulk failed, tab: {}, reason: not in bulk mode", lock.tab.as_str()));
+		}
+		let MemeryTab { root, indexes, .. } = &mut *lock;
+		for index in indexes.values_mut() {
+			index.rebuild(root);
+		}
+		lock.bulk = false;
 
-		let mut log_file_id = None;
-		// 首先加载叶子节点数据
-		let log_file_index = if chains.len() > 0 {
-			log_file_id = chains[0].parent_log_id;
-			chains[0].parent_log_id
+		Ok(())
+	}
+
+	//强制分裂表的可写日志文件，并累计表的分裂次数，返回分裂后的可写日志文件id
+	//启用分裂同步时，分裂和同步都在内存表锁内完成，提交在内存表锁内追加日志，所以同步完成前不会写入新的可写日志文件
+	pub async fn split(&self) -> Result<usize> {
+		let index = if SPLIT_FSYNC.load(Ordering::Relaxed) {
+			let _lock = self.lock_tab().await;
+			let index = self.1.log_file.split().await?;
+			sync_writable(&self.1.log_file).await?;
+			index
 		} else {
-			None
+			self.1.log_file.split().await?
 		};
-		// println!("LogFileTab::new  log_file_index = {:?}, tab = {:?}, chains = {:?}", log_file_index, tab, chains);
-		let file = match AsyncLogFileStore::open(path.clone(), 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, log_file_index).await {
-			Err(e) => panic!("!!!!!!open table = {:?} failed, e: {:?}", tab_name, e),
-			Ok(file) => file
+		self.1.appended.store(0, Ordering::Relaxed);
+		self.2.splits.fetch_add(1, Ordering::Relaxed);
+
+		//为分裂后新的可写日志文件预分配空间
+		if PREALLOCATE_LOG_FILE.load(Ordering::Relaxed) {
+			let tab_name = self.0.lock().await.tab.clone();
+			let config = ALL_TABLES.get(&tab_name).await.map(|tm| tm.config.clone()).unwrap_or_default();
+			preallocate_writable(&self.1.log_file, config.log_file_size()).await;
+		}
+
+		Ok(index)
+	}
+
+	//将表级配置应用到已打开的表
+	fn apply_config(&self, config: &TabConfig) {
+		self.1.blobs.set_policy(config.blob_policy());
+		self.2.split_limit.store(config.log_file_size(), Ordering::Relaxed);
+		self.2.counter_window.store(config.counter_window.unwrap_or(0), Ordering::Relaxed);
+	}
+
+	//将父表已提交的写入同步到已加载的实时分叉表，分叉表自己写入或删除过的主键不同步
+	//同步的值是持有父表锁时父表的当前值，所以并发提交的同步顺序不影响结果；未加载的分叉表在加载时从父表的日志文件读取
+	async fn sync_live_forks(&self, logs: &RwLogMap) {
+		if LIVE_FORKS.lock().is_empty() {
+			return;
+		}
+
+		let tab_name = self.0.lock().await.tab.clone();
+		let names: Vec<Atom> = match LIVE_FORKS.lock().get(&tab_name) {
+			None => return,
+			Some(names) => names.iter().cloned().collect(),
+		};
+		//在获取父表的锁前获取分叉表的句柄，避免与持有缓存锁再获取表锁的操作死锁
+		let forks: Vec<LogFileTab> = {
+			let cache = LOG_FILE_TABS.read().await;
+			let forks: Vec<LogFileTab> = names.iter().filter_map(|name| cache.get(name).cloned()).collect();
+			if forks.len() < names.len() {
+				if let Some(live) = LIVE_FORKS.lock().get_mut(&tab_name) {
+					live.retain(|name| cache.contains_key(name));
+				}
+			}
+			forks
 		};
 
-		let mut store = AsyncLogFileStore {
-			removed: Arc::new(SpinLock::new(XHashMap::default())),
-			map: Arc::new(SpinLock::new(BTreeMap::new())),
-			log_file: file.clone(),
-			tmp_map: Arc::new(SpinLock::new(XHashMap::default())),
-			writable_path: Arc::new(SpinLock::new(None)),
-			is_statistics: Arc::new(AtomicBool::new(false)),
-			is_init: Arc::new(AtomicBool::new(true)),
-			statistics: Arc::new(SpinLock::new(VecDeque::new())),
+		let parent = self.0.lock().await;
+		for fork in forks {
+			let mut lock = fork.0.lock().await;
+			if lock.unloaded {
+				continue;
+			}
+
+			let MemeryTab { root, indexes, .. } = &mut *lock;
+			for (key, rw_v) in logs.iter() {
+				match rw_v {
+					RwLog::Write(_) | RwLog::Increment(_) => (),
+					_ => continue,
+				}
+				if fork.1.map.lock().contains_key(key.as_slice()) || fork.1.removed.lock().contains_key(key.as_slice()) {
+					continue;
+				}
+
+				let k = Bon::new(key.clone());
+				let old = root.get(&k).cloned();
+				let new = parent.root.get(&k).cloned();
+				match &new {
+					None => {
+						root.delete(&k, false);
+					},
+					Some(value) => {
+						root.upsert(k, value.clone(), false);
+					},
+				}
+				for index in indexes.values_mut() {
+					index.update(key, old.as_ref(), new.as_ref());
+				}
+			}
+		}
+	}
+
+	//可写日志文件超过运行时修改的日志文件大小则分裂，日志文件打开时的大小限制由日志文件在追加时自动分裂
+	//每次提交只比较已追加的字节数，超过限制时再读取可写日志文件的实际大小，日志文件自动分裂后已追加的字节数按实际大小校正
+	//启用分裂同步时，已追加的字节数超过打开时的日志文件大小，且可写日志文件的实际大小小于已追加的字节数，说明日志文件已自动分裂
+	//自动分裂后在内存表锁内同步新的可写日志文件和日志目录，同步完成前其它事务无法提交，与强制分裂的同步保持一致
+	async fn split_if_oversized(&self) {
+		let limit = self.2.split_limit.load(Ordering::Relaxed);
+		let file_len = self.2.file_len.load(Ordering::Relaxed);
+		let appended = self.1.appended.load(Ordering::Relaxed);
+		let auto_split = SPLIT_FSYNC.load(Ordering::Relaxed) && file_len > 0 && appended >= file_len;
+		if !auto_split && (limit == 0 || appended < limit) {
+			return;
+		}
+
+		let len = match read_log_paths(&self.1.log_file).await {
+			Err(_) => return,
+			Ok(paths) => paths.last().and_then(|path| fs::metadata(path).ok()).map(|meta| meta.len() as usize).unwrap_or(0),
 		};
+		if auto_split && len < appended {
+			let _lock = self.lock_tab().await;
+			if let Err(e) = sync_writable(&self.1.log_file).await {
+				warn!("sync auto split log file failed, file_len: {}, reason: {:?}", file_len, e);
+			}
+		}
+		if limit == 0 || len < limit {
+			self.1.appended.store(len, Ordering::Relaxed);
+			return;
+		}
+		if let Err(e) = self.split().await {
+			warn!("split oversized log file failed, limit: {}, len: {}, reason: {:?}", limit, len, e);
+		}
+	}
 
-		file.load(&mut store, Some(path), 32 * 1024, true).await;
-		let mut root= OrdMap::<Tree<Bon, Bin>>::new(None);
-		let mut load_size = 0;
-		let map = store.map.lock();
-		for (k, v) in map.iter() {
-			load_size += k.len() + v.len();
-			root.upsert(Bon::new(Arc::new(k.clone())), Arc::new(v.to_vec()), false);
+	//按主键范围重写表的全部有效记录，每个范围的记录写入独立的日志文件，返回重写的记录数量
+	//内存表锁内只分裂并获取内存表的快照，快照在锁外重写，外置存储的值复用已有的外置存储文件，不重复写入
+	//重写期间的提交可能早于快照中的旧值写入日志文件，重写后在锁内为快照后被修改或删除的主键重新追加当前的值或删除
+	async fn group_by_range(&self, boundaries: &[Vec<u8>]) -> SResult<usize> {
+		let (tab_name, snapshot) = {
+			let mut lock = self.lock_tab().await;
+			//重写的快照记录与之后的提交交错追加，重写后到下次加载前不能按提交序号扫描日志文件
+			lock.reset_scan(None);
+			//先分裂，避免第一个范围的记录与之前的写入在同一个日志文件中
+			if let Err(e) = self.1.force_fork().await {
+				return Err(format!("group by range failed, tab: {}, reason: {:?}", lock.tab.as_str(), e));
+			}
+			(lock.tab.clone(), lock.root.clone())
+		};
+
+		let mut groups: Vec<Vec<(Bon, Bin)>> = vec![Vec::new(); boundaries.len() + 1];
+		for Entry(k, v) in snapshot.iter(None, false) {
+			groups[range_index(boundaries, k.as_slice())].push((k.clone(), v.clone()));
+		}
+		let mut count = 0;
+		for group in groups.into_iter().filter(|group| group.len() > 0) {
+			let mut id = 0;
+			for (k, v) in group.iter() {
+				id = self.1.append_current(k.as_slice(), v.as_slice(), self.1.flags(k.as_slice()));
+			}
+			count += group.len();
+			if let Err(e) = self.1.log_file.delay_commit(id, false, 1).await {
+				return Err(format!("group by range failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+			}
+			if let Err(e) = self.1.force_fork().await {
+				return Err(format!("group by range failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+			}
 		}
-		store.is_init.store(false, Ordering::SeqCst);
-		LOG_FILE_TOTAL_SIZE.fetch_add(load_size as u64, Ordering::Relaxed);
-		info!("load tab: {} {} KB", tab_name_clone.as_str(), format!("{0} {1:.2}", "size", load_size as f64 / 1024.0));
 
-		// 再加载分叉路径中的表的数据
-		for tm in chains.iter().skip(1) {
-			let file = match AsyncLogFileStore::open(tm.tab_name.as_ref(), 8000, LOG_FILE_SIZE.load(Ordering::Relaxed) * 1024 * 1024, tm.parent_log_id).await {
-				Err(e) => panic!("!!!!!!open table = {:?} failed, e: {:?}", tm.parent, e),
-				Ok(file) => file
-			};
-			let mut store = AsyncLogFileStore {
-				removed: Arc::new(SpinLock::new(XHashMap::default())),
-				map: Arc::new(SpinLock::new(BTreeMap::new())),
-				log_file: file.clone(),
-				tmp_map: Arc::new(SpinLock::new(XHashMap::default())),
-				writable_path: Arc::new(SpinLock::new(None)),
-				is_statistics: Arc::new(AtomicBool::new(false)),
-				is_init: Arc::new(AtomicBool::new(true)),
-				statistics: Arc::new(SpinLock::new(VecDeque::new())),
+		//快照中的值与当前的值是同一个值，说明重写期间没有被修改
+		let id = {
+			let lock = self.lock_tab().await;
+			let mut id = None;
+			for Entry(k, v) in snapshot.iter(None, false) {
+				match lock.root.get(k) {
+					Some(current) if Arc::ptr_eq(current, v) => (),
+					Some(current) => id = Some(self.1.append_current(k.as_slice(), current.as_slice(), self.1.flags(k.as_slice()))),
+					None => id = Some(self.1.append_log(LogMethod::Remove, k.as_slice(), &[])),
+				}
+			}
+			id
+		};
+		if let Some(id) = id {
+			if let Err(e) = self.1.log_file.delay_commit(id, false, 1).await {
+				return Err(format!("group by range failed, tab: {}, reason: {:?}", tab_name.as_str(), e));
+			}
+		}
+
+		Ok(count)
+	}
+
+	//获取日志表的摘要，不是日志表则返回None
+	pub async fn sink_footer(&self) -> Option<SinkFooter> {
+		self.0.lock().await.sink.clone()
+	}
+
+	//按追加顺序扫描表自己的日志文件中的所有写入记录，每次只读取一个日志块，返回扫描的记录数量，用于读取日志表
+	//扫描不获取内存表锁，扫描期间追加的记录可能不会被扫描到
+	pub async fn scan<F>(&self, mut f: F) -> SResult<u64>
+		where F: FnMut(&[u8], &[u8]) + Send {
+		let tab_name = self.0.lock().await.tab.clone();
+		let config = ALL_TABLES.get(&tab_name).await.map(|tm| tm.config.clone()).unwrap_or_default();
+		let log_paths = match read_log_paths(&self.1.log_file).await {
+			Err(e) => return Err(format!("scan failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+			Ok(paths) => paths,
+		};
+		let has_flags = self.1.has_flags.load(Ordering::Relaxed);
+		let mut tab_path = PathBuf::new();
+		tab_path.push(env::var("DB_PATH").unwrap_or(".".to_string()));
+		tab_path.push(tab_name.to_string());
+		let blob_dir = blob_path(&tab_path);
+
+		let rt = STORE_RUNTIME.read().await.as_ref().unwrap().clone();
+		let mut count = 0;
+		for log_path in log_paths {
+			let log_file = match AsyncFile::open(rt.clone(), log_path.clone(), AsyncFileOptions::OnlyRead).await {
+				Err(e) => return Err(format!("scan failed, tab: {}, reason: {:?}", tab_name.as_str(), e)),
+				Ok(f) => f,
 			};
 
-			let mut path = PathBuf::new();
-			path.push(tm.tab_name.clone().as_ref());
-			path.push(format!("{:0>width$}", log_file_id.unwrap()-1, width = 6));
-			file.load(&mut store, Some(path), 32 * 1024, true).await;
+			//日志块从日志文件尾向头读取，先记录所有日志块的位置，再从日志文件头开始逐块读取
+			let mut blocks = Vec::new();
+			let mut offset = None;
+			let mut read_len = config.load_block_size();
+			loop {
+				let (file_offset, bin) = read_log_file(log_path.clone(), log_file.clone(), offset, read_len).await
+					.map_err(|e| format!("scan failed, tab: {}, reason: {:?}", tab_name.as_str(), e))?;
+				let (next_file_offset, next_len, _) = read_log_file_block(log_path.clone(), &bin, file_offset, read_len, true)
+					.map_err(|e| format!("scan failed, tab: {}, reason: {:?}", tab_name.as_str(), e))?;
+				blocks.push((offset, read_len));
+				if next_file_offset == 0 && next_len == 0 {
+					break;
+				}
+				offset = Some(next_file_offset);
+				read_len = next_len;
+			}
 
-			let mut load_size = 0;
-			let start_time = Instant::now();
-			let map = store.map.lock();
-			for (k, v) in map.iter() {
-				load_size += k.len() + v.len();
-				root.upsert(Bon::new(Arc::new(k.clone())), Arc::new(v.to_vec()), false);
+			for (offset, read_len) in blocks.into_iter().rev() {
+				let (file_offset, bin) = read_log_file(log_path.clone(), log_file.clone(), offset, read_len).await
+					.map_err(|e| format!("scan failed, tab: {}, reason: {:?}", tab_name.as_str(), e))?;
+				let (_, _, logs) = read_log_file_block(log_path.clone(), &bin, file_offset, read_len, true)
+					.map_err(|e| format!("scan failed, tab: {}, reason: {:?}", tab_name.as_str(), e))?;
+				//日志块内的记录从新到旧排列
+				for (method, key, value) in logs.into_iter().rev() {
+					let value = match (method, value) {
+						(LogMethod::PlainAppend, Some(value)) => value,
+						_ => continue,
+					};
+					let value = if has_flags {
+						decode_flagged_value(&blob_dir, value).await?.1
+					} else {
+						value
+					};
+					f(&key, &value);
+					count += 1;
+				}
 			}
-			log_file_id = tm.parent_log_id;
-			store.is_init.store(false, Ordering::SeqCst);
-			debug!("====> load tab: {:?} size: {:?}byte time elapsed: {:?} <====", tm.tab_name, load_size, start_time.elapsed());
 		}
 
-		file_mem_tab.root = root;
+		Ok(count)
+	}
 
-		return LogFileTab(Arc::new(Mutex::new(file_mem_tab)), store);
+	//获取最近一次加载时从水位文件恢复的提交水位，应用记录的最后确认的提交水位大于恢复的水位，说明有已确认的提交丢失
+	pub fn recovered_watermark(&self) -> u64 {
+		self.2.recovered_seq.load(Ordering::SeqCst)
 	}
 
-	pub async fn transaction(&self, id: &Guid, writable: bool) -> RefLogFileTxn {
-		FileMemTxn::new(self.clone(), id, writable).await
+	//立即将表当前的持久化水位写入水位文件，等待已开始的异步写入完成，用于关闭前或需要确定水位文件内容时
+	pub async fn flush_watermark(&self) -> SResult<()> {
+		let tab_name = self.0.lock().await.tab.clone();
+		flush_watermark(&tab_name, &self.2).await
+			.map_err(|e| format!("write watermark failed, tab: {}, reason: {:?}", tab_name.as_str(), e))
+	}
+
+	//获取近似的记录数量，不获取内存表锁，并发提交时可能短暂偏差，但会在提交完成后收敛
+	pub fn approx_size(&self) -> usize {
+		self.2.approx_size.load(Ordering::Relaxed)
+	}
+
+	//获取内存表的锁，并累计等待锁的时间，公平模式下按到达顺序获取
+	async fn lock_tab(&self) -> impl DerefMut<Target = MemeryTab> + '_ {
+		let start = Instant::now();
+		let permit = if FAIR_TAB_LOCK.load(Ordering::Relaxed) {
+			Some(self.3.acquire().await)
+		} else {
+			None
+		};
+		let guard = self.0.lock().await;
+		let wait = start.elapsed().as_micros() as u64;
+		self.2.lock_acquires.fetch_add(1, Ordering::Relaxed);
+		self.2.lock_wait_total.fetch_add(wait, Ordering::Relaxed);
+		self.2.lock_wait_max.fetch_max(wait, Ordering::Relaxed);
+		TabGuard {
+			guard,
+			_permit: permit,
+		}
 	}
 }
@@ -574,7 +574,7 @@ struct MemeryTab {
 
 pub struct MemIter{
 	_root: BinMap,
-	_filter: Filter,
+	filter: Filter,
 	point: usize,
 	iter_count:		PrefCounter,	//迭代计数
 	iter_byte:		PrefCounter,	//迭代字节
@@ -590,7 +590,7 @@ impl MemIter{
 	pub fn new<'a>(tab: &Atom, root: BinMap, it: <Tree<Bon, Bin> as OIter<'a>>::IterType, filter: Filter) -> MemIter{
 		MemIter{
 			_root: root,
-			_filter: filter,
+			filter,
 			point: Box::into_raw(Box::new(it)) as usize,
 			iter_count: GLOBAL_PREF_COLLECT.
 				new_dynamic_counter(
@@ -609,13 +609,20 @@ impl Iter for MemIter{
 
 		let mut it = unsafe{Box::from_raw(self.point as *mut <Tree<Bin, Bin> as OIter<'_>>::IterType)};
 		// println!("MemIter next----------------------------------------------------------------");
-		let r = Some(Ok(match it.next() {
-			Some(&Entry(ref k, ref v)) => {
-				self.iter_byte.sum(k.len() + v.len());
+		let r = Some(Ok(loop {
+			match it.next() {
+				Some(&Entry(ref k, ref v)) => {
+					if let Some(filter) = &self.filter {
+						if !filter(k, Some(v)) {
+							continue;
+						}
+					}
+					self.iter_byte.sum(k.len() + v.len());
 
-				Some((k.clone(), v.clone()))
-			},
-			None => None,
+					break Some((k.clone(), v.clone()))
+				},
+				None => break None,
+			}
 		}));
 		mem::forget(it);
 		r
@@ -624,7 +631,7 @@ impl Iter for MemIter{
 
 pub struct MemKeyIter{
 	_root: BinMap,
-	_filter: Filter,
+	filter: Filter,
 	point: usize,
 	iter_count:		PrefCounter,	//迭代计数
 	iter_byte:		PrefCounter,	//迭代字节
@@ -640,7 +647,7 @@ impl MemKeyIter{
 	pub fn new(tab: &Atom, root: BinMap, keys: Keys<'_, Tree<Bon, Bin>>, filter: Filter) -> MemKeyIter{
 		MemKeyIter{
 			_root: root,
-			_filter: filter,
+			filter,
 			point: Box::into_raw(Box::new(keys)) as usize,
 			iter_count: GLOBAL_PREF_COLLECT.
 				new_dynamic_counter(
@@ -657,14 +664,21 @@ impl Iter for MemKeyIter{
 	fn next(&mut self) -> Option<NextResult<Self::Item>>{
 		self.iter_count.sum(1);
 
-		let it = unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)};
-		let r = Some(Ok(match unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)}.next() {
-			Some(k) => {
-				self.iter_byte.sum(k.len());
+		let mut it = unsafe{Box::from_raw(self.point as *mut Keys<'_, Tree<Bin, Bin>>)};
+		let r = Some(Ok(loop {
+			match it.next() {
+				Some(k) => {
+					if let Some(filter) = &self.filter {
+						if !filter(k, None) {
+							continue;
+						}
+					}
+					self.iter_byte.sum(k.len());
 
-				Some(k.clone())
-			},
-			None => None,
+					break Some(k.clone())
+				},
+				None => break None,
+			}
 		}));
 		mem::forget(it);
 		r
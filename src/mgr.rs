@@ -6,6 +6,8 @@ use std::{sync::Arc, env};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::mem;
 use std::fmt;
+use std::future::Future;
+use std::sync::mpsc;
 
 use hash::XHashMap;
 use ordmap::ordmap::{OrdMap, Entry, ImOrdMap, Keys};
@@ -13,14 +15,14 @@ use ordmap::asbtree::{Tree, new};
 use atom::Atom;
 use guid::{Guid, GuidGen};
 use r#async::lock::mutex_lock::Mutex;
-use r#async::rt::{AsyncRuntime, AsyncMap, multi_thread::MultiTaskRuntime};
+use r#async::rt::{AsyncRuntime, AsyncMap, multi_thread::{MultiTaskPool, MultiTaskRuntime}};
 use bon::{ReadBuffer, Decode, Encode, WriteBuffer, ReadBonErr};
 
-use crate::db::{SResult, IterResult, KeyIterResult, Filter, TabKV, TxCallback, TxState, Event, Bin, RwLog, TabMeta, CommitResult, DBResult};
+use crate::db::{SResult, IterResult, KeyIterResult, Filter, TabKV, TxCallback, TxState, Event, EventType, Bin, RwLog, TabMeta, CommitResult, DBResult, BuildDbType};
 use crate::memery_db::{MemDBSnapshot, MemDB, RefMemeryTxn, MemeryMetaTxn};
 use crate::tabs::TxnType;
-use crate::log_file_db::{LogFileDBSnapshot, RefLogFileTxn, LogFileMetaTxn, LogFileDB, DB_META_TAB_NAME};
-use crate::fork::{ALL_TABLES, TableMetaInfo};
+use crate::log_file_db::{LogFileDBSnapshot, RefLogFileTxn, LogFileMetaTxn, LogFileDB, DB_META_TAB_NAME, STORE_RUNTIME, with_op_timeout};
+use crate::fork::{ALL_TABLES, TableMetaInfo, ForkMode};
 
 /**
 * 表库及事务管理器
@@ -32,6 +34,8 @@ use crate::fork::{ALL_TABLES, TableMetaInfo};
 pub struct Mgr {
 	ware_map: Arc<Mutex<WareMap>>,				//库映射表，根据类型分类
 	guid: Arc<GuidGen>,							//Guid生成器
+	default_timeout: Arc<AtomicUsize>,			//事务的默认预提交超时时长，0表示使用库的最大超时时长
+	monitors: Arc<Mutex<Vec<Arc<dyn Monitor + Send + Sync>>>>,	//事务提交事件的监听器
 	// 所有的表分叉信息, 根据这些信息, 计算加载顺序
 	// forks: XHashMap<String, TableMetaInfo>
 }
@@ -41,6 +45,77 @@ unsafe impl Sync for Mgr {}
 
 const TIMEOUT: usize = 100;
 
+lazy_static! {
+	//with_db使用的存储运行时，第一次调用时创建，之后的调用复用
+	static ref WITH_DB_RUNTIME: parking_lot::Mutex<Option<MultiTaskRuntime<()>>> = parking_lot::Mutex::new(None);
+}
+
+/*
+* with_db的配置
+*/
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+	pub ware: Atom,				//注册的日志文件数据库的库名
+	pub db_size: usize,			//日志文件数据库的大小
+	pub threads: usize,			//存储运行时的线程数，只在第一次调用with_db创建存储运行时时有效
+	pub flush_timeout: usize,	//关闭前等待日志持久化的超时时长，单位毫秒，0表示不限制
+}
+
+impl Default for DbConfig {
+	fn default() -> Self {
+		DbConfig {
+			ware: Atom::from("logfile"),
+			db_size: 1024 * 1024 * 1024,
+			threads: 4,
+			flush_timeout: 30000,
+		}
+	}
+}
+
+//在指定路径上打开日志文件数据库并注册到新的事务管理器，然后用事务管理器和存储运行时执行指定的异步函数
+//异步函数完成后，等待所有提交的日志持久化完成，并关闭已缓存的表，再返回异步函数的结果，调用者不需要再等待日志写入
+//存储运行时在第一次调用时创建并在之后的调用中复用，全局的存储运行时未设置时才设置为该运行时，不会替换已设置的存储运行时
+pub fn with_db<F, Fut, T>(path: &str, config: DbConfig, f: F) -> SResult<T>
+	where F: FnOnce(Mgr, MultiTaskRuntime<()>) -> Fut + Send + 'static,
+		  Fut: Future<Output = T> + Send + 'static,
+		  T: Send + 'static {
+	let rt = WITH_DB_RUNTIME.lock().get_or_insert_with(|| {
+		let pool = MultiTaskPool::new("Store-Runtime".to_string(), config.threads, 1024 * 1024, 10, Some(10));
+		pool.startup(true)
+	}).clone();
+
+	let (sender, receiver) = mpsc::channel();
+	let path = Atom::from(path);
+	let store_rt = rt.clone();
+	let db_path = path.clone();
+	if let Err(e) = rt.spawn(rt.alloc(), async move {
+		{
+			let mut store = STORE_RUNTIME.write().await;
+			if store.is_none() {
+				*store = Some(store_rt.clone());
+			}
+		}
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(db_path.clone(), config.db_size).await);
+		if !mgr.register(config.ware.clone(), Arc::new(ware)).await {
+			let _ = sender.send(Err(format!("register db failed, ware: {}, path: {}", config.ware.as_str(), db_path.as_str())));
+			return;
+		}
+
+		let result = f(mgr, store_rt).await;
+		let r = LogFileDB::flush(config.flush_timeout).await;
+		LogFileDB::close().await;
+		let _ = sender.send(r.map(|_| result));
+	}) {
+		return Err(format!("spawn db task failed, path: {}, reason: {:?}", path.as_str(), e));
+	}
+
+	match receiver.recv() {
+		Err(_) => Err(format!("db task aborted, path: {}", path.as_str())),
+		Ok(result) => result,
+	}
+}
+
 impl Mgr {
 	/**
 	* 构建表库及事务管理器
@@ -50,7 +125,9 @@ impl Mgr {
 	pub fn new(gen: GuidGen) -> Self {
 		Self {
 			ware_map: Arc::new(Mutex::new(WareMap::new())),
-			guid: Arc::new(gen)
+			guid: Arc::new(gen),
+			default_timeout: Arc::new(AtomicUsize::new(0)),
+			monitors: Arc::new(Mutex::new(Vec::new())),
 		}
 	}
 
@@ -62,7 +139,9 @@ impl Mgr {
 	pub async fn shallow_clone(&self) -> Self {
 		Self {
 			ware_map: Arc::new(Mutex::new(self.ware_map.lock().await.wares_clone().await)),
-			guid: self.guid.clone()
+			guid: self.guid.clone(),
+			default_timeout: self.default_timeout.clone(),
+			monitors: self.monitors.clone(),
 		}
 	}
 
@@ -79,10 +158,36 @@ impl Mgr {
 		};
 		Self {
 			ware_map: Arc::new(Mutex::new(WareMap::new())),
-			guid: gen
+			guid: gen,
+			default_timeout: Arc::new(AtomicUsize::new(self.default_timeout.load(Ordering::Relaxed))),
+			monitors: Arc::new(Mutex::new(Vec::new())),
 		}
 	}
 
+	/**
+	* 增加事务提交事件的监听器，之后创建的事务提交成功后，会为每个写入的主键通知监听器
+	* @param monitor 监听器
+	*/
+	pub async fn add_monitor(&self, monitor: Arc<dyn Monitor + Send + Sync>) {
+		self.monitors.lock().await.push(monitor);
+	}
+
+	/**
+	* 设置事务的默认预提交超时时长，之后创建的事务使用该超时时长
+	* @param timeout 预提交超时时长，0表示使用所有库的最大超时时长
+	*/
+	pub fn set_default_timeout(&self, timeout: usize) {
+		self.default_timeout.store(timeout, Ordering::Relaxed);
+	}
+
+	/**
+	* 获取事务的默认预提交超时时长
+	* @returns 预提交超时时长，0表示使用所有库的最大超时时长
+	*/
+	pub fn default_timeout(&self) -> usize {
+		self.default_timeout.load(Ordering::Relaxed)
+	}
+
 	/**
 	* 注册库
 	* @param ware_name 库名，即数据库的类型名，例如"logfile"
@@ -129,19 +234,28 @@ impl Mgr {
             tmp.push((k.clone(), v));
         }
 
+        //未设置默认超时时长，则使用所有库的最大超时时长
+        let timeout = match self.default_timeout.load(Ordering::Relaxed) {
+            0 => tmp.iter().map(|(_, v)| v.timeout()).max().unwrap_or(TIMEOUT),
+            timeout => timeout,
+        };
+
         for (k, v) in tmp {
             map.insert(k, v.snapshot().await);
         }
 
         let rt = rt.as_ref().unwrap().clone();
+        let monitors = self.monitors.lock().await.clone();
 
         Tr {
             writable,
-            timeout: TIMEOUT,
+            timeout,
             id: id.clone(),
             ware_log_map: map,
             state: TxState::Ok,
             rt: Some(rt),
+            monitors,
+            mgr: Some(self.clone()),
             ..Default::default()
         }
 	}
@@ -163,6 +277,39 @@ impl Mgr {
 		arr
 	}
 
+	/**
+	* 列出所有已注册的库，按库名排序
+	* @returns 库名集合
+	*/
+	pub async fn list_wares(&self) -> Vec<Atom> {
+		let mut arr = Vec::new();
+		let lock = self.ware_map.lock().await;
+		let mut iter = lock.keys(None, false);
+		while let Some(e) = iter.next() {
+			arr.push(e.clone());
+		}
+		arr
+	}
+
+	/**
+	* 获取指定库的信息
+	* @param ware_name 库名，即数据库的类型名，例如"logfile"
+	* @returns 库信息，库未注册则返回None
+	*/
+	pub async fn ware_info(&self, ware_name: &Atom) -> Option<WareInfo> {
+		let ware = self.find(ware_name).await?;
+		let db_type = match &*ware {
+			DatabaseWare::MemWare(_) => BuildDbType::MemoryDB,
+			DatabaseWare::LogFileWare(_) => BuildDbType::LogFileDB,
+		};
+
+		Some(WareInfo {
+			name: ware_name.clone(),
+			db_type,
+			tabs: ware.list().await.count(),
+		})
+	}
+
 	/**
 	* 寻找指定的库
 	* ware_name 库名，即数据库的类型名，例如"logfile"
@@ -176,6 +323,16 @@ impl Mgr {
 	}
 }
 
+/**
+* 已注册的库的信息
+*/
+#[derive(Debug, Clone)]
+pub struct WareInfo {
+	pub name: Atom,				//库名
+	pub db_type: BuildDbType,	//库的数据库类型
+	pub tabs: usize,			//库的表数量
+}
+
 pub trait Monitor {
 	fn notify(&self, event: Event, mgr: Mgr);
 }
@@ -536,6 +693,7 @@ impl DatabaseTabTxn {
 				txn.prepare(timeout).await
 			}
 			DatabaseTabTxn::LogFileTabTxn(txn) => {
+				//只对获取内存表锁限时，见FileMemTxn::prepare_inner
 				txn.prepare(timeout).await
 			}
 		}
@@ -556,6 +714,28 @@ impl DatabaseTabTxn {
 		}
 	}
 
+	/**
+	* 获取事务的提交序号
+	* @returns 提交序号，内存表事务或未提交则返回None
+	*/
+	pub async fn commit_seq(&self) -> Option<u64> {
+		match self {
+			DatabaseTabTxn::MemTabTxn(_) => None,
+			DatabaseTabTxn::LogFileTabTxn(txn) => txn.commit_seq().await,
+		}
+	}
+
+	/**
+	* 设置事务的链路追踪id，内存表事务忽略链路追踪id
+	* @param trace_id 链路追踪id
+	*/
+	pub async fn set_trace_id(&self, trace_id: Option<Atom>) {
+		match self {
+			DatabaseTabTxn::MemTabTxn(_) => (),
+			DatabaseTabTxn::LogFileTabTxn(txn) => txn.set_trace_id(trace_id).await,
+		}
+	}
+
 	/**
 	* 回滚事务
 	* @returns 回滚事务结果
@@ -577,7 +757,7 @@ impl DatabaseTabTxn {
 				txn.key_lock(_arr, _lock_time, _readonly).await
 			}
 			DatabaseTabTxn::LogFileTabTxn(txn) => {
-				txn.key_lock(_arr, _lock_time, _readonly).await
+				with_op_timeout("key_lock", txn.key_lock(_arr, _lock_time, _readonly)).await
 			}
 		}
 	}
@@ -600,7 +780,7 @@ impl DatabaseTabTxn {
 				txn.query(arr, _lock_time, _readonly).await
 			}
 			DatabaseTabTxn::LogFileTabTxn(txn) => {
-				txn.query(arr, _lock_time, _readonly).await
+				with_op_timeout("query", txn.query(arr, _lock_time, _readonly)).await
 			}
 		}
 	}
@@ -643,7 +823,7 @@ impl DatabaseTabTxn {
 				txn.iter(tab, key, descending, filter).await
 			}
 			DatabaseTabTxn::LogFileTabTxn(txn) => {
-				txn.iter(tab, key, descending, filter).await
+				with_op_timeout("iter", txn.iter(tab, key, descending, filter)).await
 			}
 		}
 	}
@@ -666,29 +846,29 @@ impl DatabaseTabTxn {
 				txn.key_iter(key, descending, filter).await
 			}
 			DatabaseTabTxn::LogFileTabTxn(txn) => {
-				txn.key_iter(key, descending, filter).await
+				with_op_timeout("key_iter", txn.key_iter(key, descending, filter)).await
 			}
 		}
 	}
 
 	/**
-	* 数据库索引(未实现)
+	* 数据库索引，按二级索引的顺序迭代记录，内存表未实现
 	* @returns 索引迭代器
 	*/
-	pub fn index(
+	pub async fn index(
 		&self,
-		_tab: &Atom,
-		_index_key: &Atom,
-		_key: Option<Bin>,
-		_descending: bool,
-		_filter: Filter,
+		tab: &Atom,
+		index_key: &Atom,
+		key: Option<Bin>,
+		descending: bool,
+		filter: Filter,
 	) -> IterResult {
 		match self {
 			DatabaseTabTxn::MemTabTxn(txn) => {
-				txn.index(_tab, _index_key, _key, _descending, _filter)
+				txn.index(tab, index_key, key, descending, filter)
 			}
 			DatabaseTabTxn::LogFileTabTxn(txn) => {
-				txn.index(_tab, _index_key, _key, _descending, _filter)
+				txn.index(tab, index_key, key, descending, filter).await
 			}
 		}
 	}
@@ -708,6 +888,21 @@ impl DatabaseTabTxn {
 		}
 	}
 
+	/**
+	* 累加计数器
+	* @param key 计数器的主键
+	* @param delta 增量
+	* @returns 累加结果
+	*/
+	pub async fn increment(&self, key: Bin, delta: i64) -> DBResult {
+		match self {
+			DatabaseTabTxn::MemTabTxn(_) => Err("increment not supported by memory table".to_string()),
+			DatabaseTabTxn::LogFileTabTxn(txn) => {
+				txn.increment(key, delta).await
+			}
+		}
+	}
+
 	pub async fn fork_prepare(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
 		match self {
 			DatabaseTabTxn::MemTabTxn(_) => unimplemented!(),
@@ -717,11 +912,11 @@ impl DatabaseTabTxn {
 		}
 	}
 
-	pub async fn fork_commit(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta) -> DBResult {
+	pub async fn fork_commit(&self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, meta: TabMeta, mode: ForkMode) -> DBResult {
 		match self {
 			DatabaseTabTxn::MemTabTxn(_) => unimplemented!(),
 			DatabaseTabTxn::LogFileTabTxn(txn) => {
-				txn.fork_commit(ware, tab_name, fork_tab_name, meta).await
+				txn.fork_commit(ware, tab_name, fork_tab_name, meta, mode).await
 			}
 		}
 	}
@@ -930,8 +1125,11 @@ pub struct Tr {
 	state: TxState,																//事务状态
 	tab_txns: XHashMap<(Atom, Atom), Arc<DatabaseTabTxn>>, 						//数据库表事务映射表
 	meta_txns: XHashMap<Atom, Arc<DatabaseMetaTxn>>, 							//元信息表事务映射表
-	fork_txns: XHashMap<(Atom, Atom, Atom), (TabMeta, Arc<DatabaseTabTxn>)>, 	//这个事务中产生的所有分叉操作
+	fork_txns: XHashMap<(Atom, Atom, Atom), (TabMeta, Arc<DatabaseTabTxn>, ForkMode)>, 	//这个事务中产生的所有分叉操作
 	rt: Option<MultiTaskRuntime<()>>,											//异步运行时
+	trace_id: Option<Atom>,														//链路追踪id，用于关联上游请求
+	monitors: Vec<Arc<dyn Monitor + Send + Sync>>,								//创建事务时管理器的事件监听器
+	mgr: Option<Mgr>,															//创建事务的管理器，用于通知监听器
 }
 
 impl Tr {
@@ -943,6 +1141,33 @@ impl Tr {
 		self.state.clone()
 	}
 
+	/**
+	* 获得事务的预提交超时时长
+	* @returns 预提交超时时长
+	*/
+	pub fn timeout(&self) -> usize {
+		self.timeout
+	}
+
+	/**
+	* 设置事务的链路追踪id，链路追踪id会传递给事务中的表事务，并记录在日志和提交事件中
+	* @param trace_id 链路追踪id
+	*/
+	pub async fn set_trace_id(&mut self, trace_id: Option<Atom>) {
+		for txn in self.tab_txns.values() {
+			txn.set_trace_id(trace_id.clone()).await;
+		}
+		self.trace_id = trace_id;
+	}
+
+	/**
+	* 获得事务的链路追踪id
+	* @returns 链路追踪id
+	*/
+	pub fn trace_id(&self) -> Option<Atom> {
+		self.trace_id.clone()
+	}
+
 	/**
 	* 数据库元信息操作
 	* @param ware_name 库类型名
@@ -1046,6 +1271,9 @@ impl Tr {
 				_ => return Err(format!("WareNotFound: {:?}", ware_name))
 			}
 		};
+		if self.trace_id.is_some() {
+			txn.set_trace_id(self.trace_id.clone()).await;
+		}
 		self.tab_txns.insert(txn_key, txn.clone());
 		Ok(txn)
 	}
@@ -1254,6 +1482,30 @@ impl Tr {
 		}
 	}
 
+	/**
+	* 累加计数器，只记录增量，提交时累加到当前值上，并发事务对同一计数器的累加不冲突
+	* 计数器的值是Bon编码的i64，不存在则从0开始累加，当前值不是有效的整数编码则预提交失败
+	* @param ware_name 库名
+	* @param tab_name 表名
+	* @param key 计数器的主键
+	* @param delta 增量
+	* @returns 累加结果
+	*/
+	pub async fn increment(&mut self, ware_name: &Atom, tab_name: &Atom, key: Bin, delta: i64) -> DBResult {
+		self.state = TxState::Doing;
+		let txn = self.build(ware_name, tab_name).await?;
+		match txn.increment(key, delta).await {
+			Ok(_) => {
+				self.state = TxState::Ok;
+				Ok(())
+			}
+			Err(e) => {
+				self.state = TxState::Err;
+				Err(e)
+			}
+		}
+	}
+
 	/**
 	* 预提交事务
 	* @returns 预提交结果
@@ -1338,6 +1590,12 @@ impl Tr {
 	* @returns 提交结果
 	*/
 	pub async fn commit(&mut self) -> DBResult {
+		//只能提交预提交成功的事务，避免预提交失败后仍然提交元信息
+		match self.state {
+			TxState::PreparOk => (),
+			TxState::PreparFail => return Err(format!("PrepareFailed: txn: {:?}", self.id)),
+			ref state => return Err(format!("NotPrepared: txn: {:?}, state: {:?}", self.id, state)),
+		}
 		self.state = TxState::Committing;
 		//检查并移除元信息表事务在表管理器中的预提交信息
 		let alter_len = self.meta_txns.len();
@@ -1355,12 +1613,53 @@ impl Tr {
 		let rt = self.rt.as_ref().unwrap().clone();
 		let mut async_map = rt.map::<bool>();
 
+		//先提交元信息表的事务，并修改内存和文件数据，元信息持久化后再提交数据，保证同一事务中创建并写入的表提交后可以打开
+		for val in self.meta_txns.values() {
+			match val.commit().await {
+				Ok(_) => {
+					if count.fetch_sub(1, Ordering::SeqCst) == 1 {
+						self.state = TxState::Commited;
+						return Ok(())
+					}
+				}
+				Err(e) => {
+					//元信息提交失败，则回滚已预提交的数据，不再提交
+					for val in self.tab_txns.values() {
+						let _ = val.rollback().await;
+					}
+					self.state = TxState::CommitFail;
+					return Err(e)
+				}
+			}
+		}
+
 		//提交数据库表的事务
 		for (txn_name, val) in self.tab_txns.iter_mut() {
 			let val = val.clone();
+			let (ware, tab) = txn_name.clone();
+			let trace_id = self.trace_id.clone();
+			let monitors = self.monitors.clone();
+			let mgr = self.mgr.clone();
 			async_map.join(AsyncRuntime::Multi(rt.clone()), async move {
 				match val.commit().await {
 					Ok(logs) => {
+						//提交成功后，为每个写入的主键通知监听器
+						if let (false, Some(mgr)) = (monitors.is_empty(), mgr) {
+							let seq = val.commit_seq().await.unwrap_or(0);
+							for (key, log) in logs.iter() {
+								if let RwLog::Write(value) = log {
+									for monitor in monitors.iter() {
+										monitor.notify(Event {
+											seq,
+											ware: ware.clone(),
+											tab: tab.clone(),
+											other: EventType::Tab { key: key.clone(), value: value.clone() },
+											trace_id: trace_id.clone(),
+										}, mgr.clone());
+									}
+								}
+							}
+						}
 						Ok(true)
 					}
 					Err(e) => {
@@ -1387,25 +1686,9 @@ impl Tr {
 			}
 		}
 
-		//提交元信息表的事务，并修改内存和文件数据
-		for val in self.meta_txns.values() {
-			match val.commit().await {
-				Ok(_) => {
-					if count.fetch_sub(1, Ordering::SeqCst) == 1 {
-						self.state = TxState::Commited;
-						return Ok(())
-					}
-				}
-				Err(e) => {
-					self.state = TxState::CommitFail;
-					return Err(e)
-				}
-			}
-		}
-
 		// 处理表分叉的提交
 		for (k, v) in self.fork_txns.iter() {
-			match v.1.fork_commit(k.0.clone(), k.1.clone(), k.2.clone(), v.0.clone()).await {
+			match v.1.fork_commit(k.0.clone(), k.1.clone(), k.2.clone(), v.0.clone(), v.2).await {
 				Ok(_) => {
 					if count.fetch_sub(1, Ordering::SeqCst) == 1 {
 						self.state = TxState::Commited;
@@ -1427,6 +1710,10 @@ impl Tr {
 	* @returns 回滚事务结果
 	*/
 	pub async fn rollback(&mut self) -> DBResult {
+		//重复回滚直接成功
+		if self.state == TxState::Rollbacked {
+			return Ok(());
+		}
 		self.state = TxState::Rollbacking;
 		//先移除元信息表事务在表管理器中的预提交信息
 		let alter_len = self.meta_txns.len();
@@ -1454,7 +1741,7 @@ impl Tr {
 			}
 		}
 
-		//回滚元信息表上的事务(回滚不会修改元信息表，所以暂时是空实现)
+		//回滚元信息表上的事务，回滚不会修改元信息表，只丢弃本事务中创建并已打开的表
 		for val in self.meta_txns.values() {
 			match val.rollback().await {
 				Ok(()) => {
@@ -1491,8 +1778,13 @@ impl Tr {
 
 	///创建指定数据库表的一个分叉表，允许分叉后的表有新的元信息(主键和值类型)
 	///原表的log会立即强制分裂，生成一个新的log文件和文件id，之前的数据就是两个表的公共数据
-	///强制分裂后的最新的只读log文件就是分叉点
+	///强制分裂后的最新的只读log文件就是分叉点，分叉表为冻结模式，父表在分叉后的写入对分叉表不可见
 	pub async fn fork_tab(&mut self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, new_meta: TabMeta) -> DBResult {
+		self.fork_tab_with(ware, tab_name, fork_tab_name, new_meta, ForkMode::Frozen).await
+	}
+
+	///按指定的分叉模式创建指定数据库表的一个分叉表，实时模式的分叉表持续看到父表在分叉后的写入
+	pub async fn fork_tab_with(&mut self, ware: Atom, tab_name: Atom, fork_tab_name: Atom, new_meta: TabMeta, mode: ForkMode) -> DBResult {
 		//判断本事务中是否有冲突的分叉表名，例如
 		if let Some(_) = self.fork_txns.get(&(ware.clone(), tab_name.clone(), fork_tab_name.clone()))  {
 			return Err("duplicate fork tab name".to_string())
@@ -1506,16 +1798,24 @@ impl Tr {
 			},
 			_ => return Err(String::from("WareNotFound"))
 		};
-		self.fork_txns.insert((ware, tab_name, fork_tab_name), (new_meta, txn));
+		self.fork_txns.insert((ware, tab_name, fork_tab_name), (new_meta, txn, mode));
 
 		Ok(())
 	}
 
 	/**
-	* 记录锁(未实现)
-	* @returns
+	* 记录锁，lock_time为0时解锁，内存表未实现
+	* @param arr 要锁的记录，主键可以不存在
+	* @param lock_time 锁定时间，单位毫秒
+	* @param read_lock 是否读锁
+	* @returns 锁的结果，任意表加锁失败则返回错误，已加锁的表在事务提交或回滚时释放
 	*/
-	async fn key_lock(&mut self, tr: &Tr, arr: Vec<TabKV>, lock_time: usize, read_lock: bool) -> DBResult {
+	pub async fn key_lock(&mut self, arr: Vec<TabKV>, lock_time: usize, read_lock: bool) -> DBResult {
+		for ((ware_name, tab_name), val) in tab_map(arr).into_iter() {
+			let txn = self.build(&ware_name, &tab_name).await?;
+			txn.key_lock(Arc::new(val), lock_time, read_lock).await?;
+		}
+
 		Ok(())
 	}
 }
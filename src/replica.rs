@@ -0,0 +1,256 @@
+//! 只读附加到由其它进程写入的数据库目录，不打开任何表的日志文件存储，不获取数据库的独占锁
+//! 通过比较表的日志文件列表，重放主库新追加的日志记录到内存视图，主库分裂时增量重放，整理或删除日志文件时全量重放
+//! 只附加没有父表的表，分叉表需要按父表的分叉点重放父表的日志文件，暂不支持
+
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::io::ErrorKind;
+
+use atom::Atom;
+use hash::XHashMap;
+use ordmap::ordmap::OrdMap;
+use ordmap::asbtree::Tree;
+use r#async::lock::mutex_lock::Mutex;
+use r#async::rt::AsyncRuntime;
+use r#async::rt::multi_thread::MultiTaskRuntime;
+use pi_store::log_store::log_file::LogMethod;
+
+use crate::db::{Bin, Bon, SResult};
+use crate::fork::TableMetaInfo;
+use crate::log_file_db::{STORE_RUNTIME, DB_META_TAB_NAME, read_log_records, read_format_version, decode_meta_record, decode_flagged_value, blob_path};
+
+/*
+* 读取元信息表日志文件时每次读取的日志块大小
+*/
+const META_BLOCK_SIZE: usize = 32 * 1024;
+
+/*
+* 只读附加的表的内存视图
+*/
+struct ReplicaTab {
+	files:		Vec<(PathBuf, u64)>,		//已重放的日志文件和重放时的文件大小，按日志文件id升序
+	root:		OrdMap<Tree<Bon, Bin>>,		//已重放的记录
+}
+
+/*
+* 只读附加的数据库，可以在多个任务间共享
+*/
+#[derive(Clone)]
+pub struct ReadonlyDB {
+	path:		PathBuf,									//数据库目录，与主库的DB_PATH相同
+	metas:		Arc<Mutex<XHashMap<Atom, TableMetaInfo>>>,	//已附加的表的元信息
+	tabs:		Arc<Mutex<XHashMap<Atom, ReplicaTab>>>,		//已访问过的表的内存视图
+	tailing:	Arc<AtomicBool>,							//是否正在定时刷新
+}
+
+impl ReadonlyDB {
+	//只读附加到指定的数据库目录，只读取元信息表，表在第一次访问时再重放
+	pub async fn attach(path: &Path) -> SResult<Self> {
+		let meta_path = path.join(DB_META_TAB_NAME);
+		if !meta_path.is_dir() {
+			return Err(format!("CorruptMeta: path: {:?}, reason: not a directory", meta_path));
+		}
+
+		let db = ReadonlyDB {
+			path: path.to_path_buf(),
+			metas: Arc::new(Mutex::new(XHashMap::default())),
+			tabs: Arc::new(Mutex::new(XHashMap::default())),
+			tailing: Arc::new(AtomicBool::new(false)),
+		};
+		*db.metas.lock().await = read_catalog(&meta_path).await?;
+
+		Ok(db)
+	}
+
+	//获取已附加的表，按表名排序
+	pub async fn tabs(&self) -> Vec<Atom> {
+		let mut tabs: Vec<Atom> = self.metas.lock().await.keys().cloned().collect();
+		tabs.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+		tabs
+	}
+
+	//获取指定表的指定主键的值，表第一次访问时重放表的所有日志文件
+	pub async fn get(&self, tab: &Atom, key: &[u8]) -> SResult<Option<Bin>> {
+		let mut tabs = self.tabs.lock().await;
+		if !tabs.contains_key(tab) {
+			let meta = match self.metas.lock().await.get(tab).cloned() {
+				None => return Err(format!("TabNotFound: {}", tab.as_str())),
+				Some(meta) => meta,
+			};
+			let mut replica = ReplicaTab {
+				files: Vec::new(),
+				root: OrdMap::new(None),
+			};
+			replay(&self.path, tab, &meta, &mut replica).await?;
+			tabs.insert(tab.clone(), replica);
+		}
+
+		Ok(tabs.get(tab).unwrap().root.get(&Bon::new(Arc::new(key.to_vec()))).cloned())
+	}
+
+	//重新读取元信息表，并重放已访问过的表在上次刷新后追加的日志记录
+	//读取失败的表保留上次刷新的视图，在下次刷新时重试，返回第一个错误
+	pub async fn refresh(&self) -> SResult<()> {
+		let metas = read_catalog(&self.path.join(DB_META_TAB_NAME)).await?;
+		let mut result = Ok(());
+		let mut tabs = self.tabs.lock().await;
+		tabs.retain(|tab, _| metas.contains_key(tab));
+		for (tab, replica) in tabs.iter_mut() {
+			if let Err(e) = replay(&self.path, tab, metas.get(tab).unwrap(), replica).await {
+				warn!("refresh readonly tab failed, tab: {}, reason: {}", tab.as_str(), e);
+				if result.is_ok() {
+					result = Err(e);
+				}
+			}
+		}
+		*self.metas.lock().await = metas;
+
+		result
+	}
+
+	//在存储运行时上每隔指定毫秒刷新一次，已在刷新则忽略
+	pub async fn start_tail(&self, interval: usize) -> SResult<()> {
+		if self.tailing.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+			return Ok(());
+		}
+
+		let rt = match STORE_RUNTIME.read().await.as_ref().cloned() {
+			None => {
+				self.tailing.store(false, Ordering::SeqCst);
+				return Err("start tail failed, reason: store runtime not ready".to_string());
+			},
+			Some(rt) => rt,
+		};
+		schedule_tail(rt, self.clone(), interval);
+		Ok(())
+	}
+
+	//停止定时刷新，已安排的刷新不再执行
+	pub fn stop_tail(&self) {
+		self.tailing.store(false, Ordering::SeqCst);
+	}
+}
+
+//在存储运行时上定时刷新，每次刷新完成后再安排下一次，停止后不再安排
+fn schedule_tail(rt: MultiTaskRuntime<()>, db: ReadonlyDB, interval: usize) {
+	let next = rt.clone();
+	let tailing = db.tailing.clone();
+	if let Err(e) = rt.spawn_timing(rt.alloc(), async move {
+		if !db.tailing.load(Ordering::SeqCst) {
+			return;
+		}
+
+		let _ = db.refresh().await;
+		schedule_tail(next, db, interval);
+	}, interval) {
+		tailing.store(false, Ordering::SeqCst);
+		error!("schedule readonly tail failed, reason: {:?}", e);
+	}
+}
+
+//读取元信息表的所有日志文件，返回没有父表的表的元信息
+async fn read_catalog(meta_path: &Path) -> SResult<XHashMap<Atom, TableMetaInfo>> {
+	//从最新到最旧读取，每个表只采用最先读到的记录
+	let mut seen = XHashMap::default();
+	for (log_path, _) in list_log_files(meta_path)?.into_iter().rev() {
+		for (method, key, value) in read_log_records(log_path, META_BLOCK_SIZE).await? {
+			if seen.contains_key(&key) {
+				continue;
+			}
+			let meta = match (method, value) {
+				(LogMethod::Remove, _) | (_, None) => None,
+				(_, Some(value)) => Some(value),
+			};
+			seen.insert(key, meta);
+		}
+	}
+
+	let mut metas = XHashMap::default();
+	for (key, value) in seen {
+		if let Some(value) = value {
+			let (tab, meta) = decode_meta_record(&key, &value)?;
+			if meta.parent.is_none() {
+				metas.insert(tab, meta);
+			}
+		}
+	}
+
+	Ok(metas)
+}
+
+//获取指定目录下的所有日志文件和文件大小，按日志文件id升序，目录不存在则没有日志文件
+fn list_log_files(dir: &Path) -> SResult<Vec<(PathBuf, u64)>> {
+	let entries = match fs::read_dir(dir) {
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(format!("read log dir failed, path: {:?}, reason: {:?}", dir, e)),
+		Ok(entries) => entries,
+	};
+
+	let mut logs = Vec::new();
+	for entry in entries.filter_map(|entry| entry.ok()) {
+		let path = entry.path();
+		let id = match path.file_name().and_then(|name| name.to_str()).and_then(|name| name.parse::<usize>().ok()) {
+			None => continue,
+			Some(id) => id,
+		};
+		let len = match entry.metadata() {
+			Err(e) => return Err(format!("read log file metadata failed, path: {:?}, reason: {:?}", path, e)),
+			Ok(meta) => meta.len(),
+		};
+		logs.push((id, path, len));
+	}
+	logs.sort_by_key(|(id, _, _)| *id);
+
+	Ok(logs.into_iter().map(|(_, path, len)| (path, len)).collect())
+}
+
+//重放表在上次重放后新追加的日志记录，除最新的日志文件外，之前重放的日志文件都未改变时，
+//只重新重放上次最新的日志文件和之后的日志文件，否则主库已整理或删除了日志文件，从空的视图全量重放
+//重放失败时不修改视图
+async fn replay(db_path: &Path, tab: &Atom, meta: &TableMetaInfo, replica: &mut ReplicaTab) -> SResult<()> {
+	let tab_path = db_path.join(tab.as_str());
+	let has_flags = read_format_version(&tab_path)? >= 3;
+	let blob_dir = blob_path(&tab_path);
+	let files = list_log_files(&tab_path)?;
+
+	let (mut root, start) = match replica.files.split_last() {
+		Some((_, sealed)) if files.len() >= replica.files.len() && files[..sealed.len()] == *sealed => {
+			(replica.root.clone(), sealed.len())
+		},
+		_ => (OrdMap::new(None), 0),
+	};
+
+	for (log_path, _) in &files[start..] {
+		let mut records = read_log_records(log_path.clone(), meta.config.load_block_size()).await
+			.map_err(|e| format!("replay readonly tab failed, tab: {}, log: {:?}, reason: {}", tab.as_str(), log_path, e))?;
+		//读取的记录从最新到最旧，按写入顺序重放
+		records.reverse();
+		for (method, key, value) in records {
+			let key = Bon::new(Arc::new(key));
+			match (method, value) {
+				(LogMethod::Remove, _) | (_, None) => {
+					root.delete(&key, false);
+				},
+				(_, Some(value)) if has_flags => {
+					//带标志字节的记录，剥离值前的标志，外置存储的值读取完整的值
+					//被覆盖的外置存储的值可能已被主库整理删除，忽略这些记录，之后的记录会覆盖它们
+					match decode_flagged_value(&blob_dir, value).await {
+						Err(e) => debug!("skip unavailable blob, tab: {}, log: {:?}, reason: {}", tab.as_str(), log_path, e),
+						Ok((_, value)) => {
+							root.upsert(key, Arc::new(value), false);
+						},
+					}
+				},
+				(_, Some(value)) => {
+					root.upsert(key, Arc::new(value), false);
+				},
+			}
+		}
+	}
+
+	replica.root = root;
+	replica.files = files;
+	Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,295 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use atom::Atom;
+use guid::Guid;
+use r#async::lock::spin_lock::SpinLock;
+
+use crate::db::{Bin, SResult, DBResult, TabKV, Iter, FuseOnErr, NextResult, Bon};
+use crate::fork::ALL_TABLES;
+use crate::log_file_db::{LogFileDB, LogFileTab, LogFileMetaTxn, RefLogFileTxn};
+
+/*
+* 分片表的分片表名的分隔符，分片表与逻辑表在同一目录中，与逻辑表同名加分片后缀
+*/
+const SHARD_SEPARATOR: &'static str = ".shard";
+
+//获取逻辑表的指定分片的表名
+pub fn shard_name(tab: &Atom, index: usize) -> Atom {
+	Atom::from(format!("{}{}{}", tab.as_str(), SHARD_SEPARATOR, index))
+}
+
+//按主键的哈希获取主键所在的分片，使用FNV-1a哈希，保证重启后主键的分片不变
+pub fn shard_index(key: &[u8], count: usize) -> usize {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for b in key {
+		hash ^= *b as u64;
+		hash = hash.wrapping_mul(0x100000001b3);
+	}
+
+	(hash % count as u64) as usize
+}
+
+/*
+* 分片的日志文件表，逻辑表的主键按哈希分散到多个分片表，每个分片表有独立的内存表锁和日志文件，不同分片的写入不会竞争同一个锁
+* 分片表是元信息中的普通表，使用逻辑表的元信息和表级配置，分片数量在第一次打开时确定，之后不允许改变
+* 分片表只能通过分片表访问，通过Mgr访问逻辑表读写不到分片中的记录，删除逻辑表时在同一个元信息事务中删除所有分片表
+*/
+#[derive(Clone)]
+pub struct ShardedTab {
+	tab:	Atom,							//逻辑表名
+	shards:	Arc<Vec<(Atom, LogFileTab)>>,	//分片表名和分片表，按分片序号排序
+}
+
+impl ShardedTab {
+	//打开指定逻辑表的分片表，逻辑表必须已创建，分片表不存在则在一个元信息事务中全部创建
+	//已有的分片数量与指定的分片数量不一致则返回错误，避免主键被路由到错误的分片
+	pub async fn open(tab: &Atom, count: usize) -> SResult<Self> {
+		if count == 0 {
+			return Err(format!("open sharded tab failed, tab: {}, reason: invalid shard count", tab.as_str()));
+		}
+
+		let names: Vec<Atom> = (0..count).map(|index| shard_name(tab, index)).collect();
+		let (info, missing) = {
+			let tables = ALL_TABLES.lock().await;
+			let info = match tables.get(tab) {
+				None => return Err(String::from("TabNotFound: ") + tab.as_str()),
+				Some(info) => info.clone(),
+			};
+			let missing: Vec<Atom> = names.iter().filter(|name| !tables.contains_key(*name)).cloned().collect();
+			if (missing.len() > 0 && missing.len() < count) || tables.contains_key(&shard_name(tab, count)) {
+				return Err(format!("open sharded tab failed, tab: {}, count: {}, reason: shard count mismatch", tab.as_str(), count));
+			}
+			(info, missing)
+		};
+
+		if missing.len() > 0 {
+			let meta_txn = LogFileMetaTxn::new();
+			for name in missing.iter() {
+				meta_txn.alter(name, Some(Arc::new(info.meta.clone()))).await?;
+				meta_txn.alter_config(name, info.config.clone()).await?;
+			}
+			meta_txn.prepare(0).await?;
+			meta_txn.commit().await?;
+		}
+
+		let mut shards = Vec::with_capacity(count);
+		for name in names {
+			let file = LogFileDB::open(&name).await?;
+			shards.push((name, file));
+		}
+
+		Ok(ShardedTab {
+			tab: tab.clone(),
+			shards: Arc::new(shards),
+		})
+	}
+
+	//获取逻辑表名
+	pub fn name(&self) -> &Atom {
+		&self.tab
+	}
+
+	//获取所有分片表名，按分片序号排序
+	pub fn shards(&self) -> Vec<Atom> {
+		self.shards.iter().map(|(name, _)| name.clone()).collect()
+	}
+
+	//创建逻辑表的事务，在每个分片表上创建一个事务，只有访问过的分片参与预提交和提交，任意分片重新加载失败则返回错误
+	pub async fn transaction(&self, id: &Guid, writable: bool) -> SResult<ShardedTxn> {
+		let mut txns = Vec::with_capacity(self.shards.len());
+		for (_, file) in self.shards.iter() {
+			txns.push(file.transaction(id, writable).await?);
+		}
+
+		Ok(ShardedTxn {
+			tab: self.clone(),
+			touched: (0..txns.len()).map(|_| AtomicBool::new(false)).collect(),
+			written: SpinLock::new(None),
+			txns,
+		})
+	}
+}
+
+/*
+* 分片表的事务，读写按主键路由到所在分片的事务，读取可以访问多个分片，写入只允许在一个分片中
+* 预提交所有访问过的分片，全部成功后再依次提交，任意分片预提交失败则回滚所有分片，与跨表事务的提交方式相同
+* 写入的分片最后提交，之前只读的分片提交失败则回滚写入的分片，所以事务的写入全部提交或全部不提交
+*/
+pub struct ShardedTxn {
+	tab:		ShardedTab,				//分片表
+	txns:		Vec<RefLogFileTxn>,		//每个分片的事务，按分片序号排序
+	touched:	Vec<AtomicBool>,		//每个分片是否被访问过
+	written:	SpinLock<Option<usize>>,	//写入的分片序号，没有写入则为None
+}
+
+impl ShardedTxn {
+	//获取主键所在的分片，并记录分片被访问过
+	fn route(&self, key: &[u8]) -> usize {
+		let index = shard_index(key, self.txns.len());
+		self.touched[index].store(true, Ordering::Relaxed);
+		index
+	}
+
+	//获取指定主键的记录的值
+	pub async fn get(&self, key: Bin) -> SResult<Option<Bin>> {
+		let index = self.route(&key);
+		let mut values = self.txns[index].multi_get_sorted(&self.tab.shards[index].0, &[key]).await?;
+		Ok(values.pop().unwrap_or(None))
+	}
+
+	//插入或修改指定主键的记录
+	pub async fn upsert(&self, key: Bin, value: Bin) -> DBResult {
+		self.modify(key, Some(value)).await
+	}
+
+	//删除指定主键的记录
+	pub async fn delete(&self, key: Bin) -> DBResult {
+		self.modify(key, None).await
+	}
+
+	//写入不在事务已写入的分片中则返回CrossShardWrite错误，分片的提交不能跨分片原子完成
+	async fn modify(&self, key: Bin, value: Option<Bin>) -> DBResult {
+		let index = shard_index(&key, self.txns.len());
+		{
+			let mut written = self.written.lock();
+			match *written {
+				Some(shard) if shard != index => {
+					return Err(format!("CrossShardWrite: tab: {}, written: {}, shard: {}",
+									   self.tab.tab.as_str(),
+									   self.tab.shards[shard].0.as_str(),
+									   self.tab.shards[index].0.as_str()));
+				},
+				_ => *written = Some(index),
+			}
+		}
+		let index = self.route(&key);
+		let mut tabkv = TabKV::new(Atom::from("logfile"), self.tab.shards[index].0.clone(), key);
+		tabkv.value = value;
+		self.txns[index].modify(Arc::new(vec![tabkv]), None, false).await
+	}
+
+	//按主键顺序迭代所有分片的记录，每个分片的记录已按主键排序，迭代时归并所有分片
+	//指定起始主键时，升序从不小于起始主键的第一个主键开始，降序从不大于起始主键的第一个主键开始
+	pub async fn iter(&self, key: Option<Bin>, descending: bool) -> SResult<ShardedIter> {
+		let mut heads = Vec::with_capacity(self.txns.len());
+		for (index, txn) in self.txns.iter().enumerate() {
+			let mut it = txn.iter(&self.tab.shards[index].0, key.clone(), descending, None).await?;
+			let head = next_pair(&mut it)?;
+			heads.push((it, head));
+		}
+
+		Ok(ShardedIter(FuseOnErr::new(MergeIter {
+			heads,
+			descending,
+		})))
+	}
+
+	//预提交所有访问过的分片，任意分片预提交失败则回滚所有分片
+	pub async fn prepare(&self, timeout: usize) -> DBResult {
+		for (index, txn) in self.txns.iter().enumerate() {
+			if !self.touched[index].load(Ordering::Relaxed) {
+				continue;
+			}
+			if let Err(e) = txn.prepare(timeout).await {
+				let _ = self.rollback().await;
+				return Err(format!("{}, shard: {}", e, self.tab.shards[index].0.as_str()));
+			}
+		}
+
+		Ok(())
+	}
+
+	//先依次提交只读的分片，最后提交写入的分片，某个分片提交失败则回滚之后未提交的分片
+	//写入的分片最后提交，所以提交失败时事务的写入都没有提交
+	pub async fn commit(&self) -> DBResult {
+		let written = *self.written.lock();
+		let mut order: Vec<usize> = (0..self.txns.len()).filter(|index| {
+			self.touched[*index].load(Ordering::Relaxed) && Some(*index) != written
+		}).collect();
+		order.extend(written);
+
+		for (pos, index) in order.iter().enumerate() {
+			if let Err(e) = self.txns[*index].commit().await {
+				for rest in order[pos + 1..].iter() {
+					let _ = self.txns[*rest].rollback().await;
+				}
+				return Err(format!("{}, shard: {}", e, self.tab.shards[*index].0.as_str()));
+			}
+		}
+
+		Ok(())
+	}
+
+	//回滚所有访问过的分片
+	pub async fn rollback(&self) -> DBResult {
+		for (index, txn) in self.txns.iter().enumerate() {
+			if self.touched[index].load(Ordering::Relaxed) {
+				txn.rollback().await?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+//获取分片迭代器的下一条记录，迭代结束则返回None
+fn next_pair(it: &mut Box<dyn Iter<Item = (Bin, Bin)> + Send>) -> SResult<Option<(Bin, Bin)>> {
+	match it.next() {
+		None => Ok(None),
+		Some(r) => r,
+	}
+}
+
+/*
+* 分片表的迭代器，任意分片读取失败后只返回一次错误，之后总是返回None
+*/
+pub struct ShardedIter(FuseOnErr<MergeIter>);
+
+impl Iter for ShardedIter {
+	type Item = (Bin, Bin);
+	fn next(&mut self) -> Option<NextResult<Self::Item>> {
+		self.0.next()
+	}
+}
+
+/*
+* 分片表的归并迭代器，每次返回所有分片的当前记录中主键最小的记录，降序时返回主键最大的记录
+*/
+struct MergeIter {
+	heads:		Vec<(Box<dyn Iter<Item = (Bin, Bin)> + Send>, Option<(Bin, Bin)>)>,	//每个分片的迭代器和当前记录
+	descending:	bool,																//是否降序
+}
+
+impl Iter for MergeIter {
+	type Item = (Bin, Bin);
+	fn next(&mut self) -> Option<NextResult<Self::Item>> {
+		let mut selected: Option<(usize, Bon)> = None;
+		for (index, (_, head)) in self.heads.iter().enumerate() {
+			let key = match head {
+				None => continue,
+				Some((key, _)) => Bon::new(key.clone()),
+			};
+			let better = match &selected {
+				None => true,
+				Some((_, current)) if self.descending => key > *current,
+				Some((_, current)) => key < *current,
+			};
+			if better {
+				selected = Some((index, key));
+			}
+		}
+
+		let index = match selected {
+			None => return Some(Ok(None)),
+			Some((index, _)) => index,
+		};
+		let (it, head) = &mut self.heads[index];
+		let pair = head.take();
+		match next_pair(it) {
+			Err(e) => return Some(Err(e)),
+			Ok(next) => *head = next,
+		}
+
+		Some(Ok(pair))
+	}
+}
\ No newline at end of file
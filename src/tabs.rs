@@ -110,6 +110,12 @@ impl TabLog {
 		self.meta_names.insert(tab_name.clone()); //插入创建、修改或删除表时的表名称，自动去重
 	}
 
+	//判断指定表是否是在当前元信息事务中创建的表
+	fn is_created(&self, tab_name: &Atom) -> bool {
+		self.old_map.get(tab_name).is_none()
+			&& self.alter_logs.get(&(tab_name.clone(), 0)).map_or(false, |meta| meta.is_some())
+	}
+
 	//创建表事务，包括元信息表和其它表的表事务
 	pub async fn build(&self, ware: BuildDbType, tab_name: &Atom, id: &Guid, writable: bool) -> SResult<TxnType> {
 		match self.map.get(tab_name) {
@@ -132,7 +138,13 @@ impl TabLog {
 										}
 									}
 									BuildDbType::LogFileDB => {
-										match LogFileDB::open(tab_name).await {
+										//本事务中创建的表，元信息提交前也可以读写
+										let r = if self.is_created(tab_name) {
+											LogFileDB::open_created(tab_name).await
+										} else {
+											LogFileDB::open(tab_name).await
+										};
+										match r {
 											Ok(t) => {
 												var.tab_type = TabType::LogFileTab(t);
 												var.wait = None;
@@ -157,7 +169,7 @@ impl TabLog {
 						Ok(TxnType::MemTxn(Arc::new(t.transaction(&id, writable).await)))
 					}
 					TabType::LogFileTab(t) => {
-						Ok(TxnType::LogFileTxn(Arc::new(t.transaction(&id, writable).await)))
+						Ok(TxnType::LogFileTxn(Arc::new(t.transaction(&id, writable).await?)))
 					}
 					TabType::Unkonwn => Err(String::from("unknown tab type"))
 				}
@@ -192,7 +204,7 @@ impl Prepare{
 			match o_rwlog.get(key) {
 				Some(RwLog::Read) => match log_type {
 					RwLog::Read => return Ok(()),
-					RwLog::Write(_) => {
+					RwLog::Write(_) | RwLog::Increment(_) => {
 						debug!("expect read log type, found write log type");
 						let key_str = format!("{:?}", key);
 						return Err(String::from("prepare conflicted rw") + key_str.as_str());
@@ -209,7 +221,7 @@ impl Prepare{
 						let key_str = format!("{:?}", key);
 						return Err(String::from("previous read log exist") + key_str.as_str());
 					}
-					RwLog::Write(_) => {
+					RwLog::Write(_) | RwLog::Increment(_) => {
 						debug!("previous write log exist, key = {:?}", key);
 						let key_str = format!("{:?}", key);
 						return Err(String::from("previous write log exist") + key_str.as_str());
@@ -225,6 +237,19 @@ impl Prepare{
 					let key_str = format!("{:?}", key);
 					return Err(String::from("unexpected meta log type2") + key_str.as_str());
 				}
+				Some(RwLog::Increment(_)) => match log_type {
+					//累加操作之间可以交换顺序，不冲突
+					RwLog::Increment(_) => return Ok(()),
+					RwLog::Meta(_) => {
+						let key_str = format!("{:?}", key);
+						return Err(String::from("unexpected meta log type3") + key_str.as_str());
+					}
+					_ => {
+						debug!("previous increment log exist, key = {:?}", key);
+						let key_str = format!("{:?}", key);
+						return Err(String::from("previous increment log exist") + key_str.as_str());
+					}
+				},
 				None => return Ok(()),
 			}
 		}
@@ -0,0 +1,89 @@
+#![cfg(feature = "admin")]
+
+use std::sync::Arc;
+use std::net::{SocketAddr, TcpStream};
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::TabMeta;
+use pi_db::fork::ALL_TABLES;
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB};
+use pi_db::admin::start_admin;
+
+#[test]
+fn test_admin_stats() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &Atom::from("./testlogfile/admin"), Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let _ = s.send(ALL_TABLES.lock().await.len());
+	});
+
+	let tabs = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(start_admin("127.0.0.1:0", "").is_err());
+	let addr = start_admin("127.0.0.1:0", "admin-token").unwrap();
+
+	//没有令牌或令牌错误的请求被拒绝，不会执行
+	let response = request(addr, "GET /stats", None);
+	assert!(response.starts_with("HTTP/1.1 401 Unauthorized"));
+	assert!(response.contains("WWW-Authenticate: Bearer"));
+	let response = request(addr, "POST /collect", Some("wrong-token"));
+	assert!(response.starts_with("HTTP/1.1 401 Unauthorized"));
+
+	let response = request(addr, "GET /stats", Some("admin-token"));
+	assert!(response.starts_with("HTTP/1.1 200 OK"));
+	assert!(response.contains("application/json"));
+	let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+	assert!(body.starts_with(&format!("{{\"tabs\":{},", tabs)));
+
+	//执行失败的请求不返回200
+	let response = request(addr, "POST /split?tab=.%2Ftestlogfile%2Fadmin_missing", Some("admin-token"));
+	assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+	let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+	assert!(body.starts_with("{\"ok\":false,\"error\":\"TabNotFound:"));
+
+	//未知表的统计信息返回404
+	let response = request(addr, "GET /tab_stats?tab=.%2Ftestlogfile%2Fadmin_missing", Some("admin-token"));
+	assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+	let response = request(addr, "GET /tab_stats?tab=.%2Ftestlogfile%2Fadmin", Some("admin-token"));
+	assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+	//没有发送请求的连接不阻塞其它连接
+	let idle = TcpStream::connect(addr).unwrap();
+	let response = request(addr, "GET /health", Some("admin-token"));
+	assert!(response.starts_with("HTTP/1.1 200 OK"));
+	drop(idle);
+
+	//整理在后台执行，立即返回202
+	let response = request(addr, "POST /collect", Some("admin-token"));
+	assert!(response.starts_with("HTTP/1.1 202 Accepted"));
+}
+
+//发送一个管理请求，返回完整的响应
+fn request(addr: SocketAddr, line: &str, token: Option<&str>) -> String {
+	let mut stream = TcpStream::connect(addr).unwrap();
+	let auth = token.map(|token| format!("Authorization: Bearer {}\r\n", token)).unwrap_or_default();
+	stream.write_all(format!("{} HTTP/1.1\r\nHost: localhost\r\n{}\r\n", line, auth).as_bytes()).unwrap();
+	let mut response = String::new();
+	stream.read_to_string(&mut response).unwrap();
+	response
+}
\ No newline at end of file
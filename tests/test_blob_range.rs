@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use bon::WriteBuffer;
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::{TabKV, TabMeta};
+use pi_db::fork::TabConfig;
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB};
+
+#[test]
+fn test_blob_get_range() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/blob_range_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//超过64字节的值外置存储
+		let file = LogFileDB::open(&tab).await.unwrap();
+		LogFileDB::set_tab_config(&tab, TabConfig {
+			blob_inline_max: Some(64),
+			..TabConfig::default()
+		}).await.unwrap();
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"video", 0..5);
+		let key = Arc::new(wb.bytes);
+		let value: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(Arc::new(value.clone())),
+			index: 0,
+		}], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//外置存储的值已在内存表中，范围读取不读取外置存储文件，只返回指定范围的字节
+		let gen = GuidGen::new(0, 0);
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let read = LogFileDB::blob_read_bytes();
+		let middle = txn.get_range(&tab, key.clone(), 500000, 4096).await.unwrap().unwrap();
+		let read = LogFileDB::blob_read_bytes() - read;
+
+		//事务中未提交的值同样从事务的内存表中截取
+		let writer = file.transaction(&gen.gen(0), true).await.unwrap();
+		writer.modify(Arc::new(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(Arc::new(vec![9u8; 1024])),
+			index: 0,
+		}]), None, false).await.unwrap();
+		let uncommitted = writer.get_range(&tab, key.clone(), 1000, 100).await.unwrap().unwrap();
+		writer.rollback().await.unwrap();
+
+		//范围超出值的长度则截断，不存在的主键返回None
+		let tail = txn.get_range(&tab, key.clone(), value.len() - 10, 100).await.unwrap().unwrap();
+		let beyond = txn.get_range(&tab, key.clone(), value.len() + 10, 100).await.unwrap().unwrap();
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"missing", 0..7);
+		let missing = txn.get_range(&tab, Arc::new(wb.bytes), 0, 100).await.unwrap();
+
+		let _ = s.send((middle.to_vec(), read, uncommitted.to_vec(), tail.to_vec(), beyond.len(), missing.is_none(), value));
+	});
+
+	let (middle, read, uncommitted, tail, beyond, missing, value) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(middle, value[500000..504096].to_vec());
+	assert_eq!(read, 0);
+	assert_eq!(uncommitted, vec![9u8; 24]);
+	assert_eq!(tail, value[value.len() - 10..].to_vec());
+	assert_eq!(beyond, 0);
+	assert!(missing);
+}
\ No newline at end of file
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::TabMeta;
+use pi_db::fork::{ALL_TABLES, TableMetaInfo, fork_chain_from};
+use pi_db::log_file_db::{STORE_RUNTIME, LAZY_CATALOG, LogFileDB};
+
+//启动时的目录锁次数只在本测试中统计，与其它会获取ALL_TABLES锁的测试分开运行
+#[test]
+fn test_startup_catalog_registration() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tabs: Vec<Atom> = (0..200).map(|i| Atom::from(format!("./testlogfile/catalog_{}_{}", now, i))).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in tabs.iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//立即注册，重新打开数据库时所有表的元信息只在一次获取ALL_TABLES的锁时注册
+		LAZY_CATALOG.store(false, Ordering::Relaxed);
+		let _ = LogFileDB::try_new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await.unwrap();
+		let eager_locks = LogFileDB::startup_catalog_locks();
+		let eager_pending = ALL_TABLES.pending_len();
+
+		//延迟注册，重新打开数据库时不获取ALL_TABLES的锁，每个表的元信息在第一次查询或打开时注册
+		LAZY_CATALOG.store(true, Ordering::Relaxed);
+		let db = LogFileDB::try_new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await.unwrap();
+		let lazy_locks = LogFileDB::startup_catalog_locks();
+		let lazy_pending = ALL_TABLES.pending_len();
+		let mut found = 0;
+		for tab in tabs.iter() {
+			if LogFileDB::is_leaf(tab).await.unwrap() && db.tab_info(tab).await.is_some() && LogFileDB::open(tab).await.is_ok() {
+				found += 1;
+			}
+		}
+		//只注册了查询和打开的表
+		let opened_pending = ALL_TABLES.pending_len();
+		let registered = {
+			let tables = ALL_TABLES.lock().await;
+			tabs.iter().all(|tab| tables.contains_key(tab))
+		};
+		let pending = ALL_TABLES.pending_len();
+		LAZY_CATALOG.store(false, Ordering::Relaxed);
+
+		let _ = s.send((eager_locks, eager_pending, lazy_locks, lazy_pending, found, opened_pending, registered, pending));
+	});
+
+	let (eager_locks, eager_pending, lazy_locks, lazy_pending, found, opened_pending, registered, pending) = r.recv_timeout(Duration::from_secs(60)).unwrap();
+	assert_eq!(eager_locks, 1);
+	assert_eq!(eager_pending, 0);
+	assert_eq!(lazy_locks, 0);
+	assert!(lazy_pending >= 200);
+	assert_eq!(found, 200);
+	assert_eq!(opened_pending, lazy_pending - 200);
+	assert!(registered);
+	assert_eq!(pending, 0);
+}
+
+#[test]
+fn test_fork_chain_missing_parent() {
+	let mut tables = HashMap::new();
+	let root = TableMetaInfo::new(Atom::from("root"), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str));
+	let mut child = TableMetaInfo::new(Atom::from("child"), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str));
+	child.parent = Some(Atom::from("root"));
+	child.parent_log_id = Some(1);
+	let mut orphan = TableMetaInfo::new(Atom::from("orphan"), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str));
+	orphan.parent = Some(Atom::from("skipped"));
+	orphan.parent_log_id = Some(1);
+	tables.insert(root.tab_name.clone(), root);
+	tables.insert(child.tab_name.clone(), child);
+	tables.insert(orphan.tab_name.clone(), orphan);
+
+	//父表的元信息存在时返回完整的分叉链，不存在时返回错误而不是崩溃
+	let chains = fork_chain_from(&tables, &Atom::from("child")).unwrap();
+	assert_eq!(chains.len(), 2);
+	assert_eq!(chains[1].tab_name, Atom::from("root"));
+	let e = fork_chain_from(&tables, &Atom::from("orphan")).unwrap_err();
+	assert!(e.starts_with("ParentUnavailable"));
+	assert!(fork_chain_from(&tables, &Atom::from("missing")).unwrap().is_empty());
+}
\ No newline at end of file
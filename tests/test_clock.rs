@@ -0,0 +1,58 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::TabMeta;
+use pi_db::fork::ALL_TABLES;
+use pi_db::clock::{ManualClock, reset_clock};
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB};
+
+//注入手动时钟，推进时钟后修改表的元信息，元信息历史的时间戳来自手动时钟，不需要真实的等待
+#[test]
+fn test_manual_clock() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let clock = Arc::new(ManualClock::new(1_000_000));
+	LogFileDB::set_clock(clock.clone());
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/manual_clock_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+		let created = ALL_TABLES.lock().await.get(&tab).unwrap().history.last().unwrap().timestamp;
+
+		//推进一小时后修改表的元信息
+		let advanced = clock.advance(3_600_000);
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Bin);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+		let evolved = ALL_TABLES.lock().await.get(&tab).unwrap().history.iter().map(|v| v.timestamp).collect::<Vec<_>>();
+
+		let _ = s.send((created, advanced, evolved));
+	});
+
+	let (created, advanced, evolved) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	reset_clock();
+	assert_eq!(created, 1_000_000);
+	assert_eq!(advanced, 4_600_000);
+	assert_eq!(evolved, vec![1_000_000, 4_600_000]);
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use bon::WriteBuffer;
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::TabMeta;
+use pi_db::fork::TabConfig;
+use pi_db::clock::{ManualClock, reset_clock};
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB};
+
+//计数器表的计数器在周期内累加，推进时钟越过周期后读取为0，下次累加从新周期的0开始，溢出时饱和
+#[test]
+fn test_counter_window() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let clock = Arc::new(ManualClock::new(6_000_000 + 1_000));
+	LogFileDB::set_clock(clock.clone());
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/counter_window_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Bin);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = GuidGen::new(0, 0);
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"legacy", 0..6);
+		let legacy_key = Arc::new(wb.bytes);
+
+		//开启重置周期前写入的不重置的计数器
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.increment(legacy_key.clone(), 7).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+
+		//每分钟重置的计数器
+		LogFileDB::alter_tab_config(&tab, TabConfig { counter_window: Some(60_000), ..TabConfig::default() }).await.unwrap();
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"bucket", 0..6);
+		let key = Arc::new(wb.bytes);
+		let increment_key = |key: Arc<Vec<u8>>, delta: i64| {
+			let file = file.clone();
+			let id = gen.gen(0);
+			async move {
+				let txn = file.transaction(&id, true).await.unwrap();
+				txn.increment(key.clone(), delta).await.unwrap();
+				let pending = txn.counter(key).await.unwrap();
+				txn.prepare(1000).await.unwrap();
+				txn.commit().await.unwrap();
+				pending
+			}
+		};
+		let increment = |delta: i64| increment_key(key.clone(), delta);
+		let read_key = |key: Arc<Vec<u8>>| {
+			let file = file.clone();
+			let id = gen.gen(0);
+			async move {
+				file.transaction(&id, false).await.unwrap().counter(key).await.unwrap()
+			}
+		};
+		let read = || read_key(key.clone());
+
+		//旧计数器作为已过期周期的计数读取为0，累加后按周期计数
+		let legacy = read_key(legacy_key.clone()).await;
+		let legacy_restarted = increment_key(legacy_key.clone(), 4).await;
+
+		increment(5).await;
+		let pending = increment(3).await;
+		let counted = read().await;
+
+		//同一周期内推进时钟不重置，越过周期边界后读取为0
+		clock.advance(30_000);
+		let same_window = read().await;
+		clock.advance(30_000);
+		let reset = read().await;
+		let restarted = increment(2).await;
+		let after_restart = read().await;
+
+		//计数溢出时饱和到最大值
+		increment(i64::MAX).await;
+		let saturated = read().await;
+
+		//新周期中递减溢出时饱和到最小值
+		clock.advance(60_000);
+		increment(-5).await;
+		increment(i64::MIN).await;
+		let saturated_min = read().await;
+
+		let _ = s.send((legacy, legacy_restarted, pending, counted, same_window, reset, restarted, after_restart, saturated, saturated_min));
+	});
+
+	let (legacy, legacy_restarted, pending, counted, same_window, reset, restarted, after_restart, saturated, saturated_min) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	reset_clock();
+	assert_eq!(pending, 8);
+	assert_eq!(counted, 8);
+	assert_eq!(same_window, 8);
+	assert_eq!(reset, 0);
+	assert_eq!(restarted, 2);
+	assert_eq!(after_restart, 2);
+	assert_eq!(saturated, i64::MAX);
+	assert_eq!(saturated_min, i64::MIN);
+	assert_eq!(legacy, 0);
+	assert_eq!(legacy_restarted, 4);
+}
\ No newline at end of file
@@ -0,0 +1,42 @@
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use atom::Atom;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use pi_db::fork::ALL_TABLES;
+use pi_db::log_file_db::{STORE_RUNTIME, DB_META_TAB_NAME, LogFileDB};
+
+//在新的空目录中打开数据库，创建空的元信息表，再次打开时不是新的数据库
+#[test]
+fn test_open_fresh_directory() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+	let dir = format!("./testlogfile/first_run_{}", now);
+	env::set_var("DB_PATH", &dir);
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let first = match LogFileDB::try_new(Atom::from(dir.clone()), 1024 * 1024 * 1024).await {
+			Err(e) => Err(e),
+			Ok(db) => Ok((db.list().await.count(), LogFileDB::health_report().new_database, ALL_TABLES.lock().await.len())),
+		};
+		let meta_created = Path::new(&dir).join(DB_META_TAB_NAME).is_dir();
+		let second = LogFileDB::try_new(Atom::from(dir.clone()), 1024 * 1024 * 1024).await
+			.map(|_| LogFileDB::health_report().new_database);
+
+		let _ = s.send((first, meta_created, second));
+	});
+
+	let (first, meta_created, second) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	env::remove_var("DB_PATH");
+	assert_eq!(first, Ok((0, true, 0)));
+	assert!(meta_created);
+	assert_eq!(second, Ok(false));
+}
\ No newline at end of file
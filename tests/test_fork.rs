@@ -1,16 +1,17 @@
 use std::{collections::HashMap, sync::Arc};
+use std::path::PathBuf;
 use std::thread;
 use std::time::Duration;
 use std::sync::Mutex;
 use atom::Atom;
 use bon::{Encode, Decode, WriteBuffer, ReadBuffer, ReadBonErr};
 use pi_db::{log_file_db, mgr::{ DatabaseWare, Mgr }};
-use pi_db::log_file_db::LogFileDB;
+use pi_db::log_file_db::{LogFileDB, MAX_FORK_DEPTH, LENIENT_PARENT_LOAD};
 use sinfo;
 use guid::GuidGen;
 use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
 use pi_db::db::{TabKV, TabMeta};
-use pi_db::fork::TableMetaInfo;
+use pi_db::fork::{ALL_TABLES, TableMetaInfo, ForkMode};
 
 use log_file_db::STORE_RUNTIME;
 
@@ -370,4 +371,1014 @@ fn test_delete() {
 	});
 
 	thread::sleep(Duration::from_secs(3));
+}
+
+
+
+//分叉表的parent_log_id为最小分裂索引0时，加载应该返回错误而不是崩溃
+#[test]
+fn test_fork_min_parent_log_id() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let parent = Atom::from("./testlogfile/min_log_id_parent");
+		let child = Atom::from("./testlogfile/min_log_id_child");
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+
+		let mut child_info = TableMetaInfo::new(child.clone(), meta.clone());
+		child_info.parent = Some(parent.clone());
+		child_info.parent_log_id = Some(0);
+		{
+			let mut lock = ALL_TABLES.lock().await;
+			lock.insert(parent.clone(), TableMetaInfo::new(parent.clone(), meta));
+			lock.insert(child.clone(), child_info);
+		}
+
+		let res = LogFileDB::open(&child).await;
+		println!("open child with parent_log_id 0: {:?}", res.as_ref().err());
+		assert!(res.is_err());
+
+		child_info = TableMetaInfo::new(child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str));
+		child_info.parent = Some(parent.clone());
+		child_info.parent_log_id = None;
+		ALL_TABLES.lock().await.insert(child.clone(), child_info);
+		assert!(LogFileDB::open(&child).await.is_err());
+	});
+
+	thread::sleep(Duration::from_secs(3));
+}
+
+
+
+//分叉时父表引用计数和分叉表的元信息在同一批次中写入元信息表，重新加载后两者必须同时可见
+#[test]
+fn test_fork_meta_batch() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+	let (s, r) = crossbeam_channel::bounded(1);
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let parent = Atom::from("./testlogfile/batch_fork_parent");
+		let child = Atom::from(format!("./testlogfile/batch_fork_child{:?}", now));
+		//每个崩溃表保存元信息表在一个截断位置的日志文件副本
+		let crashes: Vec<Atom> = (0..3).map(|i| Atom::from(format!("./testlogfile/batch_fork_crash{:?}_{}", now, i))).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &parent, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		for crash in crashes.iter() {
+			tr.alter(&Atom::from("logfile"), crash, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		let ref_count = ALL_TABLES.lock().await.get(&parent).unwrap().ref_count;
+
+		//分叉前后元信息表的日志文件，分叉的元信息批次在两次的最新日志文件长度之间
+		let meta_path = PathBuf::from(std::env::var("DB_PATH").unwrap_or("./".to_string())).join("tabs_meta");
+		let before = meta_logs(&meta_path);
+
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.fork_tab(Atom::from("logfile"), parent.clone(), child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		let prepared = tr2.prepare().await.is_ok();
+		let committed = tr2.commit().await.is_ok();
+		let after = meta_logs(&meta_path);
+
+		//模拟在批次写入前、写入一半和写入后崩溃，截断元信息表最新的日志文件的副本，再从副本加载
+		let (last, end) = after.last().unwrap().clone();
+		let start = before.iter().find(|(path, _)| *path == last).map(|(_, len)| *len).unwrap_or(0);
+		let mut parent_key = WriteBuffer::new();
+		parent.encode(&mut parent_key);
+		let mut child_key = WriteBuffer::new();
+		child.encode(&mut child_key);
+		let mut recovered = Vec::new();
+		for (crash, len) in crashes.iter().zip(vec![start, start + (end - start) / 2, end]) {
+			std::fs::create_dir_all(crash.as_str()).unwrap();
+			for entry in std::fs::read_dir(crash.as_str()).unwrap() {
+				std::fs::remove_file(entry.unwrap().path()).unwrap();
+			}
+			for (path, _) in after.iter() {
+				let to = PathBuf::from(crash.as_str()).join(path.file_name().unwrap());
+				std::fs::copy(path, &to).unwrap();
+				if *path == last {
+					std::fs::OpenOptions::new().write(true).open(&to).unwrap().set_len(len).unwrap();
+				}
+			}
+
+			let file = LogFileDB::open(crash).await.unwrap();
+			let parent_refs = file.get_with_meta(&Arc::new(parent_key.bytes.clone())).await.unwrap()
+				.map(|(value, _)| TableMetaInfo::decode(&mut ReadBuffer::new(&value, 0)).unwrap().ref_count);
+			let has_child = file.get_with_meta(&Arc::new(child_key.bytes.clone())).await.unwrap().is_some();
+			recovered.push((parent_refs, has_child));
+		}
+
+		//清空缓存的元信息，从元信息表重新加载
+		ALL_TABLES.lock().await.clear();
+		let _db = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let (reload_refs, reload_parent) = {
+			let lock = ALL_TABLES.lock().await;
+			(lock.get(&parent).unwrap().ref_count, lock.get(&child).unwrap().parent.clone())
+		};
+
+		let _ = s.send((prepared, committed, ref_count, recovered, reload_refs, reload_parent, parent));
+	});
+
+	let (prepared, committed, ref_count, recovered, reload_refs, reload_parent, parent) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(prepared && committed);
+	assert_eq!(reload_refs, ref_count + 1);
+	assert_eq!(reload_parent, Some(parent));
+	//崩溃在批次写入前，分叉的元信息全部不存在，崩溃在批次写入后，全部存在
+	assert_eq!(recovered[0], (Some(ref_count), false));
+	assert_eq!(recovered[2], (Some(ref_count + 1), true));
+	//崩溃在批次写入一半，父表引用计数的更新和子表的元信息要么都存在，要么都不存在
+	for (parent_refs, has_child) in recovered {
+		assert_eq!(parent_refs == Some(ref_count + 1), has_child);
+	}
+}
+
+//获取元信息表目录下的所有日志文件和文件大小，按日志文件id升序
+fn meta_logs(dir: &PathBuf) -> Vec<(PathBuf, u64)> {
+	let mut logs: Vec<(usize, PathBuf, u64)> = std::fs::read_dir(dir).unwrap()
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| {
+			let path = entry.path();
+			let id = path.file_name().and_then(|name| name.to_str()).and_then(|name| name.parse::<usize>().ok())?;
+			Some((id, path, entry.metadata().ok()?.len()))
+		})
+		.collect();
+	logs.sort_by_key(|(id, _, _)| *id);
+	logs.into_iter().map(|(_, path, len)| (path, len)).collect()
+}
+
+
+
+//获取表目录中所有文件的总大小
+fn tab_log_size(tab: &Atom) -> u64 {
+	std::fs::read_dir(tab.as_str()).unwrap()
+		.filter_map(|entry| entry.ok().and_then(|entry| entry.metadata().ok()))
+		.map(|meta| meta.len())
+		.sum()
+}
+
+//子表覆盖父表，子表删除了父表中存在的主键，重新加载后主键仍然不存在
+#[test]
+fn test_fork_child_tombstone() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let parent = Atom::from(format!("./testlogfile/tombstone_parent{:?}", suffix));
+		let child = Atom::from(format!("./testlogfile/tombstone_child{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &parent, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut k = WriteBuffer::new();
+		k.write_bin(b"tombstone", 0..9);
+		let key = Arc::new(k.bytes);
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: parent.clone(),
+			key: key.clone(),
+			value: Some(key.clone()),
+			index: 0
+		}], None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.fork_tab(Atom::from("logfile"), parent.clone(), child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		tr3.prepare().await;
+		tr3.commit().await;
+
+		//在子表中删除父表中存在的主键
+		let mut tr4 = mgr.transaction(true, Some(rt.clone())).await;
+		tr4.modify(vec![TabKV::new(Atom::from("logfile"), child.clone(), key.clone())], None, false).await;
+		tr4.prepare().await;
+		tr4.commit().await;
+
+		//读修复只在第一次重新加载时写入删除记录，之后重新加载不再增长子表的日志文件
+		let file = LogFileDB::open(&child).await.unwrap();
+		let mut sizes = Vec::new();
+		for _ in 0..3 {
+			assert!(file.reload().await.is_ok());
+			assert!(file.1.read(&key).is_none());
+			sizes.push(tab_log_size(&child));
+
+			let mut tr5 = mgr.transaction(false, Some(rt.clone())).await;
+			let r = tr5.query(vec![TabKV::new(Atom::from("logfile"), child.clone(), key.clone())], None, false).await.unwrap();
+			assert_eq!(r[0].value, None);
+			tr5.prepare().await;
+			tr5.commit().await;
+		}
+
+		assert!(sizes[0] > 0);
+		assert_eq!(sizes[0], sizes[1]);
+		assert_eq!(sizes[1], sizes[2]);
+
+		//整理后清除已修复的主键，重新加载时重新检查并记录仍然覆盖父表值的主键
+		let repairs = std::path::PathBuf::from(format!("{}.repairs", child.as_str()));
+		assert!(repairs.exists());
+		file.checkpoint().await.unwrap();
+		assert!(!repairs.exists());
+		assert!(file.reload().await.is_ok());
+		assert!(file.1.read(&key).is_none());
+		assert!(repairs.exists());
+
+		//父表的值不受影响
+		let mut tr6 = mgr.transaction(false, Some(rt.clone())).await;
+		let r = tr6.query(vec![TabKV::new(Atom::from("logfile"), parent.clone(), key.clone())], None, false).await.unwrap();
+		assert_eq!(r[0].value, Some(key.clone()));
+		tr6.prepare().await;
+		tr6.commit().await;
+	});
+
+	thread::sleep(Duration::from_secs(3));
+}
+
+
+
+//分叉深度达到限制后，继续分叉会被拒绝
+#[test]
+fn test_max_fork_depth() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let root = Atom::from(format!("./testlogfile/depth{:?}_0", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &root, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		MAX_FORK_DEPTH.store(2, std::sync::atomic::Ordering::Relaxed);
+		let mut parent = root;
+		for depth in 1..4 {
+			let child = Atom::from(format!("./testlogfile/depth{:?}_{:?}", suffix, depth));
+			let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+			tr2.fork_tab(Atom::from("logfile"), parent.clone(), child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+			let r = tr2.prepare().await;
+			if depth <= 2 {
+				assert!(r.is_ok());
+				assert!(tr2.commit().await.is_ok());
+			} else {
+				assert!(r.err().unwrap().starts_with("ForkTooDeep"));
+				tr2.rollback().await;
+			}
+			parent = child;
+		}
+		MAX_FORK_DEPTH.store(0, std::sync::atomic::Ordering::Relaxed);
+	});
+
+	thread::sleep(Duration::from_secs(3));
+}
+
+
+
+//导出三层分叉拓扑，清除元信息后重新导入，分叉关系和引用计数保持一致
+#[test]
+fn test_export_import_topology() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let prefix = format!("./testlogfile/topology{:?}_", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis());
+		let tabs: Vec<Atom> = (0..3).map(|i| Atom::from(format!("{}{:?}", prefix, i))).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &tabs[0], Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+		for i in 1..3 {
+			let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+			tr2.fork_tab(Atom::from("logfile"), tabs[i - 1].clone(), tabs[i].clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+			tr2.prepare().await;
+			tr2.commit().await;
+		}
+
+		let exported: Vec<TableMetaInfo> = LogFileDB::export_topology().await.into_iter().filter(|info| info.tab_name.as_str().starts_with(&prefix)).collect();
+		assert_eq!(exported.len(), 3);
+
+		//清除三个表的元信息
+		let meta_tab = LogFileDB::open(&Atom::from("tabs_meta")).await.unwrap();
+		for tab in tabs.iter() {
+			let mut wb = WriteBuffer::new();
+			tab.encode(&mut wb);
+			meta_tab.1.remove(wb.bytes).await.unwrap();
+			ALL_TABLES.lock().await.remove(tab);
+		}
+
+		//缺少父表的拓扑无法导入
+		assert!(LogFileDB::import_topology(exported[1..].to_vec()).await.is_err());
+
+		LogFileDB::import_topology(exported.clone()).await.unwrap();
+		let imported: Vec<TableMetaInfo> = LogFileDB::export_topology().await.into_iter().filter(|info| info.tab_name.as_str().starts_with(&prefix)).collect();
+		assert_eq!(imported, exported);
+		assert_eq!(imported[0].ref_count, 1);
+		assert_eq!(imported[1].parent, Some(tabs[0].clone()));
+		assert_eq!(imported[1].ref_count, 1);
+		assert_eq!(imported[2].parent, Some(tabs[1].clone()));
+		assert_eq!(imported[2].ref_count, 0);
+	});
+
+	thread::sleep(Duration::from_secs(3));
+}
+
+
+
+//分离分叉表后删除父表，分叉表仍然可以读取所有数据
+#[test]
+fn test_detach_fork() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let parent = Atom::from(format!("./testlogfile/detach_parent{:?}", suffix));
+		let child = Atom::from(format!("./testlogfile/detach_child{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &parent, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let make_item = |tab: &Atom, i: usize| {
+			let mut k = WriteBuffer::new();
+			let key = format!("detach{:?}", i);
+			k.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(k.bytes.clone()),
+				value: Some(Arc::new(k.bytes)),
+				index: 0
+			}
+		};
+
+		//父表写入前5条记录，分叉后子表写入后5条记录
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify((0..5).map(|i| make_item(&parent, i)).collect(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.fork_tab(Atom::from("logfile"), parent.clone(), child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		tr3.prepare().await;
+		tr3.commit().await;
+
+		let mut tr4 = mgr.transaction(true, Some(rt.clone())).await;
+		tr4.modify((5..10).map(|i| make_item(&child, i)).collect(), None, false).await;
+		tr4.prepare().await;
+		tr4.commit().await;
+
+		LogFileDB::detach_fork(&child).await.unwrap();
+		{
+			let lock = ALL_TABLES.lock().await;
+			assert_eq!(lock.get(&child).unwrap().parent, None);
+			assert_eq!(lock.get(&parent).unwrap().ref_count, 0);
+		}
+
+		//删除父表
+		let mut tr5 = mgr.transaction(true, Some(rt.clone())).await;
+		assert!(tr5.alter(&Atom::from("logfile"), &parent, None).await.is_ok());
+		tr5.prepare().await;
+		assert!(tr5.commit().await.is_ok());
+
+		//重新加载分叉表，不再依赖父表
+		let file = LogFileDB::open(&child).await.unwrap();
+		assert!(file.reload().await.is_ok());
+		let mut tr6 = mgr.transaction(false, Some(rt.clone())).await;
+		let items: Vec<TabKV> = (0..10).map(|i| make_item(&child, i)).collect();
+		let r = tr6.query(items.iter().map(|item| TabKV::new(item.ware.clone(), item.tab.clone(), item.key.clone())).collect(), None, false).await.unwrap();
+		for (item, r) in items.iter().zip(r.iter()) {
+			assert_eq!(item.value, r.value);
+		}
+		tr6.prepare().await;
+		tr6.commit().await;
+	});
+
+	thread::sleep(Duration::from_secs(3));
+}
+
+
+
+//在一个事务中从不同的父表批量分叉三个子表
+#[test]
+fn test_fork_tabs_batch() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let gen = GuidGen::new(0, 0);
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let parents: Vec<Atom> = (0..3).map(|i| Atom::from(format!("./testlogfile/batch_parent{:?}_{:?}", i, suffix))).collect();
+		let children: Vec<Atom> = (0..3).map(|i| Atom::from(format!("./testlogfile/batch_child{:?}_{:?}", i, suffix))).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for parent in parents.iter() {
+			tr.alter(&Atom::from("logfile"), parent, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		let file = LogFileDB::open(&parents[0]).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+
+		//任意分叉表名与已存在的表重复，则整个批次被拒绝
+		let specs = vec![
+			(parents[0].clone(), children[0].clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)),
+			(parents[1].clone(), parents[2].clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)),
+		];
+		assert!(txn.fork_tabs(specs).await.is_err());
+		assert!(ALL_TABLES.lock().await.get(&children[0]).is_none());
+
+		let specs = parents.iter().zip(children.iter()).map(|(parent, child)| {
+			(parent.clone(), child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str))
+		}).collect();
+		assert!(txn.fork_tabs(specs).await.is_ok());
+
+		//清空缓存的元信息，从元信息表重新加载，所有分叉表都已创建
+		ALL_TABLES.lock().await.clear();
+		let _db = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let lock = ALL_TABLES.lock().await;
+		for (parent, child) in parents.iter().zip(children.iter()) {
+			assert_eq!(lock.get(parent).unwrap().ref_count, 1);
+			assert_eq!(lock.get(child).unwrap().parent, Some(parent.clone()));
+			assert!(lock.get(child).unwrap().parent_log_id.is_some());
+		}
+	});
+
+	thread::sleep(Duration::from_secs(3));
+}
+
+
+
+//hello表分叉出两层子表，只有最深的分叉表是叶节点，可以删除
+#[test]
+fn test_is_leaf() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = crossbeam_channel::bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let hello = Atom::from(format!("./testlogfile/leaf_hello{:?}", suffix));
+		let fork = Atom::from(format!("./testlogfile/leaf_hello_fork{:?}", suffix));
+		let fork_fork = Atom::from(format!("./testlogfile/leaf_hello_fork_fork{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &hello, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+		for (parent, child) in vec![(hello.clone(), fork.clone()), (fork.clone(), fork_fork.clone())] {
+			let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+			tr2.fork_tab(Atom::from("logfile"), parent, child, TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+			tr2.prepare().await.unwrap();
+			tr2.commit().await.unwrap();
+		}
+
+		let mut leafs = vec![];
+		for tab in vec![&hello, &fork, &fork_fork] {
+			leafs.push((LogFileDB::is_leaf(tab).await.unwrap(), LogFileDB::can_delete(tab).await.unwrap()));
+		}
+		let missing = LogFileDB::is_leaf(&Atom::from(format!("./testlogfile/leaf_missing{:?}", suffix))).await;
+
+		let _ = s.send((leafs, missing));
+	});
+
+	let (leafs, missing) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(leafs, vec![(false, false), (false, false), (true, true)]);
+	assert!(missing.unwrap_err().starts_with("TabNotFound"));
+}
+
+
+
+//分叉后在分叉表中修改部分主键，分叉表的净修改只包含这些主键
+#[test]
+fn test_fork_delta() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = crossbeam_channel::bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let hello = Atom::from(format!("./testlogfile/delta_hello{:?}", suffix));
+		let fork = Atom::from(format!("./testlogfile/delta_hello_fork{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &hello, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let item = |tab: &Atom, key: &str, value: Option<&str>| -> TabKV {
+			let mut k = WriteBuffer::new();
+			k.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(k.bytes),
+				value: value.map(|v| Arc::new(v.as_bytes().to_vec())),
+				index: 0
+			}
+		};
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify((1..6).map(|i| item(&hello, &format!("delta{}", i), Some("parent"))).collect(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.fork_tab(Atom::from("logfile"), hello.clone(), fork.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//分叉后父表的修改不是分叉表的修改
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![item(&hello, "delta4", Some("parent2"))], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//修改、删除和插入是净修改，写入相同的值和删除不存在的主键不是
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![
+			item(&fork, "delta1", Some("child")),
+			item(&fork, "delta2", None),
+			item(&fork, "delta3", Some("parent")),
+			item(&fork, "delta6", Some("child")),
+			item(&fork, "delta9", None),
+		], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let delta = LogFileDB::fork_delta(&fork).await.unwrap();
+		let expect: Vec<(Vec<u8>, Option<Vec<u8>>)> = vec![
+			item(&fork, "delta1", Some("child")),
+			item(&fork, "delta2", None),
+			item(&fork, "delta6", Some("child")),
+		].into_iter().map(|kv| (kv.key.to_vec(), kv.value.map(|v| v.to_vec()))).collect();
+		let delta: Vec<(Vec<u8>, Option<Vec<u8>>)> = delta.into_iter().map(|(k, v)| (k.to_vec(), v.map(|v| v.to_vec()))).collect();
+		let not_fork = LogFileDB::fork_delta(&hello).await.is_err();
+
+		let _ = s.send((delta, expect, not_fork));
+	});
+
+	let (delta, expect, not_fork) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(delta, expect);
+	assert!(not_fork);
+}
+
+//列出表的概要信息，父表和分叉表的记录数量和分叉关系与预期一致
+#[test]
+fn test_list_detailed() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = crossbeam_channel::bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let db = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let ware = DatabaseWare::new_log_file_ware(db.clone());
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let hello = Atom::from(format!("./testlogfile/summary_hello{:?}", suffix));
+		let fork = Atom::from(format!("./testlogfile/summary_hello_fork{:?}", suffix));
+		let other = Atom::from(format!("./testlogfile/summary_other{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in vec![&hello, &other] {
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		let item = |tab: &Atom, key: &str| -> TabKV {
+			let mut k = WriteBuffer::new();
+			k.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(k.bytes),
+				value: Some(Arc::new(key.as_bytes().to_vec())),
+				index: 0
+			}
+		};
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![item(&hello, "a"), item(&hello, "b"), item(&hello, "c"), item(&other, "a")], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.fork_tab(Atom::from("logfile"), hello.clone(), fork.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![item(&fork, "d")], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let summaries: Vec<(Atom, Option<usize>, bool, bool, Option<Atom>, usize)> = db.list_detailed().await.into_iter()
+			.filter(|summary| summary.name == hello || summary.name == fork || summary.name == other)
+			.map(|summary| (summary.name, summary.key_count, summary.byte_size.is_some(), summary.is_leaf, summary.parent, summary.ref_count))
+			.collect();
+
+		let _ = s.send((summaries, hello, fork, other));
+	});
+
+	let (summaries, hello, fork, other) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(summaries, vec![
+		(hello.clone(), Some(3), true, false, None, 1),
+		(fork, Some(4), true, true, Some(hello), 0),
+		(other, Some(1), true, true, None, 0),
+	]);
+}
+
+//父表的日志文件被删除后，重新加载分叉表返回错误，宽松模式下只加载分叉表自己的数据
+#[test]
+fn test_missing_parent_log() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = crossbeam_channel::bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let hello = Atom::from(format!("./testlogfile/missing_hello{:?}", suffix));
+		let fork = Atom::from(format!("./testlogfile/missing_hello_fork{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &hello, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let item = |tab: &Atom, key: &str| -> TabKV {
+			let mut k = WriteBuffer::new();
+			k.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(k.bytes),
+				value: Some(Arc::new(key.as_bytes().to_vec())),
+				index: 0
+			}
+		};
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![item(&hello, "a")], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.fork_tab(Atom::from("logfile"), hello.clone(), fork.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![item(&fork, "b")], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//删除父表的日志文件后重新加载分叉表
+		std::fs::remove_dir_all(hello.as_str()).unwrap();
+		let file = LogFileDB::open(&fork).await.unwrap();
+		let strict = file.reload().await;
+
+		LENIENT_PARENT_LOAD.store(true, std::sync::atomic::Ordering::Relaxed);
+		let lenient = file.reload().await;
+		LENIENT_PARENT_LOAD.store(false, std::sync::atomic::Ordering::Relaxed);
+
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let a = tr.query(vec![TabKV::new(Atom::from("logfile"), fork.clone(), item(&fork, "a").key)], None, false).await.unwrap();
+		let b = tr.query(vec![TabKV::new(Atom::from("logfile"), fork.clone(), item(&fork, "b").key)], None, false).await.unwrap();
+
+		let _ = s.send((strict, lenient, a[0].value.is_some(), b[0].value.is_some()));
+	});
+
+	let (strict, lenient, a, b) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(strict.unwrap_err().starts_with("ParentUnavailable"));
+	assert!(lenient.is_ok());
+	assert!(!a);
+	assert!(b);
+}
+
+//分叉表引用父表在分叉点及之前的日志文件，子孙表通过分叉表间接引用
+#[test]
+fn test_log_file_refs() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = crossbeam_channel::bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let hello = Atom::from(format!("./testlogfile/refs_hello{:?}", suffix));
+		let first = Atom::from(format!("./testlogfile/refs_first{:?}", suffix));
+		let second = Atom::from(format!("./testlogfile/refs_second{:?}", suffix));
+		let grand = Atom::from(format!("./testlogfile/refs_grand{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &hello, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let item = |tab: &Atom, key: &str| -> TabKV {
+			let mut k = WriteBuffer::new();
+			k.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(k.bytes),
+				value: Some(Arc::new(key.as_bytes().to_vec())),
+				index: 0
+			}
+		};
+
+		//hello分叉出first，hello继续写入后再分叉出second，first再分叉出grand
+		for (tab, key, parent, child) in vec![(&hello, "a", &hello, &first), (&hello, "b", &hello, &second), (&first, "c", &first, &grand)] {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(vec![item(tab, key)], None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.fork_tab(Atom::from("logfile"), parent.clone(), child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+
+		let hello_refs = LogFileDB::log_file_refs(&hello).await.unwrap();
+		let first_refs = LogFileDB::log_file_refs(&first).await.unwrap();
+		let grand_refs = LogFileDB::log_file_refs(&grand).await.unwrap();
+		let missing = LogFileDB::log_file_refs(&Atom::from(format!("./testlogfile/refs_missing{:?}", suffix))).await.is_err();
+
+		let _ = s.send((hello_refs, first_refs, grand_refs, missing, first, second, grand));
+	});
+
+	let (hello_refs, first_refs, grand_refs, missing, first, second, grand) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(missing);
+
+	//父表的可写日志文件不被引用，second在first之后分叉，引用的日志文件包括first引用的日志文件
+	assert!(hello_refs.len() >= 3);
+	assert!(hello_refs.last().unwrap().1.is_empty());
+	assert!(hello_refs.windows(2).all(|pair| pair[0].0 < pair[1].0));
+	assert_eq!(hello_refs[0].1, vec![first.clone(), grand.clone(), second.clone()]);
+	assert!(hello_refs.iter().any(|(_, refs)| refs == &vec![second.clone()]));
+	for (_, refs) in hello_refs.iter() {
+		//grand通过first间接引用hello的日志文件
+		assert_eq!(refs.contains(&first), refs.contains(&grand));
+		if refs.contains(&first) {
+			assert!(refs.contains(&second));
+		}
+	}
+
+	//first的日志文件只被grand引用，grand没有分叉表
+	assert!(first_refs.iter().any(|(_, refs)| refs == &vec![grand.clone()]));
+	assert!(first_refs.iter().all(|(_, refs)| refs.is_empty() || refs == &vec![grand.clone()]));
+	assert!(grand_refs.iter().all(|(_, refs)| refs.is_empty()));
+}
+
+//多级分叉表并发加载父表与逐个加载父表的结果相同
+#[test]
+fn test_parallel_ancestor_load() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = crossbeam_channel::bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tabs: Vec<Atom> = (0..4).map(|level| Atom::from(format!("./testlogfile/ancestor_{}_{:?}", level, suffix))).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &tabs[0], Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |key: &str| -> Arc<Vec<u8>> {
+			let mut k = WriteBuffer::new();
+			k.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(k.bytes)
+		};
+		let item = |tab: &Atom, k: &str, value: Option<&str>| -> TabKV {
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key(k),
+				value: value.map(|v| Arc::new(v.as_bytes().to_vec())),
+				index: 0
+			}
+		};
+
+		//每一级写入或删除一些主键，再分叉出下一级
+		let writes = vec![
+			vec![("a", Some("root")), ("b", Some("root")), ("c", Some("root"))],
+			vec![("b", Some("first")), ("c", None)],
+			vec![("a", Some("second")), ("d", Some("second"))],
+			vec![("b", Some("third"))],
+		];
+		for (level, items) in writes.into_iter().enumerate() {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items.into_iter().map(|(k, v)| item(&tabs[level], k, v)).collect(), None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+
+			if level + 1 < tabs.len() {
+				let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+				tr.fork_tab(Atom::from("logfile"), tabs[level].clone(), tabs[level + 1].clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+				tr.prepare().await.unwrap();
+				tr.commit().await.unwrap();
+			}
+		}
+
+		let file = LogFileDB::open(&tabs[3]).await.unwrap();
+		let mut loads = Vec::new();
+		for concurrency in vec![1, 4] {
+			log_file_db::ANCESTOR_LOAD_CONCURRENCY.store(concurrency, std::sync::atomic::Ordering::Relaxed);
+			file.reload().await.unwrap();
+			let mut values = Vec::new();
+			for k in vec!["a", "b", "c", "d"] {
+				values.push(file.get_with_meta(&key(k)).await.unwrap().map(|(v, _)| String::from_utf8(v.to_vec()).unwrap()));
+			}
+			loads.push(values);
+		}
+		log_file_db::ANCESTOR_LOAD_CONCURRENCY.store(4, std::sync::atomic::Ordering::Relaxed);
+
+		let _ = s.send(loads);
+	});
+
+	let loads = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	let expected = vec![Some("second".to_string()), Some("third".to_string()), None, Some("second".to_string())];
+	assert_eq!(loads[0], expected);
+	assert_eq!(loads[1], expected);
+}
+
+//父表在分叉后的写入对冻结分叉表不可见，对实时分叉表可见，实时分叉表自己写入过的主键不受父表写入影响，重新加载后结果相同
+#[test]
+fn test_fork_mode() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = crossbeam_channel::bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let suffix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let parent = Atom::from(format!("./testlogfile/mode_parent{:?}", suffix));
+		let frozen = Atom::from(format!("./testlogfile/mode_frozen{:?}", suffix));
+		let live = Atom::from(format!("./testlogfile/mode_live{:?}", suffix));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &parent, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |key: &str| -> Arc<Vec<u8>> {
+			let mut k = WriteBuffer::new();
+			k.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(k.bytes)
+		};
+		let item = |tab: &Atom, k: &str, value: Option<&str>| -> TabKV {
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key(k),
+				value: value.map(|v| Arc::new(v.as_bytes().to_vec())),
+				index: 0
+			}
+		};
+		let write = |items: Vec<TabKV>| {
+			let mgr = mgr.clone();
+			let rt = rt.clone();
+			async move {
+				let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+				tr.modify(items, None, false).await.unwrap();
+				tr.prepare().await.unwrap();
+				tr.commit().await.unwrap();
+			}
+		};
+
+		write(vec![item(&parent, "a", Some("root")), item(&parent, "b", Some("root")), item(&parent, "d", Some("root"))]).await;
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.fork_tab(Atom::from("logfile"), parent.clone(), frozen.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await.unwrap();
+		tr.fork_tab_with(Atom::from("logfile"), parent.clone(), live.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str), ForkMode::Live).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//实时分叉表不允许再分叉
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.fork_tab(Atom::from("logfile"), live.clone(), Atom::from(format!("./testlogfile/mode_live_fork{:?}", suffix)), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await.unwrap();
+		let refork = tr.prepare().await.is_err();
+		let _ = tr.rollback().await;
+
+		//分叉表已加载后，父表写入、修改和删除，实时分叉表自己写入b
+		write(vec![item(&live, "b", Some("own"))]).await;
+		let frozen_file = LogFileDB::open(&frozen).await.unwrap();
+		let live_file = LogFileDB::open(&live).await.unwrap();
+		write(vec![item(&parent, "a", Some("post")), item(&parent, "b", Some("post")), item(&parent, "c", Some("post")), item(&parent, "d", None)]).await;
+
+		let mut loads = Vec::new();
+		for reload in vec![false, true] {
+			for file in vec![&frozen_file, &live_file] {
+				if reload {
+					file.reload().await.unwrap();
+				}
+				let mut values = Vec::new();
+				for k in vec!["a", "b", "c", "d"] {
+					values.push(file.get_with_meta(&key(k)).await.unwrap().map(|(v, _)| String::from_utf8(v.to_vec()).unwrap()));
+				}
+				loads.push(values);
+			}
+		}
+		let mode = ALL_TABLES.lock().await.get(&live).map(|info| info.mode);
+
+		let _ = s.send((refork, mode, loads));
+	});
+
+	let (refork, mode, loads) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(refork);
+	assert_eq!(mode, Some(ForkMode::Live));
+	let value = |v: &str| Some(v.to_string());
+	let frozen = vec![value("root"), value("root"), None, value("root")];
+	let live = vec![value("post"), value("own"), value("post"), None];
+	assert_eq!(loads, vec![frozen.clone(), live.clone(), frozen, live]);
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use bon::WriteBuffer;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::{TabKV, TabMeta};
+use pi_db::clock::{ManualClock, reset_clock};
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB, CommitValidator};
+
+//写锁阻止其它事务预提交对主键的读写，只读锁可以共享，持有锁的事务提交或回滚后释放，超时的锁可以被回收
+//注入手动时钟，锁的超时只由推进时钟决定，不需要真实的等待
+#[test]
+fn test_key_lock() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let clock = Arc::new(ManualClock::new(1_000_000));
+	LogFileDB::set_clock(clock.clone());
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/key_lock_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"hot", 0..3);
+		let key = Arc::new(wb.bytes);
+		let kv = |value: Option<&[u8]>| {
+			let mut tabkv = TabKV::new(Atom::from("logfile"), tab.clone(), key.clone());
+			tabkv.value = value.map(|v| Arc::new(v.to_vec()));
+			Arc::new(vec![tabkv])
+		};
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = GuidGen::new(0, 0);
+		let mut results = Vec::new();
+
+		//写锁：其它事务的写入预提交失败，持有锁的事务可以写入，提交后释放
+		let owner = file.transaction(&gen.gen(0), true).await.unwrap();
+		owner.key_lock(kv(None), 10000, false).await.unwrap();
+		let other = file.transaction(&gen.gen(0), true).await.unwrap();
+		results.push(other.key_lock(kv(None), 10000, false).await.is_err());
+		other.modify(kv(Some(b"other")), None, false).await.unwrap();
+		results.push(other.prepare(1000).await.unwrap_err().starts_with("KeyLocked:"));
+		other.rollback().await.unwrap();
+		owner.modify(kv(Some(b"owner")), None, false).await.unwrap();
+		owner.prepare(1000).await.unwrap();
+		owner.commit().await.unwrap();
+		let after_commit = file.transaction(&gen.gen(0), true).await.unwrap();
+		after_commit.modify(kv(Some(b"next")), None, false).await.unwrap();
+		results.push(after_commit.prepare(1000).await.is_ok());
+		after_commit.commit().await.unwrap();
+
+		//只读锁：多个事务可以同时持有，只阻止写入，解锁后可以写入
+		let reader1 = file.transaction(&gen.gen(0), true).await.unwrap();
+		let reader2 = file.transaction(&gen.gen(0), true).await.unwrap();
+		reader1.key_lock(kv(None), 10000, true).await.unwrap();
+		results.push(reader2.key_lock(kv(None), 10000, true).await.is_ok());
+		let writer = file.transaction(&gen.gen(0), true).await.unwrap();
+		results.push(writer.key_lock(kv(None), 10000, false).await.is_err());
+		reader1.key_lock(kv(None), 0, true).await.unwrap();
+		reader2.rollback().await.unwrap();
+		results.push(writer.key_lock(kv(None), 10000, false).await.is_ok());
+		writer.rollback().await.unwrap();
+
+		//提交校验失败的事务仍持有锁，回滚后才释放
+		let validator: CommitValidator = Arc::new(|_| Err(String::from("reject")));
+		LogFileDB::set_commit_validator(&tab, Some(validator));
+		let rejected = file.transaction(&gen.gen(0), true).await.unwrap();
+		rejected.key_lock(kv(None), 10000, false).await.unwrap();
+		rejected.modify(kv(Some(b"rejected")), None, false).await.unwrap();
+		rejected.prepare(1000).await.unwrap();
+		results.push(rejected.commit().await.unwrap_err().starts_with("ValidationFailed:"));
+		LogFileDB::set_commit_validator(&tab, None);
+		let blocked = file.transaction(&gen.gen(0), true).await.unwrap();
+		results.push(blocked.key_lock(kv(None), 10000, false).await.is_err());
+		rejected.rollback().await.unwrap();
+		results.push(blocked.key_lock(kv(None), 10000, false).await.is_ok());
+		blocked.rollback().await.unwrap();
+
+		//超时的锁可以被其它事务回收，未推进时钟前锁没有超时
+		let crashed = file.transaction(&gen.gen(0), true).await.unwrap();
+		crashed.key_lock(kv(None), 50, false).await.unwrap();
+		let early = file.transaction(&gen.gen(0), true).await.unwrap();
+		results.push(early.key_lock(kv(None), 10000, false).await.is_err());
+		early.rollback().await.unwrap();
+		clock.advance(100);
+		let reclaim = file.transaction(&gen.gen(0), true).await.unwrap();
+		reclaim.key_lock(kv(None), 10000, false).await.unwrap();
+		reclaim.modify(kv(Some(b"reclaimed")), None, false).await.unwrap();
+		results.push(reclaim.prepare(1000).await.is_ok());
+		reclaim.commit().await.unwrap();
+		crashed.rollback().await.unwrap();
+
+		let _ = s.send(results);
+	});
+
+	let results = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	reset_clock();
+	assert_eq!(results, vec![true; 11]);
+}
\ No newline at end of file
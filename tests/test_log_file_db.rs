@@ -6,14 +6,17 @@ use std::collections::{VecDeque, BTreeMap};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use crossbeam_channel::bounded;
-use pi_db::{log_file_db::STORE_RUNTIME, mgr::{ DatabaseWare, Mgr }};
-use pi_db::log_file_db::{LOG_FILE_SIZE, AsyncLogFileStore, LogFileDB};
+use pi_db::{log_file_db::STORE_RUNTIME, mgr::{ DatabaseWare, Mgr, Monitor }};
+use pi_db::log_file_db::{LOG_FILE_SIZE, LOG_FILE_TOTAL_SIZE, TXN_SPILL_SIZE, QUERY_PARALLELISM, COMPACTION_IO_CONCURRENCY, FAIR_TAB_LOCK, PREALLOCATE_LOG_FILE, VERIFY_KEY_ORDER, QUARANTINE_CORRUPT_LOGS, MAX_PREPARED_TXNS, MAX_PREPARED_SIZE, AS_OF_HISTORY, MAX_INFLIGHT_WRITES, SPLIT_FSYNC, LOG_FORMAT_VERSION, ACCESS_SAMPLE_RATE, ACCESS_PREFIX_LEN, ACCESS_HEATMAP_BUCKETS, DURABLE_WATERMARK, STATISTICS_ALERT_LEN, RangeStat, AsyncLogFileStore, LogFileDB, KeyTransform, CommitValidator, IndexExtractor, OP_TIMEOUT, with_timeout, read_log_records, verify_key_order, read_format_version};
+use pi_db::fork::{ALL_TABLES, TabConfig, TableMetaInfo};
+use pi_db::{with_db, DbConfig};
+use pi_db::checkpoint::{write_checkpoint, load_checkpoint, checkpoint_index};
 use atom::Atom;
 use sinfo;
 use guid::GuidGen;
 use r#async::{lock::spin_lock::SpinLock, rt::multi_thread::{MultiTaskPool, MultiTaskRuntime}};
-use pi_db::db::{TabKV, TabMeta};
-use bon::WriteBuffer;
+use pi_db::db::{Bin, Filter, TabKV, TabMeta, RwLog, Event, EventType, Iter, NextResult, FuseOnErr, committed_keys, committed_deletes, committed_upserts};
+use bon::{Encode, Decode, ReadBuffer, WriteBuffer};
 use hash::XHashMap;
 
 #[test]
@@ -551,4 +554,5579 @@ async fn log_file_write(rt: &MultiTaskRuntime<()>, mgr: &Mgr, index: usize) {
     let _ = tr.modify(items, None, false).await;
     let _ = tr.prepare().await;
     let _ = tr.commit().await;
+}
+
+
+#[test]
+fn test_check_schema() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let db = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let ware = DatabaseWare::new_log_file_ware(db.clone());
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Bin);
+		tr.alter(&Atom::from("logfile"), &Atom::from("./testlogfile/schema"), Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let tab = Atom::from("./testlogfile/schema");
+		assert!(db.check_schema(&tab, &TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Bin)).await.is_ok());
+
+		let r = db.check_schema(&tab, &TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		println!("check schema result = {:?}", r);
+		assert!(r.unwrap_err().starts_with("SchemaMismatch"));
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+#[test]
+fn test_tombstones() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/tombstone");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut items = vec![];
+		for i in 0..10 {
+			let mut wb = WriteBuffer::new();
+			let key = format!("tombstone{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			items.push(TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			});
+		}
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(items.clone(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		//删除前5个主键
+		let deletes: Vec<TabKV> = items.iter().take(5).map(|item| {
+			let mut item = item.clone();
+			item.value = None;
+			item
+		}).collect();
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.modify(deletes.clone(), None, false).await;
+		tr3.prepare().await;
+		tr3.commit().await;
+
+		let tombstones = LogFileDB::tombstones(&tab).await.unwrap();
+		println!("tombstones before collect = {:?}", tombstones);
+		for item in &deletes {
+			assert!(tombstones.contains(&item.key));
+		}
+
+		LogFileDB::force_split().await;
+		LogFileDB::collect().await.unwrap();
+
+		let tombstones = LogFileDB::tombstones(&tab).await.unwrap();
+		println!("tombstones after collect = {:?}", tombstones);
+		assert!(tombstones.is_empty());
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(5));
+}
+
+
+#[test]
+fn test_reload_after_collect() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/reload");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//同一个主键写入多次，以产生需要整理的重复记录
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"reload", 0..6);
+		for i in 0..10 {
+			let mut value = WriteBuffer::new();
+			let v = format!("value{:?}", i);
+			value.write_bin(v.as_bytes(), 0..v.len());
+			let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+			tr2.modify(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(value.bytes)),
+				index: 0,
+			}], None, false).await;
+			tr2.prepare().await;
+			tr2.commit().await;
+		}
+
+		LogFileDB::force_split().await;
+		LogFileDB::collect().await.unwrap();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		assert!(file.reload().await.is_ok());
+
+		let mut expect = WriteBuffer::new();
+		expect.write_bin(b"value9", 0..6);
+		assert_eq!(file.1.read(&wb.bytes).map(|v| v.to_vec()), Some(expect.bytes.clone()));
+
+		let mut tr3 = mgr.transaction(false, Some(rt.clone())).await;
+		let r = tr3.query(vec![TabKV::new(Atom::from("logfile"), tab.clone(), Arc::new(wb.bytes.clone()))], None, false).await.unwrap();
+		assert_eq!(r[0].value, Some(Arc::new(expect.bytes)));
+		tr3.prepare().await;
+		tr3.commit().await;
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(5));
+}
+
+
+#[test]
+fn test_query_parallel() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let gen = GuidGen::new(0, 0);
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/parallel");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut items = vec![];
+		for i in 0..1000 {
+			let mut wb = WriteBuffer::new();
+			let key = format!("parallel{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			items.push(TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			});
+		}
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(items.clone(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		//逆序查询，结果必须与输入顺序一致
+		let keys: Vec<TabKV> = items.iter().rev().map(|item| TabKV::new(item.ware.clone(), item.tab.clone(), item.key.clone())).collect();
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let r = txn.query_parallel(Arc::new(keys.clone())).await.unwrap();
+		assert_eq!(r.len(), keys.len());
+		for (q, r) in keys.iter().zip(r.iter()) {
+			assert_eq!(q.key, r.key);
+			assert_eq!(Some(q.key.clone()), r.value);
+		}
+
+		//已溢出的事务从溢出日志中并发读取值，同时读取的数量不超过限制
+		let updated: Vec<TabKV> = items.iter().map(|item| {
+			let mut item = item.clone();
+			item.value = Some(Arc::new(vec![7u8; 256]));
+			item
+		}).collect();
+		TXN_SPILL_SIZE.store(1024, Ordering::Relaxed);
+		QUERY_PARALLELISM.store(8, Ordering::Relaxed);
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(updated[..500].to_vec()), None, false).await.unwrap();
+		LogFileDB::take_parallel_read_peak();
+		let r = txn.query_parallel(Arc::new(keys.clone())).await.unwrap();
+		let peak = LogFileDB::take_parallel_read_peak();
+		txn.rollback().await.unwrap();
+		TXN_SPILL_SIZE.store(0, Ordering::Relaxed);
+		for (q, r) in keys.iter().zip(r.iter()) {
+			let i = items.iter().position(|item| item.key == q.key).unwrap();
+			assert_eq!(q.key, r.key);
+			if i < 500 {
+				assert_eq!(r.value, updated[i].value);
+			} else {
+				assert_eq!(Some(q.key.clone()), r.value);
+			}
+		}
+		assert!(peak > 1 && peak <= 8);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_collect_blocking() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/collect_blocking");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//同一个主键在多个只读日志文件中重复写入，以产生需要移除和整理的只读日志文件
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"collect", 0..7);
+		for i in 0..10 {
+			let mut value = WriteBuffer::new();
+			let v = format!("value{:?}", i);
+			value.write_bin(v.as_bytes(), 0..v.len());
+			let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+			tr2.modify(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(value.bytes)),
+				index: 0,
+			}], None, false).await;
+			tr2.prepare().await;
+			tr2.commit().await;
+			LogFileDB::force_split().await;
+		}
+
+		let report = LogFileDB::collect_blocking().await.unwrap();
+		println!("collect report = {:?}", report);
+		assert!(report.tabs > 0);
+		for path in report.removed_logs.iter().chain(report.collected_logs.iter()) {
+			assert!(!path.exists(), "log file not deleted: {:?}", path);
+		}
+		assert_eq!(report.deleted_files.len(), report.removed_logs.len() + report.collected_logs.len());
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(5));
+}
+
+
+
+#[test]
+fn test_op_timeout() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		//持有分叉元信息表的锁，使打开日志文件表的操作被阻塞
+		let lock = ALL_TABLES.lock().await;
+		let tab = Atom::from("./testlogfile/op_timeout");
+		let r = with_timeout("open", 10, LogFileDB::open(&tab)).await;
+		assert!(r.is_err());
+		assert!(r.err().unwrap().starts_with("OperationTimeout"));
+		drop(lock);
+
+		//超时时间为0表示不限制
+		let r = with_timeout("open", 0, async { Ok(1) }).await;
+		assert_eq!(r, Ok(1));
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+//内存表锁被持有时，预提交和提交在获取锁时超时，超时的提交没有修改内存表，可以回滚
+#[test]
+fn test_op_timeout_on_tab_lock() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/op_timeout_lock_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let kv = |name: &str| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(name.as_bytes(), 0..name.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}
+		};
+
+		//在锁被持有前创建所有事务
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = GuidGen::new(0, 0);
+		let holder = file.transaction(&gen.gen(0), true).await.unwrap();
+		holder.modify(Arc::new(vec![kv("holder")]), None, false).await.unwrap();
+		holder.prepare(1000).await.unwrap();
+		let waiter = file.transaction(&gen.gen(0), true).await.unwrap();
+		waiter.modify(Arc::new(vec![kv("waiter")]), None, false).await.unwrap();
+		waiter.prepare(1000).await.unwrap();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![kv("mgr")], None, false).await.unwrap();
+
+		//提交校验函数在内存表锁内执行，阻塞到测试释放，使持有者的提交一直持有内存表锁
+		let (entered_s, entered_r) = futures::channel::oneshot::channel();
+		let entered_s = std::sync::Mutex::new(Some(entered_s));
+		let (release_s, release_r) = bounded::<()>(1);
+		let holder_key = kv("holder").key;
+		let validator: CommitValidator = Arc::new(move |rwlog| {
+			if rwlog.contains_key(&holder_key) {
+				if let Some(sender) = entered_s.lock().unwrap().take() {
+					let _ = sender.send(());
+				}
+				let _ = release_r.recv_timeout(Duration::from_secs(10));
+			}
+			Ok(())
+		});
+		LogFileDB::set_commit_validator(&tab, Some(validator));
+		let (done_s, done_r) = futures::channel::oneshot::channel();
+		let _ = rt.spawn(rt.alloc(), async move {
+			let _ = done_s.send(holder.commit().await.is_ok());
+		});
+		let _ = entered_r.await;
+
+		OP_TIMEOUT.store(50, Ordering::Relaxed);
+		let prepare_timeout = tr.prepare().await.unwrap_err().contains("OperationTimeout");
+		let commit_timeout = waiter.commit().await.unwrap_err().starts_with("OperationTimeout");
+		OP_TIMEOUT.store(0, Ordering::Relaxed);
+
+		let _ = release_s.send(());
+		let holder_committed = done_r.await.unwrap();
+		LogFileDB::set_commit_validator(&tab, None);
+		let waiter_applied = file.get_with_meta(&kv("waiter").key).await.unwrap().is_some();
+		let waiter_rollback = waiter.rollback().await.is_ok();
+		let _ = tr.rollback().await;
+
+		let _ = s.send((prepare_timeout, commit_timeout, holder_committed, waiter_applied, waiter_rollback));
+	});
+
+	let (prepare_timeout, commit_timeout, holder_committed, waiter_applied, waiter_rollback) = r.recv_timeout(Duration::from_secs(20)).unwrap();
+	assert!(prepare_timeout);
+	assert!(commit_timeout);
+	assert!(holder_committed);
+	assert!(!waiter_applied);
+	assert!(waiter_rollback);
+}
+
+
+
+#[test]
+fn test_committed_keys() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let gen = GuidGen::new(0, 0);
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/committed_keys");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut upserts = vec![];
+		let mut deletes = vec![];
+		let mut items = vec![];
+		for i in 0..10 {
+			let mut wb = WriteBuffer::new();
+			let key = format!("committed{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			let key = Arc::new(wb.bytes.clone());
+			let value = if i % 3 == 0 {
+				deletes.push(key.clone());
+				None
+			} else {
+				upserts.push(key.clone());
+				Some(Arc::new(wb.bytes))
+			};
+			items.push(TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key,
+				value,
+				index: 0,
+			});
+		}
+		upserts.sort();
+		deletes.sort();
+		let mut all: Vec<_> = upserts.iter().chain(deletes.iter()).cloned().collect();
+		all.sort();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(items), None, false).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		let log = txn.commit().await.unwrap();
+
+		assert_eq!(committed_keys(&log), all);
+		assert_eq!(committed_upserts(&log), upserts);
+		assert_eq!(committed_deletes(&log), deletes);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_skip_malformed_meta() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let mut tabs = vec![];
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for i in 0..3 {
+			let tab = Atom::from(format!("./testlogfile/valid_meta{:?}", i));
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+			tabs.push(tab);
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		//在元信息表中写入一条无法解析的元信息记录
+		let mut wb = WriteBuffer::new();
+		Atom::from("./testlogfile/corrupted_meta").encode(&mut wb);
+		let meta_tab = LogFileDB::open(&Atom::from("tabs_meta")).await.unwrap();
+		meta_tab.1.write(wb.bytes.clone(), vec![0xff, 0xff, 0xff]).await.unwrap();
+
+		//重新加载数据库，无法解析的记录被跳过，其它表正常打开
+		let _db = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let report = LogFileDB::health_report();
+		println!("health report = {:?}", report);
+		assert!(report.skipped_meta.iter().any(|(key, _)| key.as_ref() == &wb.bytes));
+		for tab in tabs.iter() {
+			assert!(LogFileDB::open(tab).await.is_ok());
+		}
+
+		//表名无法解析的元信息记录在分裂和整理时被跳过
+		let bad_key = vec![0xff, 0xff, 0xff];
+		meta_tab.1.write(bad_key.clone(), vec![0xff]).await.unwrap();
+		assert!(LogFileDB::force_split().await.is_ok());
+		assert!(LogFileDB::collect().await.is_ok());
+
+		meta_tab.1.remove(bad_key).await.unwrap();
+		meta_tab.1.remove(wb.bytes.clone()).await.unwrap();
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_index_checkpoint() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/checkpoint");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut items = vec![];
+		for i in 0..100 {
+			let mut key = WriteBuffer::new();
+			let k = format!("key{:?}", i);
+			key.write_bin(k.as_bytes(), 0..k.len());
+			let mut value = WriteBuffer::new();
+			let v = format!("value{:?}", i);
+			value.write_bin(v.as_bytes(), 0..v.len());
+			items.push(TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(key.bytes),
+				value: Some(Arc::new(value.bytes)),
+				index: 0,
+			});
+		}
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(items.clone(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		//建立值到主键的索引，并写入检查点
+		let mut index: Vec<(Arc<Vec<u8>>, Arc<Vec<u8>>)> = items.iter().map(|item| (item.value.clone().unwrap(), item.key.clone())).collect();
+		index.sort();
+		let snapshot = index.clone();
+		let position = write_checkpoint(&tab, "value_index", &move || snapshot.clone()).await.unwrap();
+
+		//重启时从检查点加载索引
+		let checkpoint = load_checkpoint(&tab, "value_index").await.unwrap().unwrap();
+		assert_eq!(checkpoint.position, position);
+		assert_eq!(checkpoint.entries, index);
+
+		//有新的写入后，从检查点加载索引，只重放检查点之后的写入，同一主键只重放最新的写入
+		let mut updated = items[0].clone();
+		for value in [b"updated0", b"updated1"].iter() {
+			updated.value = Some(Arc::new(value.to_vec()));
+			let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+			tr3.modify(vec![updated.clone()], None, false).await;
+			tr3.prepare().await;
+			tr3.commit().await;
+		}
+		let checkpoint = load_checkpoint(&tab, "value_index").await.unwrap().unwrap();
+		assert_eq!(checkpoint.entries, index);
+		assert_eq!(checkpoint.replay, vec![(updated.key.clone(), updated.value.clone())]);
+
+		//检查点所在的日志文件被整理后，需要完整重建索引
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.split().await.unwrap();
+		file.checkpoint().await.unwrap();
+		assert!(load_checkpoint(&tab, "value_index").await.unwrap().is_none());
+
+		//停止后定时检查点不再写入
+		let path = PathBuf::from(format!("{}.timed_index.ckpt", tab.as_str()));
+		let entries = index.clone();
+		let task = checkpoint_index(rt.clone(), tab.clone(), "timed_index".to_string(), 10, Arc::new(move || entries.clone()));
+		task.stop();
+		rt.wait_timeout(100).await;
+		assert!(!task.is_running());
+		assert!(!path.exists());
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_snapshot_iter() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let gen = GuidGen::new(0, 0);
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/snapshot_iter");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let make_items = |start: usize, end: usize, prefix: &str| {
+			(start..end).map(|i| {
+				let mut wb = WriteBuffer::new();
+				let key = format!("snapshot{:0>4}", i);
+				wb.write_bin(key.as_bytes(), 0..key.len());
+				let mut value = WriteBuffer::new();
+				let v = format!("{}{:?}", prefix, i);
+				value.write_bin(v.as_bytes(), 0..v.len());
+				TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab.clone(),
+					key: Arc::new(wb.bytes),
+					value: Some(Arc::new(value.bytes)),
+					index: 0,
+				}
+			}).collect::<Vec<TabKV>>()
+		};
+
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(make_items(0, 10, "old"), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		//开始迭代后，由其它事务修改已有记录并插入新记录
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let mut iter = txn.snapshot_iter(&tab, None, false, None).await.unwrap();
+		let first = iter.next().unwrap().unwrap().unwrap();
+
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.modify(make_items(0, 20, "new"), None, false).await;
+		tr3.prepare().await;
+		tr3.commit().await;
+
+		let mut snapshot = vec![first];
+		while let Some(Ok(Some(elem))) = iter.next() {
+			snapshot.push(elem);
+		}
+		assert_eq!(snapshot.len(), 10);
+		for (item, (key, value)) in make_items(0, 10, "old").iter().zip(snapshot.iter()) {
+			assert_eq!(&item.key, key);
+			assert_eq!(item.value.as_ref().unwrap(), value);
+		}
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_txn_spill() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let gen = GuidGen::new(0, 0);
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/spill");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut items = vec![];
+		for i in 0..1000 {
+			let mut wb = WriteBuffer::new();
+			let key = format!("spill{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			items.push(TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: if i % 10 == 0 { None } else { Some(Arc::new(wb.bytes)) },
+				index: 0,
+			});
+		}
+
+		//事务的待写入记录远大于溢出限制
+		TXN_SPILL_SIZE.store(1024, Ordering::Relaxed);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(items.clone()), None, false).await.unwrap();
+
+		//已溢出的值可以在事务中点读、批量读、检查存在性和统计数量，事务的内存表中没有已溢出的写入，所以不允许迭代
+		let values = txn.query(Arc::new(vec![items[1].clone(), items[10].clone()]), None, false).await.unwrap();
+		let read = (values[0].value.clone(), values[1].value.clone());
+		let keys = vec![items[10].key.clone(), items[1].key.clone()];
+		let sorted = txn.multi_get_sorted(&tab, &keys).await.unwrap();
+		let bits = txn.contains_bitset(&tab, &keys).await.unwrap();
+		let size = txn.tab_size().await.unwrap();
+		let iter_err = txn.iter(&tab, None, false, None).await.err().unwrap();
+
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+		TXN_SPILL_SIZE.store(0, Ordering::Relaxed);
+
+		let buffered = items.iter().all(|item| {
+			file.1.read(&item.key).map(|v| v.to_vec()) == item.value.as_ref().map(|v| v.to_vec())
+		});
+		let committed = file.transaction(&gen.gen(0), false).await.unwrap().tab_size().await.unwrap();
+
+		//提交后溢出日志已被删除
+		let spills = std::fs::read_dir("./testlogfile").unwrap().filter(|entry| {
+			entry.as_ref().unwrap().file_name().to_str().unwrap().starts_with("spill.")
+		}).count();
+
+		let _ = s.send((read, items[1].value.clone(), sorted, (bits.get(0), bits.get(1)), size, committed, iter_err, buffered, spills));
+	});
+
+	let (read, value, sorted, bits, size, committed, iter_err, buffered, spills) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(read, (value.clone(), None));
+	assert_eq!(sorted, vec![None, value]);
+	assert_eq!(bits, (false, true));
+	//每10个主键删除1个，事务中的数量与提交后的数量一致
+	assert_eq!(size, 900);
+	assert_eq!(committed, 900);
+	assert!(iter_err.starts_with("TxnSpilled"));
+	assert!(buffered);
+	assert_eq!(spills, 0);
+}
+
+
+
+#[test]
+fn test_tab_config() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+	let (s, r) = bounded(1);
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let small = Atom::from("./testlogfile/config_small");
+		let large = Atom::from("./testlogfile/config_large");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in [&small, &large].iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		//在打开表之前设置不同的日志文件大小
+		LogFileDB::set_tab_config(&small, TabConfig { log_file_size: Some(1), ..TabConfig::default() }).await.unwrap();
+		LogFileDB::set_tab_config(&large, TabConfig { log_file_size: Some(16), ..TabConfig::default() }).await.unwrap();
+
+		//写入路径未实现压缩和持久化级别，设置时失败，且不修改已有的配置
+		let compression = LogFileDB::set_tab_config(&small, TabConfig { log_file_size: Some(2), compression: Some(true), ..TabConfig::default() }).await;
+		let durability = LogFileDB::set_tab_config(&small, TabConfig { log_file_size: Some(2), durability: Some(1), ..TabConfig::default() }).await;
+		let uncompressed = LogFileDB::set_tab_config(&large, TabConfig { log_file_size: Some(16), compression: Some(false), ..TabConfig::default() }).await;
+		let small_size = ALL_TABLES.lock().await.get(&small).unwrap().config.log_file_size;
+
+		//向两个表写入相同的约4MB数据
+		let value = Arc::new(vec![0u8; 4096]);
+		for tab in [&small, &large].iter() {
+			for i in 0..1000 {
+				let mut wb = WriteBuffer::new();
+				let key = format!("config{:?}", i);
+				wb.write_bin(key.as_bytes(), 0..key.len());
+				let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+				tr2.modify(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab: (*tab).clone(),
+					key: Arc::new(wb.bytes),
+					value: Some(value.clone()),
+					index: 0,
+				}], None, false).await;
+				tr2.prepare().await;
+				tr2.commit().await;
+			}
+		}
+
+		let count = |tab: &Atom| std::fs::read_dir(tab.as_str()).unwrap().count();
+		println!("small log files: {}, large log files: {}", count(&small), count(&large));
+		let _ = s.send((compression, durability, uncompressed, small_size, count(&small), count(&large)));
+	});
+
+	let (compression, durability, uncompressed, small_size, small, large) = r.recv_timeout(std::time::Duration::from_secs(60)).unwrap();
+	assert!(compression.unwrap_err().starts_with("UnsupportedConfig:"));
+	assert!(durability.unwrap_err().starts_with("UnsupportedConfig:"));
+	assert!(uncompressed.is_ok());
+	assert_eq!(small_size, Some(1));
+	assert!(small > large);
+}
+
+
+
+#[test]
+fn test_split_counter() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/split_counter");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let before = LogFileDB::tab_stats(&tab).splits;
+		for i in 0..5 {
+			let mut wb = WriteBuffer::new();
+			let key = format!("split{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+			tr2.modify(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}], None, false).await;
+			tr2.prepare().await;
+			tr2.commit().await;
+
+			LogFileDB::force_split_tab(&tab).await.unwrap();
+		}
+		assert_eq!(LogFileDB::tab_stats(&tab).splits, before + 5);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_iter_tabkv() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let gen = GuidGen::new(0, 0);
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let src = Atom::from("./testlogfile/iter_tabkv_src");
+		let dst = Atom::from("./testlogfile/iter_tabkv_dst");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in [&src, &dst].iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut items = vec![];
+		for i in 0..10 {
+			let mut wb = WriteBuffer::new();
+			let key = format!("tabkv{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			items.push(TabKV {
+				ware: Atom::from("logfile"),
+				tab: src.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			});
+		}
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(items.clone(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		//迭代得到的TabKV与查询结果一致
+		let file = LogFileDB::open(&src).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let mut iter = txn.iter_tabkv(&Atom::from("logfile"), &src, None, false, None).await.unwrap();
+		let mut result = vec![];
+		while let Some(Ok(Some(tabkv))) = iter.next() {
+			result.push(tabkv);
+		}
+		let queried = txn.query(Arc::new(items.clone()), None, false).await.unwrap();
+		assert_eq!(result.len(), queried.len());
+		for (r, q) in result.iter().zip(queried.iter()) {
+			assert_eq!(r.ware, q.ware);
+			assert_eq!(r.tab, q.tab);
+			assert_eq!(r.key, q.key);
+			assert_eq!(r.value, q.value);
+		}
+
+		//迭代结果只修改表名，直接写入另一个表
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.modify(result.into_iter().map(|mut tabkv| { tabkv.tab = dst.clone(); tabkv }).collect(), None, false).await.unwrap();
+		tr3.prepare().await;
+		tr3.commit().await;
+
+		let dst_file = LogFileDB::open(&dst).await.unwrap();
+		for item in items.iter() {
+			assert_eq!(dst_file.1.read(&item.key).map(|v| v.to_vec()), item.value.as_ref().map(|v| v.to_vec()));
+		}
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_lock_wait_stats() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/lock_wait");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//并发提交大量事务，产生内存表锁的竞争
+		let (s, r) = bounded(100);
+		for i in 0..100 {
+			let mgr = mgr.clone();
+			let tab = tab.clone();
+			let s = s.clone();
+			let rt_copy = rt.clone();
+			let _ = rt.spawn(rt.alloc(), async move {
+				let mut wb = WriteBuffer::new();
+				let key = format!("lock_wait{:?}", i);
+				wb.write_bin(key.as_bytes(), 0..key.len());
+				let mut tr2 = mgr.transaction(true, Some(rt_copy)).await;
+				tr2.modify(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab,
+					key: Arc::new(wb.bytes.clone()),
+					value: Some(Arc::new(wb.bytes)),
+					index: 0,
+				}], None, false).await;
+				tr2.prepare().await;
+				tr2.commit().await;
+				let _ = s.send(());
+			});
+		}
+		for _ in 0..100 {
+			let _ = r.recv_timeout(std::time::Duration::from_secs(10));
+		}
+
+		let stats = LogFileDB::tab_stats(&tab);
+		println!("lock wait stats = {:?}", stats);
+		assert!(stats.lock_acquires >= 200);
+		assert!(stats.lock_wait_total > std::time::Duration::from_micros(0));
+		assert!(stats.lock_wait_max <= stats.lock_wait_total);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(15));
+}
+
+
+
+#[test]
+fn test_compaction_io_concurrency() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		//4个表各有一个删除记录占多数的只读日志文件，每个表的整理互不等待
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let mut tabs = Vec::new();
+		for index in 0..4 {
+			let tab = Atom::from(format!("./testlogfile/compaction_io_{}_{}", index, now));
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+			tr.prepare().await;
+			tr.commit().await;
+
+			let items = |value: Option<Arc<Vec<u8>>>| -> Vec<TabKV> {
+				(0..500).map(|i| {
+					let mut wb = WriteBuffer::new();
+					let key = format!("compaction{:?}", i);
+					wb.write_bin(key.as_bytes(), 0..key.len());
+					TabKV {
+						ware: Atom::from("logfile"),
+						tab: tab.clone(),
+						key: Arc::new(wb.bytes),
+						value: value.clone(),
+						index: 0,
+					}
+				}).collect()
+			};
+			for value in vec![Some(Arc::new(vec![1u8; 1024])), None] {
+				let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+				tr2.modify(items(value), None, false).await.unwrap();
+				tr2.prepare().await.unwrap();
+				tr2.commit().await.unwrap();
+				LogFileDB::force_split_tab(&tab).await.unwrap();
+			}
+			LogFileDB::open(&tab).await.unwrap().reload().await.unwrap();
+			tabs.push(tab);
+		}
+
+		//不同表的整理没有全局锁，在不同的任务中同时整理，同时执行整理文件I/O的数量仍然不超过限制
+		COMPACTION_IO_CONCURRENCY.store(1, Ordering::Relaxed);
+		LogFileDB::take_compaction_io_peak();
+		let mut done = Vec::new();
+		for tab in tabs {
+			let (done_s, done_r) = futures::channel::oneshot::channel();
+			let _ = rt.spawn(rt.alloc(), async move {
+				let _ = done_s.send(LogFileDB::collect_tombstones(&tab).await.map(|report| report.tabs));
+			});
+			done.push(done_r);
+		}
+		let mut collected = Vec::new();
+		for done_r in done {
+			collected.push(done_r.await.unwrap());
+		}
+		let peak = LogFileDB::take_compaction_io_peak();
+		COMPACTION_IO_CONCURRENCY.store(0, Ordering::Relaxed);
+
+		let _ = s.send((collected, peak));
+	});
+
+	let (collected, peak) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(collected, vec![Ok(1); 4]);
+	assert_eq!(peak, 1);
+}
+
+
+
+#[test]
+fn test_load_order_across_splits() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/load_order");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//同一个主键在三次分裂产生的三个只读日志文件中各写入一次
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"order", 0..5);
+		for i in 0..3 {
+			let mut value = WriteBuffer::new();
+			let v = format!("value{:?}", i);
+			value.write_bin(v.as_bytes(), 0..v.len());
+			let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+			tr2.modify(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(value.bytes)),
+				index: 0,
+			}], None, false).await;
+			tr2.prepare().await;
+			tr2.commit().await;
+
+			LogFileDB::force_split_tab(&tab).await.unwrap();
+		}
+
+		//重新加载后，最新的写入覆盖更早分裂的只读日志文件中的写入
+		let file = LogFileDB::open(&tab).await.unwrap();
+		assert!(file.reload().await.is_ok());
+
+		let mut expect = WriteBuffer::new();
+		expect.write_bin(b"value2", 0..6);
+		assert_eq!(file.1.read(&wb.bytes).map(|v| v.to_vec()), Some(expect.bytes.clone()));
+
+		let mut tr3 = mgr.transaction(false, Some(rt.clone())).await;
+		let r = tr3.query(vec![TabKV::new(Atom::from("logfile"), tab.clone(), Arc::new(wb.bytes.clone()))], None, false).await.unwrap();
+		assert_eq!(r[0].value, Some(Arc::new(expect.bytes)));
+		tr3.prepare().await;
+		tr3.commit().await;
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_approx_size() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/approx_size");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let before = file.approx_size();
+
+		//写入10个新主键，再删除其中的3个
+		let keys: Vec<Vec<u8>> = (0..10).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("approx{:?}_{:?}", i, before);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			wb.bytes
+		}).collect();
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(keys.iter().map(|key| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(key.clone()),
+			value: Some(Arc::new(key.clone())),
+			index: 0,
+		}).collect(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+		assert_eq!(file.approx_size(), before + 10);
+
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.modify(keys.iter().take(3).map(|key| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(key.clone()),
+			value: None,
+			index: 0,
+		}).collect(), None, false).await;
+		tr3.prepare().await;
+		tr3.commit().await;
+		assert_eq!(file.approx_size(), before + 7);
+
+		let mut tr4 = mgr.transaction(false, Some(rt.clone())).await;
+		let size = tr4.tab_size(&Atom::from("logfile"), &tab).await.unwrap();
+		tr4.prepare().await;
+		tr4.commit().await;
+		assert_eq!(file.approx_size(), size);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_schema_history() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/schema_history{:?}", now));
+		let metas = vec![
+			TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str),
+			TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Bin),
+			TabMeta::new(sinfo::EnumType::Bin, sinfo::EnumType::Bin),
+		];
+
+		//创建表后再修改两次元信息
+		for meta in metas.iter() {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta.clone()))).await;
+			tr.prepare().await;
+			tr.commit().await;
+		}
+
+		let history = LogFileDB::schema_history(&tab).await.unwrap();
+		assert_eq!(history.len(), 3);
+		for (i, version) in history.iter().enumerate() {
+			assert_eq!(version.version, i + 1);
+			assert_eq!(version.meta, metas[i]);
+		}
+
+		//元信息重新加载后，当前元信息为最新的版本，且保留所有历史版本
+		let info = ALL_TABLES.lock().await.get(&tab).cloned().unwrap();
+		let mut wb = WriteBuffer::new();
+		info.encode(&mut wb);
+		let loaded = TableMetaInfo::decode(&mut ReadBuffer::new(&wb.bytes, 0)).unwrap();
+		assert_eq!(loaded.meta, metas[2]);
+		assert_eq!(loaded.schema_version(), 3);
+		assert_eq!(loaded.history, history);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+//获取指定日志文件表的所有日志文件的总大小
+fn log_dir_size(tab: &Atom) -> u64 {
+	std::fs::read_dir(tab.as_str())
+		.unwrap()
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| entry.metadata().ok())
+		.filter(|meta| meta.is_file())
+		.map(|meta| meta.len())
+		.sum()
+}
+
+#[test]
+fn test_collect_tombstones() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/tombstones{:?}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let keys: Vec<Vec<u8>> = (0..100).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("tombstone{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			wb.bytes
+		}).collect();
+		let value = Arc::new(vec![1u8; 256]);
+
+		//写入100个主键后，删除其中的90个，每个主键只写入一次，关键字重复率不会触发普通整理
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(keys.iter().map(|key| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(key.clone()),
+			value: Some(value.clone()),
+			index: 0,
+		}).collect(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+		LogFileDB::force_split_tab(&tab).await.unwrap();
+
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.modify(keys.iter().skip(10).map(|key| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(key.clone()),
+			value: None,
+			index: 0,
+		}).collect(), None, false).await;
+		tr3.prepare().await;
+		tr3.commit().await;
+		LogFileDB::force_split_tab(&tab).await.unwrap();
+
+		//重新加载以重建只读日志文件的统计信息和删除记录
+		let file = LogFileDB::open(&tab).await.unwrap();
+		assert!(file.reload().await.is_ok());
+
+		let before = log_dir_size(&tab);
+		let report = LogFileDB::collect_tombstones(&tab).await.unwrap();
+		assert_eq!(report.tabs, 1);
+		assert!(log_dir_size(&tab) < before);
+
+		let mut tr4 = mgr.transaction(false, Some(rt.clone())).await;
+		let r = tr4.query(keys.iter().map(|key| TabKV::new(Atom::from("logfile"), tab.clone(), Arc::new(key.clone()))).collect(), None, false).await.unwrap();
+		for (i, item) in r.iter().enumerate() {
+			if i < 10 {
+				assert_eq!(item.value, Some(value.clone()));
+			} else {
+				assert_eq!(item.value, None);
+			}
+		}
+		tr4.prepare().await;
+		tr4.commit().await;
+
+		//有分叉的表只整理分叉点之后的日志文件，并保留删除记录，分叉表仍然看到分叉点的值
+		let child = Atom::from(format!("./testlogfile/tombstones_child{:?}", now));
+		let mut tr5 = mgr.transaction(true, Some(rt.clone())).await;
+		tr5.fork_tab(Atom::from("logfile"), tab.clone(), child.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await;
+		tr5.prepare().await.unwrap();
+		tr5.commit().await.unwrap();
+		let report = LogFileDB::collect_tombstones(&tab).await.unwrap();
+		assert_eq!(report.tabs, 0);
+
+		let mut tr6 = mgr.transaction(true, Some(rt.clone())).await;
+		tr6.modify(keys.iter().take(5).map(|key| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(key.clone()),
+			value: None,
+			index: 0,
+		}).collect(), None, false).await;
+		tr6.prepare().await;
+		tr6.commit().await;
+		LogFileDB::force_split_tab(&tab).await.unwrap();
+		assert!(file.reload().await.is_ok());
+		let report = LogFileDB::collect_tombstones(&tab).await.unwrap();
+		assert_eq!(report.tabs, 1);
+		assert!(file.reload().await.is_ok());
+
+		let mut tr7 = mgr.transaction(false, Some(rt.clone())).await;
+		let r = tr7.query(keys.iter().take(10).map(|key| TabKV::new(Atom::from("logfile"), tab.clone(), Arc::new(key.clone()))).collect(), None, false).await.unwrap();
+		for (i, item) in r.iter().enumerate() {
+			if i < 5 {
+				assert_eq!(item.value, None);
+			} else {
+				assert_eq!(item.value, Some(value.clone()));
+			}
+		}
+		let r = tr7.query(keys.iter().take(10).map(|key| TabKV::new(Atom::from("logfile"), child.clone(), Arc::new(key.clone()))).collect(), None, false).await.unwrap();
+		assert!(r.iter().all(|item| item.value == Some(value.clone())));
+		tr7.prepare().await;
+		tr7.commit().await;
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(5));
+}
+
+
+
+//在第fail_at个元素时注入读取错误的迭代器，出错后仍继续返回元素
+struct FailingIter {
+	index: usize,
+	fail_at: usize,
+}
+
+impl Iter for FailingIter {
+	type Item = usize;
+	fn next(&mut self) -> Option<NextResult<Self::Item>> {
+		self.index += 1;
+		if self.index == self.fail_at {
+			Some(Err(format!("read value failed, index: {}", self.index)))
+		} else {
+			Some(Ok(Some(self.index)))
+		}
+	}
+}
+
+#[test]
+fn test_iter_error_terminates() {
+	let mut it = FuseOnErr::new(FailingIter { index: 0, fail_at: 3 });
+	assert_eq!(it.next(), Some(Ok(Some(1))));
+	assert_eq!(it.next(), Some(Ok(Some(2))));
+	assert_eq!(it.next(), Some(Err("read value failed, index: 3".to_string())));
+	assert!(it.is_terminated());
+
+	//错误只返回一次，之后迭代器终止
+	assert_eq!(it.next(), None);
+	assert_eq!(it.next(), None);
+
+	//调用者按约定遍历时，收到错误后停止
+	let mut it = FuseOnErr::new(Box::new(FailingIter { index: 0, fail_at: 5 }) as Box<dyn Iter<Item = usize>>);
+	let mut items = Vec::new();
+	let mut errors = 0;
+	while let Some(r) = it.next() {
+		match r {
+			Ok(Some(item)) => items.push(item),
+			Ok(None) => break,
+			Err(_) => errors += 1,
+		}
+	}
+	assert_eq!(items, vec![1, 2, 3, 4]);
+	assert_eq!(errors, 1);
+}
+
+
+
+#[test]
+fn test_durability_ack() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let gen = GuidGen::new(0, 0);
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/durability_ack");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"ack", 0..3);
+		let key = Arc::new(wb.bytes.clone());
+		let value = Arc::new(vec![7u8; 1024]);
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let before = log_dir_size(&tab);
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(value.clone()),
+			index: 0,
+		}]), None, false).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		let (logs, mut ack) = txn.commit_with_ack().await.unwrap();
+
+		//逻辑提交返回后，修改对新事务可见，但日志持久化前键值缓冲未更新，确认未完成
+		assert_eq!(logs.len(), 1);
+		assert_eq!(file.get_with_meta(&key).await.unwrap().map(|(v, _)| v.to_vec()), Some(value.to_vec()));
+		assert!(file.1.read(&key).is_none());
+		assert!(ack.try_wait().is_none());
+
+		//持久化确认完成后，日志已写入日志文件，键值缓冲已更新
+		assert!(ack.wait().await.is_ok());
+		assert!(log_dir_size(&tab) >= before + value.len() as u64);
+		assert_eq!(file.1.read(&key).map(|v| v.to_vec()), Some(value.to_vec()));
+
+		//带确认的提交未持久化时，同一主键之后的普通提交先完成，键值缓冲仍按日志顺序保留较新的值
+		let mut acks = Vec::new();
+		let mut latest = Vec::new();
+		for round in 0..20u8 {
+			let older = Arc::new(vec![round; 16]);
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(Arc::new(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key.clone(),
+				value: Some(older),
+				index: 0,
+			}]), None, false).await.unwrap();
+			txn.prepare(1000).await.unwrap();
+			let (_, ack) = txn.commit_with_ack().await.unwrap();
+			acks.push(ack);
+
+			latest = vec![round + 100; 16];
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(Arc::new(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key.clone(),
+				value: Some(Arc::new(latest.clone())),
+				index: 0,
+			}]), None, false).await.unwrap();
+			txn.prepare(1000).await.unwrap();
+			txn.commit().await.unwrap();
+		}
+		let mut acked = true;
+		for ack in acks {
+			acked &= ack.wait().await.is_ok();
+		}
+
+		let _ = s.send((acked, file.1.read(&key).map(|v| v.to_vec()), latest));
+	});
+
+	let (acked, buffered, latest) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(acked);
+	assert_eq!(buffered, Some(latest));
+}
+
+
+
+#[test]
+fn test_evict_deleted_tab() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/evict_deleted");
+		let dangling = Atom::from("./testlogfile/evict_dangling");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for t in [&tab, &dangling].iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), t, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"evict", 0..5);
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(wb.bytes.clone()),
+			value: Some(Arc::new(wb.bytes.clone())),
+			index: 0,
+		}], None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+		assert!(LogFileDB::open(&tab).await.is_ok());
+
+		//删除表后，再次打开不会返回已删除的表
+		let mut tr3 = mgr.transaction(true, Some(rt.clone())).await;
+		tr3.alter(&Atom::from("logfile"), &tab, None).await;
+		tr3.prepare().await;
+		tr3.commit().await;
+		match LogFileDB::open(&tab).await {
+			Err(e) => assert!(e.starts_with("TabNotFound")),
+			Ok(_) => panic!("deleted tab opened"),
+		}
+
+		//缓存中残留的表被清理
+		assert!(LogFileDB::open(&dangling).await.is_ok());
+		let info = ALL_TABLES.lock().await.remove(&dangling).unwrap();
+		assert!(LogFileDB::gc_cache().await >= 1);
+		ALL_TABLES.lock().await.insert(dangling.clone(), info);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_binary_safe_keys() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/binary_keys");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//主键中包含NUL、0xFF、换行符和路径分隔符
+		let mut keys = Vec::new();
+		for a in [0u8, b'\n', b'/', 0xff].iter() {
+			for i in 0..3u8 {
+				let payload = [b'k', *a, 0, 0xff, i];
+				let mut wb = WriteBuffer::new();
+				wb.write_bin(&payload, 0..payload.len());
+				keys.push((payload.to_vec(), Arc::new(wb.bytes)));
+			}
+		}
+
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(keys.iter().map(|(payload, key)| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(Arc::new(payload.clone())),
+			index: 0,
+		}).collect(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		//重新加载前后，查询、读取和迭代的结果都与写入一致
+		let file = LogFileDB::open(&tab).await.unwrap();
+		for reload in [false, true].iter() {
+			if *reload {
+				assert!(file.reload().await.is_ok());
+			}
+
+			for (payload, key) in keys.iter() {
+				assert_eq!(file.1.read(key).map(|v| v.to_vec()), Some(payload.clone()));
+			}
+
+			let mut tr3 = mgr.transaction(false, Some(rt.clone())).await;
+			let r = tr3.query(keys.iter().map(|(_, key)| TabKV::new(Atom::from("logfile"), tab.clone(), key.clone())).collect(), None, false).await.unwrap();
+			for ((payload, _), item) in keys.iter().zip(r.iter()) {
+				assert_eq!(item.value, Some(Arc::new(payload.clone())));
+			}
+
+			let mut found = 0;
+			let mut it = tr3.iter(&Atom::from("logfile"), &tab, None, false, None).await.unwrap();
+			while let Some(Ok(Some((key, value)))) = it.next() {
+				let (payload, _) = keys.iter().find(|(_, k)| k == &key).unwrap();
+				assert_eq!(value.as_ref(), payload);
+				found += 1;
+			}
+			assert_eq!(found, keys.len());
+
+			//前缀扫描：从前缀为[b'k', 0xff]的第一个主键开始迭代，直到主键不再匹配前缀
+			let prefix = [b'k', 0xff];
+			let start = keys.iter().find(|(payload, _)| payload.starts_with(&prefix)).unwrap().1.clone();
+			let mut matched = Vec::new();
+			let mut it = tr3.iter(&Atom::from("logfile"), &tab, Some(start), false, None).await.unwrap();
+			while let Some(Ok(Some((_, value)))) = it.next() {
+				if !value.starts_with(&prefix) {
+					break;
+				}
+				matched.push(value.to_vec());
+			}
+			let expect: Vec<Vec<u8>> = keys.iter().filter(|(payload, _)| payload.starts_with(&prefix)).map(|(payload, _)| payload.clone()).collect();
+			assert_eq!(matched, expect);
+
+			tr3.prepare().await;
+			tr3.commit().await;
+		}
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_fair_tab_lock() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (result_s, result_r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/fair_lock");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		FAIR_TAB_LOCK.store(true, Ordering::Relaxed);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let stop = Arc::new(AtomicBool::new(false));
+		let shorts = 8;
+		let mut done = Vec::with_capacity(shorts);
+		let gen = Arc::new(GuidGen::new(0, 0));
+
+		//多个短事务持续竞争内存表锁，结束时通过异步通道通知，不阻塞运行时的工作线程
+		for n in 0..shorts {
+			let file = file.clone();
+			let tab = tab.clone();
+			let stop = stop.clone();
+			let (s, r) = futures::channel::oneshot::channel();
+			done.push(r);
+			let gen = gen.clone();
+			let _ = rt.spawn(rt.alloc(), async move {
+				while !stop.load(Ordering::Relaxed) {
+					let mut wb = WriteBuffer::new();
+					let key = format!("short{:?}", n);
+					wb.write_bin(key.as_bytes(), 0..key.len());
+					let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+					let _ = txn.modify(Arc::new(vec![TabKV {
+						ware: Atom::from("logfile"),
+						tab: tab.clone(),
+						key: Arc::new(wb.bytes.clone()),
+						value: Some(Arc::new(wb.bytes)),
+						index: 0,
+					}]), None, false).await;
+					if txn.prepare(1000).await.is_ok() {
+						let _ = txn.commit().await;
+					}
+				}
+				let _ = s.send(());
+			});
+		}
+
+		//长事务在短事务持续竞争时，只需等待已排在它前面的短事务
+		let items: Vec<TabKV> = (0..1000).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("long{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}
+		}).collect();
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(items), None, false).await.unwrap();
+
+		let before = LogFileDB::tab_stats(&tab).lock_acquires;
+		txn.prepare(1000).await.unwrap();
+		let acquired = LogFileDB::tab_stats(&tab).lock_acquires - before;
+		txn.commit().await.unwrap();
+
+		stop.store(true, Ordering::Relaxed);
+		for r in done {
+			r.await.unwrap();
+		}
+		FAIR_TAB_LOCK.store(false, Ordering::Relaxed);
+
+		let _ = result_s.send((acquired, shorts));
+	});
+
+	let (acquired, shorts) = result_r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(acquired <= 2 * shorts + 2, "long txn waited for {} acquisitions", acquired);
+}
+
+
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_preallocate_log_file() {
+	use std::os::unix::fs::MetadataExt;
+	use pi_store::log_store::log_file::read_log_paths;
+
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/preallocate");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+		LogFileDB::set_tab_config(&tab, TabConfig { log_file_size: Some(1), ..TabConfig::default() }).await.unwrap();
+
+		//分裂后新的可写日志文件立即分配了配置的日志文件大小的空间，但文件大小不变
+		PREALLOCATE_LOG_FILE.store(true, Ordering::Relaxed);
+		LogFileDB::force_split_tab(&tab).await.unwrap();
+		PREALLOCATE_LOG_FILE.store(false, Ordering::Relaxed);
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let paths = read_log_paths(&file.1.log_file).await.unwrap();
+		let meta = std::fs::metadata(paths.last().unwrap()).unwrap();
+		assert!(meta.blocks() * 512 >= 1024 * 1024);
+		assert!(meta.len() < 1024 * 1024);
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_verify_key_order() {
+	let keys: Vec<Vec<u8>> = ["a", "c", "b"].iter().map(|key| {
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(key.as_bytes(), 0..key.len());
+		wb.bytes
+	}).collect();
+
+	//注入顺序错误的主键，校验报告第一个违反顺序的主键
+	match verify_key_order(keys.iter()) {
+		Err(e) => assert!(e.starts_with("KeyOrderViolation: index: 2")),
+		Ok(_) => panic!("out of order key not detected"),
+	}
+	let mut sorted = keys.clone();
+	sorted.sort();
+	assert!(verify_key_order(sorted.iter()).is_ok());
+
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let tab = Atom::from("./testlogfile/verify_order");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(keys.iter().map(|key| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(key.clone()),
+			value: Some(Arc::new(key.clone())),
+			index: 0,
+		}).collect(), None, false).await;
+		tr2.prepare().await;
+		tr2.commit().await;
+
+		//正常的表在校验模式和普通模式下都可以加载
+		let file = LogFileDB::open(&tab).await.unwrap();
+		VERIFY_KEY_ORDER.store(true, Ordering::Relaxed);
+		assert!(file.reload().await.is_ok());
+		VERIFY_KEY_ORDER.store(false, Ordering::Relaxed);
+		assert!(file.reload().await.is_ok());
+	});
+
+	std::thread::sleep(std::time::Duration::from_secs(3));
+}
+
+
+
+#[test]
+fn test_concurrent_increment() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/counter_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::I64);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"hits", 0..4);
+		let key = Arc::new(wb.bytes);
+		let workers = 8;
+		let rounds = 50;
+		let (ws, wr) = bounded(workers);
+		let gen = Arc::new(GuidGen::new(0, 0));
+
+		//多个事务并发累加同一个计数器，预提交不冲突
+		for n in 0..workers {
+			let file = file.clone();
+			let key = key.clone();
+			let ws = ws.clone();
+			let gen = gen.clone();
+			let _ = rt.spawn(rt.alloc(), async move {
+				let mut conflicts = 0;
+				for _ in 0..rounds {
+					let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+					txn.increment(key.clone(), n as i64 + 1).await.unwrap();
+					match txn.prepare(1000).await {
+						Err(_) => conflicts += 1,
+						Ok(_) => {
+							txn.commit().await.unwrap();
+						},
+					}
+				}
+				let _ = ws.send(conflicts);
+			});
+		}
+
+		let mut conflicts = 0;
+		for _ in 0..workers {
+			conflicts += wr.recv_timeout(Duration::from_secs(30)).unwrap();
+		}
+
+		let query = |file: LogFileDB| {
+			let tab = tab.clone();
+			let key = key.clone();
+			let gen = gen.clone();
+			async move {
+				let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+				let r = txn.query(Arc::new(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab,
+					key: key,
+					value: None,
+					index: 0,
+				}]), None, false).await.unwrap();
+				i64::decode(&mut ReadBuffer::new(r[0].value.as_ref().unwrap(), 0)).unwrap()
+			}
+		};
+		let expect = (1..=workers as i64).sum::<i64>() * rounds;
+		let total = query(file.clone()).await;
+
+		//重新加载后，日志文件中保存的是累加后的值
+		file.reload().await.unwrap();
+		let reloaded = query(file.clone()).await;
+
+		//计数器的值不是有效的整数编码，则累加的预提交失败，原值不变
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"bad", 0..3);
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(Arc::new(wb.bytes)),
+			index: 0,
+		}]), None, false).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.increment(key.clone(), 1).await.unwrap();
+		let invalid = txn.prepare(1000).await;
+
+		let _ = s.send((conflicts, expect, total, reloaded, invalid));
+	});
+
+	let (conflicts, expect, total, reloaded, invalid) = r.recv_timeout(Duration::from_secs(60)).unwrap();
+	assert_eq!(conflicts, 0);
+	assert_eq!(total, expect);
+	assert_eq!(reloaded, expect);
+	assert!(invalid.unwrap_err().starts_with("InvalidCounter"));
+}
+
+
+
+#[test]
+fn test_evict_memory() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/evict_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let items: Vec<TabKV> = (0..100).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("evict{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}
+		}).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(items.clone(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//重新加载，使表的加载大小包含已写入的记录
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.reload().await.unwrap();
+
+		let before = LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed);
+		let evicted = file.evict_memory().await.unwrap();
+		let after = LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed);
+		//重复释放没有影响
+		let again = file.evict_memory().await.unwrap();
+
+		//句柄仍在缓存中，读取时重新加载
+		let cached = LogFileDB::open(&tab).await.unwrap();
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let values = tr.query(items.iter().map(|item| TabKV {
+			ware: item.ware.clone(),
+			tab: item.tab.clone(),
+			key: item.key.clone(),
+			value: None,
+			index: 0,
+		}).collect(), None, false).await.unwrap();
+		let reloaded = LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed);
+		let matched = values.iter().zip(items.iter()).all(|(v, item)| v.value == item.value);
+		let size = cached.approx_size();
+
+		let _ = s.send((evicted, before, after, again, reloaded, matched, size));
+	});
+
+	let (evicted, before, after, again, reloaded, matched, size) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(evicted > 0);
+	assert!(after < before);
+	assert_eq!(again, 0);
+	assert!(reloaded >= after + evicted as u64);
+	assert!(matched);
+	assert_eq!(size, 100);
+}
+
+
+
+#[test]
+fn test_iter_stable_across_reload() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/stable_iter_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//包含多字节字符和互为前缀的主键，分两批写入不同的日志文件
+		let keys = vec!["b", "a", "ab", "abc", "aa", "中", "中文", "中文字", "z", "Z", "0", "00", "\u{1F600}", "a\u{1F600}", "é", "e"];
+		let file = LogFileDB::open(&tab).await.unwrap();
+		for (n, batch) in keys.chunks(keys.len() / 2).enumerate() {
+			let items: Vec<TabKV> = batch.iter().map(|key| {
+				let mut wb = WriteBuffer::new();
+				Atom::from(*key).encode(&mut wb);
+				TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab.clone(),
+					key: Arc::new(wb.bytes.clone()),
+					value: Some(Arc::new(format!("{}{}", key, n).into_bytes())),
+					index: 0,
+				}
+			}).collect();
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+			file.split().await.unwrap();
+		}
+
+		let collect = |file: LogFileDB| {
+			let tab = tab.clone();
+			async move {
+				let txn = file.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap();
+				let mut iter = txn.iter(&tab, None, false, None).await.unwrap();
+				let mut pairs = vec![];
+				while let Some(Ok(Some((key, value)))) = iter.next() {
+					pairs.push((key.to_vec(), value.to_vec()));
+				}
+				pairs
+			}
+		};
+
+		let before = collect(file.clone()).await;
+		file.reload().await.unwrap();
+		let after = collect(file.clone()).await;
+		let ordered = verify_key_order(after.iter().map(|(key, _)| key));
+
+		let _ = s.send((keys.len(), before, after, ordered));
+	});
+
+	let (len, before, after, ordered) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(before.len(), len);
+	assert_eq!(before, after);
+	assert!(ordered.is_ok());
+}
+
+
+
+#[test]
+fn test_max_prepared() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/max_prepared_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = Arc::new(GuidGen::new(0, 0));
+		let item = |n: usize, len: usize| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("prepared{:?}", n);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes),
+				value: Some(Arc::new(vec![0; len])),
+				index: 0,
+			}])
+		};
+
+		//预提交多个事务但不提交，超过数量限制后拒绝新的预提交
+		let limit = 16;
+		MAX_PREPARED_TXNS.store(limit, Ordering::Relaxed);
+		let mut txns = vec![];
+		for n in 0..limit {
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(item(n, 8), None, false).await.unwrap();
+			txn.prepare(1000).await.unwrap();
+			txns.push(txn);
+		}
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(item(limit, 8), None, false).await.unwrap();
+		let rejected = txn.prepare(1000).await;
+
+		//回滚一个已预提交的事务后，可以再次预提交
+		txns.pop().unwrap().rollback().await.unwrap();
+		let accepted = txn.prepare(1000).await;
+		txns.push(txn);
+		MAX_PREPARED_TXNS.store(0, Ordering::Relaxed);
+
+		//超过大小限制后拒绝新的预提交
+		MAX_PREPARED_SIZE.store(1024 * 1024, Ordering::Relaxed);
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(item(limit + 1, 1024 * 1024), None, false).await.unwrap();
+		let too_large = txn.prepare(1000).await;
+		MAX_PREPARED_SIZE.store(0, Ordering::Relaxed);
+
+		for txn in txns {
+			let _ = txn.rollback().await;
+		}
+
+		let _ = s.send((rejected, accepted, too_large));
+	});
+
+	let (rejected, accepted, too_large) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(rejected.unwrap_err().starts_with("TooManyPrepared"));
+	assert!(accepted.is_ok());
+	assert!(too_large.unwrap_err().starts_with("TooManyPrepared"));
+}
+
+
+
+#[test]
+fn test_get_as_of() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/as_of_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		AS_OF_HISTORY.store(8, Ordering::Relaxed);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = Arc::new(GuidGen::new(0, 0));
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"audit", 0..5);
+		let key = Arc::new(wb.bytes);
+
+		//写入同一个主键三次，并记录每次的提交序号
+		let write = |value: &'static str| {
+			let file = file.clone();
+			let tab = tab.clone();
+			let key = key.clone();
+			let gen = gen.clone();
+			async move {
+				let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+				txn.modify(Arc::new(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab,
+					key: key,
+					value: Some(Arc::new(value.as_bytes().to_vec())),
+					index: 0,
+				}]), None, false).await.unwrap();
+				txn.prepare(1000).await.unwrap();
+				txn.commit().await.unwrap();
+				txn.commit_seq().await.unwrap()
+			}
+		};
+		let seqs = vec![write("v1").await, write("v2").await, write("v3").await];
+
+		let reader = file.transaction(&gen.gen(0), false).await.unwrap();
+		let mut values = vec![];
+		for seq in seqs.iter() {
+			values.push(reader.get_as_of(&tab, key.clone(), *seq).await.unwrap());
+		}
+		//第一次写入之前主键不存在
+		let before = reader.get_as_of(&tab, key.clone(), seqs[0] - 1).await.unwrap();
+
+		//历史版本超过保留数量后，最旧的版本从日志文件中扫描读取
+		AS_OF_HISTORY.store(2, Ordering::Relaxed);
+		let seq4 = write("v4").await;
+		LogFileDB::flush(0).await.unwrap();
+		let trimmed = reader.get_as_of(&tab, key.clone(), seqs[0]).await;
+		let latest = reader.get_as_of(&tab, key.clone(), seq4).await.unwrap();
+
+		//关闭历史版本后，内存中不再保留历史版本，全部从日志文件中扫描读取
+		AS_OF_HISTORY.store(0, Ordering::Relaxed);
+		let _ = write("v5").await;
+		LogFileDB::flush(0).await.unwrap();
+		let scanned = reader.get_as_of(&tab, key.clone(), seqs[1]).await;
+		let scanned_before = reader.get_as_of(&tab, key.clone(), seqs[0] - 1).await;
+
+		let _ = s.send((seqs, values, before, trimmed, latest, scanned, scanned_before));
+	});
+
+	let (seqs, values, before, trimmed, latest, scanned, scanned_before) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(seqs[0] < seqs[1] && seqs[1] < seqs[2]);
+	let values: Vec<Vec<u8>> = values.into_iter().map(|v| v.unwrap().to_vec()).collect();
+	assert_eq!(values, vec![b"v1".to_vec(), b"v2".to_vec(), b"v3".to_vec()]);
+	assert!(before.is_none());
+	assert_eq!(trimmed.unwrap().unwrap().as_slice(), b"v1");
+	assert_eq!(latest.unwrap().as_slice(), b"v4");
+	assert_eq!(scanned.unwrap().unwrap().as_slice(), b"v2");
+	assert!(scanned_before.unwrap().is_none());
+}
+
+
+
+#[test]
+fn test_read_during_collect() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/read_collect_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let make_items = |start: usize, end: usize, version: Option<&str>| -> Vec<TabKV> {
+			(start..end).map(|i| {
+				let mut wb = WriteBuffer::new();
+				let key = format!("read_collect{:?}", i);
+				wb.write_bin(key.as_bytes(), 0..key.len());
+				TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab.clone(),
+					key: Arc::new(wb.bytes),
+					value: version.map(|v| Arc::new(format!("{}-{}-{}", key, v, "x".repeat(128)).into_bytes())),
+					index: 0,
+				}
+			}).collect()
+		};
+
+		//重复写入和删除，使只读日志文件可以被整理
+		for items in vec![make_items(0, 200, Some("v0")), make_items(0, 200, Some("v1")), make_items(100, 200, None)] {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+			LogFileDB::force_split_tab(&tab).await.unwrap();
+		}
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.reload().await.unwrap();
+
+		//整理期间持续读取，每次读取都必须返回完整的最新值
+		let stop = Arc::new(AtomicBool::new(false));
+		let readers = 4;
+		let (rs, rr) = bounded(readers);
+		let expect = make_items(0, 100, Some("v1"));
+		let gen = Arc::new(GuidGen::new(0, 0));
+		for _ in 0..readers {
+			let file = file.clone();
+			let stop = stop.clone();
+			let rs = rs.clone();
+			let gen = gen.clone();
+			let expect = expect.clone();
+			let _ = rt.spawn(rt.alloc(), async move {
+				let mut reads = 0;
+				let mut invalid = 0;
+				while !stop.load(Ordering::Relaxed) {
+					let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+					let query = expect.iter().map(|item| TabKV {
+						value: None,
+						..item.clone()
+					}).collect();
+					match txn.query(Arc::new(query), None, false).await {
+						Err(_) => invalid += 1,
+						Ok(values) => {
+							for (value, item) in values.iter().zip(expect.iter()) {
+								if value.value != item.value {
+									invalid += 1;
+								}
+							}
+						},
+					}
+					reads += 1;
+				}
+				let _ = rs.send((reads, invalid));
+			});
+		}
+
+		let mut collected = 0;
+		for _ in 0..3 {
+			collected += LogFileDB::collect_tombstones(&tab).await.map(|report| report.tabs).unwrap_or(0);
+			collected += LogFileDB::collect_blocking().await.map(|report| report.tabs).unwrap_or(0);
+		}
+		stop.store(true, Ordering::Relaxed);
+
+		let mut reads = 0;
+		let mut invalid = 0;
+		for _ in 0..readers {
+			let (n, e) = rr.recv_timeout(Duration::from_secs(30)).unwrap();
+			reads += n;
+			invalid += e;
+		}
+
+		//整理后重新加载，内容不变
+		file.reload().await.unwrap();
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let values = txn.query(Arc::new(expect.iter().map(|item| TabKV { value: None, ..item.clone() }).collect()), None, false).await.unwrap();
+		let reloaded = values.iter().zip(expect.iter()).all(|(value, item)| value.value == item.value);
+
+		let _ = s.send((collected, reads, invalid, reloaded));
+	});
+
+	let (collected, reads, invalid, reloaded) = r.recv_timeout(Duration::from_secs(120)).unwrap();
+	assert!(collected > 0);
+	assert!(reads > 0);
+	assert_eq!(invalid, 0);
+	assert!(reloaded);
+}
+
+
+
+#[test]
+fn test_format_version() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/format_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"format", 0..6);
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(wb.bytes.clone()),
+			value: Some(Arc::new(wb.bytes)),
+			index: 0,
+		}], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let tab_path = PathBuf::from(tab.as_str());
+		let format_path = PathBuf::from(format!("{}.format", tab.as_str()));
+		//新建的表使用当前的格式版本
+		let current = read_format_version(&tab_path);
+
+		//没有格式文件的旧表按版本1加载
+		std::fs::remove_file(&format_path).unwrap();
+		let v1 = read_format_version(&tab_path);
+		let v1_reload = file.reload().await;
+		let v1_size = file.approx_size();
+
+		//高于当前支持版本的表拒绝加载
+		std::fs::write(&format_path, [LOG_FORMAT_VERSION + 1]).unwrap();
+		let future = file.reload().await;
+		std::fs::write(&format_path, [LOG_FORMAT_VERSION]).unwrap();
+
+		let _ = s.send((current, v1, v1_reload, v1_size, future));
+	});
+
+	let (current, v1, v1_reload, v1_size, future) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(current, Ok(LOG_FORMAT_VERSION));
+	assert_eq!(v1, Ok(1));
+	assert!(v1_reload.is_ok());
+	assert_eq!(v1_size, 1);
+	assert!(future.unwrap_err().starts_with("UnsupportedFormatVersion"));
+}
+
+
+
+#[test]
+fn test_default_timeout() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let db = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let ware_timeout = db.timeout();
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(DatabaseWare::new_log_file_ware(db))).await;
+
+		//未设置默认超时时长时使用库的超时时长
+		let tr = mgr.transaction(true, Some(rt.clone())).await;
+		let before = tr.timeout();
+
+		//设置后新建的事务使用默认超时时长，浅拷贝的管理器共用默认超时时长
+		mgr.set_default_timeout(5000);
+		let tr = mgr.transaction(true, Some(rt.clone())).await;
+		let after = tr.timeout();
+		let shared = mgr.shallow_clone().await.transaction(true, Some(rt.clone())).await.timeout();
+
+		let _ = s.send((ware_timeout, before, after, shared));
+	});
+
+	let (ware_timeout, before, after, shared) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(before, ware_timeout);
+	assert_eq!(after, 5000);
+	assert_eq!(shared, 5000);
+}
+
+
+
+#[test]
+fn test_contains_bitset() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/contains_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let keys: Vec<Arc<Vec<u8>>> = (0..200).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("contains{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		}).collect();
+
+		//只写入能被3整除的主键
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(keys.iter().step_by(3).map(|key| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(key.clone()),
+			index: 0,
+		}).collect(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap();
+		let bits = txn.contains_bitset(&tab, &keys).await.unwrap();
+		let _ = s.send(bits);
+	});
+
+	let bits = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(bits.len(), 200);
+	assert_eq!(bits.count_ones(), 67);
+	for i in 0..200 {
+		assert_eq!(bits.get(i), i % 3 == 0);
+	}
+	assert!(!bits.get(200));
+}
+
+
+
+#[test]
+fn test_move_key() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/move_key_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |name: &str| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(name.as_bytes(), 0..name.len());
+			Arc::new(wb.bytes)
+		};
+		let (a, b, c) = (key("a"), key("b"), key("c"));
+		let value = Arc::new(b"value".to_vec());
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = Arc::new(GuidGen::new(0, 0));
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: a.clone(),
+			value: Some(value.clone()),
+			index: 0,
+		}]), None, false).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+
+		let get = |key: Arc<Vec<u8>>| {
+			let file = file.clone();
+			let tab = tab.clone();
+			let gen = gen.clone();
+			async move {
+				let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+				txn.query(Arc::new(vec![TabKV::new(Atom::from("logfile"), tab, key)]), None, false).await.unwrap().remove(0).value
+			}
+		};
+
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		let same = txn.move_key(a.clone(), a.clone()).await.unwrap();
+		let absent = txn.move_key(c.clone(), b.clone()).await.unwrap();
+		let moved = txn.move_key(a.clone(), b.clone()).await.unwrap();
+		//提交前其它事务看不到移动
+		let uncommitted = (get(a.clone()).await, get(b.clone()).await);
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+		let committed = (get(a.clone()).await, get(b.clone()).await, get(c.clone()).await);
+
+		let _ = s.send((same, absent, moved, uncommitted, committed, value));
+	});
+
+	let (same, absent, moved, uncommitted, committed, value) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(same);
+	assert!(!absent);
+	assert!(moved);
+	assert_eq!(uncommitted, (Some(value.clone()), None));
+	assert_eq!(committed, (None, Some(value), None));
+}
+
+
+
+#[test]
+fn test_inflight_write_backpressure() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/backpressure_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let limit = 2;
+		MAX_INFLIGHT_WRITES.store(limit, Ordering::Relaxed);
+		LogFileDB::take_inflight_write_peak();
+
+		//并发提交的速度超过日志持久化的速度，等待持久化的提交数量不超过限制
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let writers = 32;
+		let (ws, wr) = bounded(writers);
+		let gen = Arc::new(GuidGen::new(0, 0));
+		let mut keys = vec![];
+		for n in 0..writers {
+			let mut wb = WriteBuffer::new();
+			let key = format!("backpressure{:?}", n);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			let key = Arc::new(wb.bytes);
+			keys.push(key.clone());
+
+			let file = file.clone();
+			let tab = tab.clone();
+			let ws = ws.clone();
+			let gen = gen.clone();
+			let _ = rt.spawn(rt.alloc(), async move {
+				let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+				txn.modify(Arc::new(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab,
+					key: key.clone(),
+					value: Some(Arc::new(vec![n as u8; 64 * 1024])),
+					index: 0,
+				}]), None, false).await.unwrap();
+				txn.prepare(1000).await.unwrap();
+				let r = if n % 2 == 0 {
+					match txn.commit_with_ack().await {
+						Err(e) => Err(e),
+						Ok((_, ack)) => ack.wait().await,
+					}
+				} else {
+					txn.commit().await.map(|_| ())
+				};
+				let _ = ws.send(r);
+			});
+		}
+
+		let mut failed = 0;
+		for _ in 0..writers {
+			if wr.recv_timeout(Duration::from_secs(60)).unwrap().is_err() {
+				failed += 1;
+			}
+		}
+		let peak = LogFileDB::take_inflight_write_peak();
+		MAX_INFLIGHT_WRITES.store(0, Ordering::Relaxed);
+
+		//所有写入最终都已提交
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let values = txn.query(Arc::new(keys.iter().map(|key| TabKV::new(Atom::from("logfile"), tab.clone(), key.clone())).collect()), None, false).await.unwrap();
+		let committed = values.iter().filter(|v| v.value.is_some()).count();
+
+		let _ = s.send((limit, peak, failed, committed, writers));
+	});
+
+	let (limit, peak, failed, committed, writers) = r.recv_timeout(Duration::from_secs(120)).unwrap();
+	assert!(peak > 0 && peak <= limit);
+	assert_eq!(failed, 0);
+	assert_eq!(committed, writers);
+}
+
+
+
+#[test]
+fn test_export_snapshot() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/export_src_{}", now));
+		let exported = Atom::from(format!("./testlogfile/export_dst_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let keys: Vec<Arc<Vec<u8>>> = (0..100).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("export{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		}).collect();
+		let write = |range: std::ops::Range<usize>, value: Option<&str>| -> Vec<TabKV> {
+			range.map(|i| TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: keys[i].clone(),
+				value: value.map(|v| Arc::new(format!("{}{}", v, i).into_bytes())),
+				index: 0,
+			}).collect()
+		};
+
+		//写入后覆盖一部分，再删除一部分
+		for items in vec![write(0..100, Some("old")), write(0..50, Some("new")), write(70..100, None)] {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let count = file.export_snapshot(exported.as_str()).await.unwrap();
+		//不能覆盖已存在的路径
+		let again = file.export_snapshot(exported.as_str()).await.is_err();
+
+		//将导出的日志作为新表打开
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &exported, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let values = tr.query(keys.iter().map(|key| TabKV::new(Atom::from("logfile"), exported.clone(), key.clone())).collect(), None, false).await.unwrap();
+		let expected: Vec<Option<Arc<Vec<u8>>>> = (0..100).map(|i| if i < 50 {
+			Some(Arc::new(format!("new{}", i).into_bytes()))
+		} else if i < 70 {
+			Some(Arc::new(format!("old{}", i).into_bytes()))
+		} else {
+			None
+		}).collect();
+		let matched = values.iter().zip(expected.iter()).all(|(v, e)| &v.value == e);
+
+		//导出的日志没有删除记录
+		let file = LogFileDB::open(&exported).await.unwrap();
+		let size = file.approx_size();
+		let tombstones = LogFileDB::tombstones(&exported).await.unwrap().len();
+
+		let _ = s.send((count, again, matched, size, tombstones));
+	});
+
+	let (count, again, matched, size, tombstones) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(count, 70);
+	assert!(again);
+	assert!(matched);
+	assert_eq!(size, 70);
+	assert_eq!(tombstones, 0);
+}
+
+
+
+#[test]
+fn test_commit_after_prepare_failed() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/not_prepared_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"not_prepared", 0..12);
+		let item = TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(wb.bytes.clone()),
+			value: Some(Arc::new(wb.bytes)),
+			index: 0,
+		};
+
+		//两个事务修改同一个主键，后预提交的事务冲突
+		let mut tr1 = mgr.transaction(true, Some(rt.clone())).await;
+		tr1.modify(vec![item.clone()], None, false).await.unwrap();
+		tr1.prepare().await.unwrap();
+		let mut tr2 = mgr.transaction(true, Some(rt.clone())).await;
+		tr2.modify(vec![item.clone()], None, false).await.unwrap();
+		let prepared = tr2.prepare().await.is_err();
+		let prepare_failed = tr2.commit().await.unwrap_err();
+		let rollbacked = tr2.rollback().await.is_ok() && tr2.rollback().await.is_ok();
+		tr1.commit().await.unwrap();
+
+		//未预提交的事务不能提交
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&GuidGen::new(0, 0).gen(0), true).await.unwrap();
+		txn.modify(Arc::new(vec![item.clone()]), None, false).await.unwrap();
+		let not_prepared = txn.commit().await.unwrap_err();
+		txn.prepare(1000).await.unwrap();
+		let committed = txn.commit().await.is_ok();
+
+		let _ = s.send((prepared, prepare_failed, rollbacked, not_prepared, committed));
+	});
+
+	let (prepared, prepare_failed, rollbacked, not_prepared, committed) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(prepared);
+	assert!(prepare_failed.starts_with("PrepareFailed:"));
+	assert!(rollbacked);
+	assert!(not_prepared.starts_with("NotPrepared:"));
+	assert!(committed);
+}
+
+
+
+#[test]
+fn test_create_and_modify_in_one_txn() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/create_modify_{}", now));
+		let items: Vec<TabKV> = (0..10).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("create_modify{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}
+		}).collect();
+		let keys: Vec<TabKV> = items.iter().map(|item| TabKV::new(item.ware.clone(), item.tab.clone(), item.key.clone())).collect();
+
+		//在同一个事务中创建表并写入
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await.unwrap();
+		tr.modify(items.clone(), None, false).await.unwrap();
+		let values = tr.query(keys.clone(), None, false).await.unwrap();
+		let visible = values.iter().zip(items.iter()).all(|(v, item)| v.value == item.value);
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		let created = ALL_TABLES.lock().await.contains_key(&tab);
+
+		//提交后从日志文件重新加载，读取已写入的记录
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.evict_memory().await.unwrap();
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let values = tr.query(keys, None, false).await.unwrap();
+		let matched = values.iter().zip(items.iter()).all(|(v, item)| v.value == item.value);
+
+		let _ = s.send((visible, created, matched));
+	});
+
+	let (visible, created, matched) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(visible);
+	assert!(created);
+	assert!(matched);
+}
+
+#[test]
+fn test_create_and_modify_discarded() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let existed = Atom::from(format!("./testlogfile/create_discard_existed_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &existed, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let item = |tab: &Atom| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(b"create_discard", 0..14);
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}
+		};
+
+		//在同一个事务中创建表并写入后回滚
+		let rollbacked = Atom::from(format!("./testlogfile/create_discard_rollback_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &rollbacked, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await.unwrap();
+		tr.modify(vec![item(&rollbacked)], None, false).await.unwrap();
+		let opened = std::path::Path::new(rollbacked.as_str()).exists();
+		tr.rollback().await.unwrap();
+
+		//在同一个事务中创建表并写入，重复创建已存在的表使元信息提交失败
+		let failed = Atom::from(format!("./testlogfile/create_discard_failed_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &failed, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await.unwrap();
+		tr.alter(&Atom::from("logfile"), &existed, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await.unwrap();
+		tr.modify(vec![item(&failed)], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		let commit_failed = tr.commit().await.is_err();
+
+		let mut leaked = Vec::new();
+		for tab in [&rollbacked, &failed].iter() {
+			let path = std::path::Path::new(tab.as_str());
+			let format = format!("{}.format", tab.as_str());
+			let not_found = LogFileDB::open(tab).await.err().map_or(false, |e| e.starts_with("TabNotFound: "));
+			leaked.push(path.exists() || std::path::Path::new(&format).exists() || !not_found);
+		}
+
+		let _ = s.send((opened, commit_failed, leaked));
+	});
+
+	let (opened, commit_failed, leaked) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(opened);
+	assert!(commit_failed);
+	//回滚或元信息提交失败后，表的目录、格式文件和缓存都已清理
+	assert_eq!(leaked, vec![false, false]);
+}
+
+
+
+#[test]
+fn test_load_buffer_sizes() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/load_buffer_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let count = 20000;
+		let deleted = 1000;
+		let items: Vec<TabKV> = (0..count).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("load_buffer{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}
+		}).collect();
+		let removes: Vec<TabKV> = items.iter().take(deleted).map(|item| TabKV::new(item.ware.clone(), item.tab.clone(), item.key.clone())).collect();
+		for items in vec![items, removes] {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+
+		//重新加载后，已加载关键字表为空，已删除关键字表只包含删除的关键字
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.reload().await.unwrap();
+		let (tmp_len, removed_len) = file.1.buffer_sizes();
+		let size = file.approx_size();
+
+		let _ = s.send((tmp_len, removed_len, size, count, deleted));
+	});
+
+	let (tmp_len, removed_len, size, count, deleted) = r.recv_timeout(Duration::from_secs(60)).unwrap();
+	assert_eq!(tmp_len, 0);
+	assert_eq!(removed_len, deleted);
+	assert_eq!(size, count - deleted);
+}
+
+
+
+#[test]
+fn test_key_transform() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/key_transform_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//忽略大小写的主键
+		let transform: KeyTransform = Arc::new(|key: &[u8]| {
+			match ReadBuffer::new(key, 0).read_bin() {
+				Err(_) => key.to_vec(),
+				Ok(bin) => {
+					let lower = bin.to_ascii_lowercase();
+					let mut wb = WriteBuffer::new();
+					wb.write_bin(&lower, 0..lower.len());
+					wb.bytes
+				},
+			}
+		});
+		LogFileDB::set_key_transform(&tab, Some(transform));
+
+		let key = |s: &str| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(s.as_bytes(), 0..s.len());
+			Arc::new(wb.bytes)
+		};
+		let item = |k: &str, v: &str| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key(k),
+			value: Some(Arc::new(v.as_bytes().to_vec())),
+			index: 0,
+		};
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![item("Hello", "v1")], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let values = tr.query(vec![
+			TabKV::new(Atom::from("logfile"), tab.clone(), key("hello")),
+			TabKV::new(Atom::from("logfile"), tab.clone(), key("HELLO")),
+		], None, false).await.unwrap();
+		let found = values.iter().all(|v| v.value == Some(Arc::new(b"v1".to_vec())));
+
+		//不同大小写的写入修改同一条记录
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![item("HELLO", "v2")], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap();
+		let mut it = txn.iter(&tab, Some(key("hELLo")), false, None).await.unwrap();
+		let mut records = vec![];
+		while let Some(Ok(Some((k, v)))) = it.next() {
+			records.push((k, v));
+		}
+		LogFileDB::set_key_transform(&tab, None);
+
+		let _ = s.send((found, records, key("hello")));
+	});
+
+	let (found, records, expected) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(found);
+	assert_eq!(records.len(), 1);
+	assert_eq!(records[0].0, expected);
+	assert_eq!(records[0].1, Arc::new(b"v2".to_vec()));
+}
+
+
+
+#[test]
+fn test_tab_checkpoint() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/tab_checkpoint_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let keys: Vec<Arc<Vec<u8>>> = (0..100).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("tab_checkpoint{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		}).collect();
+
+		//多次覆盖和删除，并分裂出多个只读日志文件
+		let file = LogFileDB::open(&tab).await.unwrap();
+		for round in 0..3 {
+			let items: Vec<TabKV> = keys.iter().enumerate().map(|(i, key)| TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key.clone(),
+				value: if round == 2 && i >= 70 { None } else { Some(Arc::new(vec![round as u8; 128])) },
+				index: 0,
+			}).collect();
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+			file.split().await.unwrap();
+		}
+
+		let log_size = |paths: &[PathBuf]| paths.iter().map(|p| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0)).sum::<u64>();
+		let before = log_size(&std::fs::read_dir(tab.as_str()).unwrap().map(|e| e.unwrap().path()).collect::<Vec<PathBuf>>());
+		let checkpoint = file.checkpoint().await.unwrap();
+		let after = log_size(&checkpoint.logs);
+
+		//检查点后只有一个整理后的只读日志文件，没有删除记录，记录都在新的可写日志文件之前
+		let tombstones = LogFileDB::tombstones(&tab).await.unwrap().len();
+		file.reload().await.unwrap();
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let values = tr.query(keys.iter().map(|key| TabKV::new(Atom::from("logfile"), tab.clone(), key.clone())).collect(), None, false).await.unwrap();
+		let matched = values.iter().enumerate().all(|(i, v)| if i < 70 {
+			v.value == Some(Arc::new(vec![2u8; 128]))
+		} else {
+			v.value.is_none()
+		});
+		let newer = checkpoint.logs.iter().all(|p| p.file_name().unwrap().to_str().unwrap().parse::<usize>().unwrap() < checkpoint.position.log_id);
+
+		let _ = s.send((checkpoint.logs.len(), before, after, tombstones, matched, newer, file.approx_size()));
+	});
+
+	let (logs, before, after, tombstones, matched, newer, size) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(logs, 1);
+	assert!(after < before);
+	assert_eq!(tombstones, 0);
+	assert!(matched);
+	assert!(newer);
+	assert_eq!(size, 70);
+}
+
+
+
+#[test]
+fn test_storage_unavailable() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+
+		//数据库路径是文件，或在文件下无法创建目录
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let _ = std::fs::create_dir_all("./testlogfile");
+		let file_path = format!("./testlogfile/storage_file_{}", now);
+		std::fs::write(&file_path, b"not a directory").unwrap();
+		let not_dir = LogFileDB::try_new(Atom::from(file_path.as_str()), 1024 * 1024 * 1024).await.err();
+		let under_file = LogFileDB::try_new(Atom::from(format!("{}/db", file_path)), 1024 * 1024 * 1024).await.err();
+		let _ = std::fs::remove_file(&file_path);
+
+		let _ = s.send((not_dir, under_file, file_path));
+	});
+
+	let (not_dir, under_file, file_path) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	let not_dir = not_dir.unwrap();
+	assert!(not_dir.starts_with("StorageUnavailable:"));
+	assert!(not_dir.contains(&file_path));
+	assert!(not_dir.contains("not a directory"));
+	assert!(under_file.unwrap().starts_with("StorageUnavailable:"));
+}
+
+
+
+#[test]
+fn test_multi_get_sorted() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/multi_get_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//只写入偶数主键，奇数主键不存在
+		let key = |i: usize| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("multi_get{:05}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		};
+		let count = 5000;
+		let items: Vec<TabKV> = (0..count).map(|i| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key(i * 2),
+			value: Some(Arc::new(format!("{}", i * 2).into_bytes())),
+			index: 0,
+		}).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(items, None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//伪随机的1000个主键，包括不存在和重复的主键
+		let mut seed: u64 = 0x2545F4914F6CDD1D;
+		let keys: Vec<Arc<Vec<u8>>> = (0..1000).map(|_| {
+			seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+			key((seed >> 33) as usize % (count * 2))
+		}).collect();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap();
+		LogFileDB::take_multi_get_steps(&tab);
+		let values = txn.multi_get_sorted(&tab, &keys).await.unwrap();
+		let steps = LogFileDB::take_multi_get_steps(&tab);
+		let expected = txn.query(Arc::new(keys.iter().map(|key| TabKV::new(Atom::from("logfile"), tab.clone(), key.clone())).collect()), None, false).await.unwrap();
+		let matched = values.len() == expected.len() && values.iter().zip(expected.iter()).all(|(v, e)| v == &e.value);
+		let found = values.iter().filter(|v| v.is_some()).count();
+		let unique = keys.iter().collect::<std::collections::BTreeSet<_>>().len();
+
+		//相距很远的少量主键，直接定位而不遍历中间的记录
+		let sparse: Vec<Arc<Vec<u8>>> = (0..10).map(|i| key(i * 1000)).collect();
+		let sparse_values = txn.multi_get_sorted(&tab, &sparse).await.unwrap();
+		let sparse_steps = LogFileDB::take_multi_get_steps(&tab);
+		let sparse_found = sparse_values.iter().all(|v| v.is_some());
+
+		let _ = s.send((matched, found, steps, unique, sparse_found, sparse_steps));
+	});
+
+	let (matched, found, steps, unique, sparse_found, sparse_steps) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(matched);
+	assert!(found > 0 && found < 1000);
+	//只向前遍历一次内存表，每条记录最多访问一次，每个主键最多遍历8条记录
+	assert!(steps > 0 && steps <= 5000);
+	assert!(steps <= unique * 9);
+	assert!(sparse_found);
+	assert!(sparse_steps > 0 && sparse_steps <= 10 * 9);
+}
+
+
+
+#[test]
+fn test_quarantine_corrupt_log() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/quarantine_{}", now));
+		let key = |name: &str, i: usize| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("quarantine_{}{:?}", name, i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			wb.bytes
+		};
+
+		//在创建表之前写入三个日志文件，前两个分裂为只读日志文件
+		{
+			let file = AsyncLogFileStore::open(tab.as_str(), 8000, 1024 * 1024, None).await.unwrap();
+			let store = AsyncLogFileStore::new(file);
+			for name in ["a", "b", "c"].iter() {
+				let bins: Vec<(Vec<u8>, Vec<u8>)> = (0..100).map(|i| (key(name, i), vec![i as u8; 64])).collect();
+				let pairs: Vec<(&[u8], &[u8])> = bins.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+				store.write_batch(&pairs).await.unwrap();
+				if *name != "c" {
+					store.force_fork().await.unwrap();
+				}
+			}
+		}
+
+		//破坏最旧的只读日志文件
+		let mut logs: Vec<PathBuf> = std::fs::read_dir(tab.as_str()).unwrap()
+			.map(|entry| entry.unwrap().path())
+			.filter(|path| path.file_name().unwrap().to_str().unwrap().parse::<usize>().is_ok())
+			.collect();
+		logs.sort();
+		let corrupt = logs[0].clone();
+		let mut bin = std::fs::read(&corrupt).unwrap();
+		let len = bin.len();
+		for b in bin[len / 4..len * 3 / 4].iter_mut() {
+			*b = !*b;
+		}
+		std::fs::write(&corrupt, bin).unwrap();
+
+		QUARANTINE_CORRUPT_LOGS.store(true, Ordering::Relaxed);
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+		let file = LogFileDB::open(&tab).await.unwrap();
+		QUARANTINE_CORRUPT_LOGS.store(false, Ordering::Relaxed);
+
+		//只丢失被隔离的日志文件中的记录
+		let txn = file.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap();
+		let mut found = XHashMap::default();
+		for name in ["a", "b", "c"].iter() {
+			let keys: Vec<Arc<Vec<u8>>> = (0..100).map(|i| Arc::new(key(name, i))).collect();
+			let values = txn.multi_get_sorted(&tab, &keys).await.unwrap();
+			found.insert(name.to_string(), values.iter().filter(|v| v.is_some()).count());
+		}
+		let quarantined = LogFileDB::health_report().quarantined_logs.iter().any(|(path, _)| {
+			path.file_name() == corrupt.file_name() && path.to_str().unwrap().contains(".corrupt")
+		});
+		let moved = !corrupt.exists();
+
+		let _ = s.send((found, quarantined, moved));
+	});
+
+	let (found, quarantined, moved) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(found.get("a"), Some(&0));
+	assert_eq!(found.get("b"), Some(&100));
+	assert_eq!(found.get("c"), Some(&100));
+	assert!(quarantined);
+	assert!(moved);
+}
+
+
+
+#[test]
+fn test_iter_from_missing_key() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/iter_pivot_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |i: usize| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("pivot{:02}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		};
+		let items: Vec<TabKV> = [10, 20, 30].iter().map(|i| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key(*i),
+			value: Some(key(*i)),
+			index: 0,
+		}).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(items, None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let txn = file.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap();
+		let mut results = vec![];
+		//起始主键在两个已有主键之间，以及起始主键存在
+		for (pivot, descending) in vec![(15, false), (15, true), (20, true), (35, true), (5, false)] {
+			let mut keys = vec![];
+			let mut it = txn.iter(&tab, Some(key(pivot)), descending, None).await.unwrap();
+			while let Some(Ok(Some((k, _)))) = it.next() {
+				keys.push(k);
+			}
+			let mut only_keys = vec![];
+			let mut it = txn.key_iter(Some(key(pivot)), descending, None).await.unwrap();
+			while let Some(Ok(Some(k))) = it.next() {
+				only_keys.push(k);
+			}
+			results.push((keys, only_keys));
+		}
+
+		let expected = vec![
+			vec![key(20), key(30)],
+			vec![key(10)],
+			vec![key(20), key(10)],
+			vec![key(30), key(20), key(10)],
+			vec![key(10), key(20), key(30)],
+		];
+		let _ = s.send((results, expected));
+	});
+
+	let (results, expected) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	for ((keys, only_keys), expected) in results.into_iter().zip(expected.into_iter()) {
+		assert_eq!(keys, expected);
+		assert_eq!(only_keys, expected);
+	}
+}
+
+
+
+//将提交事件转发到通道的监听器
+struct ChannelMonitor(crossbeam_channel::Sender<Event>);
+
+impl Monitor for ChannelMonitor {
+	fn notify(&self, event: Event, _mgr: Mgr) {
+		let _ = self.0.send(event);
+	}
+}
+
+#[test]
+fn test_trace_id_in_commit_event() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let (event_sender, event_receiver) = bounded(16);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/trace_id_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		mgr.add_monitor(Arc::new(ChannelMonitor(event_sender))).await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"traced", 0..6);
+		let key = Arc::new(wb.bytes.clone());
+		let item = TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(Arc::new(wb.bytes)),
+			index: 0,
+		};
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.set_trace_id(Some(Atom::from("req-42"))).await;
+		tr.modify(vec![item], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let _ = s.send((tab, key, tr.trace_id()));
+	});
+
+	let (tab, key, trace_id) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(trace_id, Some(Atom::from("req-42")));
+
+	let event = event_receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(event.trace_id, Some(Atom::from("req-42")));
+	assert_eq!(event.ware, Atom::from("logfile"));
+	assert_eq!(event.tab, tab);
+	assert!(event.seq > 0);
+	match event.other {
+		EventType::Tab { key: k, value } => {
+			assert_eq!(k, key);
+			assert!(value.is_some());
+		},
+		_ => panic!("unexpected event"),
+	}
+}
+
+
+
+#[test]
+fn test_key_flags_persist() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/key_flags_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let make_item = |name: &str, value: &str| -> TabKV {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(name.as_bytes(), 0..name.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes),
+				value: Some(Arc::new(value.as_bytes().to_vec())),
+				index: 0,
+			}
+		};
+		let pinned = make_item("pinned", "v1");
+		let plain = make_item("plain", "v1");
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![pinned.clone(), plain.clone()], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.set_flags(pinned.key.clone(), 0b101).await.unwrap();
+		let missing = file.set_flags(make_item("missing", "").key, 1).await.is_err();
+
+		//不带标志的写入保留主键的标志
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![make_item("pinned", "v2")], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//分裂后重新加载，标志从日志记录中恢复
+		LogFileDB::force_split_tab(&tab).await.unwrap();
+		file.reload().await.unwrap();
+		let pinned_meta = file.get_with_meta(&pinned.key).await.unwrap();
+		let plain_meta = file.get_with_meta(&plain.key).await.unwrap();
+		let missing_meta = file.get_with_meta(&make_item("missing", "").key).await.unwrap();
+
+		let _ = s.send((missing, pinned_meta, plain_meta, missing_meta));
+	});
+
+	let (missing, pinned_meta, plain_meta, missing_meta) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(missing);
+	let (value, flags) = pinned_meta.unwrap();
+	assert_eq!(value.as_slice(), b"v2");
+	assert_eq!(flags, 0b101);
+	let (value, flags) = plain_meta.unwrap();
+	assert_eq!(value.as_slice(), b"v1");
+	assert_eq!(flags, 0);
+	assert!(missing_meta.is_none());
+}
+
+
+
+#[test]
+fn test_reset_tab() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/reset_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Bin);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let items: Vec<TabKV> = (0..10).map(|i| {
+			let mut wb = WriteBuffer::new();
+			let key = format!("reset{:?}", i);
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes.clone()),
+				value: Some(Arc::new(wb.bytes)),
+				index: 0,
+			}
+		}).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(items.clone(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		let before = ALL_TABLES.lock().await.get(&tab).cloned().unwrap();
+
+		//未确认时拒绝重置
+		let refused = LogFileDB::reset_tab(&tab, false).await.is_err();
+		let backup = LogFileDB::reset_tab(&tab, true).await.unwrap();
+
+		//重置后表为空，元信息不变，原日志文件保留在备份目录中
+		let after = ALL_TABLES.lock().await.get(&tab).cloned().unwrap();
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let size = tr.tab_size(&Atom::from("logfile"), &tab).await.unwrap();
+
+		//重置后的表可以继续写入
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(items[0..1].to_vec(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.reload().await.unwrap();
+		let reloaded = file.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap().tab_size().await.unwrap();
+
+		let _ = s.send((refused, backup, before, after, size, reloaded));
+	});
+
+	let (refused, backup, before, after, size, reloaded) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(refused);
+	assert!(backup.is_dir());
+	assert_eq!(before, after);
+	assert_eq!(size, 0);
+	assert_eq!(reloaded, 1);
+}
+
+
+
+#[test]
+fn test_iter_physical() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/physical_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"physical", 0..8);
+		let key = Arc::new(wb.bytes);
+		let other = {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(b"other", 0..5);
+			Arc::new(wb.bytes)
+		};
+
+		//同一个主键写入两次，中间写入另一个主键，最后删除另一个主键
+		for (key, value) in vec![(key.clone(), Some("v1")), (other.clone(), Some("o1")), (key.clone(), Some("v2")), (other.clone(), None)] {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key,
+				value: value.map(|v| Arc::new(v.as_bytes().to_vec())),
+				index: 0,
+			}], None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let records: Vec<(bool, Vec<u8>, Option<Vec<u8>>)> = file.iter_physical().await.unwrap()
+			.map(|(method, k, v)| (matches!(method, pi_store::log_store::log_file::LogMethod::Remove), k.to_vec(), v.map(|v| v.to_vec())))
+			.collect();
+
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let latest = tr.query(vec![TabKV::new(Atom::from("logfile"), tab.clone(), key.clone())], None, false).await.unwrap();
+
+		let _ = s.send((records, latest[0].value.clone(), key, other));
+	});
+
+	let (records, latest, key, other) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(records, vec![
+		(false, key.to_vec(), Some(b"v1".to_vec())),
+		(false, other.to_vec(), Some(b"o1".to_vec())),
+		(false, key.to_vec(), Some(b"v2".to_vec())),
+		(true, other.to_vec(), None),
+	]);
+	assert_eq!(latest.unwrap().as_slice(), b"v2");
+}
+
+#[test]
+fn test_bulk_index_rebuild() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let single = Atom::from(format!("./testlogfile/index_single_{}", now));
+		let bulk = Atom::from(format!("./testlogfile/index_bulk_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in vec![single.clone(), bulk.clone()] {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		//按值的第一个字节建立索引
+		let extractor: IndexExtractor = Arc::new(|_key: &[u8], value: &[u8]| value.first().map(|b| vec![*b]));
+		let single_file = LogFileDB::open(&single).await.unwrap();
+		let bulk_file = LogFileDB::open(&bulk).await.unwrap();
+		single_file.add_index(Atom::from("group"), extractor.clone()).await.unwrap();
+		bulk_file.add_index(Atom::from("group"), extractor).await.unwrap();
+		bulk_file.begin_bulk().await.unwrap();
+		let in_bulk = bulk_file.index_entries(&Atom::from("group")).await.is_err();
+
+		//写入记录后修改和删除部分记录，修改会改变记录所在的索引键
+		let mut batches = Vec::new();
+		for i in 0..20 {
+			batches.push((format!("key{}", i), Some(format!("{}{}", ["a", "b", "c"][i % 3], i))));
+		}
+		for i in 0..5 {
+			batches.push((format!("key{}", i), Some(format!("d{}", i))));
+		}
+		for i in 15..20 {
+			batches.push((format!("key{}", i), None));
+		}
+		for tab in vec![single.clone(), bulk.clone()] {
+			for (key, value) in batches.iter() {
+				let mut wb = WriteBuffer::new();
+				wb.write_bin(key.as_bytes(), 0..key.len());
+				let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+				tr.modify(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab.clone(),
+					key: Arc::new(wb.bytes),
+					value: value.as_ref().map(|v| Arc::new(v.as_bytes().to_vec())),
+					index: 0,
+				}], None, false).await.unwrap();
+				tr.prepare().await.unwrap();
+				tr.commit().await.unwrap();
+			}
+		}
+
+		bulk_file.end_bulk().await.unwrap();
+		let single_entries = single_file.index_entries(&Atom::from("group")).await.unwrap();
+		let bulk_entries = bulk_file.index_entries(&Atom::from("group")).await.unwrap();
+		let d_keys = bulk_file.index_lookup(&Atom::from("group"), b"d").await.unwrap().len();
+
+		let _ = s.send((in_bulk, single_entries, bulk_entries, d_keys));
+	});
+
+	let (in_bulk, single_entries, bulk_entries, d_keys) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(in_bulk);
+	assert_eq!(single_entries.len(), 15);
+	assert_eq!(single_entries, bulk_entries);
+	assert_eq!(d_keys, 5);
+}
+
+#[test]
+fn test_split_fsync_barrier() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/split_fsync_{}", now));
+		let crash = Atom::from(format!("./testlogfile/split_fsync_crash_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in [&tab, &crash].iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+		//日志文件大小为1MB，写满后由日志文件在追加时自动分裂
+		LogFileDB::set_tab_config(&tab, TabConfig { log_file_size: Some(1), ..TabConfig::default() }).await.unwrap();
+
+		let keys: Vec<Arc<Vec<u8>>> = vec!["before".to_string(), "after".to_string()].into_iter()
+			.chain((0..20).map(|i| format!("auto{}", i)))
+			.map(|key| {
+				let mut wb = WriteBuffer::new();
+				wb.write_bin(key.as_bytes(), 0..key.len());
+				Arc::new(wb.bytes)
+			}).collect();
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let write = |key: Arc<Vec<u8>>, len: usize| {
+			let mgr = mgr.clone();
+			let rt = rt.clone();
+			let tab = tab.clone();
+			async move {
+				let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+				tr.modify(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab.clone(),
+					key,
+					value: Some(Arc::new(vec![0u8; len])),
+					index: 0,
+				}], None, false).await.unwrap();
+				tr.prepare().await.unwrap();
+				tr.commit().await.unwrap();
+			}
+		};
+		let key = |name: &str| -> Arc<Vec<u8>> {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(name.as_bytes(), 0..name.len());
+			Arc::new(wb.bytes)
+		};
+
+		//强制分裂前后各写入一个主键
+		let syncs = LogFileDB::split_syncs();
+		write(key("before"), 5).await;
+		SPLIT_FSYNC.store(true, Ordering::Relaxed);
+		file.split().await.unwrap();
+		write(key("after"), 5).await;
+		let forced_syncs = LogFileDB::split_syncs() - syncs;
+
+		//写入超过日志文件大小，日志文件自动分裂后同步新的可写日志文件
+		let paths_before = pi_store::log_store::log_file::read_log_paths(&file.1.log_file).await.unwrap().len();
+		for i in 0..20 {
+			write(key(&format!("auto{}", i)), 64 * 1024).await;
+		}
+		SPLIT_FSYNC.store(false, Ordering::Relaxed);
+		let auto_syncs = LogFileDB::split_syncs() - syncs - forced_syncs;
+
+		//不经过内存表，直接从磁盘上的每个日志文件读取记录
+		let paths = pi_store::log_store::log_file::read_log_paths(&file.1.log_file).await.unwrap();
+		let mut owners = Vec::new();
+		for path in paths.iter() {
+			let records = read_log_records(path.clone(), TabConfig::default().load_block_size()).await.unwrap();
+			for key in keys.iter() {
+				if records.iter().any(|(_, k, _)| k.as_slice() == key.as_slice()) {
+					owners.push((key.clone(), path.clone()));
+				}
+			}
+		}
+
+		//模拟崩溃，复制表的日志文件，并丢弃最新的可写日志文件中未同步的写入，再从磁盘加载
+		std::fs::create_dir_all(crash.as_str()).unwrap();
+		for entry in std::fs::read_dir(crash.as_str()).unwrap() {
+			std::fs::remove_file(entry.unwrap().path()).unwrap();
+		}
+		for path in paths.iter() {
+			let to = PathBuf::from(crash.as_str()).join(path.file_name().unwrap());
+			std::fs::copy(path, &to).unwrap();
+			if path == paths.last().unwrap() {
+				std::fs::OpenOptions::new().write(true).open(&to).unwrap().set_len(0).unwrap();
+			}
+		}
+		let _ = std::fs::copy(format!("{}.format", tab.as_str()), format!("{}.format", crash.as_str()));
+		let recovered = LogFileDB::open(&crash).await.unwrap();
+		let mut survived = Vec::new();
+		for key in keys.iter() {
+			survived.push((key.clone(), recovered.get_with_meta(key).await.unwrap().is_some()));
+		}
+
+		let _ = s.send((forced_syncs, auto_syncs, paths_before, owners, paths, survived, key("before"), key("after")));
+	});
+
+	let (forced_syncs, auto_syncs, paths_before, owners, paths, survived, before, after) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(forced_syncs >= 1);
+	assert!(paths.len() > paths_before);
+	assert!(auto_syncs >= paths.len() - paths_before);
+	//分裂前的主键只在只读日志文件中，分裂后的主键在分裂产生的日志文件中
+	let owner = |key: &Arc<Vec<u8>>| owners.iter().find(|(k, _)| k == key).unwrap().1.clone();
+	assert!(owner(&before) != owner(&after));
+	assert_eq!(owners.len(), survived.len());
+	//已分裂的只读日志文件中的主键在崩溃后仍然存在，只有最新的可写日志文件中未同步的写入丢失
+	for (key, found) in survived {
+		assert_eq!(found, owner(&key) != *paths.last().unwrap());
+	}
+	assert!(owners.iter().any(|(_, path)| path == paths.last().unwrap()));
+}
+
+#[test]
+fn test_with_db() {
+	let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+	let tab = Atom::from(format!("./testlogfile/with_db_{}", now));
+
+	let mut wb = WriteBuffer::new();
+	wb.write_bin(b"with_db", 0..7);
+	let key = Arc::new(wb.bytes);
+
+	let write_tab = tab.clone();
+	let write_key = key.clone();
+	with_db("./testlogfile", DbConfig::default(), move |mgr, rt| async move {
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &write_tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: write_tab.clone(),
+			key: write_key,
+			value: Some(Arc::new(b"value".to_vec())),
+			index: 0,
+		}], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+	}).unwrap();
+
+	//写入后不需要等待，直接在新的数据库中读取，关闭后表的内存已释放，读取从日志文件重新加载
+	let (unloaded, value) = with_db("./testlogfile", DbConfig::default(), move |mgr, rt| async move {
+		let unloaded = LogFileDB::open(&tab).await.unwrap().is_unloaded().await;
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let r = tr.query(vec![TabKV::new(Atom::from("logfile"), tab, key)], None, false).await.unwrap();
+		(unloaded, r[0].value.clone())
+	}).unwrap();
+
+	assert!(unloaded);
+	assert_eq!(value.unwrap().as_slice(), b"value");
+}
+
+#[test]
+fn test_replay_limit() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/replay_limit_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let keys: Vec<Arc<Vec<u8>>> = vec!["old", "mid", "new"].into_iter().map(|key| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		}).collect();
+
+		//每个主键写入不同的日志文件，最新的主键在可写日志文件中
+		let file = LogFileDB::open(&tab).await.unwrap();
+		for (i, key) in keys.iter().enumerate() {
+			if i > 0 {
+				file.split().await.unwrap();
+			}
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key.clone(),
+				value: Some(Arc::new(b"value".to_vec())),
+				index: 0,
+			}], None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+
+		let query = |mgr: Mgr, rt: MultiTaskRuntime<()>, tab: Atom, keys: Vec<Arc<Vec<u8>>>| async move {
+			let mut tr = mgr.transaction(false, Some(rt)).await;
+			let mut found = Vec::new();
+			for key in keys {
+				let r = tr.query(vec![TabKV::new(Atom::from("logfile"), tab.clone(), key)], None, false).await.unwrap();
+				found.push(r[0].value.is_some());
+			}
+			found
+		};
+
+		//只重放最新的日志文件，未加载的主键在读取时从更早的日志文件读取
+		LogFileDB::set_tab_config(&tab, TabConfig { replay_logs: Some(1), ..TabConfig::default() }).await.unwrap();
+		file.reload().await.unwrap();
+		let mut missing = WriteBuffer::new();
+		missing.write_bin(b"missing", 0..7);
+		let mut with_missing = keys.clone();
+		with_missing.push(Arc::new(missing.bytes));
+		let recent = (file.is_cold().await, file.approx_size(), query(mgr.clone(), rt.clone(), tab.clone(), with_missing.clone()).await);
+		//再次读取时使用已建立的主键索引
+		let indexed = query(mgr.clone(), rt.clone(), tab.clone(), with_missing).await;
+
+		//冷表不支持按范围遍历
+		let gen = GuidGen::new(0, 0);
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let iter_err = txn.iter(&tab, None, false, None).await.err().unwrap();
+		let key_iter_err = txn.key_iter(None, false, None).await.err().unwrap();
+
+		//未加载的主键已存在，不能插入
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		let inserted = txn.insert(keys[0].clone(), Arc::new(b"insert".to_vec())).await;
+		txn.rollback().await.unwrap();
+
+		//事务开始后其它事务覆盖了未加载的主键，事务仍然读到开始时的值
+		let reader = file.transaction(&gen.gen(0), false).await.unwrap();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: keys[0].clone(),
+			value: Some(Arc::new(b"overwrite".to_vec())),
+			index: 0,
+		}, TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: keys[1].clone(),
+			value: None,
+			index: 0,
+		}], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		let snapshot: Vec<Option<Vec<u8>>> = reader.query(Arc::new(keys[0..2].iter().map(|key| TabKV::new(Atom::from("logfile"), tab.clone(), key.clone())).collect()), None, false).await.unwrap()
+			.into_iter().map(|kv| kv.value.map(|v| v.to_vec())).collect();
+		let current = query(mgr.clone(), rt.clone(), tab.clone(), keys.clone()).await;
+
+		//需要时加载更早的日志文件
+		file.load_cold().await.unwrap();
+		let all = (file.is_cold().await, file.approx_size(), query(mgr.clone(), rt.clone(), tab.clone(), keys.clone()).await);
+
+		let _ = s.send((recent, indexed, iter_err, key_iter_err, inserted, snapshot, current, all));
+	});
+
+	let (recent, indexed, iter_err, key_iter_err, inserted, snapshot, current, all) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(recent, (true, 1, vec![true, true, true, false]));
+	assert_eq!(indexed, recent.2);
+	assert!(iter_err.starts_with("TabCold"));
+	assert!(key_iter_err.starts_with("TabCold"));
+	assert!(inserted.unwrap_err().starts_with("KeyExists"));
+	assert_eq!(snapshot, vec![Some(b"value".to_vec()), Some(b"value".to_vec())]);
+	assert_eq!(current, vec![true, false, true]);
+	assert_eq!(all, (false, 2, vec![true, false, true]));
+}
+
+#[test]
+fn test_apply_changeset() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let source = Atom::from(format!("./testlogfile/changeset_source_{}", now));
+		let target = Atom::from(format!("./testlogfile/changeset_target_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in vec![&source, &target] {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |key: &str| -> Arc<Vec<u8>> {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		};
+		let write = |tab: &Atom, k: &str, value: Option<&str>| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key(k),
+			value: value.map(|v| Arc::new(v.as_bytes().to_vec())),
+			index: 0,
+		};
+
+		//源表中同一个主键被多次写入和删除，目标表中有需要被删除的主键和不在修改集中的主键
+		for items in vec![
+			vec![write(&source, "a", Some("a1")), write(&source, "b", Some("b1"))],
+			vec![write(&source, "a", Some("a2")), write(&source, "c", Some("c1"))],
+			vec![write(&source, "b", None)],
+			vec![write(&target, "b", Some("old")), write(&target, "d", Some("d1"))],
+		] {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+
+		//按日志顺序从源表的日志记录产生修改集，修改集中有重复的主键
+		let changes: Vec<(Arc<Vec<u8>>, Option<Arc<Vec<u8>>>)> = LogFileDB::open(&source).await.unwrap().iter_physical().await.unwrap()
+			.map(|(_, k, v)| (k, v))
+			.collect();
+
+		let gen = GuidGen::new(0, 0);
+		let file = LogFileDB::open(&target).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		let count = txn.apply_changeset(&target, changes.clone()).await.unwrap();
+		let wrong_tab = txn.apply_changeset(&source, changes.clone()).await.is_err();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+
+		let mut results = Vec::new();
+		for k in vec!["a", "b", "c", "d"] {
+			let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+			let source_value = tr.query(vec![TabKV::new(Atom::from("logfile"), source.clone(), key(k))], None, false).await.unwrap()[0].value.clone();
+			let target_value = tr.query(vec![TabKV::new(Atom::from("logfile"), target.clone(), key(k))], None, false).await.unwrap()[0].value.clone();
+			results.push((source_value.map(|v| v.to_vec()), target_value.map(|v| v.to_vec())));
+		}
+
+		let _ = s.send((changes.len(), count, wrong_tab, results));
+	});
+
+	let (changes, count, wrong_tab, results) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(changes, 5);
+	assert_eq!(count, 3);
+	assert!(wrong_tab);
+	//修改集中的主键与源表一致，不在修改集中的主键保持不变
+	assert_eq!(&results[..3], &[
+		(Some(b"a2".to_vec()), Some(b"a2".to_vec())),
+		(None, None),
+		(Some(b"c1".to_vec()), Some(b"c1".to_vec())),
+	]);
+	assert_eq!(results[3], (None, Some(b"d1".to_vec())));
+}
+
+#[test]
+fn test_attach_readonly() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/attach_readonly_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let keys: Vec<Arc<Vec<u8>>> = vec!["first", "second"].into_iter().map(|key| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		}).collect();
+		let write = |key: Arc<Vec<u8>>| {
+			let mgr = mgr.clone();
+			let tab = tab.clone();
+			let rt = rt.clone();
+			async move {
+				let mut tr = mgr.transaction(true, Some(rt)).await;
+				tr.modify(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab,
+					key,
+					value: Some(Arc::new(b"value".to_vec())),
+					index: 0,
+				}], None, false).await.unwrap();
+				tr.prepare().await.unwrap();
+				tr.commit().await.unwrap();
+				LogFileDB::flush(10000).await.unwrap();
+			}
+		};
+
+		//主库写入后附加，附加的视图可以读到已写入的记录
+		write(keys[0].clone()).await;
+		let replica = LogFileDB::attach_readonly("./").await.unwrap();
+		let attached = replica.tabs().await.contains(&tab);
+		let first = replica.get(&tab, &keys[0]).await.unwrap();
+		let missing = replica.get(&tab, &keys[1]).await.unwrap();
+
+		//主库分裂后继续写入，定时刷新最终读到分裂后写入的记录
+		LogFileDB::open(&tab).await.unwrap().split().await.unwrap();
+		write(keys[1].clone()).await;
+		replica.start_tail(10).await.unwrap();
+		let mut second = None;
+		for _ in 0..500 {
+			second = replica.get(&tab, &keys[1]).await.unwrap();
+			if second.is_some() {
+				break;
+			}
+			rt.wait_timeout(10).await;
+		}
+		replica.stop_tail();
+		let still_first = replica.get(&tab, &keys[0]).await.unwrap();
+
+		let _ = s.send((attached, first, missing, second, still_first));
+	});
+
+	let (attached, first, missing, second, still_first) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(attached);
+	assert_eq!(first, Some(Arc::new(b"value".to_vec())));
+	assert!(missing.is_none());
+	assert_eq!(second, Some(Arc::new(b"value".to_vec())));
+	assert_eq!(still_first, Some(Arc::new(b"value".to_vec())));
+}
+
+#[test]
+fn test_blob_promotion() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/blob_promotion_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//超过64字节的值外置存储，不小于32字节的值在两次整理之间更新3次后外置存储
+		let file = LogFileDB::open(&tab).await.unwrap();
+		LogFileDB::set_tab_config(&tab, TabConfig {
+			blob_inline_max: Some(64),
+			blob_hot_size: Some(32),
+			blob_hot_updates: Some(3),
+			..TabConfig::default()
+		}).await.unwrap();
+
+		let keys: Vec<Arc<Vec<u8>>> = vec!["small", "large", "hot"].into_iter().map(|key| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		}).collect();
+		let write = |key: Arc<Vec<u8>>, value: Vec<u8>| {
+			let mgr = mgr.clone();
+			let tab = tab.clone();
+			let rt = rt.clone();
+			async move {
+				let mut tr = mgr.transaction(true, Some(rt)).await;
+				tr.modify(vec![TabKV {
+					ware: Atom::from("logfile"),
+					tab,
+					key,
+					value: Some(Arc::new(value)),
+					index: 0,
+				}], None, false).await.unwrap();
+				tr.prepare().await.unwrap();
+				tr.commit().await.unwrap();
+			}
+		};
+
+		write(keys[0].clone(), vec![1; 10]).await;
+		write(keys[1].clone(), vec![2; 100]).await;
+		write(keys[2].clone(), vec![3; 40]).await;
+		write(keys[2].clone(), vec![4; 40]).await;
+		let warm = file.is_blob(&keys[2]);
+		write(keys[2].clone(), vec![5; 40]).await;
+		let promoted = (file.is_blob(&keys[0]), file.is_blob(&keys[1]), file.is_blob(&keys[2]));
+
+		//重新加载后从外置存储读取完整的值
+		file.reload().await.unwrap();
+		let mut reloaded = Vec::new();
+		for key in keys.iter() {
+			reloaded.push(file.get_with_meta(key).await.unwrap().unwrap().0.to_vec());
+		}
+		let reloaded_blobs = (file.is_blob(&keys[1]), file.is_blob(&keys[2]));
+
+		//本次整理前热点值仍在更新，保持外置存储；下次整理前没有更新，重新内联，不再被引用的外置存储的值被删除
+		let mut dir = PathBuf::from(".");
+		dir.push(format!("{}.blobs", tab.as_str()));
+		let blob_ids = |dir: &PathBuf| {
+			let mut ids: Vec<u64> = std::fs::read_dir(dir).unwrap()
+				.filter_map(|entry| entry.unwrap().file_name().to_str().and_then(|name| name.parse::<u64>().ok()))
+				.collect();
+			ids.sort();
+			ids
+		};
+		file.checkpoint().await.unwrap();
+		let used = blob_ids(&dir);
+		let kept = (file.is_blob(&keys[1]), file.is_blob(&keys[2]), used.len());
+		file.checkpoint().await.unwrap();
+		let demoted = (file.is_blob(&keys[1]), file.is_blob(&keys[2]));
+		let blob_files = blob_ids(&dir).len();
+
+		file.reload().await.unwrap();
+		let mut compacted = Vec::new();
+		for key in keys.iter() {
+			compacted.push(file.get_with_meta(key).await.unwrap().unwrap().0.to_vec());
+		}
+		let compacted_blobs = (file.is_blob(&keys[1]), file.is_blob(&keys[2]));
+
+		//整理删除的值的id不会被重新分配，重新加载后新写入的值的id大于之前用过的所有id
+		write(keys[0].clone(), vec![6; 100]).await;
+		let new_ids: Vec<u64> = blob_ids(&dir).into_iter().filter(|id| !used.contains(id)).collect();
+		let monotonic = new_ids.len() == 1 && new_ids[0] > *used.iter().max().unwrap();
+
+		let _ = s.send((warm, promoted, reloaded, reloaded_blobs, kept, demoted, blob_files, compacted, compacted_blobs, monotonic));
+	});
+
+	let (warm, promoted, reloaded, reloaded_blobs, kept, demoted, blob_files, compacted, compacted_blobs, monotonic) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	let expected = vec![vec![1; 10], vec![2; 100], vec![5; 40]];
+	assert!(!warm);
+	assert_eq!(promoted, (false, true, true));
+	assert_eq!(reloaded, expected);
+	assert_eq!(reloaded_blobs, (true, true));
+	assert_eq!(kept, (true, true, 2));
+	assert_eq!(demoted, (true, false));
+	assert_eq!(blob_files, 1);
+	assert_eq!(compacted, expected);
+	assert_eq!(compacted_blobs, (true, false));
+	assert!(monotonic);
+}
+
+#[test]
+fn test_drop_prepared_txn() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/drop_prepared_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"key", 0..3);
+		let items = Arc::new(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(wb.bytes),
+			value: Some(Arc::new(b"value".to_vec())),
+			index: 0,
+		}]);
+
+		//已预提交的事务未提交就被释放，释放前其它事务修改同一主键的预提交冲突
+		let gen = GuidGen::new(0, 0);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let dropped = file.transaction(&gen.gen(0), true).await.unwrap();
+		dropped.modify(items.clone(), None, false).await.unwrap();
+		dropped.prepare(1000).await.unwrap();
+		let conflicted = {
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(items.clone(), None, false).await.unwrap();
+			let result = txn.prepare(1000).await.is_err();
+			txn.rollback().await.unwrap();
+			result
+		};
+		drop(dropped);
+
+		//释放后预提交被异步移除，之后的事务可以预提交并提交
+		let mut released = false;
+		for _ in 0..100 {
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(items.clone(), None, false).await.unwrap();
+			if txn.prepare(1000).await.is_ok() {
+				released = txn.commit().await.is_ok();
+				break;
+			}
+			txn.rollback().await.unwrap();
+			thread::sleep(Duration::from_millis(10));
+		}
+
+		let _ = s.send((conflicted, released));
+	});
+
+	let (conflicted, released) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(conflicted);
+	assert!(released);
+}
+
+#[test]
+fn test_access_heatmap() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/access_heatmap_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |key: String| -> Arc<Vec<u8>> {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		};
+		let hot: Vec<Arc<Vec<u8>>> = (0..50).map(|i| key(format!("hot-{:02}", i))).collect();
+		let cold: Vec<Arc<Vec<u8>>> = (0..5).map(|i| key(format!("cold{:02}", i))).collect();
+
+		//按去掉两位序号后的主键分桶，每次读写都采样
+		let prefix_len = hot[0].len() - 2;
+		ACCESS_PREFIX_LEN.store(prefix_len, Ordering::Relaxed);
+		ACCESS_SAMPLE_RATE.store(1, Ordering::Relaxed);
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(hot.iter().chain(cold.iter()).map(|k| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: k.clone(),
+			value: Some(Arc::new(b"value".to_vec())),
+			index: 0,
+		}).collect(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		//热点主键每个读取5次，冷主键每个读取1次
+		for round in 0..5 {
+			let mut keys: Vec<TabKV> = hot.iter().map(|k| TabKV::new(Atom::from("logfile"), tab.clone(), k.clone())).collect();
+			if round == 0 {
+				keys.extend(cold.iter().map(|k| TabKV::new(Atom::from("logfile"), tab.clone(), k.clone())));
+			}
+			let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+			tr.query(keys, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+
+		let heatmap = LogFileDB::access_heatmap(&tab);
+
+		//限制前缀数量为2，每次读取新的前缀都淘汰采样次数最少的前缀，热点前缀保留
+		ACCESS_HEATMAP_BUCKETS.store(2, Ordering::Relaxed);
+		let fresh: Vec<Arc<Vec<u8>>> = (0..3).map(|i| key(format!("new{}-00", i))).collect();
+		for k in fresh.iter() {
+			let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+			tr.query(vec![TabKV::new(Atom::from("logfile"), tab.clone(), k.clone())], None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+		ACCESS_HEATMAP_BUCKETS.store(1024, Ordering::Relaxed);
+
+		ACCESS_SAMPLE_RATE.store(0, Ordering::Relaxed);
+		let _ = s.send((hot[0][..prefix_len].to_vec(), cold[0][..prefix_len].to_vec(), fresh[2][..prefix_len].to_vec(), heatmap, LogFileDB::access_heatmap(&tab)));
+	});
+
+	let (hot_prefix, cold_prefix, fresh_prefix, heatmap, bounded_heatmap) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(heatmap, vec![(hot_prefix.clone(), 250, 50), (cold_prefix, 5, 5)]);
+	assert_eq!(bounded_heatmap, vec![(hot_prefix, 250, 50), (fresh_prefix, 1, 0)]);
+}
+
+#[test]
+fn test_alter_tab_config() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/alter_config_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let write = |from: usize, count: usize, len: usize| {
+			let mgr = mgr.clone();
+			let rt = rt.clone();
+			let tab = tab.clone();
+			async move {
+				let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+				tr.modify((from..from + count).map(|i| {
+					let mut wb = WriteBuffer::new();
+					let key = format!("alter{:?}", i);
+					wb.write_bin(key.as_bytes(), 0..key.len());
+					TabKV {
+						ware: Atom::from("logfile"),
+						tab: tab.clone(),
+						key: Arc::new(wb.bytes),
+						value: Some(Arc::new(vec![0u8; len])),
+						index: 0,
+					}
+				}).collect(), None, false).await.unwrap();
+				tr.prepare().await.unwrap();
+				tr.commit().await.unwrap();
+			}
+		};
+
+		//表已打开时将日志文件大小修改为1MB，写入不超过1MB时不分裂
+		write(0, 1, 16).await;
+		let not_found = LogFileDB::alter_tab_config(&Atom::from("./testlogfile/alter_config_not_found"), TabConfig::default()).await;
+		LogFileDB::alter_tab_config(&tab, TabConfig { log_file_size: Some(1), ..TabConfig::default() }).await.unwrap();
+		write(1, 4, 64 * 1024).await;
+		let before = LogFileDB::tab_stats(&tab).splits;
+
+		//写入超过1MB后，下一次提交按新的日志文件大小分裂
+		write(5, 16, 64 * 1024).await;
+		let after = LogFileDB::tab_stats(&tab).splits;
+		let files = std::fs::read_dir(tab.as_str()).unwrap().count();
+		let config = ALL_TABLES.lock().await.get(&tab).unwrap().config.clone();
+
+		let _ = s.send((not_found, before, after, files, config));
+	});
+
+	let (not_found, before, after, files, config) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(not_found.unwrap_err().starts_with("TabNotFound: "));
+	assert_eq!(before, 0);
+	assert_eq!(after, 1);
+	assert_eq!(files, 2);
+	assert_eq!(config.log_file_size, Some(1));
+}
+
+#[test]
+fn test_recovered_watermark() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/watermark_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |i: usize| -> Arc<Vec<u8>> {
+			let key = format!("watermark{}", i);
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		};
+
+		DURABLE_WATERMARK.store(true, Ordering::Relaxed);
+		let gen = GuidGen::new(0, 0);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let mut acked = Vec::new();
+		let mut snapshot = Vec::new();
+		for i in 0..4 {
+			if i == 3 {
+				//水位文件异步写入，先写入当前水位再记录最后一次提交前的磁盘状态，作为崩溃后恢复的状态
+				file.flush_watermark().await.unwrap();
+				snapshot = std::fs::read_dir(tab.as_str()).unwrap()
+					.map(|entry| entry.unwrap().path())
+					.chain(std::iter::once(PathBuf::from(format!("{}.watermark", tab.as_str()))))
+					.map(|path| {
+						let bin = std::fs::read(&path).unwrap();
+						(path, bin)
+					})
+					.collect();
+			}
+
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(Arc::new(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key(i),
+				value: Some(Arc::new(b"value".to_vec())),
+				index: 0,
+			}]), None, false).await.unwrap();
+			txn.prepare(1000).await.unwrap();
+			txn.commit().await.unwrap();
+			acked.push(txn.watermark().await.unwrap());
+		}
+		DURABLE_WATERMARK.store(false, Ordering::Relaxed);
+		//等待最后一次提交的水位写入完成，避免覆盖恢复的水位文件
+		file.flush_watermark().await.unwrap();
+
+		//模拟最后一次提交的日志未持久化就崩溃，恢复到最后一次提交前的磁盘状态后重新加载
+		for (path, bin) in snapshot {
+			std::fs::write(path, bin).unwrap();
+		}
+		file.reload().await.unwrap();
+
+		let lost = file.get_with_meta(&key(3)).await.unwrap().is_none();
+		let _ = s.send((acked, file.recovered_watermark(), lost));
+	});
+
+	let (acked, recovered, lost) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(acked[1], acked[0] + 1);
+	assert_eq!(acked[3], acked[2] + 1);
+	assert_eq!(recovered, acked[2]);
+	assert!(recovered < acked[3]);
+	assert!(lost);
+}
+
+#[test]
+fn test_range_stats() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/range_stats_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |key: String| -> Vec<u8> {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			wb.bytes
+		};
+		let item = |k: String, value: Option<&str>| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(key(k)),
+			value: value.map(|v| Arc::new(v.as_bytes().to_vec())),
+			index: 0,
+		};
+
+		//以m00为边界划分两个范围，写入集中在a开头的范围
+		let boundary = key("m00".to_string());
+		LogFileDB::set_range_boundaries(&tab, vec![boundary.clone()]);
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let mut items: Vec<TabKV> = (0..100).map(|i| item(format!("a{:02}", i), Some("value"))).collect();
+		items.extend((0..5).map(|i| item(format!("z{:02}", i), Some("value"))));
+		tr.modify(items, None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify((0..10).map(|i| item(format!("a{:02}", i), None)).collect(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+
+		let _ = s.send((boundary, LogFileDB::tab_stats(&tab).ranges));
+	});
+
+	let (boundary, ranges) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(ranges, vec![
+		RangeStat { start: None, end: Some(boundary.clone()), writes: 100, deletes: 10 },
+		RangeStat { start: Some(boundary), end: None, writes: 5, deletes: 0 },
+	]);
+}
+
+#[test]
+fn test_disjoint_key_writers() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/disjoint_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let item = |i: usize, value: &str| {
+			let key = format!("disjoint{}", i);
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes),
+				value: Some(Arc::new(value.as_bytes().to_vec())),
+				index: 0,
+			}])
+		};
+
+		//一半的主键已存在，事务修改已存在的主键和插入新的主键
+		let gen = GuidGen::new(0, 0);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		for i in 0..16 {
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(item(i, "old"), None, false).await.unwrap();
+			txn.prepare(1000).await.unwrap();
+			txn.commit().await.unwrap();
+		}
+
+		//所有事务从同一个根节点开始，期间表被重新加载，未修改的值都是新的拷贝
+		let mut txns = Vec::new();
+		for i in 0..32 {
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(item(i, "new"), None, false).await.unwrap();
+			txns.push(txn);
+		}
+		file.reload().await.unwrap();
+
+		//并发预提交和提交，其它事务的提交改变了根节点，但写入的主键不相交
+		let mut results = Vec::new();
+		for txn in txns {
+			let (sender, receiver) = futures::channel::oneshot::channel();
+			let _ = rt.spawn(rt.alloc(), async move {
+				let r = match txn.prepare(1000).await {
+					Err(e) => Err(e),
+					Ok(_) => txn.commit().await.map(|_| ()),
+				};
+				let _ = sender.send(r);
+			});
+			results.push(receiver);
+		}
+		let mut errors = Vec::new();
+		for receiver in results {
+			if let Err(e) = receiver.await.unwrap() {
+				errors.push(e);
+			}
+		}
+
+		let mut values = Vec::new();
+		for i in 0..32 {
+			let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+			values.push(txn.query(item(i, ""), None, false).await.unwrap()[0].value.clone());
+		}
+
+		let _ = s.send((errors, values));
+	});
+
+	let (errors, values) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(errors.is_empty(), "{:?}", errors);
+	assert!(values.iter().all(|value| value.as_ref().map(|v| v.as_slice() == b"new") == Some(true)));
+}
+
+#[test]
+fn test_log_sink() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/log_sink_{}", now));
+		let filled = Atom::from(format!("./testlogfile/log_sink_filled_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in [&tab, &filled].iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await;
+		}
+		tr.prepare().await;
+		tr.commit().await;
+		//在表第一次打开前设置为日志表
+		LogFileDB::alter_tab_config(&tab, TabConfig { log_sink: Some(true), ..TabConfig::default() }).await.unwrap();
+
+		//已有记录的表设置为日志表后记录无法读取，所以设置失败，表仍然是普通表
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: filled.clone(),
+			key: Arc::new(b"filled".to_vec()),
+			value: Some(Arc::new(b"value".to_vec())),
+			index: 0,
+		}], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		let refused = LogFileDB::alter_tab_config(&filled, TabConfig { log_sink: Some(true), ..TabConfig::default() }).await;
+		let filled_sink = ALL_TABLES.lock().await.get(&filled).unwrap().config.is_log_sink();
+
+		let key = |i: u64| -> Arc<Vec<u8>> {
+			let bytes = i.to_be_bytes();
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(&bytes, 0..bytes.len());
+			Arc::new(wb.bytes)
+		};
+
+		//每个事务追加1000个事件，共追加一百万个事件
+		let gen = GuidGen::new(0, 0);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		for batch in 0..1000u64 {
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(Arc::new((batch * 1000..(batch + 1) * 1000).map(|i| TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: key(i),
+				value: Some(Arc::new(format!("event{}", i).into_bytes())),
+				index: 0,
+			}).collect()), None, false).await.unwrap();
+			txn.prepare(1000).await.unwrap();
+			txn.commit().await.unwrap();
+		}
+
+		//日志表不维护内存表和键值缓冲，不支持按主键读取和删除
+		let footer = file.sink_footer().await.unwrap();
+		let buffered = file.1.map.lock().len() + file.approx_size();
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		let size = txn.tab_size().await.unwrap();
+		let read = txn.query(Arc::new(vec![TabKV::new(Atom::from("logfile"), tab.clone(), key(0))]), None, false).await;
+		let delete = txn.modify(Arc::new(vec![TabKV::new(Atom::from("logfile"), tab.clone(), key(0))]), None, false).await;
+		txn.rollback().await.unwrap();
+
+		//按追加顺序扫描所有事件
+		let mut next = 0u64;
+		let mut ordered = true;
+		let scanned = file.scan(|k, v| {
+			ordered &= k == key(next).as_slice() && v == format!("event{}", next).as_bytes();
+			next += 1;
+		}).await.unwrap();
+
+		let _ = s.send((footer, key(999_999), buffered, size, read.is_err(), delete.is_err(), scanned, ordered, refused, filled_sink));
+	});
+
+	let (footer, last_key, buffered, size, read_err, delete_err, scanned, ordered, refused, filled_sink) = r.recv_timeout(Duration::from_secs(300)).unwrap();
+	assert!(refused.unwrap_err().starts_with("LogSink: table not empty"));
+	assert!(!filled_sink);
+	assert_eq!(footer.count, 1_000_000);
+	assert_eq!(footer.last_key, Some(last_key.to_vec()));
+	assert_eq!(buffered, 0);
+	assert_eq!(size, 0);
+	assert!(read_err);
+	assert!(delete_err);
+	assert_eq!(scanned, 1_000_000);
+	assert!(ordered);
+}
+
+#[test]
+fn test_commit_validator() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/commit_validator_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//拒绝写入空值的提交
+		let validator: CommitValidator = Arc::new(|rwlog| {
+			for (_, rw_v) in rwlog.iter() {
+				if let RwLog::Write(Some(value)) = rw_v {
+					if value.is_empty() {
+						return Err("empty value".to_string());
+					}
+				}
+			}
+			Ok(())
+		});
+		LogFileDB::set_commit_validator(&tab, Some(validator));
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"key", 0..3);
+		let key = Arc::new(wb.bytes);
+		let item = |value: &str| Arc::new(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(Arc::new(value.as_bytes().to_vec())),
+			index: 0,
+		}]);
+
+		let gen = GuidGen::new(0, 0);
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let mut results = Vec::new();
+		for value in vec!["", "value", ""] {
+			let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+			txn.modify(item(value), None, false).await.unwrap();
+			txn.prepare(1000).await.unwrap();
+			results.push(txn.commit().await.map(|_| ()));
+		}
+		LogFileDB::set_commit_validator(&tab, None);
+
+		let value = file.get_with_meta(&key).await.unwrap().map(|(v, _)| v.to_vec());
+		let _ = s.send((results, value));
+	});
+
+	let (results, value) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(results[0].as_ref().unwrap_err().starts_with("ValidationFailed: "));
+	assert!(results[1].is_ok());
+	assert!(results[2].as_ref().unwrap_err().starts_with("ValidationFailed: "));
+	assert_eq!(value, Some(b"value".to_vec()));
+}
+
+//预检时损坏的表报告失败，其它表通过，预检不会缓存表
+#[test]
+fn test_validate_all() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let db = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let ware = DatabaseWare::new_log_file_ware(db.clone());
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let good = Atom::from(format!("./testlogfile/validate_good_{}", now));
+		let bad = Atom::from(format!("./testlogfile/validate_bad_{}", now));
+
+		//在创建表之前为每个表写入两个日志文件
+		for tab in [&good, &bad].iter() {
+			let file = AsyncLogFileStore::open(tab.as_str(), 8000, 1024 * 1024, None).await.unwrap();
+			let store = AsyncLogFileStore::new(file);
+			for round in 0..2 {
+				let bins: Vec<(Vec<u8>, Vec<u8>)> = (0..100).map(|i| {
+					let key = format!("validate_{}_{}", round, i);
+					let mut wb = WriteBuffer::new();
+					wb.write_bin(key.as_bytes(), 0..key.len());
+					(wb.bytes, vec![i as u8; 64])
+				}).collect();
+				let pairs: Vec<(&[u8], &[u8])> = bins.iter().map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+				store.write_batch(&pairs).await.unwrap();
+				if round == 0 {
+					store.force_fork().await.unwrap();
+				}
+			}
+		}
+
+		//破坏其中一个表最旧的日志文件
+		let mut logs: Vec<PathBuf> = std::fs::read_dir(bad.as_str()).unwrap()
+			.map(|entry| entry.unwrap().path())
+			.filter(|path| path.file_name().unwrap().to_str().unwrap().parse::<usize>().is_ok())
+			.collect();
+		logs.sort();
+		let mut bin = std::fs::read(&logs[0]).unwrap();
+		let len = bin.len();
+		for b in bin[len / 4..len * 3 / 4].iter_mut() {
+			*b = !*b;
+		}
+		std::fs::write(&logs[0], bin).unwrap();
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &good, Some(Arc::new(meta.clone()))).await;
+		tr.alter(&Atom::from("logfile"), &bad, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let summary = LogFileDB::validate_all().await.unwrap();
+		let find = |tab: &Atom| summary.tabs.iter().find(|t| &t.name == tab).cloned().unwrap();
+		let (good_result, bad_result) = (find(&good), find(&bad));
+		let failed = summary.failed().iter().any(|(name, _)| name == &bad);
+
+		//预检后两个表都没有被缓存，损坏的日志文件没有被移动
+		let cached = db.list_detailed().await.into_iter()
+			.filter(|summary| (summary.name == good || summary.name == bad) && summary.key_count.is_some())
+			.count();
+		let kept = logs[0].exists();
+
+		let _ = s.send((good_result, bad_result, failed, cached, kept));
+	});
+
+	let (good, bad, failed, cached, kept) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(good.error, None);
+	assert_eq!(good.log_files, 2);
+	assert!(bad.error.unwrap().contains("read log failed"));
+	assert!(failed);
+	assert_eq!(cached, 0);
+	assert!(kept);
+}
+
+//insert只插入不存在的主键，update只修改已存在的主键，事务中的写入也计入
+#[test]
+fn test_insert_update() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/insert_update_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |name: &str| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(name.as_bytes(), 0..name.len());
+			Arc::new(wb.bytes)
+		};
+		let (a, b, c) = (key("a"), key("b"), key("c"));
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = GuidGen::new(0, 0);
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.insert(a.clone(), Arc::new(b"a1".to_vec())).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		let insert_existing = txn.insert(a.clone(), Arc::new(b"a2".to_vec())).await;
+		let update_missing = txn.update(b.clone(), Arc::new(b"b1".to_vec())).await;
+		txn.update(a.clone(), Arc::new(b"a2".to_vec())).await.unwrap();
+		txn.insert(c.clone(), Arc::new(b"c1".to_vec())).await.unwrap();
+		//同一事务中插入的主键可以修改，但不能再次插入
+		let insert_again = txn.insert(c.clone(), Arc::new(b"c2".to_vec())).await;
+		txn.update(c.clone(), Arc::new(b"c2".to_vec())).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let values = txn.multi_get_sorted(&tab, &[a, b, c]).await.unwrap();
+
+		let _ = s.send((insert_existing, update_missing, insert_again, values));
+	});
+
+	let (insert_existing, update_missing, insert_again, values) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(insert_existing.unwrap_err().starts_with("KeyExists:"));
+	assert!(update_missing.unwrap_err().starts_with("KeyNotFound:"));
+	assert!(insert_again.unwrap_err().starts_with("KeyExists:"));
+	assert_eq!(values, vec![Some(Arc::new(b"a2".to_vec())), None, Some(Arc::new(b"c2".to_vec()))]);
+}
+
+//每次整理表后追加一条整理记录，整理前后的大小来自表的日志文件
+#[test]
+fn test_compaction_history() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/compaction_history_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let empty = LogFileDB::compaction_history(&tab);
+
+		//同一个主键在多个只读日志文件中重复写入，以产生需要移除的只读日志文件
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"history", 0..7);
+		let write = |round: usize| {
+			let mgr = mgr.clone();
+			let rt = rt.clone();
+			let tab = tab.clone();
+			let key = Arc::new(wb.bytes.clone());
+			async move {
+				for i in 0..10 {
+					let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+					tr.modify(vec![TabKV {
+						ware: Atom::from("logfile"),
+						tab: tab.clone(),
+						key: key.clone(),
+						value: Some(Arc::new(vec![(round * 10 + i) as u8; 1024])),
+						index: 0,
+					}], None, false).await.unwrap();
+					tr.prepare().await.unwrap();
+					tr.commit().await.unwrap();
+					LogFileDB::force_split_tab(&tab).await.unwrap();
+				}
+			}
+		};
+
+		write(0).await;
+		LogFileDB::collect_blocking().await.unwrap();
+		let first = LogFileDB::compaction_history(&tab);
+		write(1).await;
+		LogFileDB::collect_blocking().await.unwrap();
+		let second = LogFileDB::compaction_history(&tab);
+
+		let _ = s.send((empty, first, second));
+	});
+
+	let (empty, first, second) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(empty.is_empty());
+	assert!(first.len() >= 1);
+	assert!(second.len() >= first.len() + 1);
+	assert_eq!(&second[..first.len()], &first[..]);
+	//两次整理都移除了重复写入的只读日志文件，整理后变小
+	let runs = [&first[first.len() - 1], &second[second.len() - 1]];
+	for run in runs.iter() {
+		assert!(run.removed_logs > 0);
+		assert!(run.before > 0);
+		assert!(run.after < run.before);
+	}
+	assert!(runs[0].time <= runs[1].time);
+}
+
+//迭代器只返回过滤器匹配的记录，降序迭代同样过滤，没有匹配的记录则直接结束
+#[test]
+fn test_iter_filter() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/iter_filter_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let key = |name: &str| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(name.as_bytes(), 0..name.len());
+			Arc::new(wb.bytes)
+		};
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let gen = GuidGen::new(0, 0);
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(["a", "b", "c"].iter().map(|name| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key(name),
+			value: Some(Arc::new(name.as_bytes().to_vec())),
+			index: 0,
+		}).collect()), None, false).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+
+		let b = key("b");
+		let only_b: Filter = Some(Arc::new(move |k: &Bin, _: Option<&Bin>| k == &b));
+		let none: Filter = Some(Arc::new(|_: &Bin, _: Option<&Bin>| false));
+		let by_value: Filter = Some(Arc::new(|_: &Bin, v: Option<&Bin>| v.map(|v| v.as_slice() != b"b").unwrap_or(false)));
+
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let mut results = Vec::new();
+		for (filter, descending) in vec![(only_b.clone(), false), (only_b.clone(), true), (none.clone(), false), (by_value, true)] {
+			let mut it = txn.iter(&tab, None, descending, filter).await.unwrap();
+			let mut values = Vec::new();
+			while let Some(Ok(Some((_, v)))) = it.next() {
+				values.push(String::from_utf8(v.to_vec()).unwrap());
+			}
+			results.push(values);
+		}
+		let mut keys = Vec::new();
+		for filter in vec![only_b, none] {
+			let mut it = txn.key_iter(None, false, filter).await.unwrap();
+			let mut count = 0;
+			while let Some(Ok(Some(_))) = it.next() {
+				count += 1;
+			}
+			keys.push(count);
+		}
+
+		let _ = s.send((results, keys));
+	});
+
+	let (results, keys) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(results[0], vec!["b".to_string()]);
+	assert_eq!(results[1], vec!["b".to_string()]);
+	assert!(results[2].is_empty());
+	assert_eq!(results[3], vec!["c".to_string(), "a".to_string()]);
+	assert_eq!(keys, vec![1, 0]);
+}
+
+//没有整理的表的只读日志文件越来越多，重新加载后统计信息超过告警长度时记录告警
+#[test]
+fn test_statistics_alert() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/statistics_alert_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let before = LogFileDB::tab_stats(&tab);
+
+		//每次写入后分裂，产生8个只读日志文件
+		for i in 0..8 {
+			let key = format!("statistics{}", i);
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(vec![TabKV {
+				ware: Atom::from("logfile"),
+				tab: tab.clone(),
+				key: Arc::new(wb.bytes),
+				value: Some(Arc::new(vec![i as u8; 64])),
+				index: 0,
+			}], None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+			LogFileDB::force_split_tab(&tab).await.unwrap();
+		}
+
+		STATISTICS_ALERT_LEN.store(4, Ordering::Relaxed);
+		file.reload().await.unwrap();
+		STATISTICS_ALERT_LEN.store(1024, Ordering::Relaxed);
+		let after = LogFileDB::tab_stats(&tab);
+
+		let _ = s.send((before, after));
+	});
+
+	let (before, after) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(before.statistics_alerts, 0);
+	assert!(after.statistics_len >= 8);
+	assert!(after.statistics_alerts >= 1);
+}
+
+//按二级索引顺序迭代主键，索引键相同的主键都被迭代，修改和删除移除旧的索引条目，事务中未提交的写入也被迭代
+#[test]
+fn test_index_iter() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/index_iter_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str).with_index(Atom::from("city"));
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//主键是用户id，表元信息声明了按值中的城市建立的索引
+		let file = LogFileDB::open(&tab).await.unwrap();
+		let extractor: IndexExtractor = Arc::new(|_key: &[u8], value: &[u8]| Some(value.to_vec()));
+		LogFileDB::set_index_extractor(&tab, &Atom::from("city"), Some(extractor));
+
+		let key = |name: &str| {
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(name.as_bytes(), 0..name.len());
+			Arc::new(wb.bytes)
+		};
+		let gen = GuidGen::new(0, 0);
+		let txn = file.transaction(&gen.gen(0), true).await.unwrap();
+		txn.modify(Arc::new(vec![("u1", "paris"), ("u2", "berlin"), ("u3", "paris"), ("u4", "oslo")].into_iter().map(|(user, city)| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key(user),
+			value: Some(Arc::new(city.as_bytes().to_vec())),
+			index: 0,
+		}).collect()), None, false).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+		//索引建立前开始的事务
+		let early = Arc::new(file.transaction(&gen.gen(0), false).await.unwrap());
+
+		let scan = |txn: Arc<pi_db::log_file_db::RefLogFileTxn>, start: Option<&str>, descending: bool| {
+			let tab = tab.clone();
+			let start = start.map(|s| Arc::new(s.as_bytes().to_vec()));
+			async move {
+				let mut it = txn.index(&tab, &Atom::from("city"), start, descending, None).await.unwrap();
+				let mut users = Vec::new();
+				while let Some(Ok(Some((k, _)))) = it.next() {
+					users.push(k);
+				}
+				users
+			}
+		};
+
+		let txn = Arc::new(file.transaction(&gen.gen(0), false).await.unwrap());
+		let ascending = scan(txn.clone(), None, false).await;
+		let descending = scan(txn.clone(), None, true).await;
+		let from_oslo = scan(txn.clone(), Some("oslo"), false).await;
+		let to_oslo = scan(txn.clone(), Some("oslo"), true).await;
+		//索引建立后开始的事务
+		let snapshot = Arc::new(file.transaction(&gen.gen(0), false).await.unwrap());
+
+		//u1搬到oslo，删除u3，新增u5在berlin，提交前只有本事务能看到
+		let txn = Arc::new(file.transaction(&gen.gen(0), true).await.unwrap());
+		txn.modify(Arc::new(vec![("u1", Some("oslo")), ("u3", None), ("u5", Some("berlin"))].into_iter().map(|(user, city)| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key(user),
+			value: city.map(|c| Arc::new(c.as_bytes().to_vec())),
+			index: 0,
+		}).collect()), None, false).await.unwrap();
+		let pending = scan(txn.clone(), None, false).await;
+		let other = scan(Arc::new(file.transaction(&gen.gen(0), false).await.unwrap()), None, false).await;
+		txn.prepare(1000).await.unwrap();
+		txn.commit().await.unwrap();
+		let committed = scan(Arc::new(file.transaction(&gen.gen(0), false).await.unwrap()), None, false).await;
+		//提交前开始的事务仍按事务开始时的记录迭代
+		let early = scan(early, None, false).await;
+		let snapshot = scan(snapshot, None, false).await;
+		let missing = file.transaction(&gen.gen(0), false).await.unwrap().index(&tab, &Atom::from("none"), None, false, None).await.is_err();
+
+		let expect = |users: Vec<&str>| users.into_iter().map(|user| key(user)).collect::<Vec<_>>();
+		let _ = s.send((
+			ascending == expect(vec!["u2", "u4", "u1", "u3"]),
+			descending == expect(vec!["u3", "u1", "u4", "u2"]),
+			from_oslo == expect(vec!["u4", "u1", "u3"]),
+			to_oslo == expect(vec!["u4", "u2"]),
+			pending == expect(vec!["u2", "u5", "u1", "u4"]),
+			other == ascending,
+			committed == pending,
+			(early == ascending, snapshot == ascending),
+			missing,
+		));
+	});
+
+	let checks = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(checks, (true, true, true, true, true, true, true, (true, true), true));
+}
+
+//整理到新表后交换，源表名下只保留有效的记录，被覆盖和删除的记录占用的空间被回收，交换出的旧日志仍在目标表名下
+#[test]
+fn test_compact_to_new() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let src = Atom::from(format!("./testlogfile/compact_src_{}", now));
+		let dst = Atom::from(format!("./testlogfile/compact_dst_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &src, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		//反复覆盖同一批主键，最后删除一半主键
+		let key = |i: usize| {
+			let key = format!("key{:02}", i);
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		};
+		for round in 0..5 {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			let kvs = (0..20).map(|i| TabKV {
+				ware: Atom::from("logfile"),
+				tab: src.clone(),
+				key: key(i),
+				value: if round == 4 && i % 2 == 1 { None } else { Some(Arc::new(vec![(round * 20 + i) as u8; 1024])) },
+				index: 0,
+			}).collect();
+			tr.modify(kvs, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+			LogFileDB::force_split_tab(&src).await.unwrap();
+		}
+
+		let dir_size = |tab: &Atom| -> u64 {
+			std::fs::read_dir(tab.as_str()).unwrap().filter_map(|entry| entry.ok()).filter_map(|entry| entry.metadata().ok()).map(|meta| meta.len()).sum()
+		};
+		let before = dir_size(&src);
+		let old = LogFileDB::open(&src).await.unwrap();
+		let count = LogFileDB::compact_to_new(&src, &dst).await.unwrap();
+		let exists = LogFileDB::compact_to_new(&src, &dst).await.is_err();
+
+		//导出后到交换前源表的写入被隔离
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: src.clone(),
+			key: key(0),
+			value: Some(Arc::new(vec![0; 8])),
+			index: 0,
+		}], None, false).await.unwrap();
+		let fenced = format!("{:?}", tr.prepare().await.unwrap_err()).contains("TabFenced");
+		let _ = tr.rollback().await;
+
+		LogFileDB::swap_tabs(&src, &dst).await.unwrap();
+		let after = dir_size(&src);
+		let swapped = dir_size(&dst);
+		//交换前持有的表句柄失效
+		let invalid = old.get_with_meta(&key(0)).await.unwrap_err().starts_with("TabInvalid:");
+
+		//模拟交换回去时在阶段0后中断，重新打开数据库时按交换日志完成交换
+		let db_path = std::path::PathBuf::from(std::env::var("DB_PATH").unwrap_or(".".to_string()));
+		std::fs::rename(src.as_str(), format!("{}.swap.crash", src.as_str())).unwrap();
+		std::fs::write(db_path.join(".swap_journal"), format!("{}\n{}\n.swap.crash\n0\n", src.as_str(), dst.as_str())).unwrap();
+		let _ = LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await;
+		let journal = db_path.join(".swap_journal").exists();
+		let recovered = (dir_size(&src), dir_size(&dst));
+
+		let gen = GuidGen::new(0, 0);
+		let file = LogFileDB::open(&dst).await.unwrap();
+		let txn = file.transaction(&gen.gen(0), false).await.unwrap();
+		let mut it = txn.iter(&dst, None, false, None).await.unwrap();
+		let mut live = Vec::new();
+		while let Some(Ok(Some((k, v)))) = it.next() {
+			live.push((k, v));
+		}
+		let _ = s.send((count, exists, fenced, invalid, journal, recovered, before, after, swapped, live, (0..20).filter(|i| i % 2 == 0).map(|i| (key(i), Arc::new(vec![(80 + i) as u8; 1024]))).collect::<Vec<_>>()));
+	});
+
+	let (count, exists, fenced, invalid, journal, recovered, before, after, swapped, live, expected) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(count, 10);
+	assert!(exists);
+	assert!(fenced);
+	assert!(invalid);
+	assert!(!journal);
+	assert_eq!(recovered, (swapped, after));
+	assert_eq!(live, expected);
+	assert!(after < before);
+	assert!(swapped >= before);
 }
\ No newline at end of file
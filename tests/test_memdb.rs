@@ -6,7 +6,9 @@ use atom::Atom;
 use sinfo;
 use guid::GuidGen;
 use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
-use pi_db::db::{TabKV, TabMeta};
+use pi_db::db::{TabKV, TabMeta, BuildDbType};
+use pi_db::log_file_db::LogFileDB;
+use crossbeam_channel::bounded;
 use bon::WriteBuffer;
 
 #[test]
@@ -147,4 +149,45 @@ fn test_memory_db() {
 	});
 
 	std::thread::sleep(std::time::Duration::from_secs(2));
+}
+
+
+
+#[test]
+fn test_list_wares() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let _ = mgr.register(Atom::from("memory"), Arc::new(DatabaseWare::new_mem_ware(MemDB::new()))).await;
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("memory"), &Atom::from("ware_info"), Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let wares = mgr.list_wares().await;
+		let memory = mgr.ware_info(&Atom::from("memory")).await;
+		let logfile = mgr.ware_info(&Atom::from("logfile")).await;
+		let missing = mgr.ware_info(&Atom::from("missing")).await;
+		let _ = s.send((wares, memory, logfile, missing));
+	});
+
+	let (wares, memory, logfile, missing) = r.recv_timeout(std::time::Duration::from_secs(30)).unwrap();
+	assert_eq!(wares.len(), 2);
+	assert!(wares.contains(&Atom::from("memory")));
+	assert!(wares.contains(&Atom::from("logfile")));
+
+	let memory = memory.unwrap();
+	assert_eq!(memory.db_type, BuildDbType::MemoryDB);
+	assert_eq!(memory.tabs, 1);
+	assert_eq!(logfile.unwrap().db_type, BuildDbType::LogFileDB);
+	assert!(missing.is_none());
 }
\ No newline at end of file
@@ -0,0 +1,166 @@
+use std::thread;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::sync::atomic::Ordering;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use bon::WriteBuffer;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::{TabKV, TabMeta};
+use pi_db::log_file_db::{STORE_RUNTIME, LOG_FILE_TOTAL_SIZE, MEMORY_HIGH_WATER, MEMORY_LOW_WATER, LogFileDB};
+
+#[test]
+fn test_memory_shrink() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let mut tabs = vec![];
+		for name in ["a", "b", "c"].iter() {
+			let tab = Atom::from(format!("./testlogfile/shrink_{}_{}", name, now));
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+			tr.prepare().await;
+			tr.commit().await;
+
+			let items: Vec<TabKV> = (0..200).map(|i| {
+				let mut wb = WriteBuffer::new();
+				let key = format!("shrink{:?}", i);
+				wb.write_bin(key.as_bytes(), 0..key.len());
+				TabKV {
+					ware: Atom::from("logfile"),
+					tab: tab.clone(),
+					key: Arc::new(wb.bytes),
+					value: Some(Arc::new(vec![i as u8; 256])),
+					index: 0,
+				}
+			}).collect();
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.modify(items, None, false).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+
+			//重新加载，使表的加载大小包含已写入的记录
+			let file = LogFileDB::open(&tab).await.unwrap();
+			file.reload().await.unwrap();
+			tabs.push((tab, file));
+		}
+
+		//按a、b的顺序访问，c有未完成的事务
+		let gen = GuidGen::new(0, 0);
+		let _ = tabs[0].1.transaction(&gen.gen(0), false).await.unwrap();
+		let _ = tabs[1].1.transaction(&gen.gen(0), false).await.unwrap();
+		let active = tabs[2].1.transaction(&gen.gen(0), true).await.unwrap();
+
+		//超过高水位，释放到低水位以下，只有c不能被释放
+		let total = LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed);
+		//每个表的加载大小略大于值的总大小，低水位只能容纳一个表
+		let low = 200 * 256 * 2;
+		MEMORY_HIGH_WATER.store(total - 1, Ordering::Relaxed);
+		MEMORY_LOW_WATER.store(low, Ordering::Relaxed);
+		let evicted = LogFileDB::shrink_memory().await;
+		let after = LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed);
+
+		//有未完成的事务的表没有被释放，已释放的表在创建事务时重新加载
+		let c_evicted = tabs[2].1.evict_memory().await.unwrap();
+		drop(active);
+		let reader = tabs[0].1.transaction(&gen.gen(0), false).await.unwrap();
+		let reloaded = reader.tab_size().await.unwrap();
+		drop(reader);
+
+		//后台检查在超过高水位后自动释放
+		MEMORY_HIGH_WATER.store(1, Ordering::Relaxed);
+		LogFileDB::start_memory_shrink(10).await.unwrap();
+
+		let _ = s.send((total, low, evicted, after, c_evicted, reloaded));
+	});
+
+	let (total, low, evicted, after, c_evicted, reloaded) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(total > low);
+	assert!(evicted >= 2);
+	assert!(after <= low);
+	assert!(c_evicted > 0);
+	assert_eq!(reloaded, 200);
+
+	let start = Instant::now();
+	while LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed) > low && start.elapsed() < Duration::from_secs(10) {
+		thread::sleep(Duration::from_millis(10));
+	}
+	LogFileDB::stop_memory_shrink();
+	MEMORY_HIGH_WATER.store(0, Ordering::Relaxed);
+	MEMORY_LOW_WATER.store(0, Ordering::Relaxed);
+	assert!(LOG_FILE_TOTAL_SIZE.load(Ordering::Relaxed) <= low);
+}
+
+#[test]
+fn test_reload_failed() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/reload_failed_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(b"reload", 0..6);
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(vec![TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: Arc::new(wb.bytes),
+			value: Some(Arc::new(vec![1; 256])),
+			index: 0,
+		}], None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		LogFileDB::flush(0).await.unwrap();
+
+		//释放表的内存后损坏格式文件，重新加载失败时创建事务返回错误，而不是读到空表
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.evict_memory().await.unwrap();
+		let format = format!("{}.format", tab.as_str());
+		let version = std::fs::read(&format).unwrap();
+		std::fs::write(&format, [u8::MAX]).unwrap();
+		let gen = GuidGen::new(0, 0);
+		let failed = file.transaction(&gen.gen(0), false).await.err();
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let mgr_failed = tr.tab_size(&Atom::from("logfile"), &tab).await.err();
+
+		//恢复格式文件后可以重新加载
+		std::fs::write(&format, version).unwrap();
+		let reader = file.transaction(&gen.gen(0), false).await.unwrap();
+		let size = reader.tab_size().await.unwrap();
+
+		let _ = s.send((failed, mgr_failed, size));
+	});
+
+	let (failed, mgr_failed, size) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert!(failed.unwrap().starts_with("UnsupportedFormatVersion"));
+	assert!(mgr_failed.is_some());
+	assert_eq!(size, 1);
+}
\ No newline at end of file
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::TabMeta;
+use pi_db::fork::ALL_TABLES;
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB};
+
+#[test]
+fn test_batched_meta_commit() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tabs: Vec<Atom> = (0..3).map(|i| Atom::from(format!("./testlogfile/meta_batch_{}_{}", i, now))).collect();
+
+		//在一个元信息事务中创建多个表，只打开一次元信息表
+		let opens = LogFileDB::meta_store_opens();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in tabs.iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await.unwrap();
+		}
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		let created_opens = LogFileDB::meta_store_opens() - opens;
+		let created = {
+			let tables = ALL_TABLES.lock().await;
+			tabs.iter().all(|tab| tables.contains_key(tab))
+		};
+
+		//其中一个修改检查失败，则所有修改都不写入，也不打开元信息表
+		let others: Vec<Atom> = (0..2).map(|i| Atom::from(format!("./testlogfile/meta_batch_other_{}_{}", i, now))).collect();
+		let opens = LogFileDB::meta_store_opens();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		for tab in others.iter() {
+			let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+			tr.alter(&Atom::from("logfile"), tab, Some(Arc::new(meta))).await.unwrap();
+		}
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tabs[0], Some(Arc::new(meta))).await.unwrap();
+		tr.prepare().await.unwrap();
+		let failed = tr.commit().await.is_err();
+		let failed_opens = LogFileDB::meta_store_opens() - opens;
+		let untouched = {
+			let tables = ALL_TABLES.lock().await;
+			others.iter().all(|tab| !tables.contains_key(tab))
+		};
+
+		let _ = s.send((created_opens, created, failed, failed_opens, untouched));
+	});
+
+	let (created_opens, created, failed, failed_opens, untouched) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(created_opens, 1);
+	assert!(created);
+	assert!(failed);
+	assert_eq!(failed_opens, 0);
+	assert!(untouched);
+}
+
+#[test]
+fn test_concurrent_fork_deletes() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let parent = Atom::from(format!("./testlogfile/meta_ref_parent_{}", now));
+		let forks: Vec<Atom> = (0..2).map(|i| Atom::from(format!("./testlogfile/meta_ref_fork_{}_{}", i, now))).collect();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.alter(&Atom::from("logfile"), &parent, Some(Arc::new(TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)))).await.unwrap();
+		tr.prepare().await.unwrap();
+		tr.commit().await.unwrap();
+		for fork in forks.iter() {
+			let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+			tr.fork_tab(Atom::from("logfile"), parent.clone(), fork.clone(), TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str)).await.unwrap();
+			tr.prepare().await.unwrap();
+			tr.commit().await.unwrap();
+		}
+		let forked = ALL_TABLES.lock().await.get(&parent).unwrap().ref_count;
+
+		//两个元信息事务并发删除同一个父表的两个分叉表，父表的引用计数都要减一
+		let delete = |fork: Atom| {
+			let mgr = mgr.clone();
+			let rt = rt.clone();
+			async move {
+				let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+				tr.alter(&Atom::from("logfile"), &fork, None).await.unwrap();
+				tr.prepare().await.unwrap();
+				tr.commit().await
+			}
+		};
+		let (r0, r1) = futures::future::join(delete(forks[0].clone()), delete(forks[1].clone())).await;
+		let deleted = r0.is_ok() && r1.is_ok();
+		let ref_count = ALL_TABLES.lock().await.get(&parent).unwrap().ref_count;
+
+		let _ = s.send((forked, deleted, ref_count));
+	});
+
+	let (forked, deleted, ref_count) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert_eq!(forked, 2);
+	assert!(deleted);
+	assert_eq!(ref_count, 0);
+}
\ No newline at end of file
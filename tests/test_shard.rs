@@ -0,0 +1,214 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use bon::WriteBuffer;
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::{Bin, TabMeta, Iter};
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB, CommitValidator};
+use pi_db::fork::ALL_TABLES;
+use pi_db::shard::{ShardedTab, shard_index};
+
+//并发写入分片表，写入分散到所有分片的内存表锁，归并迭代按主键顺序返回所有分片的记录
+#[test]
+fn test_sharded_tab() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let rt_copy = rt.clone();
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt_copy.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/sharded_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt_copy.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let sharded = ShardedTab::open(&tab, 4).await.unwrap();
+		//再次打开使用相同的分片，分片数量不一致则失败
+		let reopened = ShardedTab::open(&tab, 4).await.unwrap().shards() == sharded.shards();
+		let mismatch = ShardedTab::open(&tab, 8).await.is_err();
+		let _ = s.send((sharded, reopened, mismatch));
+	});
+	let (sharded, reopened, mismatch) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+	assert!(reopened);
+	assert!(mismatch);
+
+	let key = |i: usize| -> Bin {
+		let key = format!("sharded{:04}", i);
+		let mut wb = WriteBuffer::new();
+		wb.write_bin(key.as_bytes(), 0..key.len());
+		Arc::new(wb.bytes)
+	};
+
+	//8个写入者并发提交，每个事务写入1个主键
+	let (s, r) = bounded(8);
+	for writer in 0..8 {
+		let sharded = sharded.clone();
+		let s = s.clone();
+		let _ = rt.spawn(rt.alloc(), async move {
+			let gen = GuidGen::new(0, 0);
+			for i in 0..50 {
+				let txn = sharded.transaction(&gen.gen(0), true).await.unwrap();
+				let k = key(writer * 50 + i);
+				txn.upsert(k.clone(), k).await.unwrap();
+				txn.prepare(1000).await.unwrap();
+				txn.commit().await.unwrap();
+			}
+			let _ = s.send(());
+		});
+	}
+	for _ in 0..8 {
+		r.recv_timeout(Duration::from_secs(30)).unwrap();
+	}
+
+	let (s, r) = bounded(1);
+	let sharded_copy = sharded.clone();
+	let _ = rt.spawn(rt.alloc(), async move {
+		let sharded = sharded_copy;
+		let gen = GuidGen::new(0, 0);
+		let txn = sharded.transaction(&gen.gen(0), false).await.unwrap();
+		let collect = |mut it: pi_db::shard::ShardedIter| {
+			let mut keys = Vec::new();
+			while let Some(Ok(Some((k, _)))) = it.next() {
+				keys.push(k);
+			}
+			keys
+		};
+		let ascending = collect(txn.iter(None, false).await.unwrap());
+		let descending = collect(txn.iter(None, true).await.unwrap());
+		let from = collect(txn.iter(Some(key(100)), false).await.unwrap());
+		let value = txn.get(key(123)).await.unwrap();
+		let _ = s.send((ascending, descending, from, value));
+	});
+	let (ascending, descending, from, value) = r.recv_timeout(Duration::from_secs(10)).unwrap();
+
+	let expected: Vec<Bin> = (0..400).map(|i| key(i)).collect();
+	assert_eq!(ascending, expected);
+	assert_eq!(descending, expected.iter().rev().cloned().collect::<Vec<Bin>>());
+	assert_eq!(from, expected[100..].to_vec());
+	assert_eq!(value, Some(key(123)));
+}
+
+//不同分片的提交同时持有各自的内存表锁，一个事务不能写入多个分片，写入全部提交或全部不提交，删除逻辑表时同时删除分片表
+#[test]
+fn test_sharded_commit() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let rt_copy = rt.clone();
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt_copy.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/sharded_commit_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt_copy.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+		let sharded = ShardedTab::open(&tab, 4).await.unwrap();
+
+		//分别找到分片0和分片1中的主键
+		let key = |i: usize| -> Bin {
+			let key = format!("sharded{:04}", i);
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		};
+		let first = (0..).map(|i| key(i)).find(|k| shard_index(k, 4) == 0).unwrap();
+		let second = (0..).map(|i| key(i)).find(|k| shard_index(k, 4) == 1).unwrap();
+
+		//提交校验在内存表锁内执行，等待另一个分片的提交也进入校验，两个分片的提交同时持有各自的锁则并发数为2
+		let inside = Arc::new(AtomicUsize::new(0));
+		let peak = Arc::new(AtomicUsize::new(0));
+		let shards = sharded.shards();
+		for name in shards[0..2].iter() {
+			let inside = inside.clone();
+			let peak = peak.clone();
+			let validator: CommitValidator = Arc::new(move |_| {
+				let current = inside.fetch_add(1, Ordering::SeqCst) + 1;
+				peak.fetch_max(current, Ordering::SeqCst);
+				let start = Instant::now();
+				while inside.load(Ordering::SeqCst) < 2 && start.elapsed() < Duration::from_secs(2) {
+					std::thread::yield_now();
+				}
+				peak.fetch_max(inside.load(Ordering::SeqCst), Ordering::SeqCst);
+				inside.fetch_sub(1, Ordering::SeqCst);
+				Ok(())
+			});
+			LogFileDB::set_commit_validator(name, Some(validator));
+		}
+		//两个写入在不同的任务中提交，校验会阻塞所在的任务
+		let mut done = Vec::new();
+		for k in vec![first.clone(), second.clone()] {
+			let sharded = sharded.clone();
+			let (done_s, done_r) = futures::channel::oneshot::channel();
+			let _ = rt_copy.spawn(rt_copy.alloc(), async move {
+				let txn = sharded.transaction(&GuidGen::new(0, 0).gen(0), true).await.unwrap();
+				txn.upsert(k.clone(), k).await.unwrap();
+				txn.prepare(1000).await.unwrap();
+				let _ = done_s.send(txn.commit().await);
+			});
+			done.push(done_r);
+		}
+		for done_r in done {
+			done_r.await.unwrap().unwrap();
+		}
+		let concurrent = peak.load(Ordering::SeqCst);
+
+		//写入第二个分片被拒绝
+		let txn = sharded.transaction(&GuidGen::new(0, 0).gen(0), true).await.unwrap();
+		txn.delete(first.clone()).await.unwrap();
+		let cross = txn.delete(second.clone()).await;
+		txn.rollback().await.unwrap();
+
+		//只读的分片1拒绝提交，写入的分片0也不提交
+		let reject: CommitValidator = Arc::new(|_| Err(String::from("reject")));
+		LogFileDB::set_commit_validator(&shards[0], None);
+		LogFileDB::set_commit_validator(&shards[1], Some(reject));
+		let txn = sharded.transaction(&GuidGen::new(0, 0).gen(0), true).await.unwrap();
+		txn.get(second.clone()).await.unwrap();
+		txn.delete(first.clone()).await.unwrap();
+		txn.prepare(1000).await.unwrap();
+		let failed = txn.commit().await;
+		LogFileDB::set_commit_validator(&shards[1], None);
+		let reader = sharded.transaction(&GuidGen::new(0, 0).gen(0), false).await.unwrap();
+		let values = (reader.get(first.clone()).await.unwrap(), reader.get(second.clone()).await.unwrap());
+
+		//删除逻辑表时同时删除所有分片表
+		let mut tr = mgr.transaction(true, Some(rt_copy.clone())).await;
+		tr.alter(&Atom::from("logfile"), &tab, None).await;
+		tr.prepare().await;
+		tr.commit().await;
+		let tables = ALL_TABLES.lock().await;
+		let remained: Vec<Atom> = shards.into_iter().filter(|name| tables.contains_key(name)).collect();
+
+		let _ = s.send((concurrent, cross, failed, values, first, second, remained));
+	});
+
+	let (concurrent, cross, failed, values, first, second, remained) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(concurrent, 2);
+	assert!(cross.unwrap_err().starts_with("CrossShardWrite"));
+	assert!(failed.is_err());
+	assert_eq!(values, (Some(first), Some(second)));
+	assert!(remained.is_empty());
+}
\ No newline at end of file
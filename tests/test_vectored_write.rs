@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use atom::Atom;
+use sinfo;
+use guid::GuidGen;
+use crossbeam_channel::bounded;
+use r#async::rt::multi_thread::{MultiTaskPool, MultiTaskRuntime};
+use bon::WriteBuffer;
+use pi_db::mgr::{DatabaseWare, Mgr};
+use pi_db::db::{TabKV, TabMeta};
+use pi_db::log_file_db::{STORE_RUNTIME, LogFileDB};
+
+//读取进程的写系统调用次数，只有linux下可以读取
+fn write_syscalls() -> Option<u64> {
+	let io = std::fs::read_to_string("/proc/self/io").ok()?;
+	io.lines().find(|line| line.starts_with("syscw:")).and_then(|line| line[6..].trim().parse().ok())
+}
+
+#[test]
+fn test_vectored_write_batch() {
+	let pool = MultiTaskPool::new("Store-Runtime".to_string(), 4, 1024 * 1024, 10, Some(10));
+	let rt: MultiTaskRuntime<()>  = pool.startup(true);
+	let rt1 = rt.clone();
+
+	let (s, r) = bounded(1);
+	let _ = rt1.spawn(rt.alloc(), async move {
+		*STORE_RUNTIME.write().await = Some(rt.clone());
+		let mgr = Mgr::new(GuidGen::new(0, 0));
+		let ware = DatabaseWare::new_log_file_ware(LogFileDB::new(Atom::from("./testlogfile"), 1024 * 1024 * 1024).await);
+		let _ = mgr.register(Atom::from("logfile"), Arc::new(ware)).await;
+
+		let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+		let tab = Atom::from(format!("./testlogfile/vectored_{}", now));
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		let meta = TabMeta::new(sinfo::EnumType::Str, sinfo::EnumType::Str);
+		tr.alter(&Atom::from("logfile"), &tab, Some(Arc::new(meta))).await;
+		tr.prepare().await;
+		tr.commit().await;
+
+		let keys: Vec<Arc<Vec<u8>>> = (0..5000).map(|i| {
+			let key = format!("key{}", i);
+			let mut wb = WriteBuffer::new();
+			wb.write_bin(key.as_bytes(), 0..key.len());
+			Arc::new(wb.bytes)
+		}).collect();
+
+		//一次提交大量记录，只产生一次聚合写入，写系统调用的次数与记录数量无关
+		let writes = LogFileDB::vectored_writes();
+		let mut tr = mgr.transaction(true, Some(rt.clone())).await;
+		tr.modify(keys.iter().enumerate().map(|(i, key)| TabKV {
+			ware: Atom::from("logfile"),
+			tab: tab.clone(),
+			key: key.clone(),
+			value: Some(Arc::new(format!("value{}", i).into_bytes())),
+			index: 0,
+		}).collect(), None, false).await.unwrap();
+		tr.prepare().await.unwrap();
+		let syscalls = write_syscalls();
+		tr.commit().await.unwrap();
+		let syscalls = match (syscalls, write_syscalls()) {
+			(Some(before), Some(after)) => Some(after - before),
+			_ => None,
+		};
+		let writes = LogFileDB::vectored_writes() - writes;
+
+		//重新从日志文件加载后读取所有记录
+		let file = LogFileDB::open(&tab).await.unwrap();
+		file.reload().await.unwrap();
+		let mut tr = mgr.transaction(false, Some(rt.clone())).await;
+		let values = tr.query(keys.iter().map(|key| TabKV::new(Atom::from("logfile"), tab.clone(), key.clone())).collect(), None, false).await.unwrap();
+		let expected: HashMap<Vec<u8>, Vec<u8>> = keys.iter().enumerate().map(|(i, key)| (key.to_vec(), format!("value{}", i).into_bytes())).collect();
+		let matched = values.iter().all(|item| {
+			item.value.as_ref().map(|v| expected.get(item.key.as_slice()) == Some(v.as_ref())).unwrap_or(false)
+		});
+
+		let _ = s.send((writes, syscalls, values.len(), matched));
+	});
+
+	let (writes, syscalls, count, matched) = r.recv_timeout(Duration::from_secs(30)).unwrap();
+	assert_eq!(writes, 1);
+	//逐条写入至少需要5000次写系统调用，聚合写入只按日志块写入
+	if let Some(syscalls) = syscalls {
+		assert!(syscalls < 500, "write syscalls: {}", syscalls);
+	}
+	assert_eq!(count, 5000);
+	assert!(matched);
+}
\ No newline at end of file